@@ -0,0 +1,241 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{sync::Notify, time::Instant};
+
+use crate::RespFrame;
+
+use super::Backend;
+
+/// Clients blocked on keys, queued per key in the order they blocked.
+///
+/// A write that may let a blocked command proceed calls `Backend::signal_ready`, which wakes
+/// the first client queued on the key. A woken client that still can't proceed (another client
+/// got there first, or the key was deleted or now holds another type) passes the wakeup on to
+/// the client queued behind it and keeps waiting; one that leaves the queue wakes the new head.
+#[derive(Debug, Default)]
+pub struct BlockedClients {
+    next_id: AtomicU64,
+    queues: Mutex<HashMap<String, VecDeque<QueuedClient>>>,
+}
+
+// a client's id and the handle that wakes it
+type QueuedClient = (u64, Arc<Notify>);
+
+// a client's place in the queues of all its keys; leaving them on drop also covers timeouts
+// and connections that go away while blocked
+struct Waiter<'a> {
+    clients: &'a BlockedClients,
+    id: u64,
+    keys: &'a [String],
+    notify: Arc<Notify>,
+}
+
+impl BlockedClients {
+    fn register<'a>(&'a self, keys: &'a [String]) -> Waiter<'a> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let notify = Arc::new(Notify::new());
+        let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        for key in keys {
+            queues
+                .entry(key.clone())
+                .or_default()
+                .push_back((id, notify.clone()));
+        }
+        Waiter {
+            clients: self,
+            id,
+            keys,
+            notify,
+        }
+    }
+
+    // wakes the first client queued on `key` whose id comes after `after`
+    fn wake(&self, key: &str, after: Option<u64>) {
+        let queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(queue) = queues.get(key) else {
+            return;
+        };
+        let next = match after {
+            Some(id) => queue.iter().skip_while(|(w, _)| *w != id).nth(1),
+            None => queue.front(),
+        };
+        if let Some((_, notify)) = next {
+            notify.notify_one();
+        }
+    }
+
+    fn unregister(&self, id: u64, keys: &[String]) {
+        let mut queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        for key in keys {
+            if let Some(queue) = queues.get_mut(key) {
+                queue.retain(|(w, _)| *w != id);
+                if queue.is_empty() {
+                    queues.remove(key);
+                }
+            }
+        }
+    }
+
+    fn waiting(&self, key: &str) -> usize {
+        let queues = self.queues.lock().unwrap_or_else(|e| e.into_inner());
+        queues.get(key).map_or(0, |q| q.len())
+    }
+}
+
+impl Waiter<'_> {
+    fn pass_on(&self) {
+        for key in self.keys {
+            self.clients.wake(key, Some(self.id));
+        }
+    }
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.clients.unregister(self.id, self.keys);
+        // whatever this client was woken for may still be there for the next one
+        for key in self.keys {
+            self.clients.wake(key, None);
+        }
+    }
+}
+
+impl Backend {
+    /// Wakes the longest waiting client blocked on `key`. Called by writes that may let a
+    /// blocked command proceed.
+    pub(crate) fn signal_ready(&self, key: &str) {
+        self.blocked.wake(key, None);
+    }
+
+    /// Runs `attempt` until it returns a reply, waiting for `signal_ready` on any of `keys`
+    /// in between. Returns None once `timeout` elapses; no timeout waits forever.
+    pub async fn block_on<F>(
+        &self,
+        keys: &[String],
+        timeout: Option<Duration>,
+        mut attempt: F,
+    ) -> Option<RespFrame>
+    where
+        F: FnMut() -> Option<RespFrame>,
+    {
+        let waiter = self.blocked.register(keys);
+        // a deadline too far out to represent is as good as none
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut woken = false;
+        loop {
+            if let Some(frame) = attempt() {
+                return Some(frame);
+            }
+            if woken {
+                waiter.pass_on();
+            }
+            match deadline {
+                Some(deadline) => {
+                    tokio::time::timeout_at(deadline, waiter.notify.notified())
+                        .await
+                        .ok()?;
+                }
+                None => waiter.notify.notified().await,
+            }
+            woken = true;
+        }
+    }
+
+    // the number of clients blocked on key
+    pub fn blocked_clients(&self, key: &str) -> usize {
+        self.blocked.waiting(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, ListEnd};
+
+    use super::*;
+
+    fn pop(backend: &Backend, key: &str) -> Option<RespFrame> {
+        backend
+            .list_pop(key, ListEnd::Left, 1)
+            .and_then(|mut v| v.pop())
+    }
+
+    async fn wait_until_blocked(backend: &Backend, key: &str, n: usize) {
+        while backend.blocked_clients(key) < n {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_block_on_times_out() {
+        let backend = Backend::new();
+        let keys = ["q".to_string()];
+        let ret = backend
+            .block_on(&keys, Some(Duration::from_millis(20)), || {
+                pop(&backend, "q")
+            })
+            .await;
+        assert_eq!(ret, None);
+        assert_eq!(backend.blocked_clients("q"), 0);
+
+        // an unrepresentable deadline waits forever rather than panicking
+        let value = vec![BulkString::from("a").into()];
+        backend.list_push("q".to_string(), ListEnd::Right, value, false);
+        let ret = backend
+            .block_on(&keys, Some(Duration::MAX), || pop(&backend, "q"))
+            .await;
+        assert_eq!(ret, Some(BulkString::from("a").into()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_block_on_serves_clients_in_order() {
+        let backend = Backend::new();
+        let mut handles = vec![];
+        for i in 0..3 {
+            let b = backend.clone();
+            handles.push(tokio::spawn(async move {
+                let keys = ["q".to_string()];
+                b.block_on(&keys, None, || pop(&b, "q")).await
+            }));
+            wait_until_blocked(&backend, "q", i + 1).await;
+        }
+
+        for v in ["a", "b", "c"] {
+            let value = vec![BulkString::from(v).into()];
+            backend.list_push("q".to_string(), ListEnd::Right, value, false);
+        }
+        let mut got = vec![];
+        for handle in handles {
+            got.push(handle.await.unwrap());
+        }
+        let expected = ["a", "b", "c"].map(|v| Some(BulkString::from(v).into()));
+        assert_eq!(got, expected);
+        assert_eq!(backend.blocked_clients("q"), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_block_on_keeps_waiting_if_key_is_gone() {
+        let backend = Backend::new();
+        let b = backend.clone();
+        let handle = tokio::spawn(async move {
+            let keys = ["q".to_string()];
+            b.block_on(&keys, None, || pop(&b, "q")).await
+        });
+        wait_until_blocked(&backend, "q", 1).await;
+
+        // the key is signalled but deleted before the client gets to it
+        backend.signal_ready("q");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!handle.is_finished());
+
+        let value = vec![BulkString::from("a").into()];
+        backend.list_push("q".to_string(), ListEnd::Left, value, false);
+        assert_eq!(handle.await.unwrap(), Some(BulkString::from("a").into()));
+    }
+}
@@ -0,0 +1,454 @@
+use std::f64::consts::LN_2;
+
+use dashmap::mapref::entry::Entry;
+
+use super::{hyperloglog::murmurhash64a, Backend, BackendError};
+
+const BLOOM_HASH_SEED: u64 = 0xc6a4_a793_5bd1_e995;
+// each new layer's error rate is this fraction of the previous one's, which bounds the
+// error rate of the whole filter
+const BLOOM_TIGHTENING_RATIO: f64 = 0.5;
+const BLOOM_MAGIC: &[u8] = b"SRBF";
+
+// the most data SCANDUMP returns at once after the header
+pub(super) const DUMP_CHUNK_SIZE: usize = 64 * 1024;
+// the largest layer a filter may have, as Redis' proto-max-bulk-len, which keeps a client
+// from making the server allocate without bound and lets every layer be dumped and loaded
+pub(super) const MAX_LAYER_BYTES: u64 = 512 * 1024 * 1024;
+
+/// The parameters BF.RESERVE and BF.INSERT create a filter with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomOptions {
+    pub error_rate: f64,
+    pub capacity: u64,
+    // how many times larger each new layer is than the previous; 0 for NONSCALING
+    pub expansion: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BloomInfo {
+    pub capacity: u64,
+    pub size: usize,
+    pub filters: usize,
+    pub items: u64,
+    pub expansion: u64,
+}
+
+/// A scalable Bloom filter: a stack of Bloom filters where a new, larger and stricter one
+/// is added whenever the newest fills up.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    layers: Vec<BloomLayer>,
+    expansion: u64,
+}
+
+#[derive(Debug, Clone)]
+struct BloomLayer {
+    bits: Vec<u8>,
+    hashes: u64,
+    capacity: u64,
+    count: u64,
+    error_rate: f64,
+}
+
+impl Default for BloomOptions {
+    fn default() -> Self {
+        BloomOptions {
+            error_rate: 0.01,
+            capacity: 100,
+            expansion: 2,
+        }
+    }
+}
+
+// the two hashes every bit position of an item derives from
+fn bloom_hash(item: &[u8]) -> (u64, u64) {
+    let h1 = murmurhash64a(item, BLOOM_HASH_SEED);
+    (h1, murmurhash64a(item, h1))
+}
+
+impl BloomLayer {
+    fn new(capacity: u64, error_rate: f64) -> Result<Self, BackendError> {
+        let bits_per_entry = -error_rate.ln() / (LN_2 * LN_2);
+        // the cast saturates, so a huge capacity cannot wrap around to a small layer
+        let bits = ((capacity as f64 * bits_per_entry).ceil() as u64).max(64);
+        let bytes = bits.div_ceil(8);
+        if bytes > MAX_LAYER_BYTES {
+            return Err(BackendError::FilterTooLarge);
+        }
+        Ok(BloomLayer {
+            bits: vec![0; bytes as usize],
+            hashes: (-error_rate.log2()).ceil().max(1.0) as u64,
+            capacity,
+            count: 0,
+            error_rate,
+        })
+    }
+
+    fn positions(&self, (h1, h2): (u64, u64)) -> impl Iterator<Item = usize> {
+        let nbits = self.bits.len() as u64 * 8;
+        (0..self.hashes).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % nbits) as usize)
+    }
+
+    fn contains(&self, hash: (u64, u64)) -> bool {
+        self.positions(hash)
+            .all(|pos| self.bits[pos / 8] & (1 << (pos % 8)) != 0)
+    }
+
+    fn insert(&mut self, hash: (u64, u64)) {
+        let positions = self.positions(hash).collect::<Vec<_>>();
+        for pos in positions {
+            self.bits[pos / 8] |= 1 << (pos % 8);
+        }
+        self.count += 1;
+    }
+}
+
+impl BloomFilter {
+    pub fn new(options: BloomOptions) -> Result<Self, BackendError> {
+        Ok(BloomFilter {
+            layers: vec![BloomLayer::new(options.capacity, options.error_rate)?],
+            expansion: options.expansion,
+        })
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let hash = bloom_hash(item);
+        self.layers.iter().any(|layer| layer.contains(hash))
+    }
+
+    /// Adds an item, returning false if it (probably) was there already.
+    pub fn insert(&mut self, item: &[u8]) -> Result<bool, BackendError> {
+        let hash = bloom_hash(item);
+        if self.layers.iter().any(|layer| layer.contains(hash)) {
+            return Ok(false);
+        }
+        let last = self.layers.last().expect("a filter has at least one layer");
+        if last.count >= last.capacity {
+            if self.expansion == 0 {
+                return Err(BackendError::BloomFull);
+            }
+            let capacity = last.capacity.saturating_mul(self.expansion);
+            let layer = BloomLayer::new(capacity, last.error_rate * BLOOM_TIGHTENING_RATIO)?;
+            self.layers.push(layer);
+        }
+        let last = self
+            .layers
+            .last_mut()
+            .expect("a filter has at least one layer");
+        last.insert(hash);
+        Ok(true)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn info(&self) -> BloomInfo {
+        BloomInfo {
+            capacity: self.layers.iter().map(|layer| layer.capacity).sum(),
+            size: self.memory_usage(),
+            filters: self.layers.len(),
+            items: self.len(),
+            expansion: self.expansion,
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self
+                .layers
+                .iter()
+                .map(|layer| size_of::<BloomLayer>() + layer.bits.len())
+                .sum::<usize>()
+    }
+
+    // everything but the bits, which SCANDUMP sends in later chunks
+    fn header(&self) -> Vec<u8> {
+        let mut header = BLOOM_MAGIC.to_vec();
+        for v in [self.expansion, self.layers.len() as u64] {
+            header.extend_from_slice(&v.to_le_bytes());
+        }
+        for layer in &self.layers {
+            let fields = [
+                layer.capacity,
+                layer.count,
+                layer.error_rate.to_bits(),
+                layer.hashes,
+                layer.bits.len() as u64,
+            ];
+            for v in fields {
+                header.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        header
+    }
+
+    // a filter with the header's layers and all their bits clear
+    fn from_header(header: &[u8]) -> Result<Self, BackendError> {
+        let mut reader = DumpReader::new(header, BLOOM_MAGIC)?;
+        let expansion = reader.u64()?;
+        let layers = (0..reader.u64()?)
+            .map(|_| {
+                let (capacity, count) = (reader.u64()?, reader.u64()?);
+                let error_rate = f64::from_bits(reader.u64()?);
+                let (hashes, bytes) = (reader.u64()?, reader.u64()?);
+                let valid = (1..=MAX_LAYER_BYTES).contains(&bytes)
+                    && (1..=64).contains(&hashes)
+                    && (0.0..1.0).contains(&error_rate);
+                if !valid {
+                    return Err(BackendError::BadDump);
+                }
+                Ok(BloomLayer {
+                    bits: vec![0; bytes as usize],
+                    hashes,
+                    capacity,
+                    count,
+                    error_rate,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if layers.is_empty() || !reader.is_done() {
+            return Err(BackendError::BadDump);
+        }
+        Ok(BloomFilter { layers, expansion })
+    }
+}
+
+// reads the fields of a SCANDUMP header
+pub(super) struct DumpReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DumpReader<'a> {
+    pub(super) fn new(data: &'a [u8], magic: &[u8]) -> Result<Self, BackendError> {
+        match data.strip_prefix(magic) {
+            Some(data) => Ok(DumpReader { data }),
+            None => Err(BackendError::BadDump),
+        }
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, BackendError> {
+        let (v, rest) = self.data.split_first_chunk().ok_or(BackendError::BadDump)?;
+        self.data = rest;
+        Ok(u64::from_le_bytes(*v))
+    }
+
+    pub(super) fn is_done(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// The SCANDUMP reply at `iter` for a filter with `header` and the data `blobs`: the header
+/// at 0, then chunks of the blobs one after the other, each with the iterator to continue
+/// from, and (0, empty) at the end. A chunk never spans two blobs.
+pub(super) fn dump_chunk(header: Vec<u8>, blobs: &[&[u8]], iter: u64) -> (u64, Vec<u8>) {
+    if iter == 0 {
+        return (1, header);
+    }
+    let mut pos = (iter - 1) as usize;
+    for blob in blobs {
+        if pos < blob.len() {
+            let chunk = &blob[pos..blob.len().min(pos + DUMP_CHUNK_SIZE)];
+            return (iter + chunk.len() as u64, chunk.to_vec());
+        }
+        pos -= blob.len();
+    }
+    (0, Vec::new())
+}
+
+/// Writes a chunk SCANDUMP returned with `iter` back into the blobs; see `dump_chunk`.
+pub(super) fn load_chunk<'a>(
+    blobs: impl Iterator<Item = &'a mut Vec<u8>>,
+    iter: u64,
+    chunk: &[u8],
+) -> Result<(), BackendError> {
+    // `iter` points just past the chunk
+    let end = iter.checked_sub(1).ok_or(BackendError::BadDump)? as usize;
+    let mut pos = end.checked_sub(chunk.len()).ok_or(BackendError::BadDump)?;
+    for blob in blobs {
+        if pos < blob.len() {
+            let target = blob
+                .get_mut(pos..pos + chunk.len())
+                .ok_or(BackendError::BadDump)?;
+            target.copy_from_slice(chunk);
+            return Ok(());
+        }
+        pos -= blob.len();
+    }
+    Err(BackendError::BadDump)
+}
+
+impl Backend {
+    pub fn bf_reserve(&self, key: String, options: BloomOptions) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        match self.bloom.entry(key) {
+            Entry::Occupied(_) => Err(BackendError::ItemExists),
+            Entry::Vacant(entry) => {
+                entry.insert(BloomFilter::new(options)?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the items, creating the filter with `options` if it is missing and `options` is
+    /// set. Each result tells whether the item was new.
+    pub fn bf_insert(
+        &self,
+        key: String,
+        items: &[Vec<u8>],
+        options: Option<BloomOptions>,
+    ) -> Result<Vec<Result<bool, BackendError>>, BackendError> {
+        let _guard = self.shared_lock();
+        let mut filter = match (self.bloom.entry(key), options) {
+            (Entry::Occupied(entry), _) => entry.into_ref(),
+            (Entry::Vacant(entry), Some(options)) => entry.insert(BloomFilter::new(options)?),
+            (Entry::Vacant(_), None) => return Err(BackendError::NotFound),
+        };
+        Ok(items.iter().map(|item| filter.insert(item)).collect())
+    }
+
+    pub fn bf_exists(&self, key: &str, items: &[Vec<u8>]) -> Vec<bool> {
+        match self.bloom.get(key) {
+            Some(filter) => items.iter().map(|item| filter.contains(item)).collect(),
+            None => vec![false; items.len()],
+        }
+    }
+
+    pub fn bf_card(&self, key: &str) -> u64 {
+        self.bloom.get(key).map_or(0, |filter| filter.len())
+    }
+
+    pub fn bf_info(&self, key: &str) -> Result<BloomInfo, BackendError> {
+        let filter = self.bloom.get(key).ok_or(BackendError::NotFound)?;
+        Ok(filter.info())
+    }
+
+    pub fn bf_scandump(&self, key: &str, iter: u64) -> Result<(u64, Vec<u8>), BackendError> {
+        let filter = self.bloom.get(key).ok_or(BackendError::NotFound)?;
+        let blobs = filter
+            .layers
+            .iter()
+            .map(|layer| layer.bits.as_slice())
+            .collect::<Vec<_>>();
+        Ok(dump_chunk(filter.header(), &blobs, iter))
+    }
+
+    /// Restores a filter from the chunks of BF.SCANDUMP, in order; the header replaces the
+    /// key with an empty filter of the same shape.
+    pub fn bf_loadchunk(&self, key: String, iter: u64, chunk: &[u8]) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        if iter == 1 {
+            self.bloom.insert(key, BloomFilter::from_header(chunk)?);
+            return Ok(());
+        }
+        let mut filter = self.bloom.get_mut(&key).ok_or(BackendError::NotFound)?;
+        let blobs = filter.layers.iter_mut().map(|layer| &mut layer.bits);
+        load_chunk(blobs, iter, chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(n: usize, prefix: &str) -> Vec<Vec<u8>> {
+        (0..n)
+            .map(|i| format!("{}{}", prefix, i).into_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_bloom_scaling_and_error_rate() -> Result<(), BackendError> {
+        let options = BloomOptions {
+            capacity: 1000,
+            ..Default::default()
+        };
+        let mut filter = BloomFilter::new(options)?;
+        let added = items(5000, "in");
+        for item in &added {
+            filter.insert(item)?;
+        }
+        assert!(added.iter().all(|item| filter.contains(item)));
+        // 1000, 2000 and 4000 items
+        assert_eq!(filter.info().filters, 3);
+        assert!(filter.len() <= 5000 && filter.len() > 4900);
+        let false_positives = items(10000, "out")
+            .iter()
+            .filter(|item| filter.contains(item))
+            .count();
+        assert!(false_positives < 200, "{}", false_positives);
+
+        let mut fixed = BloomFilter::new(BloomOptions {
+            capacity: 10,
+            expansion: 0,
+            ..Default::default()
+        })?;
+        let results = items(20, "x")
+            .iter()
+            .map(|item| fixed.insert(item))
+            .collect::<Vec<_>>();
+        assert!(results.contains(&Err(BackendError::BloomFull)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bloom_growth_is_bounded() -> Result<(), BackendError> {
+        let mut filter = BloomFilter::new(BloomOptions {
+            error_rate: 0.5,
+            capacity: 1,
+            expansion: 32768,
+        })?;
+        // the third layer, for 2^30 items, would be larger than a layer may be
+        let results = items(40000, "x")
+            .iter()
+            .map(|item| filter.insert(item))
+            .collect::<Vec<_>>();
+        assert_eq!(filter.info().filters, 2);
+        assert!(results.contains(&Err(BackendError::FilterTooLarge)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bloom_scandump_and_loadchunk() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let options = BloomOptions {
+            capacity: 100_000,
+            ..Default::default()
+        };
+        let added = items(1000, "item");
+        backend.bf_insert("bf".to_string(), &added, Some(options))?;
+        backend.bf_insert("bf".to_string(), &[b"x".to_vec()], None)?;
+
+        let mut iter = 0;
+        let mut chunks = Vec::new();
+        loop {
+            let (next, chunk) = backend.bf_scandump("bf", iter)?;
+            if next == 0 {
+                break;
+            }
+            chunks.push((next, chunk));
+            iter = next;
+        }
+        // the header and the bits of a filter over the chunk size
+        assert!(chunks.len() > 2);
+        for (iter, chunk) in chunks {
+            backend.bf_loadchunk("copy".to_string(), iter, &chunk)?;
+        }
+        assert!(backend.bf_exists("copy", &added).into_iter().all(|b| b));
+        assert_eq!(backend.bf_info("copy")?, backend.bf_info("bf")?);
+        assert_eq!(
+            backend.bf_loadchunk("bad".to_string(), 1, b"nonsense"),
+            Err(BackendError::BadDump)
+        );
+        assert_eq!(
+            backend.bf_insert("missing".to_string(), &added, None),
+            Err(BackendError::NotFound)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use dashmap::mapref::entry::Entry;
+
+use super::{bloom::MAX_LAYER_BYTES, hyperloglog::murmurhash64a, Backend, BackendError};
+
+/// A Count-Min Sketch: `depth` rows of `width` counters, where an item's count is the
+/// smallest of its counters and so never under-estimates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CountMinSketch {
+    width: u64,
+    depth: u64,
+    counters: Vec<u64>,
+    // the sum of all increments
+    count: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CountMinSketchInfo {
+    pub width: u64,
+    pub depth: u64,
+    pub count: u64,
+}
+
+impl CountMinSketch {
+    /// Fails if the counters would take more than a filter layer may.
+    pub fn new(width: u64, depth: u64) -> Result<Self, BackendError> {
+        let counters = width
+            .checked_mul(depth)
+            .filter(|n| n.saturating_mul(size_of::<u64>() as u64) <= MAX_LAYER_BYTES)
+            .ok_or(BackendError::SketchTooLarge)?;
+        Ok(CountMinSketch {
+            width,
+            depth,
+            counters: vec![0; counters as usize],
+            count: 0,
+        })
+    }
+
+    /// The dimensions that keep over-estimates within `error` of the total count with
+    /// `probability` of failing.
+    pub fn dimensions(error: f64, probability: f64) -> (u64, u64) {
+        let width = (2.0 / error).ceil() as u64;
+        let depth = (probability.ln() / 0.5f64.ln()).ceil().max(1.0) as u64;
+        (width, depth)
+    }
+
+    // the item's counter in each row
+    fn positions(&self, item: &[u8]) -> impl Iterator<Item = usize> + '_ {
+        let hash = murmurhash64a(item, 0);
+        (0..self.depth).map(move |row| {
+            let col = murmurhash64a(&hash.to_le_bytes(), row) % self.width;
+            (row * self.width + col) as usize
+        })
+    }
+
+    /// Adds `incr` to the item and returns its new count.
+    pub fn incr(&mut self, item: &[u8], incr: u64) -> u64 {
+        let positions = self.positions(item).collect::<Vec<_>>();
+        let mut min = u64::MAX;
+        for pos in positions {
+            let counter = &mut self.counters[pos];
+            *counter = counter.saturating_add(incr);
+            min = min.min(*counter);
+        }
+        self.count = self.count.saturating_add(incr);
+        min
+    }
+
+    pub fn query(&self, item: &[u8]) -> u64 {
+        self.positions(item)
+            .map(|pos| self.counters[pos])
+            .min()
+            .unwrap_or(0)
+    }
+
+    pub fn info(&self) -> CountMinSketchInfo {
+        CountMinSketchInfo {
+            width: self.width,
+            depth: self.depth,
+            count: self.count,
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.counters.len() * size_of::<u64>()
+    }
+}
+
+impl Backend {
+    pub fn cms_init(&self, key: String, width: u64, depth: u64) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        match self.cms.entry(key) {
+            Entry::Occupied(_) => Err(BackendError::CmsKeyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(CountMinSketch::new(width, depth)?);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn cms_incrby(
+        &self,
+        key: &str,
+        items: &[(Vec<u8>, u64)],
+    ) -> Result<Vec<u64>, BackendError> {
+        let _guard = self.shared_lock();
+        let mut sketch = self.cms.get_mut(key).ok_or(BackendError::CmsNoKey)?;
+        Ok(items
+            .iter()
+            .map(|(item, incr)| sketch.incr(item, *incr))
+            .collect())
+    }
+
+    pub fn cms_query(&self, key: &str, items: &[Vec<u8>]) -> Result<Vec<u64>, BackendError> {
+        let sketch = self.cms.get(key).ok_or(BackendError::CmsNoKey)?;
+        Ok(items.iter().map(|item| sketch.query(item)).collect())
+    }
+
+    /// Overwrites `dest` with the sum of the sources, each multiplied by its weight. All the
+    /// sketches must exist and have the same dimensions.
+    pub fn cms_merge(&self, dest: &str, sources: &[(String, i64)]) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        let (width, depth, len) = {
+            let dest = self.cms.get(dest).ok_or(BackendError::CmsNoKey)?;
+            (dest.width, dest.depth, dest.counters.len())
+        };
+        let mut counters = vec![0i128; len];
+        let mut count = 0i128;
+        for (key, weight) in sources {
+            let source = self.cms.get(key).ok_or(BackendError::CmsNoKey)?;
+            if (source.width, source.depth) != (width, depth) {
+                return Err(BackendError::CmsDimensions);
+            }
+            let weight = *weight as i128;
+            for (merged, counter) in counters.iter_mut().zip(&source.counters) {
+                *merged += weight * *counter as i128;
+            }
+            count += weight * source.count as i128;
+        }
+
+        let clamp = |v: i128| v.clamp(0, u64::MAX as i128) as u64;
+        let mut dest = self.cms.get_mut(dest).ok_or(BackendError::CmsNoKey)?;
+        dest.counters = counters.into_iter().map(clamp).collect();
+        dest.count = clamp(count);
+        Ok(())
+    }
+
+    pub fn cms_info(&self, key: &str) -> Result<CountMinSketchInfo, BackendError> {
+        let sketch = self.cms.get(key).ok_or(BackendError::CmsNoKey)?;
+        Ok(sketch.info())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cms_never_underestimates() {
+        let (width, depth) = CountMinSketch::dimensions(0.001, 0.01);
+        assert_eq!((width, depth), (2000, 7));
+        let mut sketch = CountMinSketch::new(width, depth).unwrap();
+        for i in 0..5000u64 {
+            sketch.incr(format!("item{}", i).as_bytes(), i % 10 + 1);
+        }
+        assert!(sketch.incr(b"heavy", 1000) >= 1000);
+        let total = sketch.info().count;
+        for i in 0..5000u64 {
+            let (count, added) = (sketch.query(format!("item{}", i).as_bytes()), i % 10 + 1);
+            assert!(count >= added);
+            assert!(count <= added + total / 100);
+        }
+    }
+
+    #[test]
+    fn test_cms_merge() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for key in ["a", "b", "dest"] {
+            backend.cms_init(key.to_string(), 100, 4)?;
+        }
+        backend.cms_init("small".to_string(), 10, 4)?;
+        backend.cms_incrby("a", &[(b"x".to_vec(), 3)])?;
+        backend.cms_incrby("b", &[(b"x".to_vec(), 2), (b"y".to_vec(), 5)])?;
+
+        backend.cms_merge("dest", &[("a".to_string(), 1), ("b".to_string(), 2)])?;
+        assert_eq!(
+            backend.cms_query("dest", &[b"x".to_vec(), b"y".to_vec()])?,
+            vec![7, 10]
+        );
+        assert_eq!(backend.cms_info("dest")?.count, 17);
+        assert_eq!(
+            backend.cms_merge("dest", &[("small".to_string(), 1)]),
+            Err(BackendError::CmsDimensions)
+        );
+        assert_eq!(
+            backend.cms_init("a".to_string(), 1, 1),
+            Err(BackendError::CmsKeyExists)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::BackendError;
+
+/// Runtime tunables, readable and writable through CONFIG GET/SET.
+#[derive(Debug)]
+pub struct Config {
+    // hashes with more fields than this use the hashtable encoding
+    hash_max_listpack_entries: AtomicUsize,
+    // hashes with a field or value longer than this use the hashtable encoding
+    hash_max_listpack_value: AtomicUsize,
+    // sparse HyperLogLogs larger than this, header included, are converted to dense
+    hll_sparse_max_bytes: AtomicUsize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            hash_max_listpack_entries: AtomicUsize::new(128),
+            hash_max_listpack_value: AtomicUsize::new(64),
+            hll_sparse_max_bytes: AtomicUsize::new(3000),
+        }
+    }
+}
+
+impl Config {
+    pub const NAMES: &'static [&'static str] = &[
+        "hash-max-listpack-entries",
+        "hash-max-listpack-value",
+        "hll-sparse-max-bytes",
+    ];
+
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.param(name)
+            .map(|v| v.load(Ordering::Relaxed).to_string())
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), BackendError> {
+        let (param, value) = self.parse(name, value)?;
+        param.store(value, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // like CONFIG SET with several parameters: nothing is changed unless all of them are valid
+    pub fn set_all(&self, params: &[(String, String)]) -> Result<(), BackendError> {
+        let parsed = params
+            .iter()
+            .map(|(name, value)| self.parse(name, value))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (param, value) in parsed {
+            param.store(value, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    pub fn hash_max_listpack_entries(&self) -> usize {
+        self.hash_max_listpack_entries.load(Ordering::Relaxed)
+    }
+
+    pub fn hash_max_listpack_value(&self) -> usize {
+        self.hash_max_listpack_value.load(Ordering::Relaxed)
+    }
+
+    pub fn hll_sparse_max_bytes(&self) -> usize {
+        self.hll_sparse_max_bytes.load(Ordering::Relaxed)
+    }
+
+    fn parse(&self, name: &str, value: &str) -> Result<(&AtomicUsize, usize), BackendError> {
+        let param = self
+            .param(name)
+            .ok_or_else(|| BackendError::UnknownConfig(name.to_string()))?;
+        let value = value
+            .parse::<usize>()
+            .map_err(|_| BackendError::InvalidConfigValue(name.to_string()))?;
+        Ok((param, value))
+    }
+
+    fn param(&self, name: &str) -> Option<&AtomicUsize> {
+        match name.to_ascii_lowercase().as_str() {
+            "hash-max-listpack-entries" => Some(&self.hash_max_listpack_entries),
+            "hash-max-listpack-value" => Some(&self.hash_max_listpack_value),
+            "hll-sparse-max-bytes" => Some(&self.hll_sparse_max_bytes),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_get_set() {
+        let config = Config::default();
+        assert_eq!(
+            config.get("hash-max-listpack-entries"),
+            Some("128".to_string())
+        );
+        config.set("HASH-MAX-LISTPACK-VALUE", "16").unwrap();
+        assert_eq!(config.hash_max_listpack_value(), 16);
+        assert_eq!(
+            config.set("hash-max-listpack-value", "-1"),
+            Err(BackendError::InvalidConfigValue(
+                "hash-max-listpack-value".to_string()
+            ))
+        );
+        assert_eq!(
+            config.set("nope", "1"),
+            Err(BackendError::UnknownConfig("nope".to_string()))
+        );
+    }
+}
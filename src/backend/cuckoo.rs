@@ -0,0 +1,465 @@
+use dashmap::mapref::entry::Entry;
+use rand::Rng;
+
+use super::{
+    bloom::{dump_chunk, load_chunk, DumpReader, MAX_LAYER_BYTES},
+    hyperloglog::murmurhash64a,
+    Backend, BackendError,
+};
+
+const CUCKOO_MAGIC: &[u8] = b"SRCF";
+// an empty slot; fingerprints are never 0
+const CUCKOO_EMPTY: u8 = 0;
+
+/// The parameters CF.RESERVE and CF.INSERT create a filter with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuckooOptions {
+    pub capacity: u64,
+    pub bucket_size: u64,
+    // how many fingerprints an insert may relocate before the filter counts as full
+    pub max_iterations: u64,
+    // how many times larger each new layer is than the previous; 0 never adds layers
+    pub expansion: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CuckooInfo {
+    pub size: usize,
+    pub buckets: u64,
+    pub filters: usize,
+    pub inserted: u64,
+    pub deleted: u64,
+    pub bucket_size: u64,
+    pub expansion: u64,
+    pub max_iterations: u64,
+}
+
+/// A cuckoo filter of 8-bit fingerprints, which unlike a Bloom filter can delete items and
+/// count how often they were added. It grows by stacking larger layers.
+#[derive(Debug, Clone)]
+pub struct CuckooFilter {
+    // each layer holds `bucket_size` slots per bucket, with a power of two buckets
+    layers: Vec<Vec<u8>>,
+    bucket_size: u64,
+    max_iterations: u64,
+    expansion: u64,
+    inserted: u64,
+    deleted: u64,
+}
+
+impl Default for CuckooOptions {
+    fn default() -> Self {
+        CuckooOptions {
+            capacity: 1024,
+            bucket_size: 2,
+            max_iterations: 20,
+            expansion: 1,
+        }
+    }
+}
+
+// where an item's fingerprint may live: two buckets of a layer with `buckets` buckets
+#[derive(Debug, Clone, Copy)]
+struct Slots {
+    fp: u8,
+    i1: u64,
+    i2: u64,
+}
+
+fn cuckoo_hash(item: &[u8]) -> u64 {
+    murmurhash64a(item, 0)
+}
+
+// the other bucket of a fingerprint; applying it twice gives back the first
+fn alt_bucket(i: u64, fp: u8, buckets: u64) -> u64 {
+    (i ^ (fp as u64).wrapping_mul(0x5bd1_e995)) & (buckets - 1)
+}
+
+impl CuckooFilter {
+    pub fn new(options: CuckooOptions) -> Result<Self, BackendError> {
+        let size = options
+            .capacity
+            .div_ceil(options.bucket_size)
+            .checked_next_power_of_two()
+            .and_then(|buckets| buckets.checked_mul(options.bucket_size))
+            .filter(|size| *size <= MAX_LAYER_BYTES)
+            .ok_or(BackendError::FilterTooLarge)?;
+        Ok(CuckooFilter {
+            layers: vec![vec![CUCKOO_EMPTY; size as usize]],
+            bucket_size: options.bucket_size,
+            max_iterations: options.max_iterations,
+            // keeps every layer a power of two buckets
+            expansion: match options.expansion {
+                0 => 0,
+                e => e.next_power_of_two(),
+            },
+            inserted: 0,
+            deleted: 0,
+        })
+    }
+
+    fn buckets(&self, layer: &[u8]) -> u64 {
+        layer.len() as u64 / self.bucket_size
+    }
+
+    fn slots(&self, hash: u64, layer: &[u8]) -> Slots {
+        let buckets = self.buckets(layer);
+        let fp = (hash % 255 + 1) as u8;
+        let i1 = hash.rotate_right(32) & (buckets - 1);
+        Slots {
+            fp,
+            i1,
+            i2: alt_bucket(i1, fp, buckets),
+        }
+    }
+
+    fn bucket<'a>(&self, layer: &'a [u8], i: u64) -> &'a [u8] {
+        let start = (i * self.bucket_size) as usize;
+        &layer[start..start + self.bucket_size as usize]
+    }
+
+    /// How many times the item's fingerprint was added, which may count other items too.
+    pub fn count(&self, item: &[u8]) -> u64 {
+        let hash = cuckoo_hash(item);
+        self.layers
+            .iter()
+            .map(|layer| {
+                let slots = self.slots(hash, layer);
+                let count = |i| {
+                    self.bucket(layer, i)
+                        .iter()
+                        .filter(|fp| **fp == slots.fp)
+                        .count()
+                };
+                match slots.i1 == slots.i2 {
+                    true => count(slots.i1),
+                    false => count(slots.i1) + count(slots.i2),
+                }
+            })
+            .sum::<usize>() as u64
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.count(item) > 0
+    }
+
+    /// Adds the item's fingerprint, even if it is already there.
+    pub fn insert(&mut self, item: &[u8]) -> Result<(), BackendError> {
+        let hash = cuckoo_hash(item);
+        // a free slot in any layer will do
+        for l in (0..self.layers.len()).rev() {
+            let slots = self.slots(hash, &self.layers[l]);
+            if self.place(l, slots.fp, [slots.i1, slots.i2]) {
+                self.inserted += 1;
+                return Ok(());
+            }
+        }
+        if self.relocate(hash) {
+            self.inserted += 1;
+            return Ok(());
+        }
+        if self.expansion == 0 {
+            return Err(BackendError::CuckooFull);
+        }
+        let last = self.layers.last().expect("a filter has at least one layer");
+        let size = last.len().saturating_mul(self.expansion as usize);
+        if size as u64 > MAX_LAYER_BYTES {
+            return Err(BackendError::CuckooFull);
+        }
+        self.layers.push(vec![CUCKOO_EMPTY; size]);
+        let l = self.layers.len() - 1;
+        let slots = self.slots(hash, &self.layers[l]);
+        self.place(l, slots.fp, [slots.i1, slots.i2]);
+        self.inserted += 1;
+        Ok(())
+    }
+
+    // puts the fingerprint in an empty slot of one of the buckets
+    fn place(&mut self, l: usize, fp: u8, buckets: [u64; 2]) -> bool {
+        let size = self.bucket_size as usize;
+        for i in buckets {
+            let start = i as usize * size;
+            let bucket = &mut self.layers[l][start..start + size];
+            if let Some(slot) = bucket.iter_mut().find(|slot| **slot == CUCKOO_EMPTY) {
+                *slot = fp;
+                return true;
+            }
+        }
+        false
+    }
+
+    // makes room in the newest layer by moving fingerprints to their other bucket, undoing
+    // the moves if that takes more than `max_iterations`
+    fn relocate(&mut self, hash: u64) -> bool {
+        let l = self.layers.len() - 1;
+        let slots = self.slots(hash, &self.layers[l]);
+        let buckets = self.buckets(&self.layers[l]);
+        let mut rng = rand::thread_rng();
+        let (mut fp, mut i) = (slots.fp, slots.i1);
+        let mut moved = Vec::new();
+        for _ in 0..self.max_iterations {
+            let slot = (i * self.bucket_size + rng.gen_range(0..self.bucket_size)) as usize;
+            std::mem::swap(&mut fp, &mut self.layers[l][slot]);
+            moved.push(slot);
+            i = alt_bucket(i, fp, buckets);
+            if self.place(l, fp, [i, i]) {
+                return true;
+            }
+        }
+        for slot in moved.into_iter().rev() {
+            std::mem::swap(&mut fp, &mut self.layers[l][slot]);
+        }
+        false
+    }
+
+    /// Removes one copy of the item's fingerprint, newest layers first.
+    pub fn remove(&mut self, item: &[u8]) -> bool {
+        let hash = cuckoo_hash(item);
+        for l in (0..self.layers.len()).rev() {
+            let slots = self.slots(hash, &self.layers[l]);
+            let size = self.bucket_size as usize;
+            for i in [slots.i1, slots.i2] {
+                let start = i as usize * size;
+                let bucket = &mut self.layers[l][start..start + size];
+                if let Some(slot) = bucket.iter_mut().find(|slot| **slot == slots.fp) {
+                    *slot = CUCKOO_EMPTY;
+                    self.inserted = self.inserted.saturating_sub(1);
+                    self.deleted += 1;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    pub fn info(&self) -> CuckooInfo {
+        CuckooInfo {
+            size: self.memory_usage(),
+            buckets: self.layers.iter().map(|layer| self.buckets(layer)).sum(),
+            filters: self.layers.len(),
+            inserted: self.inserted,
+            deleted: self.deleted,
+            bucket_size: self.bucket_size,
+            expansion: self.expansion,
+            max_iterations: self.max_iterations,
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.layers.iter().map(Vec::len).sum::<usize>()
+    }
+
+    // everything but the slots, which SCANDUMP sends in later chunks
+    fn header(&self) -> Vec<u8> {
+        let mut header = CUCKOO_MAGIC.to_vec();
+        let fields = [
+            self.bucket_size,
+            self.max_iterations,
+            self.expansion,
+            self.inserted,
+            self.deleted,
+            self.layers.len() as u64,
+        ];
+        for v in fields
+            .into_iter()
+            .chain(self.layers.iter().map(|l| l.len() as u64))
+        {
+            header.extend_from_slice(&v.to_le_bytes());
+        }
+        header
+    }
+
+    // a filter with the header's layers and all their slots empty
+    fn from_header(header: &[u8]) -> Result<Self, BackendError> {
+        let mut reader = DumpReader::new(header, CUCKOO_MAGIC)?;
+        let (bucket_size, max_iterations) = (reader.u64()?, reader.u64()?);
+        let (expansion, inserted, deleted) = (reader.u64()?, reader.u64()?, reader.u64()?);
+        if !(1..=255).contains(&bucket_size) || !(expansion == 0 || expansion.is_power_of_two()) {
+            return Err(BackendError::BadDump);
+        }
+        let layers = (0..reader.u64()?)
+            .map(|_| match reader.u64()? {
+                len if len > MAX_LAYER_BYTES
+                    || len % bucket_size != 0
+                    || !(len / bucket_size).is_power_of_two() =>
+                {
+                    Err(BackendError::BadDump)
+                }
+                len => Ok(vec![CUCKOO_EMPTY; len as usize]),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if layers.is_empty() || !reader.is_done() {
+            return Err(BackendError::BadDump);
+        }
+        Ok(CuckooFilter {
+            layers,
+            bucket_size,
+            max_iterations,
+            expansion,
+            inserted,
+            deleted,
+        })
+    }
+}
+
+impl Backend {
+    pub fn cf_reserve(&self, key: String, options: CuckooOptions) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        match self.cuckoo.entry(key) {
+            Entry::Occupied(_) => Err(BackendError::ItemExists),
+            Entry::Vacant(entry) => {
+                entry.insert(CuckooFilter::new(options)?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds the items, creating the filter with `options` if it is missing and `options` is
+    /// set. With `nx`, items already in the filter are skipped. Each result tells whether the
+    /// item was added.
+    pub fn cf_insert(
+        &self,
+        key: String,
+        items: &[Vec<u8>],
+        nx: bool,
+        options: Option<CuckooOptions>,
+    ) -> Result<Vec<Result<bool, BackendError>>, BackendError> {
+        let _guard = self.shared_lock();
+        let mut filter = match (self.cuckoo.entry(key), options) {
+            (Entry::Occupied(entry), _) => entry.into_ref(),
+            (Entry::Vacant(entry), Some(options)) => entry.insert(CuckooFilter::new(options)?),
+            (Entry::Vacant(_), None) => return Err(BackendError::NotFound),
+        };
+        let added = items
+            .iter()
+            .map(|item| match nx && filter.contains(item) {
+                true => Ok(false),
+                false => filter.insert(item).map(|()| true),
+            })
+            .collect();
+        Ok(added)
+    }
+
+    pub fn cf_exists(&self, key: &str, items: &[Vec<u8>]) -> Vec<bool> {
+        match self.cuckoo.get(key) {
+            Some(filter) => items.iter().map(|item| filter.contains(item)).collect(),
+            None => vec![false; items.len()],
+        }
+    }
+
+    pub fn cf_count(&self, key: &str, item: &[u8]) -> u64 {
+        self.cuckoo.get(key).map_or(0, |filter| filter.count(item))
+    }
+
+    pub fn cf_del(&self, key: &str, item: &[u8]) -> Result<bool, BackendError> {
+        let _guard = self.shared_lock();
+        let mut filter = self.cuckoo.get_mut(key).ok_or(BackendError::NotFound)?;
+        Ok(filter.remove(item))
+    }
+
+    pub fn cf_info(&self, key: &str) -> Result<CuckooInfo, BackendError> {
+        let filter = self.cuckoo.get(key).ok_or(BackendError::NotFound)?;
+        Ok(filter.info())
+    }
+
+    pub fn cf_scandump(&self, key: &str, iter: u64) -> Result<(u64, Vec<u8>), BackendError> {
+        let filter = self.cuckoo.get(key).ok_or(BackendError::NotFound)?;
+        let blobs = filter.layers.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Ok(dump_chunk(filter.header(), &blobs, iter))
+    }
+
+    /// Restores a filter from the chunks of CF.SCANDUMP, in order; the header replaces the
+    /// key with an empty filter of the same shape.
+    pub fn cf_loadchunk(&self, key: String, iter: u64, chunk: &[u8]) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        if iter == 1 {
+            self.cuckoo.insert(key, CuckooFilter::from_header(chunk)?);
+            return Ok(());
+        }
+        let mut filter = self.cuckoo.get_mut(&key).ok_or(BackendError::NotFound)?;
+        load_chunk(filter.layers.iter_mut(), iter, chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cuckoo_insert_count_remove() -> Result<(), BackendError> {
+        let mut filter = CuckooFilter::new(CuckooOptions {
+            capacity: 1000,
+            ..Default::default()
+        })?;
+        let items = (0..3000)
+            .map(|i| format!("item{}", i).into_bytes())
+            .collect::<Vec<_>>();
+        for item in &items {
+            filter.insert(item)?;
+        }
+        assert!(items.iter().all(|item| filter.contains(item)));
+        assert!(filter.info().filters > 1);
+        assert_eq!(filter.info().inserted, 3000);
+
+        filter.insert(b"dup")?;
+        filter.insert(b"dup")?;
+        assert!(filter.count(b"dup") >= 2);
+        assert!(filter.remove(b"dup") && filter.remove(b"dup"));
+        assert_eq!(filter.info().deleted, 2);
+        for item in &items {
+            assert!(filter.remove(item));
+        }
+        assert_eq!(filter.info().inserted, 0);
+        assert!(filter.layers.iter().flatten().all(|fp| *fp == CUCKOO_EMPTY));
+
+        let mut fixed = CuckooFilter::new(CuckooOptions {
+            capacity: 4,
+            expansion: 0,
+            ..Default::default()
+        })?;
+        let results = (0..20)
+            .map(|i| fixed.insert(format!("{}", i).as_bytes()))
+            .collect::<Vec<_>>();
+        assert!(results.contains(&Err(BackendError::CuckooFull)));
+        // a failed insert leaves every fingerprint that was there in place
+        assert_eq!(
+            fixed.layers[0]
+                .iter()
+                .filter(|fp| **fp != CUCKOO_EMPTY)
+                .count() as u64,
+            fixed.info().inserted
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_cuckoo_scandump_and_loadchunk() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let options = CuckooOptions {
+            capacity: 100_000,
+            ..Default::default()
+        };
+        let items = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec()];
+        backend.cf_insert("cf".to_string(), &items, false, Some(options))?;
+        assert_eq!(
+            backend.cf_insert("cf".to_string(), &items[..1], true, None)?,
+            vec![Ok(false)]
+        );
+
+        let mut iter = 0;
+        loop {
+            let (next, chunk) = backend.cf_scandump("cf", iter)?;
+            if next == 0 {
+                break;
+            }
+            backend.cf_loadchunk("copy".to_string(), next, &chunk)?;
+            iter = next;
+        }
+        assert_eq!(backend.cf_exists("copy", &items), vec![true; 3]);
+        assert_eq!(backend.cf_info("copy")?, backend.cf_info("cf")?);
+        assert!(backend.cf_del("copy", b"a")?);
+        assert_eq!(backend.cf_count("copy", b"a"), 0);
+        assert_eq!(backend.cf_del("missing", b"a"), Err(BackendError::NotFound));
+        Ok(())
+    }
+}
@@ -0,0 +1,579 @@
+use std::f64::consts::PI;
+
+use super::{Backend, BackendError, RangeBy, ScoreBound, ZAddFlags, ZRangeSpec, ZSet};
+
+// positions are stored as sorted set scores: 52-bit geohashes that interleave 26 bits of
+// latitude (even bits) with 26 bits of longitude (odd bits), as in Redis
+const GEO_STEP_MAX: u32 = 26;
+const GEO_LAT_MIN: f64 = -85.051_128_78;
+const GEO_LAT_MAX: f64 = 85.051_128_78;
+const GEO_LON_MIN: f64 = -180.0;
+const GEO_LON_MAX: f64 = 180.0;
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+const MERCATOR_MAX: f64 = 20_037_726.37;
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Where a GEOSEARCH is centered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(String),
+    // longitude, latitude
+    LonLat(f64, f64),
+}
+
+/// The area a GEOSEARCH covers, in the query's unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    Radius(f64),
+    // width, height
+    Box(f64, f64),
+}
+
+/// A GEOSEARCH query. Distances, in the shape and in the results, are in `unit`s, given as a
+/// number of meters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    pub unit: f64,
+    // None leaves the results unsorted, Some(true) sorts them farthest first
+    pub desc: Option<bool>,
+    pub count: Option<usize>,
+    // with `count`, stops at the first `count` matches found instead of the nearest ones
+    pub any: bool,
+}
+
+/// A member found by GEOSEARCH.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoMatch {
+    pub member: String,
+    pub dist: f64,
+    pub hash: u64,
+    pub lon: f64,
+    pub lat: f64,
+}
+
+// a cell of the geohash grid at some precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GeoHash {
+    bits: u64,
+    step: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    lon_min: f64,
+    lon_max: f64,
+    lat_min: f64,
+    lat_max: f64,
+}
+
+// spreads the bits of v to the even positions of a u64
+fn spread(v: u32) -> u64 {
+    let mut x = v as u64;
+    x = (x | x << 16) & 0x0000_ffff_0000_ffff;
+    x = (x | x << 8) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x << 2) & 0x3333_3333_3333_3333;
+    (x | x << 1) & 0x5555_5555_5555_5555
+}
+
+// the reverse of `spread`: gathers the even bits of v
+fn squash(v: u64) -> u32 {
+    let mut x = v & 0x5555_5555_5555_5555;
+    x = (x | x >> 1) & 0x3333_3333_3333_3333;
+    x = (x | x >> 2) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | x >> 4) & 0x00ff_00ff_00ff_00ff;
+    x = (x | x >> 8) & 0x0000_ffff_0000_ffff;
+    (x | x >> 16) as u32
+}
+
+fn valid_lon_lat(lon: f64, lat: f64) -> bool {
+    (GEO_LON_MIN..=GEO_LON_MAX).contains(&lon) && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&lat)
+}
+
+fn encode(lon: f64, lat: f64, (lat_min, lat_max): (f64, f64), step: u32) -> GeoHash {
+    let cells = (1u64 << step) as f64;
+    let lat_offset = (lat - lat_min) / (lat_max - lat_min) * cells;
+    let lon_offset = (lon - GEO_LON_MIN) / (GEO_LON_MAX - GEO_LON_MIN) * cells;
+    GeoHash {
+        bits: spread(lat_offset as u32) | spread(lon_offset as u32) << 1,
+        step,
+    }
+}
+
+fn decode(hash: GeoHash) -> Area {
+    let cells = (1u64 << hash.step) as f64;
+    let lat = squash(hash.bits) as f64;
+    let lon = squash(hash.bits >> 1) as f64;
+    let (lat_scale, lon_scale) = (GEO_LAT_MAX - GEO_LAT_MIN, GEO_LON_MAX - GEO_LON_MIN);
+    Area {
+        lon_min: GEO_LON_MIN + lon / cells * lon_scale,
+        lon_max: GEO_LON_MIN + (lon + 1.0) / cells * lon_scale,
+        lat_min: GEO_LAT_MIN + lat / cells * lat_scale,
+        lat_max: GEO_LAT_MIN + (lat + 1.0) / cells * lat_scale,
+    }
+}
+
+/// The sorted set score of a position, None if it is outside the valid range.
+fn geo_score(lon: f64, lat: f64) -> Option<f64> {
+    valid_lon_lat(lon, lat)
+        .then(|| encode(lon, lat, (GEO_LAT_MIN, GEO_LAT_MAX), GEO_STEP_MAX).bits as f64)
+}
+
+/// The position a score stands for: the center of its geohash cell.
+fn geo_position(score: f64) -> (f64, f64) {
+    let area = decode(GeoHash {
+        bits: score as u64,
+        step: GEO_STEP_MAX,
+    });
+    let lon = ((area.lon_min + area.lon_max) / 2.0).clamp(GEO_LON_MIN, GEO_LON_MAX);
+    let lat = ((area.lat_min + area.lat_max) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+    (lon, lat)
+}
+
+// the standard 11 character geohash of a position, which covers latitudes -90 to 90
+fn geohash_string(lon: f64, lat: f64) -> String {
+    let hash = encode(lon, lat, (-90.0, 90.0), GEO_STEP_MAX);
+    (0..11)
+        .map(|i| {
+            // the 52 bits don't fill the last character
+            let index = match i {
+                10 => 0,
+                _ => (hash.bits >> (52 - (i + 1) * 5)) & 0x1f,
+            };
+            GEOHASH_ALPHABET[index as usize] as char
+        })
+        .collect()
+}
+
+fn deg_rad(deg: f64) -> f64 {
+    deg * PI / 180.0
+}
+
+fn rad_deg(rad: f64) -> f64 {
+    rad * 180.0 / PI
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (deg_rad(lat2) - deg_rad(lat1)).abs()
+}
+
+/// The haversine distance in meters between two positions.
+fn geo_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let v = ((deg_rad(lon2) - deg_rad(lon1)) / 2.0).sin();
+    if v == 0.0 {
+        return lat_distance(lat1, lat2);
+    }
+    let (lat1, lat2) = (deg_rad(lat1), deg_rad(lat2));
+    let u = ((lat2 - lat1) / 2.0).sin();
+    let a = u * u + lat1.cos() * lat2.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+impl GeoShape {
+    // half the width and half the height of the shape, in meters
+    fn half_extent(&self, unit: f64) -> (f64, f64) {
+        match *self {
+            GeoShape::Radius(r) => (r * unit, r * unit),
+            GeoShape::Box(w, h) => (w * unit / 2.0, h * unit / 2.0),
+        }
+    }
+
+    // the distance in meters from the center to a position, None if it lies outside
+    fn distance_within(
+        &self,
+        unit: f64,
+        (lon, lat): (f64, f64),
+        center: (f64, f64),
+    ) -> Option<f64> {
+        let (clon, clat) = center;
+        match *self {
+            GeoShape::Radius(r) => {
+                let dist = geo_distance(clon, clat, lon, lat);
+                (dist <= r * unit).then_some(dist)
+            }
+            GeoShape::Box(..) => {
+                // the latitude distance is cheaper, so it is checked first
+                let (half_width, half_height) = self.half_extent(unit);
+                if lat_distance(lat, clat) > half_height
+                    || geo_distance(lon, lat, clon, lat) > half_width
+                {
+                    return None;
+                }
+                Some(geo_distance(clon, clat, lon, lat))
+            }
+        }
+    }
+}
+
+// how precise the cells searched around a center must be to cover `range` meters
+fn estimate_steps(range: f64, lat: f64) -> u32 {
+    if range == 0.0 {
+        return GEO_STEP_MAX;
+    }
+    let (mut range, mut step) = (range, 1i32);
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    // make sure the range fits in most cases, and widen it towards the poles
+    step -= 2;
+    if lat.abs() > 66.0 {
+        step -= 1;
+        if lat.abs() > 80.0 {
+            step -= 1;
+        }
+    }
+    step.clamp(1, GEO_STEP_MAX as i32) as u32
+}
+
+// the cell containing the center followed by its neighbors, N, S, E, W, NE, NW, SE and SW,
+// leaving out the neighbors that can't overlap the search area
+fn search_cells(shape: &GeoShape, unit: f64, (lon, lat): (f64, f64)) -> Vec<GeoHash> {
+    let (half_width, half_height) = shape.half_extent(unit);
+    let lat_delta = rad_deg(half_height / EARTH_RADIUS_IN_METERS);
+    let lon_delta = |lat: f64| rad_deg(half_width / EARTH_RADIUS_IN_METERS / deg_rad(lat).cos());
+    // the edge nearer to the pole is the wider one
+    let lon_delta = match lat < 0.0 {
+        true => lon_delta(lat - lat_delta),
+        false => lon_delta(lat + lat_delta),
+    };
+    let bounds = Area {
+        lon_min: lon - lon_delta,
+        lon_max: lon + lon_delta,
+        lat_min: lat - lat_delta,
+        lat_max: lat + lat_delta,
+    };
+
+    let radius = match *shape {
+        GeoShape::Radius(r) => r * unit,
+        GeoShape::Box(..) => half_width.hypot(half_height),
+    };
+    let mut step = estimate_steps(radius, lat);
+    let lat_range = (GEO_LAT_MIN, GEO_LAT_MAX);
+    let mut cells = neighbors(encode(lon, lat, lat_range, step));
+    // the cells next to the center one may still be too small near the edges of the area
+    let [_, north, south, east, west, ..] = cells.map(decode);
+    if step > 1
+        && (north.lat_max < bounds.lat_max
+            || south.lat_min > bounds.lat_min
+            || east.lon_max < bounds.lon_max
+            || west.lon_min > bounds.lon_min)
+    {
+        step -= 1;
+        cells = neighbors(encode(lon, lat, lat_range, step));
+    }
+
+    let area = decode(cells[0]);
+    let mut useless = [false; 9];
+    if step >= 2 {
+        let mut exclude = |indexes: [usize; 3]| indexes.iter().for_each(|&i| useless[i] = true);
+        if area.lat_min < bounds.lat_min {
+            exclude([2, 7, 8]);
+        }
+        if area.lat_max > bounds.lat_max {
+            exclude([1, 5, 6]);
+        }
+        if area.lon_min < bounds.lon_min {
+            exclude([4, 6, 8]);
+        }
+        if area.lon_max > bounds.lon_max {
+            exclude([3, 5, 7]);
+        }
+    }
+    cells
+        .into_iter()
+        .zip(useless)
+        .filter_map(|(cell, useless)| (!useless).then_some(cell))
+        .collect()
+}
+
+fn neighbors(hash: GeoHash) -> [GeoHash; 9] {
+    let mask = (1u64 << hash.step) - 1;
+    let (lat, lon) = (squash(hash.bits) as u64, squash(hash.bits >> 1) as u64);
+    let cell = |dlat: i64, dlon: i64| GeoHash {
+        bits: spread((lat.wrapping_add_signed(dlat) & mask) as u32)
+            | spread((lon.wrapping_add_signed(dlon) & mask) as u32) << 1,
+        step: hash.step,
+    };
+    [
+        hash,
+        cell(1, 0),
+        cell(-1, 0),
+        cell(0, 1),
+        cell(0, -1),
+        cell(1, 1),
+        cell(1, -1),
+        cell(-1, 1),
+        cell(-1, -1),
+    ]
+}
+
+impl ZSet {
+    // the members whose score falls in a cell
+    fn in_cell(&self, cell: GeoHash) -> Vec<(String, f64)> {
+        let shift = 52 - cell.step * 2;
+        let min = ScoreBound {
+            value: (cell.bits << shift) as f64,
+            exclusive: false,
+        };
+        let max = ScoreBound {
+            value: ((cell.bits + 1) << shift) as f64,
+            exclusive: true,
+        };
+        self.range(&ZRangeSpec::new(RangeBy::Score(min, max)))
+    }
+
+    fn geosearch(&self, query: &GeoQuery) -> Result<Vec<GeoMatch>, BackendError> {
+        let center = match &query.origin {
+            GeoOrigin::LonLat(lon, lat) => (*lon, *lat),
+            GeoOrigin::Member(member) => {
+                geo_position(self.score(member).ok_or(BackendError::GeoMemberNotFound)?)
+            }
+        };
+        let limit = match query.any {
+            true => query.count.unwrap_or(usize::MAX),
+            false => usize::MAX,
+        };
+        let mut matches = Vec::new();
+        let mut last = None;
+        for cell in search_cells(&query.shape, query.unit, center) {
+            // with huge areas, neighboring cells can be the same
+            if last == Some(cell) {
+                continue;
+            }
+            last = Some(cell);
+            for (member, score) in self.in_cell(cell) {
+                let (lon, lat) = geo_position(score);
+                let Some(dist) = query.shape.distance_within(query.unit, (lon, lat), center) else {
+                    continue;
+                };
+                matches.push(GeoMatch {
+                    member,
+                    dist: dist / query.unit,
+                    hash: score as u64,
+                    lon,
+                    lat,
+                });
+                if matches.len() == limit {
+                    break;
+                }
+            }
+            if matches.len() == limit {
+                break;
+            }
+        }
+
+        // COUNT without ANY wants the nearest ones
+        let desc = match query.count {
+            Some(_) if !query.any => query.desc.or(Some(false)),
+            _ => query.desc,
+        };
+        if let Some(desc) = desc {
+            matches.sort_by(|a, b| a.dist.total_cmp(&b.dist));
+            if desc {
+                matches.reverse();
+            }
+        }
+        matches.truncate(query.count.unwrap_or(usize::MAX));
+        Ok(matches)
+    }
+}
+
+impl Backend {
+    /// Adds or updates members at positions given as (longitude, latitude, member), all of
+    /// which must be valid. Returns the members added and the members added or moved.
+    pub fn geoadd(
+        &self,
+        key: String,
+        items: Vec<(f64, f64, String)>,
+        flags: ZAddFlags,
+    ) -> Result<(usize, usize), BackendError> {
+        let members = items
+            .into_iter()
+            .map(|(lon, lat, member)| {
+                geo_score(lon, lat)
+                    .map(|score| (score, member))
+                    .ok_or_else(|| BackendError::InvalidLonLat(format!("{:.6},{:.6}", lon, lat)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.zadd(key, members, flags))
+    }
+
+    pub fn geopos(&self, key: &str, members: &[String]) -> Vec<Option<(f64, f64)>> {
+        self.zmscore(key, members)
+            .into_iter()
+            .map(|score| score.map(geo_position))
+            .collect()
+    }
+
+    // the distance in meters between two members, None if either is missing
+    pub fn geodist(&self, key: &str, member1: &str, member2: &str) -> Option<f64> {
+        let zset = self.zset.get(key)?;
+        let (lon1, lat1) = geo_position(zset.score(member1)?);
+        let (lon2, lat2) = geo_position(zset.score(member2)?);
+        Some(geo_distance(lon1, lat1, lon2, lat2))
+    }
+
+    pub fn geohash(&self, key: &str, members: &[String]) -> Vec<Option<String>> {
+        self.zmscore(key, members)
+            .into_iter()
+            .map(|score| {
+                score.map(|score| {
+                    let (lon, lat) = geo_position(score);
+                    geohash_string(lon, lat)
+                })
+            })
+            .collect()
+    }
+
+    /// The members within the query's area, looked up in the cells of the geohash grid around
+    /// its center.
+    pub fn geosearch(&self, key: &str, query: &GeoQuery) -> Result<Vec<GeoMatch>, BackendError> {
+        match self.zset.get(key) {
+            Some(zset) => zset.geosearch(query),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Stores the result of a GEOSEARCH on `source` at `destination`, replacing it, and
+    /// returns its size. Members keep their positions, or get their distance as score with
+    /// `store_dist`. An empty result deletes `destination`.
+    pub fn geosearchstore(
+        &self,
+        destination: &str,
+        source: &str,
+        query: &GeoQuery,
+        store_dist: bool,
+    ) -> Result<usize, BackendError> {
+        let _guard = self.exclusive_lock();
+        let matches = self.geosearch(source, query)?;
+        let len = matches.len();
+        if matches.is_empty() {
+            self.zset.remove(destination);
+        } else {
+            let mut zset = ZSet::default();
+            for m in matches {
+                let score = if store_dist { m.dist } else { m.hash as f64 };
+                zset.insert(m.member, score);
+            }
+            self.zset.insert(destination.to_string(), zset);
+            self.signal_ready(destination);
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_sicily(backend: &Backend) {
+        let items = vec![
+            (13.361389, 38.115556, "Palermo".to_string()),
+            (15.087269, 37.502669, "Catania".to_string()),
+            (12.758489, 38.788135, "edge1".to_string()),
+            (2.349014, 48.864716, "Paris".to_string()),
+        ];
+        let added = backend.geoadd("Sicily".to_string(), items, ZAddFlags::default());
+        assert_eq!(added, Ok((4, 4)));
+    }
+
+    fn query(origin: GeoOrigin, shape: GeoShape, unit: f64) -> GeoQuery {
+        GeoQuery {
+            origin,
+            shape,
+            unit,
+            desc: Some(false),
+            count: None,
+            any: false,
+        }
+    }
+
+    #[test]
+    fn test_geohash_encoding() {
+        // the values Redis stores and reports for Palermo
+        let score = geo_score(13.361389, 38.115556).unwrap();
+        assert_eq!(score, 3479099956230698.0);
+        let (lon, lat) = geo_position(score);
+        assert!((lon - 13.361_389_338_970_184).abs() < 1e-12);
+        assert!((lat - 38.115_556_395_496_3).abs() < 1e-12);
+        assert_eq!(geohash_string(lon, lat), "sqc8b49rny0");
+
+        assert_eq!(geo_score(181.0, 0.0), None);
+        assert_eq!(geo_score(0.0, 86.0), None);
+        assert_eq!(squash(spread(0x3ff_ffff)), 0x3ff_ffff);
+        assert_eq!(estimate_steps(200_000.0, 38.0), 6);
+    }
+
+    #[test]
+    fn test_geodist_and_geopos() {
+        let backend = Backend::new();
+        add_sicily(&backend);
+        let dist = backend.geodist("Sicily", "Palermo", "Catania").unwrap();
+        assert!((dist - 166274.1516).abs() < 1e-3, "{}", dist);
+        assert_eq!(backend.geodist("Sicily", "Palermo", "nope"), None);
+        let pos = backend.geopos("Sicily", &["Catania".to_string(), "nope".to_string()]);
+        assert!(pos[0].is_some_and(|(lon, _)| (lon - 15.087269).abs() < 1e-5));
+        assert_eq!(pos[1], None);
+        assert_eq!(
+            backend.geoadd(
+                "Sicily".to_string(),
+                vec![(0.0, 90.0, "pole".to_string())],
+                ZAddFlags::default()
+            ),
+            Err(BackendError::InvalidLonLat(
+                "0.000000,90.000000".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_geosearch() {
+        let backend = Backend::new();
+        add_sicily(&backend);
+        let center = GeoOrigin::LonLat(15.0, 37.0);
+        let found = backend
+            .geosearch(
+                "Sicily",
+                &query(center.clone(), GeoShape::Radius(200.0), 1000.0),
+            )
+            .unwrap();
+        let members = found.iter().map(|m| m.member.as_str()).collect::<Vec<_>>();
+        assert_eq!(members, ["Catania", "Palermo"]);
+        assert!((found[0].dist - 56.4413).abs() < 1e-3, "{}", found[0].dist);
+
+        let found = backend
+            .geosearch(
+                "Sicily",
+                &query(center.clone(), GeoShape::Box(400.0, 400.0), 1000.0),
+            )
+            .unwrap();
+        let members = found.iter().map(|m| m.member.as_str()).collect::<Vec<_>>();
+        assert_eq!(members, ["Catania", "Palermo", "edge1"]);
+
+        // the whole planet, from a member
+        let mut q = query(
+            GeoOrigin::Member("Paris".to_string()),
+            GeoShape::Radius(20_000.0),
+            1000.0,
+        );
+        q.desc = Some(true);
+        q.count = Some(2);
+        let found = backend.geosearch("Sicily", &q).unwrap();
+        let members = found.iter().map(|m| m.member.as_str()).collect::<Vec<_>>();
+        assert_eq!(members, ["Catania", "Palermo"]);
+
+        q.origin = GeoOrigin::Member("nope".to_string());
+        assert_eq!(
+            backend.geosearch("Sicily", &q),
+            Err(BackendError::GeoMemberNotFound)
+        );
+
+        let q = query(center, GeoShape::Radius(200.0), 1000.0);
+        assert_eq!(backend.geosearchstore("dest", "Sicily", &q, true), Ok(2));
+        let dist = backend.zscore("dest", "Catania").unwrap();
+        assert!((dist - 56.4413).abs() < 1e-3);
+    }
+}
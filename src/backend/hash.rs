@@ -0,0 +1,879 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::{DefaultHasher, Hash as _, Hasher},
+};
+
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use crate::{BulkString, RespFrame};
+
+use super::{
+    frame_heap_size, frame_to_string,
+    listpack::{self, Listpack},
+    now_ms, Backend, BackendError, Config,
+};
+
+/// A hash value. Small hashes are packed into a `Listpack` and converted to a hashtable for
+/// good once they outgrow the `hash-max-listpack-*` limits. Fields may carry an expiration
+/// time; expired fields are invisible to readers and get reclaimed by
+/// `Backend::sweep_expired_fields`.
+#[derive(Debug, Clone, Default)]
+pub struct Hash {
+    fields: Fields,
+    // unix time in milliseconds at which a field expires
+    expires: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone)]
+enum Fields {
+    Listpack(Listpack),
+    // the fields, plus their scan positions in order so that HSCAN can resume where it left off
+    HashTable(HashMap<String, RespFrame>, BTreeSet<(u64, String)>),
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Fields::Listpack(Listpack::new())
+    }
+}
+
+impl Fields {
+    fn get(&self, field: &str) -> Option<RespFrame> {
+        match self {
+            Fields::Listpack(lp) => lp.get(field),
+            Fields::HashTable(map, _) => map.get(field).cloned(),
+        }
+    }
+
+    fn contains(&self, field: &str) -> bool {
+        match self {
+            Fields::Listpack(lp) => lp.contains(field),
+            Fields::HashTable(map, _) => map.contains_key(field),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Fields::Listpack(lp) => lp.len(),
+            Fields::HashTable(map, _) => map.len(),
+        }
+    }
+
+    fn keys(&self) -> Box<dyn Iterator<Item = &str> + '_> {
+        match self {
+            Fields::Listpack(lp) => Box::new(lp.keys()),
+            Fields::HashTable(map, _) => Box::new(map.keys().map(|k| k.as_str())),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&str, RespFrame)> + '_> {
+        match self {
+            Fields::Listpack(lp) => Box::new(lp.iter()),
+            Fields::HashTable(map, _) => Box::new(map.iter().map(|(k, v)| (k.as_str(), v.clone()))),
+        }
+    }
+
+    // fields at or after scan position `cursor`, in position order
+    fn scan_from(&self, cursor: u64) -> Box<dyn Iterator<Item = (u64, &str)> + '_> {
+        match self {
+            Fields::Listpack(lp) => {
+                // a listpack is small enough to sort on every call
+                let mut fields = lp
+                    .keys()
+                    .map(|k| (scan_position(k), k))
+                    .filter(|(pos, _)| *pos >= cursor)
+                    .collect::<Vec<_>>();
+                fields.sort_unstable();
+                Box::new(fields.into_iter())
+            }
+            Fields::HashTable(_, positions) => Box::new(
+                positions
+                    .range((cursor, String::new())..)
+                    .map(|(pos, k)| (*pos, k.as_str())),
+            ),
+        }
+    }
+
+    fn insert(&mut self, field: String, value: RespFrame, config: &Config) {
+        if let Fields::Listpack(lp) = self {
+            let max_value = config.hash_max_listpack_value();
+            let fits = field.len() <= max_value
+                && listpack::value_len(&value) <= max_value
+                && (lp.len() < config.hash_max_listpack_entries() || lp.contains(&field));
+            if fits {
+                lp.insert(&field, &value);
+                return;
+            }
+            self.convert_to_hashtable();
+        }
+        if let Fields::HashTable(map, positions) = self {
+            if !map.contains_key(&field) {
+                positions.insert((scan_position(&field), field.clone()));
+            }
+            map.insert(field, value);
+        }
+    }
+
+    fn remove(&mut self, field: &str) -> bool {
+        match self {
+            Fields::Listpack(lp) => lp.remove(field),
+            Fields::HashTable(map, positions) => {
+                positions.remove(&(scan_position(field), field.to_string()));
+                map.remove(field).is_some()
+            }
+        }
+    }
+
+    fn convert_to_hashtable(&mut self) {
+        if let Fields::Listpack(lp) = self {
+            let map = lp
+                .iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect::<HashMap<_, _>>();
+            let positions = map.keys().map(|k| (scan_position(k), k.clone())).collect();
+            *self = Fields::HashTable(map, positions);
+        }
+    }
+}
+
+/// Conditions accepted by HEXPIRE and friends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    Nx,
+    Xx,
+    Gt,
+    Lt,
+}
+
+/// What HGETEX/HSETEX should do with the TTL of the fields they touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldExpiry {
+    Keep,
+    Persist,
+    // unix time in milliseconds, may be in the past
+    At(i64),
+}
+
+impl Hash {
+    fn is_expired(&self, field: &str, now: u64) -> bool {
+        self.expires.get(field).is_some_and(|&at| at <= now)
+    }
+
+    pub fn get(&self, field: &str) -> Option<RespFrame> {
+        if self.is_expired(field, now_ms()) {
+            return None;
+        }
+        self.fields.get(field)
+    }
+
+    pub fn contains_key(&self, field: &str) -> bool {
+        !self.is_expired(field, now_ms()) && self.fields.contains(field)
+    }
+
+    pub fn len(&self) -> usize {
+        if self.expires.is_empty() {
+            return self.fields.len();
+        }
+        let now = now_ms();
+        self.fields
+            .keys()
+            .filter(|k| !self.is_expired(k, now))
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        let now = now_ms();
+        self.fields.keys().filter(move |k| !self.is_expired(k, now))
+    }
+
+    // live fields at or after scan position `cursor`, in position order
+    fn scan_from(&self, cursor: u64) -> impl Iterator<Item = (u64, &str)> {
+        let now = now_ms();
+        self.fields
+            .scan_from(cursor)
+            .filter(move |(_, k)| !self.is_expired(k, now))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, RespFrame)> {
+        let now = now_ms();
+        self.fields
+            .iter()
+            .filter(move |(k, _)| !self.is_expired(k, now))
+    }
+
+    // like HSET, overwriting a field clears its TTL; returns true if the field is new
+    pub fn insert(&mut self, field: String, value: RespFrame, config: &Config) -> bool {
+        let new = !self.contains_key(&field);
+        self.expires.remove(&field);
+        self.fields.insert(field, value, config);
+        new
+    }
+
+    fn insert_keep_ttl(&mut self, field: String, value: RespFrame, config: &Config) {
+        if self.is_expired(&field, now_ms()) {
+            self.expires.remove(&field);
+        }
+        self.fields.insert(field, value, config);
+    }
+
+    // returns true if a live field was removed
+    pub fn remove(&mut self, field: &str) -> bool {
+        let live = self.contains_key(field);
+        self.expires.remove(field);
+        self.fields.remove(field) && live
+    }
+
+    pub fn expire_at(&self, field: &str) -> Option<u64> {
+        self.expires.get(field).copied()
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self.fields {
+            Fields::Listpack(_) if self.expires.is_empty() => "listpack",
+            Fields::Listpack(_) => "listpackex",
+            Fields::HashTable(..) => "hashtable",
+        }
+    }
+
+    // estimated bytes used by the hash, see `Backend::memory_usage`
+    pub fn memory_usage(&self) -> usize {
+        let fields = match &self.fields {
+            Fields::Listpack(lp) => lp.blob_len(),
+            Fields::HashTable(map, positions) => {
+                // one control byte per bucket on top of the entry itself, and the field name
+                // again in the scan order
+                map.capacity() * (size_of::<(String, RespFrame)>() + 1)
+                    + positions.len() * size_of::<(u64, String)>()
+                    + map
+                        .iter()
+                        .map(|(k, v)| 2 * k.len() + frame_heap_size(v))
+                        .sum::<usize>()
+            }
+        };
+        let expires = self.expires.capacity() * (size_of::<(String, u64)>() + 1)
+            + self.expires.keys().map(|k| k.len()).sum::<usize>();
+        size_of::<Hash>() + fields + expires
+    }
+
+    fn has_expired(&self, now: u64) -> bool {
+        self.expires.values().any(|&at| at <= now)
+    }
+
+    fn remove_expired(&mut self, now: u64) -> usize {
+        let expired = self
+            .expires
+            .iter()
+            .filter(|(_, &at)| at <= now)
+            .map(|(k, _)| k.clone())
+            .collect::<Vec<_>>();
+        for field in expired.iter() {
+            self.expires.remove(field);
+            self.fields.remove(field);
+        }
+        expired.len()
+    }
+
+    fn apply_expiry(&mut self, field: &str, expiry: FieldExpiry, now: u64) {
+        match expiry {
+            FieldExpiry::Keep => {}
+            FieldExpiry::Persist => {
+                self.expires.remove(field);
+            }
+            FieldExpiry::At(at) if at <= now as i64 => {
+                self.remove(field);
+            }
+            FieldExpiry::At(at) => {
+                self.expires.insert(field.to_string(), at as u64);
+            }
+        }
+    }
+}
+
+impl Backend {
+    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
+        self.hmap.get(key).and_then(|v| v.get(field))
+    }
+
+    // returns true if the field is new
+    pub fn hset(&self, key: String, field: String, value: RespFrame) -> bool {
+        let mut hash = self.hmap.entry(key.clone()).or_default();
+        let new = hash.insert(field, value, &self.config);
+        drop(hash);
+        self.index_hash(&key);
+        new
+    }
+
+    // all fields are set under one entry lock, so readers never see part of them; returns the
+    // number of new fields
+    pub fn hset_multi(&self, key: String, fields: Vec<(String, RespFrame)>) -> i64 {
+        let mut hash = self.hmap.entry(key.clone()).or_default();
+        let mut added = 0;
+        for (field, value) in fields {
+            if hash.insert(field, value, &self.config) {
+                added += 1;
+            }
+        }
+        drop(hash);
+        self.index_hash(&key);
+        added
+    }
+
+    pub fn hsetnx(&self, key: String, field: String, value: RespFrame) -> bool {
+        let mut hash = self.hmap.entry(key.clone()).or_default();
+        if hash.contains_key(&field) {
+            return false;
+        }
+        let new = hash.insert(field, value, &self.config);
+        drop(hash);
+        self.index_hash(&key);
+        new
+    }
+
+    pub fn hgetall(&self, key: &str) -> Option<HashMap<String, RespFrame>> {
+        self.hmap
+            .get(key)
+            .map(|v| v.iter().map(|(k, v)| (k.to_owned(), v)).collect())
+    }
+
+    // the whole read-modify-write runs under the hash's entry lock
+    pub fn hincrby(&self, key: String, field: String, increment: i64) -> Result<i64, BackendError> {
+        let mut hash = self.hmap.entry(key.clone()).or_default();
+        let value = match hash.get(&field) {
+            Some(current) => frame_to_string(&current)
+                .and_then(|s| s.parse::<i64>().ok())
+                .ok_or(BackendError::HashValueNotInteger)?
+                .checked_add(increment)
+                .ok_or(BackendError::Overflow)?,
+            None => increment,
+        };
+        hash.insert_keep_ttl(
+            field,
+            BulkString::from(value.to_string()).into(),
+            &self.config,
+        );
+        drop(hash);
+        self.index_hash(&key);
+        Ok(value)
+    }
+
+    pub fn hincrbyfloat(
+        &self,
+        key: String,
+        field: String,
+        increment: f64,
+    ) -> Result<String, BackendError> {
+        if !increment.is_finite() {
+            return Err(BackendError::NanOrInfinity);
+        }
+        let mut hash = self.hmap.entry(key.clone()).or_default();
+        let value = match hash.get(&field) {
+            Some(current) => {
+                frame_to_string(&current)
+                    .and_then(|s| s.parse::<f64>().ok())
+                    .filter(|v| !v.is_nan())
+                    .ok_or(BackendError::HashValueNotFloat)?
+                    + increment
+            }
+            None => increment,
+        };
+        if !value.is_finite() {
+            return Err(BackendError::NanOrInfinity);
+        }
+        let value = format_float(value);
+        hash.insert_keep_ttl(field, BulkString::from(value.clone()).into(), &self.config);
+        drop(hash);
+        self.index_hash(&key);
+        Ok(value)
+    }
+
+    // removes the hash itself once its last field is gone
+    pub fn hdel(&self, key: &str, fields: &[String]) -> i64 {
+        let deleted = match self.hmap.get_mut(key) {
+            Some(mut hash) => fields.iter().filter(|f| hash.remove(f)).count(),
+            None => return 0,
+        };
+        self.hmap.remove_if(key, |_, v| v.is_empty());
+        self.index_hash(key);
+        deleted as i64
+    }
+
+    pub fn hexists(&self, key: &str, field: &str) -> bool {
+        self.hmap.get(key).is_some_and(|v| v.contains_key(field))
+    }
+
+    pub fn hlen(&self, key: &str) -> usize {
+        self.hmap.get(key).map_or(0, |v| v.len())
+    }
+
+    pub fn hkeys(&self, key: &str) -> Vec<String> {
+        self.hmap
+            .get(key)
+            .map_or(vec![], |v| v.keys().map(|k| k.to_owned()).collect())
+    }
+
+    pub fn hvals(&self, key: &str) -> Vec<RespFrame> {
+        self.hmap
+            .get(key)
+            .map_or(vec![], |v| v.iter().map(|(_, v)| v).collect())
+    }
+
+    // per field: -2 no such field, 0 condition not met, 1 expiry set, 2 field deleted
+    pub fn hexpire(
+        &self,
+        key: &str,
+        fields: &[String],
+        at: i64,
+        condition: Option<ExpireCondition>,
+    ) -> Vec<i64> {
+        let now = now_ms();
+        let ret = match self.hmap.get_mut(key) {
+            Some(mut hash) => fields
+                .iter()
+                .map(|field| {
+                    if !hash.contains_key(field) {
+                        return -2;
+                    }
+                    let current = hash.expire_at(field).map(|v| v as i64);
+                    let ok = match condition {
+                        None => true,
+                        Some(ExpireCondition::Nx) => current.is_none(),
+                        Some(ExpireCondition::Xx) => current.is_some(),
+                        Some(ExpireCondition::Gt) => current.is_some_and(|c| at > c),
+                        Some(ExpireCondition::Lt) => current.is_none_or(|c| at < c),
+                    };
+                    if !ok {
+                        return 0;
+                    }
+                    hash.apply_expiry(field, FieldExpiry::At(at), now);
+                    if at <= now as i64 {
+                        2
+                    } else {
+                        1
+                    }
+                })
+                .collect(),
+            None => return vec![-2; fields.len()],
+        };
+        self.hmap.remove_if(key, |_, v| v.is_empty());
+        self.track_field_ttls(key);
+        self.index_hash(key);
+        ret
+    }
+
+    // per field: -2 no such field, -1 no expiry, otherwise the unix time in milliseconds
+    pub fn hexpiretime(&self, key: &str, fields: &[String]) -> Vec<i64> {
+        let hash = self.hmap.get(key);
+        fields
+            .iter()
+            .map(|field| match hash {
+                Some(ref hash) if hash.contains_key(field) => {
+                    hash.expire_at(field).map_or(-1, |at| at as i64)
+                }
+                _ => -2,
+            })
+            .collect()
+    }
+
+    // per field: -2 no such field, -1 no expiry, 1 expiry removed
+    pub fn hpersist(&self, key: &str, fields: &[String]) -> Vec<i64> {
+        let Some(mut hash) = self.hmap.get_mut(key) else {
+            return vec![-2; fields.len()];
+        };
+        fields
+            .iter()
+            .map(|field| {
+                if !hash.contains_key(field) {
+                    -2
+                } else if hash.expires.remove(field).is_some() {
+                    1
+                } else {
+                    -1
+                }
+            })
+            .collect()
+    }
+
+    pub fn hgetex(
+        &self,
+        key: &str,
+        fields: &[String],
+        expiry: FieldExpiry,
+    ) -> Vec<Option<RespFrame>> {
+        let now = now_ms();
+        let ret = match self.hmap.get_mut(key) {
+            Some(mut hash) => fields
+                .iter()
+                .map(|field| {
+                    let value = hash.get(field);
+                    if value.is_some() {
+                        hash.apply_expiry(field, expiry, now);
+                    }
+                    value
+                })
+                .collect(),
+            None => return vec![None; fields.len()],
+        };
+        self.hmap.remove_if(key, |_, v| v.is_empty());
+        self.track_field_ttls(key);
+        self.index_hash(key);
+        ret
+    }
+
+    // `only_if` is Some(true) for FXX (all fields must exist) and Some(false) for FNX (none may
+    // exist); returns false if the condition failed and nothing was set
+    pub fn hsetex(
+        &self,
+        key: String,
+        fields: Vec<(String, RespFrame)>,
+        only_if: Option<bool>,
+        expiry: FieldExpiry,
+    ) -> bool {
+        let now = now_ms();
+        let mut hash = self.hmap.entry(key.clone()).or_default();
+        if let Some(exists) = only_if {
+            if !fields.iter().all(|(f, _)| hash.contains_key(f) == exists) {
+                drop(hash);
+                self.hmap.remove_if(&key, |_, v| v.is_empty());
+                return false;
+            }
+        }
+        for (field, value) in fields {
+            match expiry {
+                FieldExpiry::Keep => hash.insert_keep_ttl(field, value, &self.config),
+                _ => {
+                    hash.insert(field.clone(), value, &self.config);
+                    hash.apply_expiry(&field, expiry, now);
+                }
+            }
+        }
+        drop(hash);
+        self.hmap.remove_if(&key, |_, v| v.is_empty());
+        self.track_field_ttls(&key);
+        self.index_hash(&key);
+        true
+    }
+
+    /// Returns up to `count` fields whose scan position is at or after `cursor`, along with the
+    /// cursor to continue from (0 once the scan is complete). A field's position is a hash of
+    /// its name, so fields that exist for the whole scan are returned no matter how the hash is
+    /// modified between calls.
+    pub fn hscan(&self, key: &str, cursor: u64, count: usize) -> (u64, Vec<(String, RespFrame)>) {
+        let Some(hash) = self.hmap.get(key) else {
+            return (0, vec![]);
+        };
+        let count = count.max(1);
+        let mut fields = hash.scan_from(cursor).peekable();
+        let mut ret = vec![];
+        while let Some((pos, field)) = fields.next() {
+            if let Some(value) = hash.fields.get(field) {
+                ret.push((field.to_owned(), value));
+            }
+            // fields sharing the boundary position must be returned together
+            if ret.len() >= count && fields.peek().is_none_or(|(next, _)| *next != pos) {
+                break;
+            }
+        }
+        let next = fields.peek().map_or(0, |(pos, _)| *pos);
+        (next, ret)
+    }
+
+    // a positive count returns distinct fields, a negative one may repeat them
+    pub fn hrandfield(&self, key: &str, count: i64) -> Vec<(String, RespFrame)> {
+        let Some(hash) = self.hmap.get(key) else {
+            return vec![];
+        };
+        let mut rng = rand::thread_rng();
+        let picked = if count >= 0 {
+            let count = (count as usize).min(hash.len());
+            hash.keys().choose_multiple(&mut rng, count)
+        } else {
+            let all = hash.keys().collect::<Vec<_>>();
+            (0..count.unsigned_abs())
+                .filter_map(|_| all.choose(&mut rng).copied())
+                .collect()
+        };
+        picked
+            .into_iter()
+            .filter_map(|k| hash.get(k).map(|v| (k.to_owned(), v)))
+            .collect()
+    }
+
+    // called after setting field TTLs on a hash, so that the sweeper visits it; the hash
+    // guard must already be released
+    fn track_field_ttls(&self, key: &str) {
+        self.hash_ttl_keys.insert(key.to_string());
+    }
+
+    /// Removes expired hash fields, dropping hashes that end up empty. Only hashes that had
+    /// field TTLs set are visited. Returns the number of fields removed.
+    pub fn sweep_expired_fields(&self) -> usize {
+        let now = now_ms();
+        let keys = self
+            .hash_ttl_keys
+            .iter()
+            .map(|k| k.key().clone())
+            .collect::<Vec<_>>();
+        let mut removed = 0;
+        for key in keys {
+            let swept = match self.hmap.get_mut(&key) {
+                Some(mut hash) if hash.has_expired(now) => hash.remove_expired(now),
+                _ => 0,
+            };
+            if swept > 0 {
+                removed += swept;
+                self.hmap.remove_if(&key, |_, v| v.is_empty());
+                self.index_hash(&key);
+            }
+            // checked while holding the set's lock, so a TTL set in the meantime re-adds the key
+            // only after this removal
+            self.hash_ttl_keys.remove_if(&key, |_| {
+                self.hmap
+                    .get(&key)
+                    .is_none_or(|hash| hash.expires.is_empty())
+            });
+        }
+        removed
+    }
+}
+
+// Formats an HINCRBYFLOAT result like Redis' `%.17Lg`. Redis adds in long doubles, whose
+// extra digits keep 0.1 + 0.2 at 0.3; an f64 sum is only good to about an ulp, so the 15 digit
+// rendering is used whenever it lies that close.
+fn format_float(value: f64) -> String {
+    let short = format_significant(value, 15);
+    let ulp = f64::from_bits(value.abs().to_bits() + 1) - value.abs();
+    match short.parse::<f64>() {
+        Ok(v) if (v - value).abs() <= ulp => short,
+        _ => format_significant(value, 17),
+    }
+}
+
+// printf's `%.{digits}g`: fixed notation unless the exponent is out of range, trailing zeros
+// removed
+fn format_significant(value: f64, digits: usize) -> String {
+    let trim = |s: &str| {
+        if s.contains('.') {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            s.to_string()
+        }
+    };
+    let sci = format!("{:.*e}", digits - 1, value);
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let exp = exp.parse::<i32>().unwrap_or(0);
+    if exp < -4 || exp >= digits as i32 {
+        let sign = if exp < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", trim(mantissa), sign, exp.abs())
+    } else {
+        trim(&format!("{:.*}", (digits as i32 - 1 - exp) as usize, value))
+    }
+}
+
+fn scan_position(field: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    field.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_encoding_conversion() {
+        let backend = Backend::new();
+        backend
+            .config
+            .set("hash-max-listpack-entries", "2")
+            .unwrap();
+        backend.hset("map".to_string(), "a".to_string(), b"1".into());
+        backend.hset("map".to_string(), "b".to_string(), b"2".into());
+        backend.hset("map".to_string(), "a".to_string(), b"3".into());
+        assert_eq!(backend.object_encoding("map"), Some("listpack"));
+        assert_eq!(
+            backend.hincrby("map".to_string(), "a".to_string(), 1),
+            Ok(4)
+        );
+
+        backend.hexpire("map", &["b".to_string()], now_ms() as i64 + 100_000, None);
+        assert_eq!(backend.object_encoding("map"), Some("listpackex"));
+
+        backend.hset("map".to_string(), "c".to_string(), b"5".into());
+        assert_eq!(backend.object_encoding("map"), Some("hashtable"));
+        assert_eq!(backend.hget("map", "a"), Some(BulkString::from("4").into()));
+        assert_eq!(backend.hlen("map"), 3);
+
+        // a value over hash-max-listpack-value converts right away
+        let long = BulkString::from("x".repeat(65));
+        backend.hset("other".to_string(), "f".to_string(), long.clone().into());
+        assert_eq!(backend.object_encoding("other"), Some("hashtable"));
+        assert_eq!(backend.hget("other", "f"), Some(long.into()));
+    }
+
+    #[test]
+    fn test_hset_multi() {
+        let backend = Backend::new();
+        backend.hset("map".to_string(), "a".to_string(), b"1".into());
+        let fields = vec![
+            ("a".to_string(), b"2".into()),
+            ("b".to_string(), b"3".into()),
+            ("c".to_string(), b"4".into()),
+        ];
+        assert_eq!(backend.hset_multi("map".to_string(), fields), 2);
+        assert_eq!(backend.hget("map", "a"), Some(b"2".into()));
+        assert_eq!(backend.hlen("map"), 3);
+    }
+
+    #[test]
+    fn test_hash_field_expiration() {
+        let backend = Backend::new();
+        backend.hset("map".to_string(), "a".to_string(), b"1".into());
+        backend.hset("map".to_string(), "b".to_string(), b"2".into());
+        let fields = ["a".to_string(), "b".to_string(), "c".to_string()];
+
+        let future = now_ms() as i64 + 100_000;
+        let ret = backend.hexpire("map", &fields[..1], future, Some(ExpireCondition::Nx));
+        assert_eq!(ret, vec![1]);
+        let ret = backend.hexpire("map", &fields, future + 1, Some(ExpireCondition::Gt));
+        assert_eq!(ret, vec![1, 0, -2]);
+        let ret = backend.hexpire("map", &fields[1..2], future, Some(ExpireCondition::Lt));
+        assert_eq!(ret, vec![1]);
+        assert_eq!(
+            backend.hexpiretime("map", &fields),
+            vec![future + 1, future, -2]
+        );
+        assert_eq!(backend.hpersist("map", &fields), vec![1, 1, -2]);
+        assert_eq!(backend.hexpiretime("map", &fields), vec![-1, -1, -2]);
+
+        // an expiry in the past deletes the field right away
+        assert_eq!(backend.hexpire("map", &fields[..1], 0, None), vec![2]);
+        assert_eq!(backend.hget("map", "a"), None);
+        assert_eq!(backend.hlen("map"), 1);
+    }
+
+    #[test]
+    fn test_expired_fields_are_invisible_and_swept() {
+        let backend = Backend::new();
+        backend.hset("map".to_string(), "a".to_string(), b"1".into());
+        backend.hset("map".to_string(), "b".to_string(), b"2".into());
+        backend
+            .hmap
+            .get_mut("map")
+            .unwrap()
+            .expires
+            .insert("a".to_string(), now_ms() - 1);
+        backend.track_field_ttls("map");
+
+        assert_eq!(backend.hget("map", "a"), None);
+        assert_eq!(backend.hkeys("map"), vec!["b".to_string()]);
+        assert_eq!(backend.sweep_expired_fields(), 1);
+        assert_eq!(backend.hmap.get("map").unwrap().fields.len(), 1);
+
+        backend
+            .hmap
+            .get_mut("map")
+            .unwrap()
+            .expires
+            .insert("b".to_string(), now_ms() - 1);
+        backend.track_field_ttls("map");
+        assert_eq!(backend.sweep_expired_fields(), 1);
+        assert!(!backend.hmap.contains_key("map"));
+        assert!(backend.hash_ttl_keys.is_empty());
+
+        // hashes without field TTLs are never visited, and are forgotten once theirs are gone
+        backend.hset("plain".to_string(), "a".to_string(), b"1".into());
+        let future = now_ms() as i64 + 100_000;
+        backend.hexpire("ttl", &["a".to_string()], future, None);
+        backend.hset("ttl".to_string(), "a".to_string(), b"1".into());
+        backend.hexpire("ttl", &["a".to_string()], future, None);
+        assert_eq!(backend.sweep_expired_fields(), 0);
+        assert_eq!(backend.hash_ttl_keys.len(), 1);
+        backend.hpersist("ttl", &["a".to_string()]);
+        backend.sweep_expired_fields();
+        assert!(backend.hash_ttl_keys.is_empty());
+    }
+
+    #[test]
+    fn test_hscan_survives_modification() {
+        let backend = Backend::new();
+        for i in 0..100 {
+            backend.hset("map".to_string(), format!("f{}", i), b"v".into());
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        let mut cursor = 0;
+        let mut rounds = 0;
+        loop {
+            let (next, items) = backend.hscan("map", cursor, 10);
+            assert!(items.len() >= 10 || next == 0);
+            seen.extend(items.into_iter().map(|(k, _)| k));
+            backend.hset("map".to_string(), format!("new{}", rounds), b"v".into());
+            backend.hdel("map", &[format!("new{}", rounds.max(1) - 1)]);
+            rounds += 1;
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert!((0..100).all(|i| seen.contains(&format!("f{}", i))));
+    }
+
+    #[test]
+    fn test_hscan_hashtable() {
+        let backend = Backend::new();
+        for i in 0..1000 {
+            backend.hset("map".to_string(), format!("f{}", i), b"v".into());
+        }
+        backend.hdel("map", &["f0".to_string()]);
+        assert_eq!(backend.hmap.get("map").unwrap().encoding(), "hashtable");
+
+        let mut seen = Vec::new();
+        let mut cursor = 0;
+        loop {
+            let (next, items) = backend.hscan("map", cursor, 100);
+            assert!(items.len() >= 100 || next == 0);
+            seen.extend(items.into_iter().map(|(k, _)| k));
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), 999);
+        assert!(!seen.contains(&"f0".to_string()));
+    }
+
+    #[test]
+    fn test_format_float() {
+        assert_eq!(format_float(0.1 + 0.2), "0.3");
+        assert_eq!(format_float(10.0), "10");
+        assert_eq!(format_float(-2.5), "-2.5");
+        assert_eq!(format_float(0.12345678901234567), "0.12345678901234566");
+        assert_eq!(format_float(1e20), "1e+20");
+        assert_eq!(format_float(1.5e-7), "1.5e-07");
+        assert_eq!(format_float(0.0), "0");
+    }
+
+    #[test]
+    fn test_hrandfield() {
+        let backend = Backend::new();
+        for i in 0..5 {
+            backend.hset("map".to_string(), format!("f{}", i), b"v".into());
+        }
+        let distinct = backend.hrandfield("map", 10);
+        assert_eq!(distinct.len(), 5);
+        let keys = distinct
+            .iter()
+            .map(|(k, _)| k)
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(keys.len(), 5);
+
+        assert_eq!(backend.hrandfield("map", -20).len(), 20);
+        assert!(backend.hrandfield("missing", 3).is_empty());
+    }
+}
@@ -0,0 +1,475 @@
+use super::{Backend, BackendError};
+use crate::{BulkString, RespFrame};
+
+// Redis' HyperLogLog layout: a 16 byte header ("HYLL", the encoding, 3 unused bytes and the
+// cached cardinality in little endian) followed by 16384 6-bit registers, either packed (dense)
+// or run-length encoded (sparse).
+const HLL_P: u32 = 14;
+const HLL_Q: u32 = 64 - HLL_P;
+const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_HDR_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HDR_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_MAGIC: &[u8] = b"HYLL";
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+// offset of the cached cardinality, whose top bit is set while it is stale
+const HLL_CARD: usize = 8;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const HLL_HASH_SEED: u64 = 0xadc8_3b19;
+
+// sparse opcodes: ZERO 00xxxxxx, XZERO 01xxxxxx yyyyyyyy and VAL 1vvvvvxx
+const SPARSE_ZERO_MAX_LEN: usize = 64;
+const SPARSE_XZERO_MAX_LEN: usize = 16384;
+const SPARSE_VAL_MAX_VALUE: u8 = 32;
+const SPARSE_VAL_MAX_LEN: usize = 4;
+
+/// A new, empty HyperLogLog: sparse, with a cached cardinality of 0.
+fn hll_new() -> Vec<u8> {
+    let mut bytes = header(HLL_SPARSE);
+    let run = SPARSE_XZERO_MAX_LEN - 1;
+    bytes.extend([0x40 | (run >> 8) as u8, run as u8]);
+    bytes
+}
+
+fn header(encoding: u8) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HLL_DENSE_SIZE);
+    bytes.extend_from_slice(HLL_MAGIC);
+    bytes.extend([encoding, 0, 0, 0]);
+    bytes.extend([0; 8]);
+    bytes
+}
+
+// checks that a string value looks like a HyperLogLog and returns its encoding
+fn check_header(bytes: &[u8]) -> Result<u8, BackendError> {
+    if bytes.len() < HLL_HDR_SIZE || &bytes[..4] != HLL_MAGIC {
+        return Err(BackendError::NotHll);
+    }
+    match bytes[4] {
+        HLL_DENSE if bytes.len() == HLL_DENSE_SIZE => Ok(HLL_DENSE),
+        HLL_SPARSE => Ok(HLL_SPARSE),
+        _ => Err(BackendError::NotHll),
+    }
+}
+
+fn cached_cardinality(bytes: &[u8]) -> Option<u64> {
+    let card = u64::from_le_bytes(bytes[HLL_CARD..HLL_HDR_SIZE].try_into().ok()?);
+    (bytes[HLL_HDR_SIZE - 1] & 0x80 == 0).then_some(card)
+}
+
+fn set_cached_cardinality(bytes: &mut [u8], card: u64) {
+    bytes[HLL_CARD..HLL_HDR_SIZE].copy_from_slice(&card.to_le_bytes());
+}
+
+fn invalidate_cache(bytes: &mut [u8]) {
+    bytes[HLL_HDR_SIZE - 1] |= 0x80;
+}
+
+fn dense_get(registers: &[u8], index: usize) -> u8 {
+    let (byte, fb) = (index * HLL_BITS / 8, index * HLL_BITS % 8);
+    let b0 = registers[byte] as u16;
+    let b1 = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+    ((b0 >> fb | b1 << (8 - fb)) & HLL_REGISTER_MAX as u16) as u8
+}
+
+fn dense_set(registers: &mut [u8], index: usize, value: u8) {
+    let (byte, fb) = (index * HLL_BITS / 8, index * HLL_BITS % 8);
+    let max = HLL_REGISTER_MAX as u16;
+    registers[byte] &= !(max << fb) as u8;
+    registers[byte] |= ((value as u16) << fb) as u8;
+    // the register spills into the next byte
+    if fb > 8 - HLL_BITS {
+        registers[byte + 1] &= !(max >> (8 - fb)) as u8;
+        registers[byte + 1] |= value >> (8 - fb);
+    }
+}
+
+/// The registers of a HyperLogLog, one per byte.
+fn registers(bytes: &[u8]) -> Result<Vec<u8>, BackendError> {
+    let encoding = check_header(bytes)?;
+    let body = &bytes[HLL_HDR_SIZE..];
+    if encoding == HLL_DENSE {
+        return Ok((0..HLL_REGISTERS).map(|i| dense_get(body, i)).collect());
+    }
+
+    let mut registers = Vec::with_capacity(HLL_REGISTERS);
+    let mut ops = body.iter();
+    while let Some(&op) = ops.next() {
+        let (value, run) = match op & 0xc0 {
+            0x00 => (0, (op & 0x3f) as usize + 1),
+            0x40 => {
+                let low = *ops.next().ok_or(BackendError::CorruptHll)?;
+                (0, (((op & 0x3f) as usize) << 8 | low as usize) + 1)
+            }
+            _ => ((op >> 2 & 0x1f) + 1, (op & 0x03) as usize + 1),
+        };
+        if registers.len() + run > HLL_REGISTERS {
+            return Err(BackendError::CorruptHll);
+        }
+        registers.resize(registers.len() + run, value);
+    }
+    match registers.len() {
+        HLL_REGISTERS => Ok(registers),
+        _ => Err(BackendError::CorruptHll),
+    }
+}
+
+// rewrites the registers after the header of `bytes`, as sparse unless that won't do
+fn encode(bytes: &mut Vec<u8>, registers: &[u8], dense: bool, sparse_max_bytes: usize) {
+    let mut body = match dense {
+        true => None,
+        false => encode_sparse(registers).filter(|b| HLL_HDR_SIZE + b.len() <= sparse_max_bytes),
+    };
+    let encoding = if body.is_some() {
+        HLL_SPARSE
+    } else {
+        HLL_DENSE
+    };
+    let body = body.get_or_insert_with(|| {
+        let mut dense = vec![0; HLL_DENSE_SIZE - HLL_HDR_SIZE];
+        for (i, &value) in registers.iter().enumerate() {
+            dense_set(&mut dense, i, value);
+        }
+        dense
+    });
+    bytes.truncate(HLL_HDR_SIZE);
+    bytes[4] = encoding;
+    bytes.extend_from_slice(body);
+}
+
+// None if a register is too large for the sparse encoding
+fn encode_sparse(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut body = Vec::new();
+    let mut i = 0;
+    while i < registers.len() {
+        let value = registers[i];
+        if value > SPARSE_VAL_MAX_VALUE {
+            return None;
+        }
+        let mut run = registers[i..].iter().take_while(|&&v| v == value).count();
+        i += run;
+        while run > 0 {
+            let len = match value {
+                0 if run > SPARSE_ZERO_MAX_LEN => {
+                    let len = run.min(SPARSE_XZERO_MAX_LEN);
+                    body.extend([0x40 | ((len - 1) >> 8) as u8, (len - 1) as u8]);
+                    len
+                }
+                0 => {
+                    body.push((run - 1) as u8);
+                    run
+                }
+                _ => {
+                    let len = run.min(SPARSE_VAL_MAX_LEN);
+                    body.push(0x80 | (value - 1) << 2 | (len - 1) as u8);
+                    len
+                }
+            };
+            run -= len;
+        }
+    }
+    Some(body)
+}
+
+// the register an element maps to, and the value it would set there: the position of the first
+// set bit of the rest of its hash
+fn hll_pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmurhash64a(element, HLL_HASH_SEED);
+    let index = hash as usize & (HLL_REGISTERS - 1);
+    let rest = hash >> HLL_P | 1 << HLL_Q;
+    (index, rest.trailing_zeros() as u8 + 1)
+}
+
+// MurmurHash2, 64-bit version, by Austin Appleby, as Redis uses it
+pub(super) fn murmurhash64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (data.len() as u64).wrapping_mul(M);
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+// the cardinality estimate of Otmar Ertl's "New cardinality estimation algorithms for
+// HyperLogLog sketches", which Redis uses
+fn hll_count(registers: &[u8]) -> u64 {
+    let m = HLL_REGISTERS as f64;
+    let mut histogram = [0u32; 64];
+    for &value in registers {
+        histogram[value as usize] += 1;
+    }
+    let q = HLL_Q as usize;
+    let mut z = m * hll_tau((m - histogram[q + 1] as f64) / m);
+    for &count in histogram[1..=q].iter().rev() {
+        z += count as f64;
+        z *= 0.5;
+    }
+    z += m * hll_sigma(histogram[0] as f64 / m);
+    (HLL_ALPHA_INF * m * m / z).round() as u64
+}
+
+fn hll_sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let prev = z;
+        z += x * y;
+        y += y;
+        if prev == z {
+            return z;
+        }
+    }
+}
+
+fn hll_tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let prev = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if prev == z {
+            return z / 3.0;
+        }
+    }
+}
+
+impl Backend {
+    // the registers of the HyperLogLog at key, None if there is no such key
+    fn hll_registers(&self, key: &str) -> Result<Option<Vec<u8>>, BackendError> {
+        match self.map.get(key).as_deref() {
+            Some(RespFrame::BulkString(s)) => registers(s).map(Some),
+            Some(_) => Err(BackendError::NotHll),
+            None => Ok(None),
+        }
+    }
+
+    /// Adds elements to the HyperLogLog at key, creating it if needed. Returns whether it
+    /// changed, which is also the case when it was created.
+    pub fn pfadd(&self, key: String, elements: &[Vec<u8>]) -> Result<bool, BackendError> {
+        let _guard = self.shared_lock();
+        let sparse_max_bytes = self.config.hll_sparse_max_bytes();
+        let mut created = false;
+        let mut value = self.map.entry(key).or_insert_with(|| {
+            created = true;
+            BulkString::new(hll_new()).into()
+        });
+        let RespFrame::BulkString(s) = value.value_mut() else {
+            return Err(BackendError::NotHll);
+        };
+        let bytes = &mut s.0;
+        let dense = check_header(bytes)? == HLL_DENSE;
+
+        let mut changed = false;
+        if dense {
+            let body = &mut bytes[HLL_HDR_SIZE..];
+            for element in elements {
+                let (index, count) = hll_pattern(element);
+                if dense_get(body, index) < count {
+                    dense_set(body, index, count);
+                    changed = true;
+                }
+            }
+        } else {
+            let mut registers = registers(bytes)?;
+            for element in elements {
+                let (index, count) = hll_pattern(element);
+                if registers[index] < count {
+                    registers[index] = count;
+                    changed = true;
+                }
+            }
+            if changed {
+                encode(bytes, &registers, false, sparse_max_bytes);
+            }
+        }
+        if changed {
+            invalidate_cache(bytes);
+        }
+        Ok(changed || created)
+    }
+
+    /// The estimated number of distinct elements added to the HyperLogLogs at `keys`, as if
+    /// they were merged. A single key's estimate is cached in its header.
+    pub fn pfcount(&self, keys: &[String]) -> Result<u64, BackendError> {
+        if let [key] = keys {
+            let _guard = self.shared_lock();
+            let Some(mut value) = self.map.get_mut(key) else {
+                return Ok(0);
+            };
+            let RespFrame::BulkString(s) = value.value_mut() else {
+                return Err(BackendError::NotHll);
+            };
+            check_header(s)?;
+            if let Some(card) = cached_cardinality(s) {
+                return Ok(card);
+            }
+            let card = hll_count(&registers(s)?);
+            set_cached_cardinality(&mut s.0, card);
+            return Ok(card);
+        }
+
+        let _guard = self.exclusive_lock();
+        let mut merged = vec![0; HLL_REGISTERS];
+        for key in keys {
+            if let Some(registers) = self.hll_registers(key)? {
+                merge(&mut merged, &registers);
+            }
+        }
+        Ok(hll_count(&merged))
+    }
+
+    /// Merges the HyperLogLogs at `sources` into the one at `dest`, creating it if needed. The
+    /// result is dense if any of them is.
+    pub fn pfmerge(&self, dest: String, sources: &[String]) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        let mut merged = vec![0; HLL_REGISTERS];
+        let mut dense = false;
+        for key in std::iter::once(&dest).chain(sources) {
+            let Some(value) = self.map.get(key) else {
+                continue;
+            };
+            let RespFrame::BulkString(s) = value.value() else {
+                return Err(BackendError::NotHll);
+            };
+            dense |= check_header(s)? == HLL_DENSE;
+            merge(&mut merged, &registers(s)?);
+        }
+
+        let sparse_max_bytes = self.config.hll_sparse_max_bytes();
+        let mut value = self
+            .map
+            .entry(dest)
+            .or_insert_with(|| BulkString::new(hll_new()).into());
+        let RespFrame::BulkString(s) = value.value_mut() else {
+            return Err(BackendError::NotHll);
+        };
+        encode(&mut s.0, &merged, dense, sparse_max_bytes);
+        invalidate_cache(&mut s.0);
+        Ok(())
+    }
+}
+
+fn merge(merged: &mut [u8], registers: &[u8]) {
+    for (m, &r) in merged.iter_mut().zip(registers) {
+        *m = (*m).max(r);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(range: std::ops::Range<u32>) -> Vec<Vec<u8>> {
+        range
+            .map(|i| format!("element:{}", i).into_bytes())
+            .collect()
+    }
+
+    fn bytes(backend: &Backend, key: &str) -> Vec<u8> {
+        match backend.get(key) {
+            Some(RespFrame::BulkString(s)) => s.0,
+            _ => panic!("expected a string"),
+        }
+    }
+
+    #[test]
+    fn test_hll_encoding() {
+        assert_eq!(murmurhash64a(b"", 0), 0);
+        let empty = hll_new();
+        assert_eq!(empty, b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f\xff");
+        assert_eq!(hll_count(&registers(&empty).unwrap()), 0);
+
+        let mut registers = vec![0; HLL_REGISTERS];
+        registers[0] = 3;
+        registers[1..6].fill(2);
+        registers[HLL_REGISTERS - 1] = 1;
+        let sparse = encode_sparse(&registers).unwrap();
+        // VAL 3, VAL 2 x4, VAL 2, XZERO 16377, VAL 1
+        assert_eq!(sparse, [0x88, 0x87, 0x84, 0x7f, 0xf8, 0x80]);
+
+        let mut bytes = hll_new();
+        encode(&mut bytes, &registers, true, 3000);
+        assert_eq!(bytes.len(), HLL_DENSE_SIZE);
+        assert_eq!(super::registers(&bytes).unwrap(), registers);
+
+        assert_eq!(check_header(b"HYLL\x00"), Err(BackendError::NotHll));
+        let mut corrupt = hll_new();
+        corrupt.push(0x00);
+        assert_eq!(super::registers(&corrupt), Err(BackendError::CorruptHll));
+    }
+
+    #[test]
+    fn test_pfadd_pfcount() {
+        let backend = Backend::new();
+        assert_eq!(backend.pfadd("h".to_string(), &[]), Ok(true));
+        assert_eq!(backend.pfadd("h".to_string(), &elements(0..100)), Ok(true));
+        assert_eq!(backend.pfadd("h".to_string(), &elements(0..100)), Ok(false));
+        assert_eq!(bytes(&backend, "h")[4], HLL_SPARSE);
+        let count = backend.pfcount(&["h".to_string()]).unwrap();
+        assert!((98..=102).contains(&count), "{}", count);
+        // the estimate is cached until the next change
+        assert_eq!(cached_cardinality(&bytes(&backend, "h")), Some(count));
+
+        backend
+            .pfadd("h".to_string(), &elements(100..20000))
+            .unwrap();
+        assert_eq!(bytes(&backend, "h")[4], HLL_DENSE);
+        assert_eq!(cached_cardinality(&bytes(&backend, "h")), None);
+        let count = backend.pfcount(&["h".to_string()]).unwrap();
+        assert!((19600..20400).contains(&count), "{}", count);
+
+        backend.set("s".to_string(), BulkString::from("nope").into());
+        assert_eq!(
+            backend.pfadd("s".to_string(), &elements(0..1)),
+            Err(BackendError::NotHll)
+        );
+    }
+
+    #[test]
+    fn test_pfmerge() {
+        let backend = Backend::new();
+        backend.pfadd("a".to_string(), &elements(0..600)).unwrap();
+        backend
+            .pfadd("b".to_string(), &elements(300..1000))
+            .unwrap();
+        let keys = ["a".to_string(), "b".to_string(), "none".to_string()];
+        let count = backend.pfcount(&keys).unwrap();
+        assert!((980..1020).contains(&count), "{}", count);
+
+        backend.pfmerge("dest".to_string(), &keys).unwrap();
+        assert_eq!(backend.pfcount(&["dest".to_string()]), Ok(count));
+        assert_eq!(bytes(&backend, "dest")[4], HLL_SPARSE);
+
+        backend.config.set("hll-sparse-max-bytes", "0").unwrap();
+        backend.pfmerge("dest".to_string(), &[]).unwrap();
+        assert_eq!(bytes(&backend, "dest")[4], HLL_DENSE);
+        assert_eq!(backend.pfcount(&["dest".to_string()]), Ok(count));
+    }
+}
@@ -0,0 +1,162 @@
+/// A sorted set of integers packed into the smallest width (16, 32 or 64 bits) that fits all
+/// of them, like Redis' intset encoding. Elements are stored little-endian back to back, so
+/// lookups are a binary search over the raw bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntSet {
+    // bytes per element: 2, 4 or 8
+    width: usize,
+    contents: Vec<u8>,
+}
+
+impl Default for IntSet {
+    fn default() -> Self {
+        IntSet {
+            width: 2,
+            contents: Vec::new(),
+        }
+    }
+}
+
+impl IntSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len() / self.width
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    pub fn contains(&self, value: i64) -> bool {
+        width_for(value) <= self.width && self.search(value).is_ok()
+    }
+
+    // returns true if the value was not already present
+    pub fn insert(&mut self, value: i64) -> bool {
+        let width = width_for(value);
+        if width > self.width {
+            self.upgrade(width);
+        }
+        match self.search(value) {
+            Ok(_) => false,
+            Err(pos) => {
+                let at = pos * self.width;
+                self.contents.splice(
+                    at..at,
+                    encode(value, self.width).into_iter().take(self.width),
+                );
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, value: i64) -> bool {
+        if width_for(value) > self.width {
+            return false;
+        }
+        match self.search(value) {
+            Ok(pos) => {
+                let at = pos * self.width;
+                self.contents.drain(at..at + self.width);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    // bytes used by the packed contents
+    pub fn blob_len(&self) -> usize {
+        self.contents.len()
+    }
+
+    fn get(&self, index: usize) -> i64 {
+        let bytes = &self.contents[index * self.width..(index + 1) * self.width];
+        let mut buf = [0u8; 8];
+        buf[..self.width].copy_from_slice(bytes);
+        // sign-extend from the element width
+        let shift = 64 - self.width * 8;
+        (i64::from_le_bytes(buf) << shift) >> shift
+    }
+
+    fn search(&self, value: i64) -> Result<usize, usize> {
+        let (mut lo, mut hi) = (0, self.len());
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.get(mid).cmp(&value) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(lo)
+    }
+
+    fn upgrade(&mut self, width: usize) {
+        let values = self.iter().collect::<Vec<_>>();
+        self.width = width;
+        self.contents = values
+            .into_iter()
+            .flat_map(|v| encode(v, width).into_iter().take(width))
+            .collect();
+    }
+}
+
+fn width_for(value: i64) -> usize {
+    if i16::try_from(value).is_ok() {
+        2
+    } else if i32::try_from(value).is_ok() {
+        4
+    } else {
+        8
+    }
+}
+
+// little-endian bytes of the value; only the first `width` are meaningful
+fn encode(value: i64, width: usize) -> [u8; 8] {
+    debug_assert!(width_for(value) <= width);
+    value.to_le_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intset_insert_remove() {
+        let mut set = IntSet::new();
+        assert!(set.insert(5));
+        assert!(set.insert(-3));
+        assert!(!set.insert(5));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![-3, 5]);
+        assert_eq!(set.blob_len(), 4);
+
+        assert!(set.remove(-3));
+        assert!(!set.remove(-3));
+        assert!(!set.remove(i64::MAX));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_intset_upgrade_keeps_order() {
+        let mut set = IntSet::new();
+        set.insert(100);
+        set.insert(-100);
+        set.insert(70_000);
+        assert_eq!(set.blob_len(), 12);
+        set.insert(i64::MIN);
+        assert_eq!(set.blob_len(), 32);
+        assert_eq!(
+            set.iter().collect::<Vec<_>>(),
+            vec![i64::MIN, -100, 100, 70_000]
+        );
+        assert!(set.contains(70_000));
+        assert!(!set.contains(1));
+    }
+}
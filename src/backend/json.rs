@@ -0,0 +1,862 @@
+use serde_json::{Map, Number, Value};
+
+use super::{Backend, BackendError};
+
+/// A path into a JSON document: either JSONPath (`$..a[*]`), which matches any number of
+/// values, or the legacy syntax (`.a.b[0]`), which stands for a single value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    raw: String,
+    legacy: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    // `.name`, `[...]`
+    Child(Selector),
+    // `..name`, `..[...]`: matches at any depth
+    Descendant(Selector),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    Slice(Option<i64>, Option<i64>, i64),
+    Union(Vec<Selector>),
+}
+
+// one step from a value to one of its children
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+type Location = Vec<Step>;
+
+/// What a command returns for the values a path matched: a legacy path yields a single
+/// result, a JSONPath yields one per match, None where the match had the wrong type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonMatches<T> {
+    Legacy(T),
+    Path(Vec<Option<T>>),
+}
+
+impl JsonPath {
+    /// The legacy root path `.`, used when a command is given no path.
+    pub fn root() -> Self {
+        JsonPath {
+            raw: ".".to_string(),
+            legacy: true,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn parse(raw: &str) -> Option<Self> {
+        let (legacy, mut rest) = match raw.strip_prefix('$') {
+            Some(rest) => (false, rest),
+            None => (true, raw),
+        };
+        let mut segments = Vec::new();
+        if legacy && rest == "." {
+            rest = "";
+        } else if legacy && !rest.is_empty() && !rest.starts_with(['.', '[']) {
+            // legacy paths may leave out the leading dot
+            let (name, tail) = split_name(rest);
+            segments.push(Segment::Child(Selector::Key(name.to_string())));
+            rest = tail;
+        }
+        while !rest.is_empty() {
+            let (segment, tail) = parse_segment(rest)?;
+            segments.push(segment);
+            rest = tail;
+        }
+        Some(JsonPath {
+            raw: raw.to_string(),
+            legacy,
+            segments,
+        })
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    // the locations of the values the path matches, in document order
+    fn select(&self, doc: &Value) -> Vec<Location> {
+        let mut ret = Vec::new();
+        walk(doc, &mut Vec::new(), &self.segments, &mut ret);
+        ret
+    }
+}
+
+// a member name runs until the next `.` or `[`
+fn split_name(s: &str) -> (&str, &str) {
+    s.split_at(s.find(['.', '[']).unwrap_or(s.len()))
+}
+
+fn parse_segment(s: &str) -> Option<(Segment, &str)> {
+    if let Some(rest) = s.strip_prefix("..") {
+        let (selector, rest) = parse_selector(rest)?;
+        return Some((Segment::Descendant(selector), rest));
+    }
+    if let Some(rest) = s.strip_prefix('.') {
+        if rest.starts_with('[') {
+            return None;
+        }
+        let (selector, rest) = parse_selector(rest)?;
+        return Some((Segment::Child(selector), rest));
+    }
+    let (selector, rest) = parse_bracket(s)?;
+    Some((Segment::Child(selector), rest))
+}
+
+// a `*`, a member name or a bracketed selector
+fn parse_selector(s: &str) -> Option<(Selector, &str)> {
+    if let Some(rest) = s.strip_prefix('*') {
+        return Some((Selector::Wildcard, rest));
+    }
+    if s.starts_with('[') {
+        return parse_bracket(s);
+    }
+    match split_name(s) {
+        ("", _) => None,
+        (name, rest) => Some((Selector::Key(name.to_string()), rest)),
+    }
+}
+
+// `[*]`, `['name']`, `[0]`, `[1:5:2]` or a comma separated union of those
+fn parse_bracket(s: &str) -> Option<(Selector, &str)> {
+    let s = s.strip_prefix('[')?;
+    let mut items = Vec::new();
+    let mut rest = s.trim_start();
+    loop {
+        let (item, tail) = parse_bracket_item(rest)?;
+        items.push(item);
+        rest = tail.trim_start();
+        match rest.chars().next()? {
+            ',' => rest = rest[1..].trim_start(),
+            ']' => break,
+            _ => return None,
+        }
+    }
+    let selector = match items.len() {
+        1 => items.pop()?,
+        _ if items.contains(&Selector::Wildcard) => return None,
+        _ => Selector::Union(items),
+    };
+    Some((selector, &rest[1..]))
+}
+
+fn parse_bracket_item(s: &str) -> Option<(Selector, &str)> {
+    if let Some(rest) = s.strip_prefix('*') {
+        return Some((Selector::Wildcard, rest));
+    }
+    if let Some(quote) = s.chars().next().filter(|c| *c == '\'' || *c == '"') {
+        let mut name = String::new();
+        let mut chars = s[1..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => name.push(chars.next()?.1),
+                c if c == quote => return Some((Selector::Key(name), &s[i + 2..])),
+                c => name.push(c),
+            }
+        }
+        return None;
+    }
+    let end = s.find([',', ']'])?;
+    let (item, rest) = s.split_at(end);
+    let item = item.trim();
+    if !item.contains(':') {
+        return Some((Selector::Index(item.parse().ok()?), rest));
+    }
+    let mut parts = item.splitn(3, ':').map(str::trim);
+    let mut bound = || -> Option<Option<i64>> {
+        match parts.next() {
+            None | Some("") => Some(None),
+            Some(n) => n.parse().ok().map(Some),
+        }
+    };
+    let (start, end, step) = (bound()?, bound()?, bound()?.unwrap_or(1));
+    Some((Selector::Slice(start, end, step), rest))
+}
+
+fn walk(value: &Value, loc: &mut Location, segments: &[Segment], out: &mut Vec<Location>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        out.push(loc.clone());
+        return;
+    };
+    let selector = match segment {
+        Segment::Child(selector) | Segment::Descendant(selector) => selector,
+    };
+    for (step, child) in children(value, selector) {
+        loc.push(step);
+        walk(child, loc, rest, out);
+        loc.pop();
+    }
+    if let Segment::Descendant(_) = segment {
+        for (step, child) in children(value, &Selector::Wildcard) {
+            loc.push(step);
+            walk(child, loc, segments, out);
+            loc.pop();
+        }
+    }
+}
+
+fn children<'a>(value: &'a Value, selector: &Selector) -> Vec<(Step, &'a Value)> {
+    match (selector, value) {
+        (Selector::Key(key), Value::Object(map)) => map
+            .get(key)
+            .map(|v| (Step::Key(key.clone()), v))
+            .into_iter()
+            .collect(),
+        (Selector::Wildcard, Value::Object(map)) => {
+            map.iter().map(|(k, v)| (Step::Key(k.clone()), v)).collect()
+        }
+        (Selector::Wildcard, Value::Array(arr)) => arr
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (Step::Index(i), v))
+            .collect(),
+        (Selector::Index(i), Value::Array(arr)) => {
+            let i = if *i < 0 { arr.len() as i64 + i } else { *i };
+            match usize::try_from(i).ok().and_then(|i| Some((i, arr.get(i)?))) {
+                Some((i, v)) => vec![(Step::Index(i), v)],
+                None => Vec::new(),
+            }
+        }
+        (Selector::Slice(start, end, step), Value::Array(arr)) => {
+            slice_indices(arr.len(), *start, *end, *step)
+                .into_iter()
+                .map(|i| (Step::Index(i), &arr[i]))
+                .collect()
+        }
+        (Selector::Union(selectors), _) => {
+            selectors.iter().flat_map(|s| children(value, s)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+// the indices `[start:end:step]` selects from an array of `len` elements, as in RFC 9535
+fn slice_indices(len: usize, start: Option<i64>, end: Option<i64>, step: i64) -> Vec<usize> {
+    let len = len as i64;
+    let normalize = |i: i64| if i < 0 { len + i } else { i };
+    if step > 0 {
+        let lower = start.map_or(0, normalize).clamp(0, len);
+        let upper = end.map_or(len, normalize).clamp(0, len);
+        (lower..upper)
+            .step_by(step as usize)
+            .map(|i| i as usize)
+            .collect()
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut ret = Vec::new();
+        let mut i = upper;
+        while i > lower {
+            ret.push(i as usize);
+            i += step;
+        }
+        ret
+    } else {
+        Vec::new()
+    }
+}
+
+fn resolve<'a>(doc: &'a Value, loc: &[Step]) -> Option<&'a Value> {
+    loc.iter().try_fold(doc, |value, step| match (step, value) {
+        (Step::Key(k), Value::Object(map)) => map.get(k),
+        (Step::Index(i), Value::Array(arr)) => arr.get(*i),
+        _ => None,
+    })
+}
+
+fn resolve_mut<'a>(doc: &'a mut Value, loc: &[Step]) -> Option<&'a mut Value> {
+    loc.iter().try_fold(doc, |value, step| match (step, value) {
+        (Step::Key(k), Value::Object(map)) => map.get_mut(k),
+        (Step::Index(i), Value::Array(arr)) => arr.get_mut(*i),
+        _ => None,
+    })
+}
+
+/// The type of a JSON value, as reported by JSON.TYPE.
+pub fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Estimated bytes used by a JSON value, see `Backend::memory_usage`.
+pub(crate) fn json_memory_usage(value: &Value) -> usize {
+    size_of::<Value>()
+        + match value {
+            Value::String(s) => s.len(),
+            Value::Array(items) => items.iter().map(json_memory_usage).sum(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(k, v)| size_of::<String>() + k.len() + json_memory_usage(v))
+                .sum(),
+            _ => 0,
+        }
+}
+
+/// Serializes a value as JSON.GET does, with `indent` repeated once per nesting level,
+/// `newline` before every nested element and `space` after every colon.
+pub fn json_to_string(value: &Value, indent: &str, newline: &str, space: &str) -> String {
+    let mut ret = String::new();
+    write_json(value, (indent, newline, space), 0, &mut ret);
+    ret
+}
+
+fn write_json(value: &Value, format: (&str, &str, &str), depth: usize, out: &mut String) {
+    let (indent, newline, space) = format;
+    let (open, close, items): (char, char, Vec<(Option<&String>, &Value)>) = match value {
+        Value::Array(arr) if !arr.is_empty() => ('[', ']', arr.iter().map(|v| (None, v)).collect()),
+        Value::Object(map) if !map.is_empty() => {
+            ('{', '}', map.iter().map(|(k, v)| (Some(k), v)).collect())
+        }
+        _ => {
+            out.push_str(&value.to_string());
+            return;
+        }
+    };
+    out.push(open);
+    for (i, (key, item)) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(newline);
+        out.push_str(&indent.repeat(depth + 1));
+        if let Some(key) = key {
+            out.push_str(&Value::from(key.as_str()).to_string());
+            out.push(':');
+            out.push_str(space);
+        }
+        write_json(item, format, depth + 1, out);
+    }
+    out.push_str(newline);
+    out.push_str(&indent.repeat(depth));
+    out.push(close);
+}
+
+fn wrong_type(expected: &'static str, found: &Value) -> BackendError {
+    BackendError::JsonWrongType(expected, json_type_name(found))
+}
+
+fn array_mut(value: &mut Value) -> Result<&mut Vec<Value>, BackendError> {
+    match value {
+        Value::Array(arr) => Ok(arr),
+        other => Err(wrong_type("an array", other)),
+    }
+}
+
+// a legacy path reports the result for the last value it matched, or fails if it matched none
+fn number_add(a: &Number, b: &Number) -> Result<Number, BackendError> {
+    match (a.as_i64(), b.as_i64()) {
+        (Some(a), Some(b)) => Ok(Number::from(
+            a.checked_add(b).ok_or(BackendError::Overflow)?,
+        )),
+        _ => {
+            let sum = a.as_f64().unwrap_or_default() + b.as_f64().unwrap_or_default();
+            Number::from_f64(sum).ok_or(BackendError::NanOrInfinity)
+        }
+    }
+}
+
+fn collect_matches<T>(
+    path: &JsonPath,
+    results: Vec<Result<T, BackendError>>,
+) -> Result<JsonMatches<T>, BackendError> {
+    if !path.legacy {
+        return Ok(JsonMatches::Path(
+            results.into_iter().map(Result::ok).collect(),
+        ));
+    }
+    match results.into_iter().last() {
+        Some(result) => result.map(JsonMatches::Legacy),
+        None => Err(BackendError::JsonPathNotFound(path.raw.clone())),
+    }
+}
+
+impl Backend {
+    /// Sets the values the path matches, or adds the member the path names to the objects
+    /// matching the rest of it. Returns false when NX/XX (`only_if`) prevented the write or
+    /// a JSONPath matched nothing.
+    pub fn json_set(
+        &self,
+        key: String,
+        path: &JsonPath,
+        value: Value,
+        only_if: Option<bool>,
+    ) -> Result<bool, BackendError> {
+        let _guard = self.shared_lock();
+        let mut doc = match self.json.get_mut(&key) {
+            Some(doc) => doc,
+            None if !path.is_root() => return Err(BackendError::JsonNewAtRoot),
+            None if only_if == Some(true) => return Ok(false),
+            None => {
+                self.json.insert(key, value);
+                return Ok(true);
+            }
+        };
+        let locations = path.select(&doc);
+        if !locations.is_empty() {
+            if only_if == Some(false) {
+                return Ok(false);
+            }
+            for loc in &locations {
+                if let Some(target) = resolve_mut(&mut doc, loc) {
+                    *target = value.clone();
+                }
+            }
+            return Ok(true);
+        }
+        if only_if == Some(true) {
+            return Ok(false);
+        }
+        // only a missing object member can be created
+        let (last, parent) = path.segments.split_last().expect("the root always matches");
+        let Segment::Child(Selector::Key(name)) = last else {
+            return Ok(false);
+        };
+        let mut parents = Vec::new();
+        walk(&doc, &mut Vec::new(), parent, &mut parents);
+        let mut created = false;
+        for loc in &parents {
+            if let Some(Value::Object(map)) = resolve_mut(&mut doc, loc) {
+                map.insert(name.clone(), value.clone());
+                created = true;
+            }
+        }
+        match created {
+            false if path.legacy => Err(BackendError::JsonPathNotFound(path.raw.clone())),
+            created => Ok(created),
+        }
+    }
+
+    /// The value a legacy path names, or the array of values a JSONPath matches. With
+    /// several paths, an object from each path to its result. None for a missing key.
+    pub fn json_get(&self, key: &str, paths: &[JsonPath]) -> Result<Option<Value>, BackendError> {
+        let Some(doc) = self.json.get(key) else {
+            return Ok(None);
+        };
+        // a single JSONPath among several paths switches them all to the array form
+        let legacy = paths.iter().all(JsonPath::is_legacy);
+        let get = |path: &JsonPath| {
+            let mut values = path
+                .select(&doc)
+                .into_iter()
+                .filter_map(|loc| resolve(&doc, &loc));
+            match legacy {
+                true => values
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| BackendError::JsonPathNotFound(path.raw.clone())),
+                false => Ok(Value::Array(values.cloned().collect())),
+            }
+        };
+        match paths {
+            [] => Ok(Some(doc.clone())),
+            [path] => get(path).map(Some),
+            paths => {
+                let mut ret = Map::new();
+                for path in paths {
+                    ret.insert(path.raw.clone(), get(path)?);
+                }
+                Ok(Some(Value::Object(ret)))
+            }
+        }
+    }
+
+    /// JSON.GET with a single path for each of the keys; None for missing keys and legacy
+    /// paths that match nothing.
+    pub fn json_mget(&self, keys: &[String], path: &JsonPath) -> Vec<Option<Value>> {
+        keys.iter()
+            .map(|key| {
+                self.json_get(key, std::slice::from_ref(path))
+                    .ok()
+                    .flatten()
+            })
+            .collect()
+    }
+
+    /// Removes the values the path matches and returns how many there were. The root path
+    /// removes the key.
+    pub fn json_del(&self, key: &str, path: &JsonPath) -> usize {
+        let _guard = self.shared_lock();
+        if path.is_root() {
+            return self.json.remove(key).is_some() as usize;
+        }
+        let Some(mut doc) = self.json.get_mut(key) else {
+            return 0;
+        };
+        let mut locations = path.select(&doc);
+        // later array elements go first so that earlier indices stay valid
+        locations.sort_unstable();
+        locations.dedup();
+        let mut deleted = 0;
+        for loc in locations.iter().rev() {
+            let (last, parent) = loc.split_last().expect("not the root");
+            let removed = match (resolve_mut(&mut doc, parent), last) {
+                (Some(Value::Object(map)), Step::Key(k)) => map.shift_remove(k).is_some(),
+                (Some(Value::Array(arr)), Step::Index(i)) if *i < arr.len() => {
+                    arr.remove(*i);
+                    true
+                }
+                _ => false,
+            };
+            deleted += removed as usize;
+        }
+        deleted
+    }
+
+    pub fn json_type(
+        &self,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Option<JsonMatches<&'static str>>, BackendError> {
+        self.json_read(key, path, |value| Ok(json_type_name(value)))
+    }
+
+    pub fn json_objkeys(
+        &self,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Option<JsonMatches<Vec<String>>>, BackendError> {
+        self.json_read(key, path, |value| match value {
+            Value::Object(map) => Ok(map.keys().cloned().collect()),
+            other => Err(wrong_type("an object", other)),
+        })
+    }
+
+    /// Adds `by` to the numbers the path matches and returns their new values. The sum of
+    /// two integers stays an integer. A sum that overflows fails the whole command and leaves
+    /// the document as it was.
+    pub fn json_numincrby(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        by: &Number,
+    ) -> Result<JsonMatches<Value>, BackendError> {
+        let _guard = self.shared_lock();
+        let mut doc = self.json.get_mut(key).ok_or(BackendError::JsonNoKey)?;
+        let locations = path.select(&doc);
+        let mut results = locations
+            .iter()
+            .map(|loc| match resolve(&doc, loc) {
+                Some(Value::Number(n)) => number_add(n, by).map(Value::Number),
+                Some(other) => Err(wrong_type("a number", other)),
+                None => Err(BackendError::JsonPathNotFound(path.raw.clone())),
+            })
+            .collect::<Vec<_>>();
+        // an overflow anywhere fails the command before any number is changed
+        let overflow = results.iter().position(|result| {
+            matches!(
+                result,
+                Err(BackendError::Overflow | BackendError::NanOrInfinity)
+            )
+        });
+        if let Some(Err(e)) = overflow.map(|i| results.swap_remove(i)) {
+            return Err(e);
+        }
+        for (loc, result) in locations.iter().zip(&results) {
+            if let (Some(value), Ok(sum)) = (resolve_mut(&mut doc, loc), result) {
+                *value = sum.clone();
+            }
+        }
+        collect_matches(path, results)
+    }
+
+    pub fn json_strappend(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        suffix: &str,
+    ) -> Result<JsonMatches<usize>, BackendError> {
+        self.json_modify(key, path, |value| match value {
+            Value::String(s) => {
+                s.push_str(suffix);
+                Ok(s.len())
+            }
+            other => Err(wrong_type("a string", other)),
+        })
+    }
+
+    pub fn json_arrappend(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        values: &[Value],
+    ) -> Result<JsonMatches<usize>, BackendError> {
+        self.json_modify(key, path, |value| {
+            let arr = array_mut(value)?;
+            arr.extend_from_slice(values);
+            Ok(arr.len())
+        })
+    }
+
+    /// Inserts the values before `index`, which counts from the end when negative.
+    pub fn json_arrinsert(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        index: i64,
+        values: &[Value],
+    ) -> Result<JsonMatches<usize>, BackendError> {
+        self.json_modify(key, path, |value| {
+            let arr = array_mut(value)?;
+            let len = arr.len() as i64;
+            let index = if index < 0 { len + index } else { index };
+            if !(0..=len).contains(&index) {
+                return Err(BackendError::JsonIndexOutOfBounds);
+            }
+            let index = index as usize;
+            arr.splice(index..index, values.iter().cloned());
+            Ok(arr.len())
+        })
+    }
+
+    /// Removes and returns the element at `index`, clamped to the array; None for an empty
+    /// array.
+    pub fn json_arrpop(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        index: i64,
+    ) -> Result<JsonMatches<Option<Value>>, BackendError> {
+        self.json_modify(key, path, |value| {
+            let arr = array_mut(value)?;
+            if arr.is_empty() {
+                return Ok(None);
+            }
+            let len = arr.len() as i64;
+            let index = if index < 0 { len + index } else { index };
+            Ok(Some(arr.remove(index.clamp(0, len - 1) as usize)))
+        })
+    }
+
+    // applies `f` to the value a legacy path names, or to every value a JSONPath matches
+    fn json_read<T>(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        f: impl Fn(&Value) -> Result<T, BackendError>,
+    ) -> Result<Option<JsonMatches<T>>, BackendError> {
+        let Some(doc) = self.json.get(key) else {
+            return Ok(None);
+        };
+        let mut locations = path.select(&doc);
+        if path.legacy {
+            locations.truncate(1);
+        }
+        let results = locations
+            .iter()
+            .filter_map(|loc| resolve(&doc, loc))
+            .map(f)
+            .collect();
+        collect_matches(path, results).map(Some)
+    }
+
+    // applies `f` to every value the path matches; the key must exist
+    fn json_modify<T>(
+        &self,
+        key: &str,
+        path: &JsonPath,
+        mut f: impl FnMut(&mut Value) -> Result<T, BackendError>,
+    ) -> Result<JsonMatches<T>, BackendError> {
+        let _guard = self.shared_lock();
+        let mut doc = self.json.get_mut(key).ok_or(BackendError::JsonNoKey)?;
+        let locations = path.select(&doc);
+        let results = locations
+            .iter()
+            .map(|loc| match resolve_mut(&mut doc, loc) {
+                Some(value) => f(value),
+                // an earlier change in the same command moved it
+                None => Err(BackendError::JsonPathNotFound(path.raw.clone())),
+            })
+            .collect();
+        collect_matches(path, results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn path(raw: &str) -> JsonPath {
+        JsonPath::parse(raw).unwrap()
+    }
+
+    #[test]
+    fn test_path_parse_and_select() {
+        let doc = json!({
+            "a": 1,
+            "b": {"a": [1, 2, 3], "c": {"a": "x"}},
+            "d": [{"a": true}, {"e": null}]
+        });
+        let select = |raw: &str| -> Vec<Value> {
+            let path = path(raw);
+            path.select(&doc)
+                .iter()
+                .map(|loc| resolve(&doc, loc).unwrap().clone())
+                .collect()
+        };
+        assert_eq!(select("$"), vec![doc.clone()]);
+        assert_eq!(select("."), vec![doc.clone()]);
+        assert_eq!(
+            select("$..a"),
+            vec![json!(1), json!([1, 2, 3]), json!("x"), json!(true)]
+        );
+        assert_eq!(select("$.b.a[*]"), vec![json!(1), json!(2), json!(3)]);
+        assert_eq!(select("$.b.a[-1]"), vec![json!(3)]);
+        assert_eq!(select("$.b.a[0:2]"), vec![json!(1), json!(2)]);
+        assert_eq!(select("$.b.a[::-1]"), vec![json!(3), json!(2), json!(1)]);
+        assert_eq!(select("$['a','b'].c"), vec![json!({"a": "x"})]);
+        assert_eq!(select("$.d[*].*"), vec![json!(true), json!(null)]);
+        assert_eq!(select("b.c.a"), vec![json!("x")]);
+        assert_eq!(select(".b[\"c\"]"), vec![json!({"a": "x"})]);
+        assert!(select("$.missing").is_empty());
+
+        assert!(path("$.a").segments.len() == 1 && !path("$.a").is_legacy());
+        assert!(path(".a").is_legacy());
+        for bad in ["$.", "$..", "$[", "$[x]", "$.a[1", "$['a]", "$[*,0]"] {
+            assert_eq!(JsonPath::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_json_set_and_get() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let key = "doc".to_string();
+        assert_eq!(
+            backend.json_set(key.clone(), &path("$.a"), json!(1), None),
+            Err(BackendError::JsonNewAtRoot)
+        );
+        assert!(backend.json_set(key.clone(), &path("$"), json!({"a": {"b": 1}}), None)?);
+        assert!(!backend.json_set(key.clone(), &path("$.a.b"), json!(2), Some(false))?);
+        assert!(backend.json_set(key.clone(), &path("$.a.c"), json!([1]), Some(false))?);
+        assert!(!backend.json_set(key.clone(), &path("$.x.y"), json!(1), None)?);
+        assert_eq!(
+            backend.json_set(key.clone(), &path(".x.y"), json!(1), None),
+            Err(BackendError::JsonPathNotFound(".x.y".to_string()))
+        );
+
+        assert_eq!(backend.json_get(&key, &[path(".a.c")])?, Some(json!([1])));
+        assert_eq!(backend.json_get(&key, &[path("$..b")])?, Some(json!([1])));
+        assert_eq!(
+            backend.json_get(&key, &[path("$.a.b"), path(".a.c")])?,
+            Some(json!({"$.a.b": [1], ".a.c": [[1]]}))
+        );
+        assert_eq!(backend.json_get("missing", &[path("$")])?, None);
+        assert_eq!(
+            backend.json_mget(&[key.clone(), "missing".to_string()], &path(".a.b")),
+            vec![Some(json!(1)), None]
+        );
+
+        assert_eq!(backend.json_del(&key, &path("$..c")), 1);
+        assert_eq!(backend.json_get(&key, &[])?, Some(json!({"a": {"b": 1}})));
+        assert_eq!(backend.json_del(&key, &path("$")), 1);
+        assert_eq!(backend.json_get(&key, &[])?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_modify() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let doc = json!({"n": 1, "f": 1.5, "s": "ab", "arr": [1, 2, 3], "o": {"n": "x"}});
+        backend.json_set("doc".to_string(), &JsonPath::root(), doc, None)?;
+
+        assert_eq!(
+            backend.json_numincrby("doc", &path("$..n"), &Number::from(2))?,
+            JsonMatches::Path(vec![Some(json!(3)), None])
+        );
+        assert_eq!(
+            backend.json_numincrby("doc", &path(".f"), &Number::from(1))?,
+            JsonMatches::Legacy(json!(2.5))
+        );
+        assert_eq!(
+            backend.json_numincrby("doc", &path(".s"), &Number::from(1)),
+            Err(BackendError::JsonWrongType("a number", "string"))
+        );
+        // b is left as it was, because the sum for a overflows
+        let doc = json!({"b": 1.0, "a": f64::MAX});
+        backend.json_set("big".to_string(), &JsonPath::root(), doc, None)?;
+        let max = Number::from_f64(f64::MAX).unwrap();
+        assert_eq!(
+            backend.json_numincrby("big", &path("$.*"), &max),
+            Err(BackendError::NanOrInfinity)
+        );
+        assert_eq!(backend.json_get("big", &[path("$.b")])?, Some(json!([1.0])));
+        assert_eq!(
+            backend.json_strappend("doc", &path("$.s"), "cd")?,
+            JsonMatches::Path(vec![Some(4)])
+        );
+        assert_eq!(
+            backend.json_arrappend("doc", &path(".arr"), &[json!(4)])?,
+            JsonMatches::Legacy(4)
+        );
+        assert_eq!(
+            backend.json_arrinsert("doc", &path(".arr"), -1, &[json!("x"), json!("y")])?,
+            JsonMatches::Legacy(6)
+        );
+        assert_eq!(
+            backend.json_arrinsert("doc", &path(".arr"), 7, &[json!(0)]),
+            Err(BackendError::JsonIndexOutOfBounds)
+        );
+        assert_eq!(
+            backend.json_arrpop("doc", &path(".arr"), 100)?,
+            JsonMatches::Legacy(Some(json!(4)))
+        );
+        assert_eq!(
+            backend.json_get("doc", &[path(".arr")])?,
+            Some(json!([1, 2, 3, "x", "y"]))
+        );
+        assert_eq!(
+            backend.json_type("doc", &path("$.*"))?,
+            Some(JsonMatches::Path(
+                ["integer", "number", "string", "array", "object"]
+                    .into_iter()
+                    .map(Some)
+                    .collect()
+            ))
+        );
+        assert_eq!(
+            backend.json_objkeys("doc", &path(".o"))?,
+            Some(JsonMatches::Legacy(vec!["n".to_string()]))
+        );
+        assert_eq!(
+            backend.json_strappend("missing", &path("$"), "x"),
+            Err(BackendError::JsonNoKey)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_to_string() {
+        let value = json!({"a": [1, {}], "b": "x"});
+        assert_eq!(
+            json_to_string(&value, "", "", ""),
+            r#"{"a":[1,{}],"b":"x"}"#
+        );
+        assert_eq!(
+            json_to_string(&value, "  ", "\n", " "),
+            "{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": \"x\"\n}"
+        );
+    }
+}
@@ -0,0 +1,370 @@
+use crate::RespFrame;
+
+use super::{Backend, BackendError};
+
+/// Which end of a list to push to or pop from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+// resolves a possibly negative index against a list of `len` elements
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+// clamps an LRANGE/LTRIM style inclusive range; None if it selects nothing
+pub(super) fn resolve_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 { start + len } else { start }.max(0);
+    let stop = if stop < 0 { stop + len } else { stop }.min(len - 1);
+    (start <= stop).then_some((start as usize, stop as usize))
+}
+
+impl Backend {
+    // returns the length of the list afterwards; with `only_existing` (LPUSHX/RPUSHX) nothing is
+    // pushed to a missing list and 0 is returned
+    pub fn list_push(
+        &self,
+        key: String,
+        end: ListEnd,
+        values: Vec<RespFrame>,
+        only_existing: bool,
+    ) -> usize {
+        let _guard = self.shared_lock();
+        if only_existing && !self.list.contains_key(&key) {
+            return 0;
+        }
+        let len = {
+            let mut list = self.list.entry(key.clone()).or_default();
+            for value in values {
+                match end {
+                    ListEnd::Left => list.push_front(value),
+                    ListEnd::Right => list.push_back(value),
+                }
+            }
+            list.len()
+        };
+        self.signal_ready(&key);
+        len
+    }
+
+    // pops up to `count` elements, None if the list does not exist
+    pub fn list_pop(&self, key: &str, end: ListEnd, count: usize) -> Option<Vec<RespFrame>> {
+        let _guard = self.shared_lock();
+        self.list_pop_locked(key, end, count)
+    }
+
+    // callers must hold the keyspace lock
+    fn list_pop_locked(&self, key: &str, end: ListEnd, count: usize) -> Option<Vec<RespFrame>> {
+        let popped = {
+            let mut list = self.list.get_mut(key)?;
+            let count = count.min(list.len());
+            match end {
+                ListEnd::Left => list.drain(..count).collect(),
+                ListEnd::Right => {
+                    let at = list.len() - count;
+                    list.drain(at..).rev().collect()
+                }
+            }
+        };
+        self.list.remove_if(key, |_, list| list.is_empty());
+        Some(popped)
+    }
+
+    pub fn llen(&self, key: &str) -> usize {
+        self.list.get(key).map_or(0, |list| list.len())
+    }
+
+    pub fn lrange(&self, key: &str, start: i64, stop: i64) -> Vec<RespFrame> {
+        let Some(list) = self.list.get(key) else {
+            return vec![];
+        };
+        match resolve_range(start, stop, list.len()) {
+            Some((start, stop)) => list.range(start..=stop).cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn lindex(&self, key: &str, index: i64) -> Option<RespFrame> {
+        let list = self.list.get(key)?;
+        resolve_index(index, list.len()).and_then(|i| list.get(i).cloned())
+    }
+
+    pub fn lset(&self, key: &str, index: i64, value: RespFrame) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        let mut list = self.list.get_mut(key).ok_or(BackendError::NoSuchKey)?;
+        let index = resolve_index(index, list.len()).ok_or(BackendError::IndexOutOfRange)?;
+        list[index] = value;
+        Ok(())
+    }
+
+    // returns the new length, -1 if the pivot was not found or 0 if the list does not exist
+    pub fn linsert(&self, key: &str, before: bool, pivot: &RespFrame, value: RespFrame) -> i64 {
+        let _guard = self.shared_lock();
+        let Some(mut list) = self.list.get_mut(key) else {
+            return 0;
+        };
+        match list.iter().position(|v| v == pivot) {
+            Some(at) => {
+                list.insert(if before { at } else { at + 1 }, value);
+                list.len() as i64
+            }
+            None => -1,
+        }
+    }
+
+    // removes up to |count| occurrences, from the head for a positive count and from the tail
+    // for a negative one; 0 removes all of them
+    pub fn lrem(&self, key: &str, count: i64, value: &RespFrame) -> usize {
+        let _guard = self.shared_lock();
+        let removed = {
+            let Some(mut list) = self.list.get_mut(key) else {
+                return 0;
+            };
+            let limit = match count {
+                0 => usize::MAX,
+                n => n.unsigned_abs() as usize,
+            };
+            let matching = list
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| *v == value)
+                .map(|(i, _)| i);
+            let mut positions = if count < 0 {
+                matching.rev().take(limit).collect::<Vec<_>>()
+            } else {
+                matching.take(limit).collect::<Vec<_>>()
+            };
+            positions.sort_unstable_by(|a, b| b.cmp(a));
+            // positions are in descending order so earlier removals don't shift later ones
+            for &i in positions.iter() {
+                list.remove(i);
+            }
+            positions.len()
+        };
+        self.list.remove_if(key, |_, list| list.is_empty());
+        removed
+    }
+
+    pub fn ltrim(&self, key: &str, start: i64, stop: i64) {
+        let _guard = self.shared_lock();
+        if let Some(mut list) = self.list.get_mut(key) {
+            match resolve_range(start, stop, list.len()) {
+                Some((start, stop)) => {
+                    list.truncate(stop + 1);
+                    list.drain(..start);
+                }
+                None => list.clear(),
+            }
+        }
+        self.list.remove_if(key, |_, list| list.is_empty());
+    }
+
+    /// Positions of `value`, skipping the first `|rank| - 1` matches and scanning from the tail
+    /// for a negative rank. Stops after `count` matches (0 for all) or after comparing `maxlen`
+    /// elements (0 for the whole list).
+    pub fn lpos(
+        &self,
+        key: &str,
+        value: &RespFrame,
+        rank: i64,
+        count: usize,
+        maxlen: usize,
+    ) -> Vec<usize> {
+        let Some(list) = self.list.get(key) else {
+            return vec![];
+        };
+        let maxlen = match maxlen {
+            0 => list.len(),
+            n => n,
+        };
+        let count = match count {
+            0 => usize::MAX,
+            n => n,
+        };
+        let skip = rank.unsigned_abs() as usize - 1;
+        let indexed = list.iter().enumerate();
+        let matches: Box<dyn Iterator<Item = (usize, &RespFrame)>> = if rank < 0 {
+            Box::new(indexed.rev().take(maxlen))
+        } else {
+            Box::new(indexed.take(maxlen))
+        };
+        matches
+            .filter(|(_, v)| *v == value)
+            .map(|(i, _)| i)
+            .skip(skip)
+            .take(count)
+            .collect()
+    }
+
+    // no other writer can observe the element in neither or both lists
+    pub fn lmove(
+        &self,
+        source: &str,
+        destination: &str,
+        from: ListEnd,
+        to: ListEnd,
+    ) -> Option<RespFrame> {
+        let _guard = self.exclusive_lock();
+        let value = self.list_pop_locked(source, from, 1)?.pop()?;
+        {
+            let mut list = self.list.entry(destination.to_string()).or_default();
+            match to {
+                ListEnd::Left => list.push_front(value.clone()),
+                ListEnd::Right => list.push_back(value.clone()),
+            }
+        }
+        self.signal_ready(destination);
+        Some(value)
+    }
+
+    // pops from the first non-empty list among `keys`, returning its key and the elements
+    pub fn lmpop(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+        count: usize,
+    ) -> Option<(String, Vec<RespFrame>)> {
+        let _guard = self.exclusive_lock();
+        keys.iter().find_map(|key| {
+            self.list_pop_locked(key, end, count)
+                .map(|values| (key.clone(), values))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    fn values(v: &[&str]) -> Vec<RespFrame> {
+        v.iter().map(|s| BulkString::from(*s).into()).collect()
+    }
+
+    #[test]
+    fn test_list_push_pop() {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.list_push("l".to_string(), ListEnd::Left, values(&["b", "a"]), false),
+            2
+        );
+        assert_eq!(
+            backend.list_push("l".to_string(), ListEnd::Right, values(&["c", "d"]), false),
+            4
+        );
+        assert_eq!(
+            backend.list_push("missing".to_string(), ListEnd::Left, values(&["x"]), true),
+            0
+        );
+        assert!(!backend.list.contains_key("missing"));
+        assert_eq!(backend.lrange("l", 0, -1), values(&["a", "b", "c", "d"]));
+
+        assert_eq!(
+            backend.list_pop("l", ListEnd::Right, 3),
+            Some(values(&["d", "c", "b"]))
+        );
+        assert_eq!(
+            backend.list_pop("l", ListEnd::Left, 5),
+            Some(values(&["a"]))
+        );
+        assert!(!backend.list.contains_key("l"));
+        assert_eq!(backend.list_pop("l", ListEnd::Left, 1), None);
+    }
+
+    #[test]
+    fn test_list_indexes() {
+        let backend = Backend::new();
+        backend.list_push(
+            "l".to_string(),
+            ListEnd::Right,
+            values(&["a", "b", "c"]),
+            false,
+        );
+        assert_eq!(backend.lrange("l", -2, 100), values(&["b", "c"]));
+        assert_eq!(backend.lrange("l", -100, 0), values(&["a"]));
+        assert!(backend.lrange("l", 2, 1).is_empty());
+        assert!(backend.lrange("l", 5, 10).is_empty());
+
+        assert_eq!(backend.lindex("l", -1), Some(BulkString::from("c").into()));
+        assert_eq!(backend.lindex("l", 3), None);
+        assert_eq!(backend.lset("l", -3, BulkString::from("z").into()), Ok(()));
+        assert_eq!(backend.lindex("l", 0), Some(BulkString::from("z").into()));
+        assert_eq!(
+            backend.lset("l", 3, BulkString::from("z").into()),
+            Err(BackendError::IndexOutOfRange)
+        );
+        assert_eq!(
+            backend.lset("missing", 0, BulkString::from("z").into()),
+            Err(BackendError::NoSuchKey)
+        );
+    }
+
+    #[test]
+    fn test_list_editing() {
+        let backend = Backend::new();
+        let list = values(&["a", "b", "a", "c", "a"]);
+        backend.list_push("l".to_string(), ListEnd::Right, list, false);
+        let a: RespFrame = BulkString::from("a").into();
+
+        assert_eq!(backend.lpos("l", &a, 1, 0, 0), vec![0, 2, 4]);
+        assert_eq!(backend.lpos("l", &a, -1, 2, 0), vec![4, 2]);
+        assert_eq!(backend.lpos("l", &a, 2, 1, 0), vec![2]);
+        assert_eq!(backend.lpos("l", &a, 1, 0, 2), vec![0]);
+
+        assert_eq!(backend.lrem("l", -2, &a), 2);
+        assert_eq!(backend.lrange("l", 0, -1), values(&["a", "b", "c"]));
+        let b = BulkString::from("b").into();
+        assert_eq!(backend.linsert("l", false, &b, a.clone()), 4);
+        assert_eq!(
+            backend.linsert("l", true, &BulkString::from("x").into(), a.clone()),
+            -1
+        );
+        assert_eq!(backend.linsert("missing", true, &b, a.clone()), 0);
+        assert_eq!(backend.lrange("l", 0, -1), values(&["a", "b", "a", "c"]));
+
+        backend.ltrim("l", 1, -2);
+        assert_eq!(backend.lrange("l", 0, -1), values(&["b", "a"]));
+        backend.ltrim("l", 5, 10);
+        assert!(!backend.list.contains_key("l"));
+    }
+
+    #[test]
+    fn test_lmove_and_lmpop() {
+        let backend = Backend::new();
+        backend.list_push(
+            "src".to_string(),
+            ListEnd::Right,
+            values(&["a", "b"]),
+            false,
+        );
+        assert_eq!(
+            backend.lmove("src", "dst", ListEnd::Right, ListEnd::Left),
+            Some(BulkString::from("b").into())
+        );
+        // a list can be rotated onto itself
+        assert_eq!(
+            backend.lmove("dst", "dst", ListEnd::Left, ListEnd::Right),
+            Some(BulkString::from("b").into())
+        );
+        assert_eq!(
+            backend.lmove("missing", "dst", ListEnd::Left, ListEnd::Left),
+            None
+        );
+
+        let keys = ["missing".to_string(), "src".to_string(), "dst".to_string()];
+        assert_eq!(
+            backend.lmpop(&keys, ListEnd::Left, 5),
+            Some(("src".to_string(), values(&["a"])))
+        );
+        assert_eq!(
+            backend.lmpop(&keys, ListEnd::Left, 5),
+            Some(("dst".to_string(), values(&["b"])))
+        );
+        assert_eq!(backend.lmpop(&keys, ListEnd::Left, 1), None);
+    }
+}
@@ -0,0 +1,216 @@
+use bytes::BytesMut;
+
+use crate::{RespDecode, RespEncode, RespFrame};
+
+// how a value is stored after its length prefix
+const TAG_BULK_STRING: u8 = 0;
+const TAG_FRAME: u8 = 1;
+
+/// Field/value pairs packed back to back into a single buffer, in the spirit of Redis'
+/// listpack. Each entry is `<field len><field><tag><value len><value>` with varint lengths;
+/// bulk string values are stored raw, anything else as its RESP encoding. Lookups are a
+/// linear scan, which beats hashing for the handful of entries this is used for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Listpack {
+    buf: Vec<u8>,
+    len: usize,
+}
+
+struct Entry<'a> {
+    start: usize,
+    end: usize,
+    field: &'a str,
+    tag: u8,
+    value: &'a [u8],
+}
+
+impl Listpack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // bytes used by the packed entries
+    pub fn blob_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn get(&self, field: &str) -> Option<RespFrame> {
+        self.find(field).map(|e| decode_value(e.tag, e.value))
+    }
+
+    pub fn contains(&self, field: &str) -> bool {
+        self.find(field).is_some()
+    }
+
+    // returns true if the field is new
+    pub fn insert(&mut self, field: &str, value: &RespFrame) -> bool {
+        let mut encoded = Vec::new();
+        encode_entry(&mut encoded, field, value);
+        match self.find(field).map(|e| (e.start, e.end)) {
+            Some((start, end)) => {
+                self.buf.splice(start..end, encoded);
+                false
+            }
+            None => {
+                self.buf.extend_from_slice(&encoded);
+                self.len += 1;
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, field: &str) -> bool {
+        match self.find(field).map(|e| (e.start, e.end)) {
+            Some((start, end)) => {
+                self.buf.drain(start..end);
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries().map(|e| e.field)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, RespFrame)> {
+        self.entries()
+            .map(|e| (e.field, decode_value(e.tag, e.value)))
+    }
+
+    fn find(&self, field: &str) -> Option<Entry<'_>> {
+        self.entries().find(|e| e.field == field)
+    }
+
+    fn entries(&self) -> impl Iterator<Item = Entry<'_>> {
+        let mut pos = 0;
+        std::iter::from_fn(move || {
+            if pos >= self.buf.len() {
+                return None;
+            }
+            let start = pos;
+            let field = read_chunk(&self.buf, &mut pos);
+            let tag = self.buf[pos];
+            pos += 1;
+            let value = read_chunk(&self.buf, &mut pos);
+            Some(Entry {
+                start,
+                end: pos,
+                // fields are only ever written from a &str
+                field: std::str::from_utf8(field).unwrap_or_default(),
+                tag,
+                value,
+            })
+        })
+    }
+}
+
+// the number of bytes a value takes once packed, used for the listpack value size limit
+pub(crate) fn value_len(value: &RespFrame) -> usize {
+    match value {
+        RespFrame::BulkString(s) => s.len(),
+        frame => frame.clone().encode().len(),
+    }
+}
+
+fn encode_entry(buf: &mut Vec<u8>, field: &str, value: &RespFrame) {
+    write_chunk(buf, field.as_bytes());
+    match value {
+        RespFrame::BulkString(s) => {
+            buf.push(TAG_BULK_STRING);
+            write_chunk(buf, s);
+        }
+        frame => {
+            buf.push(TAG_FRAME);
+            write_chunk(buf, &frame.clone().encode());
+        }
+    }
+}
+
+fn decode_value(tag: u8, value: &[u8]) -> RespFrame {
+    match tag {
+        TAG_BULK_STRING => RespFrame::BulkString(value.into()),
+        _ => RespFrame::decode(&mut BytesMut::from(value))
+            .expect("listpack values are encoded by encode_entry"),
+    }
+}
+
+fn write_chunk(buf: &mut Vec<u8>, data: &[u8]) {
+    let mut len = data.len();
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            buf.push(byte);
+            break;
+        }
+        buf.push(byte | 0x80);
+    }
+    buf.extend_from_slice(data);
+}
+
+fn read_chunk<'a>(buf: &'a [u8], pos: &mut usize) -> &'a [u8] {
+    let mut len = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = buf[*pos];
+        *pos += 1;
+        len |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    let data = &buf[*pos..*pos + len];
+    *pos += len;
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_listpack_insert_get_remove() {
+        let mut lp = Listpack::new();
+        assert!(lp.insert("a", &BulkString::from("1").into()));
+        assert!(lp.insert("b", &RespFrame::Integer(42)));
+        assert!(!lp.insert("a", &BulkString::from("a much longer value").into()));
+        assert_eq!(lp.len(), 2);
+        assert_eq!(
+            lp.get("a"),
+            Some(BulkString::from("a much longer value").into())
+        );
+        assert_eq!(lp.get("b"), Some(RespFrame::Integer(42)));
+        assert_eq!(lp.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        assert!(lp.remove("a"));
+        assert!(!lp.remove("a"));
+        assert_eq!(lp.get("a"), None);
+        assert_eq!(
+            lp.iter().collect::<Vec<_>>(),
+            vec![("b", RespFrame::Integer(42))]
+        );
+    }
+
+    #[test]
+    fn test_listpack_long_entries() {
+        let mut lp = Listpack::new();
+        let value = BulkString::from("x".repeat(300));
+        lp.insert("field", &value.clone().into());
+        assert_eq!(lp.get("field"), Some(value.into()));
+        // 2 byte varint + 300 bytes of value, 1 byte varint + 5 bytes of field, 1 tag byte
+        assert_eq!(lp.blob_len(), 309);
+    }
+}
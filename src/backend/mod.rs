@@ -1,17 +1,234 @@
-use std::{ops::Deref, sync::Arc};
+mod blocking;
+mod bloom;
+mod cms;
+mod config;
+mod cuckoo;
+mod geo;
+mod hash;
+mod hyperloglog;
+mod intset;
+mod json;
+mod list;
+mod listpack;
+mod search;
+mod set;
+mod skiplist;
+mod stream;
+mod stream_group;
+mod tdigest;
+mod timeseries;
+mod topk;
+mod zset;
+
+use std::{
+    collections::VecDeque,
+    ops::Deref,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use dashmap::{DashMap, DashSet};
+use thiserror::Error;
+
+use crate::{RespFrame, SimpleError};
+use json::json_memory_usage;
+
+pub use blocking::BlockedClients;
+pub use bloom::{BloomFilter, BloomInfo, BloomOptions};
+pub use cms::{CountMinSketch, CountMinSketchInfo};
+pub use config::Config;
+pub use cuckoo::{CuckooFilter, CuckooInfo, CuckooOptions};
+pub use geo::{GeoMatch, GeoOrigin, GeoQuery, GeoShape};
+pub use hash::{ExpireCondition, FieldExpiry, Hash};
+pub use intset::IntSet;
+pub use json::{json_to_string, json_type_name, JsonMatches, JsonPath};
+pub use list::ListEnd;
+pub use listpack::Listpack;
+pub use search::{
+    AggregateQuery, AggregateRow, IndexDefinition, NumericBound, Reducer, SearchClause, SearchDoc,
+    SearchField, SearchFieldType, SearchIndex, SearchIndexInfo, SearchOptions, SearchQuery,
+};
+pub use set::{Set, SetOp};
+pub use skiplist::SkipList;
+pub use stream::{Stream, StreamFields, StreamId, StreamTrim, TrimStrategy, XAddId};
+pub use stream_group::{
+    AutoClaimed, ClaimOptions, ConsumerGroup, ConsumerInfo, DeliveredEntry, GroupInfo, PendingInfo,
+    PendingSummary, StreamInfo,
+};
+pub use tdigest::TDigest;
+pub use timeseries::{
+    DuplicatePolicy, LabelMatcher, Labels, Sample, TimeSeries, TimeSeriesInfo, TsAggregation,
+    TsAggregator, TsOptions, TsRangeQuery, TsRule,
+};
+pub use topk::{TopK, TopKOptions, TopKSketchInfo};
+pub use zset::{Aggregate, LexBound, RangeBy, ScoreBound, ZAddFlags, ZRangeSpec, ZSet};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum BackendError {
+    #[error("ERR hash value is not an integer")]
+    HashValueNotInteger,
+    #[error("ERR hash value is not a float")]
+    HashValueNotFloat,
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+    #[error("ERR increment would produce NaN or Infinity")]
+    NanOrInfinity,
+    #[error("ERR Unknown option or number of arguments for CONFIG SET - '{0}'")]
+    UnknownConfig(String),
+    #[error("ERR CONFIG SET failed (possibly related to argument '{0}') - argument couldn't be parsed into an integer")]
+    InvalidConfigValue(String),
+    #[error("ERR no such key")]
+    NoSuchKey,
+    #[error("ERR index out of range")]
+    IndexOutOfRange,
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNan,
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+    #[error("ERR The stream has exhausted the last possible ID, unable to add more items")]
+    StreamExhausted,
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    StreamNoKey,
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+    #[error("WRONGTYPE Key is not a valid HyperLogLog string value.")]
+    NotHll,
+    #[error("INVALIDOBJ Corrupted HLL object detected")]
+    CorruptHll,
+    #[error("ERR invalid longitude,latitude pair {0}")]
+    InvalidLonLat(String),
+    #[error("ERR could not decode requested zset member")]
+    GeoMemberNotFound,
+    #[error("ERR Path '{0}' does not exist")]
+    JsonPathNotFound(String),
+    #[error("ERR wrong type of path value - expected {0} but found {1}")]
+    JsonWrongType(&'static str, &'static str),
+    #[error("ERR new objects must be created at the root")]
+    JsonNewAtRoot,
+    #[error("ERR could not perform this operation on a key that doesn't exist")]
+    JsonNoKey,
+    #[error("ERR index out of bounds")]
+    JsonIndexOutOfBounds,
+    #[error("ERR TSDB: key already exists")]
+    TsKeyExists,
+    #[error("ERR TSDB: the key does not exist")]
+    TsNoKey,
+    #[error("ERR TSDB: Error at upsert, update is not supported when DUPLICATE_POLICY is set to BLOCK mode")]
+    TsDuplicate,
+    #[error("ERR TSDB: Timestamp is older than retention")]
+    TsTooOld,
+    #[error("ERR TSDB: timestamp must be equal to or higher than the maximum existing timestamp")]
+    TsIncrTooOld,
+    #[error("ERR TSDB: the source key and destination key should be different")]
+    TsSameRuleKey,
+    #[error("ERR TSDB: the source or destination key is already part of a compaction rule")]
+    TsRuleExists,
+    #[error("ERR TSDB: compaction rule does not exist")]
+    TsNoRule,
+    #[error("ERR item exists")]
+    ItemExists,
+    #[error("ERR not found")]
+    NotFound,
+    #[error("ERR non scaling filter is full")]
+    BloomFull,
+    #[error("ERR Filter is full")]
+    CuckooFull,
+    #[error("ERR filter would be too large")]
+    FilterTooLarge,
+    #[error("ERR received bad data")]
+    BadDump,
+    #[error("ERR CMS: key already exists")]
+    CmsKeyExists,
+    #[error("ERR CMS: key does not exist")]
+    CmsNoKey,
+    #[error("ERR CMS: width/depth is not equal")]
+    CmsDimensions,
+    #[error("ERR sketch would be too large")]
+    SketchTooLarge,
+    #[error("ERR TopK: key already exists")]
+    TopKKeyExists,
+    #[error("ERR TopK: key does not exist")]
+    TopKNoKey,
+    #[error("ERR T-Digest: key already exist")]
+    TDigestKeyExists,
+    #[error("ERR T-Digest: key does not exist")]
+    TDigestNoKey,
+    #[error("ERR Index already exists")]
+    SearchIndexExists,
+    #[error("ERR {0}: no such index")]
+    SearchNoIndex(String),
+    #[error("ERR Unknown field `{0}`")]
+    SearchUnknownField(String),
+    #[error("ERR Field `{0}` is not a {1} field")]
+    SearchFieldType(String, &'static str),
+}
+
+// the type of the value a key holds; each type is kept in a map of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    String,
+    Hash,
+    Set,
+    List,
+    ZSet,
+    Stream,
+    Json,
+    TimeSeries,
+    Bloom,
+    Cuckoo,
+    Cms,
+    TopK,
+    TDigest,
+}
 
-use crate::RespFrame;
+impl KeyType {
+    const ALL: [KeyType; 13] = [
+        KeyType::String,
+        KeyType::Hash,
+        KeyType::Set,
+        KeyType::List,
+        KeyType::ZSet,
+        KeyType::Stream,
+        KeyType::Json,
+        KeyType::TimeSeries,
+        KeyType::Bloom,
+        KeyType::Cuckoo,
+        KeyType::Cms,
+        KeyType::TopK,
+        KeyType::TDigest,
+    ];
+}
 
 #[derive(Debug, Clone)]
 pub struct Backend(Arc<BackendInner>);
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct BackendInner {
     pub(crate) map: DashMap<String, RespFrame>,
-    pub(crate) hmap: DashMap<String, DashMap<String, RespFrame>>,
-    pub(crate) set: DashMap<String, DashSet<String>>,
+    pub(crate) hmap: DashMap<String, Hash>,
+    // keys of hashes that may have field TTLs, which are all that the sweeper visits
+    pub(crate) hash_ttl_keys: DashSet<String>,
+    pub(crate) set: DashMap<String, Set>,
+    pub(crate) list: DashMap<String, VecDeque<RespFrame>>,
+    pub(crate) zset: DashMap<String, ZSet>,
+    pub(crate) stream: DashMap<String, Stream>,
+    pub(crate) json: DashMap<String, serde_json::Value>,
+    pub(crate) timeseries: DashMap<String, TimeSeries>,
+    pub(crate) bloom: DashMap<String, BloomFilter>,
+    pub(crate) cuckoo: DashMap<String, CuckooFilter>,
+    pub(crate) cms: DashMap<String, CountMinSketch>,
+    pub(crate) topk: DashMap<String, TopK>,
+    pub(crate) tdigest: DashMap<String, TDigest>,
+    // FT.* indexes by name, each kept up to date by writes to the hashes it covers
+    pub(crate) search: DashMap<String, SearchIndex>,
+    pub(crate) config: Config,
+    pub(crate) blocked: BlockedClients,
+    // single-key writes hold this shared, commands spanning several keys hold it exclusively
+    keyspace_lock: RwLock<()>,
 }
 
 impl Deref for Backend {
@@ -32,7 +249,22 @@ impl Default for BackendInner {
         BackendInner {
             map: DashMap::new(),
             hmap: DashMap::new(),
+            hash_ttl_keys: DashSet::new(),
             set: DashMap::new(),
+            list: DashMap::new(),
+            zset: DashMap::new(),
+            stream: DashMap::new(),
+            json: DashMap::new(),
+            timeseries: DashMap::new(),
+            bloom: DashMap::new(),
+            cuckoo: DashMap::new(),
+            cms: DashMap::new(),
+            topk: DashMap::new(),
+            tdigest: DashMap::new(),
+            search: DashMap::new(),
+            config: Config::default(),
+            blocked: BlockedClients::default(),
+            keyspace_lock: RwLock::new(()),
         }
     }
 }
@@ -46,34 +278,172 @@ impl Backend {
         self.map.get(key).map(|v| v.value().clone())
     }
 
+    /// Stores a string value at `key`, replacing whatever value of any type it held.
     pub fn set(&self, key: String, value: RespFrame) {
-        self.map.insert(key, value);
+        // the key moves between maps, which must look atomic to writers of the other types
+        let _guard = self.exclusive_lock();
+        let was_hash = self.hmap.remove(&key).is_some();
+        let replaced = [
+            was_hash,
+            self.set.remove(&key).is_some(),
+            self.list.remove(&key).is_some(),
+            self.zset.remove(&key).is_some(),
+            self.stream.remove(&key).is_some(),
+            self.json.remove(&key).is_some(),
+            self.timeseries.remove(&key).is_some(),
+            self.bloom.remove(&key).is_some(),
+            self.cuckoo.remove(&key).is_some(),
+            self.cms.remove(&key).is_some(),
+            self.topk.remove(&key).is_some(),
+            self.tdigest.remove(&key).is_some(),
+        ]
+        .contains(&true);
+        if was_hash {
+            self.hash_ttl_keys.remove(&key);
+            self.index_hash(&key);
+        }
+        self.map.insert(key.clone(), value);
+        if replaced {
+            // blocked commands find the key no longer holds their type
+            self.signal_ready(&key);
+        }
     }
 
-    pub fn hget(&self, key: &str, field: &str) -> Option<RespFrame> {
-        self.hmap
-            .get(key)
-            .and_then(|v| v.get(field).map(|v| v.value().clone()))
+    pub fn key_type(&self, key: &str) -> Option<KeyType> {
+        KeyType::ALL.into_iter().find(|t| match t {
+            KeyType::String => self.map.contains_key(key),
+            KeyType::Hash => self.hmap.contains_key(key),
+            KeyType::Set => self.set.contains_key(key),
+            KeyType::List => self.list.contains_key(key),
+            KeyType::ZSet => self.zset.contains_key(key),
+            KeyType::Stream => self.stream.contains_key(key),
+            KeyType::Json => self.json.contains_key(key),
+            KeyType::TimeSeries => self.timeseries.contains_key(key),
+            KeyType::Bloom => self.bloom.contains_key(key),
+            KeyType::Cuckoo => self.cuckoo.contains_key(key),
+            KeyType::Cms => self.cms.contains_key(key),
+            KeyType::TopK => self.topk.contains_key(key),
+            KeyType::TDigest => self.tdigest.contains_key(key),
+        })
     }
 
-    pub fn hset(&self, key: String, field: String, value: RespFrame) {
-        let hmap = self.hmap.entry(key).or_default();
-        hmap.insert(field, value);
+    // whether a write that would create `key` as `expected` must be refused with WRONGTYPE
+    pub fn holds_other_type(&self, key: &str, expected: KeyType) -> bool {
+        self.key_type(key).is_some_and(|t| t != expected)
     }
 
-    pub fn hgetall(&self, key: &str) -> Option<DashMap<String, RespFrame>> {
-        self.hmap.get(key).map(|v| v.clone())
+    // the internal encoding of the value stored at key, as reported by OBJECT ENCODING
+    pub fn object_encoding(&self, key: &str) -> Option<&'static str> {
+        if let Some(value) = self.map.get(key) {
+            let encoding = match frame_to_string(value.value()) {
+                Some(s) if s.parse::<i64>().is_ok_and(|v| v.to_string() == s) => "int",
+                Some(s) if s.len() <= 44 => "embstr",
+                _ => "raw",
+            };
+            return Some(encoding);
+        }
+        if let Some(hash) = self.hmap.get(key) {
+            return Some(hash.encoding());
+        }
+        if let Some(set) = self.set.get(key) {
+            return Some(set.encoding());
+        }
+        if self.list.contains_key(key) {
+            return Some("quicklist");
+        }
+        if self.zset.contains_key(key) {
+            return Some("skiplist");
+        }
+        if self.stream.contains_key(key) {
+            return Some("stream");
+        }
+        // module types have no encodings of their own, which Redis reports as raw
+        let module = self.json.contains_key(key)
+            || self.timeseries.contains_key(key)
+            || self.bloom.contains_key(key)
+            || self.cuckoo.contains_key(key)
+            || self.cms.contains_key(key)
+            || self.topk.contains_key(key)
+            || self.tdigest.contains_key(key);
+        module.then_some("raw")
+    }
+
+    // a rough estimate of the bytes used by the key and its value, as reported by MEMORY USAGE
+    pub fn memory_usage(&self, key: &str) -> Option<usize> {
+        let value = if let Some(value) = self.map.get(key) {
+            size_of::<RespFrame>() + frame_heap_size(value.value())
+        } else if let Some(hash) = self.hmap.get(key) {
+            hash.memory_usage()
+        } else if let Some(set) = self.set.get(key) {
+            set.memory_usage()
+        } else if let Some(list) = self.list.get(key) {
+            size_of::<VecDeque<RespFrame>>()
+                + list.capacity() * size_of::<RespFrame>()
+                + list.iter().map(frame_heap_size).sum::<usize>()
+        } else if let Some(zset) = self.zset.get(key) {
+            zset.memory_usage()
+        } else if let Some(stream) = self.stream.get(key) {
+            stream.memory_usage()
+        } else if let Some(json) = self.json.get(key) {
+            json_memory_usage(json.value())
+        } else if let Some(series) = self.timeseries.get(key) {
+            series.memory_usage()
+        } else if let Some(bloom) = self.bloom.get(key) {
+            bloom.memory_usage()
+        } else if let Some(cuckoo) = self.cuckoo.get(key) {
+            cuckoo.memory_usage()
+        } else if let Some(cms) = self.cms.get(key) {
+            cms.memory_usage()
+        } else if let Some(topk) = self.topk.get(key) {
+            topk.memory_usage()
+        } else if let Some(tdigest) = self.tdigest.get(key) {
+            tdigest.memory_usage()
+        } else {
+            return None;
+        };
+        Some(size_of::<String>() + key.len() + value)
+    }
+
+    pub(crate) fn shared_lock(&self) -> RwLockReadGuard<'_, ()> {
+        self.keyspace_lock.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn exclusive_lock(&self) -> RwLockWriteGuard<'_, ()> {
+        self.keyspace_lock
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
     }
+}
+
+// current unix time in milliseconds
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
 
-    pub fn sadd(&self, key: String, value: String) -> bool {
-        let set = self.set.entry(key).or_default();
-        set.insert(value)
+pub(crate) fn frame_to_string(frame: &RespFrame) -> Option<String> {
+    match frame {
+        RespFrame::BulkString(s) => String::from_utf8(s.0.clone()).ok(),
+        RespFrame::SimpleString(s) => Some(s.0.clone()),
+        RespFrame::Integer(i) => Some(i.to_string()),
+        RespFrame::Double(d) => Some(d.to_string()),
+        _ => None,
     }
+}
 
-    pub fn sismember(&self, key: &str, value: &str) -> bool {
-        let set = self.set.get(key).unwrap();
+// bytes a frame owns on the heap, not counting the frame itself
+pub(crate) fn frame_heap_size(frame: &RespFrame) -> usize {
+    match frame {
+        RespFrame::BulkString(s) => s.len(),
+        RespFrame::SimpleString(s) => s.0.len(),
+        RespFrame::Error(e) => e.0.len(),
+        _ => 0,
+    }
+}
 
-        let b = set.get(value).is_some();
-        b
+impl From<BackendError> for RespFrame {
+    fn from(e: BackendError) -> Self {
+        SimpleError::new(e.to_string()).into()
     }
 }
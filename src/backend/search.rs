@@ -0,0 +1,914 @@
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap, HashSet},
+};
+
+use dashmap::mapref::entry::Entry;
+
+use crate::RespFrame;
+
+use super::{frame_to_string, Backend, BackendError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchFieldType {
+    // tags are split on `separator` and, unless case sensitive, lowercased
+    Tag {
+        separator: char,
+        case_sensitive: bool,
+    },
+    Numeric,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchField {
+    // the hash field indexed
+    pub name: String,
+    // what queries call it, which is `name` unless given with AS
+    pub alias: String,
+    pub kind: SearchFieldType,
+}
+
+/// What FT.CREATE indexes: the hashes whose keys start with one of `prefixes` (all of them if
+/// there are none), by the fields in `fields`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexDefinition {
+    pub prefixes: Vec<String>,
+    pub fields: Vec<SearchField>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumericBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchClause {
+    // `@field:{a | b}`, any of the tags
+    Tag {
+        field: String,
+        tags: Vec<String>,
+    },
+    // `@field:[min max]`
+    Numeric {
+        field: String,
+        min: NumericBound,
+        max: NumericBound,
+    },
+}
+
+/// An FT.SEARCH query: clauses that must all hold, each negated if prefixed with `-`. `*`
+/// has no clauses and matches every document.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    clauses: Vec<(bool, SearchClause)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchOptions {
+    // the fields to return, all of them if None
+    pub return_fields: Option<Vec<String>>,
+    // (field, descending); documents are ordered by key otherwise
+    pub sort_by: Option<(String, bool)>,
+    pub offset: usize,
+    pub num: usize,
+}
+
+/// A document FT.SEARCH found, with the fields it returns.
+pub type SearchDoc = (String, Vec<(String, RespFrame)>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reducer {
+    Count,
+    Sum(String),
+}
+
+/// An FT.AGGREGATE pipeline, which loads fields, groups, sorts and limits in that order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AggregateQuery {
+    pub load: Vec<String>,
+    pub group_by: Option<Vec<String>>,
+    // each reducer with the name of the property it produces
+    pub reducers: Vec<(Reducer, String)>,
+    // (property, descending)
+    pub sort_by: Vec<(String, bool)>,
+    // (offset, num)
+    pub limit: Option<(usize, usize)>,
+}
+
+/// An FT.AGGREGATE result row, property names with their values.
+pub type AggregateRow = Vec<(String, Option<String>)>;
+
+// a hash that matched a query, by key
+type MatchedDoc = (String, HashMap<String, RespFrame>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchIndexInfo {
+    pub name: String,
+    pub definition: IndexDefinition,
+    pub num_docs: usize,
+}
+
+/// A secondary index over hashes, kept up to date by every hash write. Lookups only narrow
+/// down the candidates; each is checked against the hash itself, whose fields may have
+/// expired since they were indexed.
+#[derive(Debug, Clone)]
+pub struct SearchIndex {
+    definition: IndexDefinition,
+    // field alias -> tag -> keys
+    tags: HashMap<String, HashMap<String, HashSet<String>>>,
+    // field alias -> (sortable value, key)
+    numbers: HashMap<String, BTreeSet<(u64, String)>>,
+    // every indexed key with the values it was indexed under, by field alias
+    docs: HashMap<String, Vec<(String, IndexedValue)>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum IndexedValue {
+    Tags(Vec<String>),
+    Number(f64),
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            return_fields: None,
+            sort_by: None,
+            offset: 0,
+            num: 10,
+        }
+    }
+}
+
+impl SearchField {
+    fn value(&self, doc: &HashMap<String, RespFrame>) -> Option<IndexedValue> {
+        let value = frame_to_string(doc.get(&self.name)?)?;
+        match self.kind {
+            SearchFieldType::Tag {
+                separator,
+                case_sensitive,
+            } => {
+                let tags = value
+                    .split(separator)
+                    .map(|tag| normalize_tag(tag, case_sensitive))
+                    .filter(|tag| !tag.is_empty())
+                    .collect::<Vec<_>>();
+                (!tags.is_empty()).then_some(IndexedValue::Tags(tags))
+            }
+            SearchFieldType::Numeric => value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| !v.is_nan())
+                .map(IndexedValue::Number),
+        }
+    }
+}
+
+fn normalize_tag(tag: &str, case_sensitive: bool) -> String {
+    match case_sensitive {
+        true => tag.trim().to_string(),
+        false => tag.trim().to_lowercase(),
+    }
+}
+
+// maps a float to an integer of the same order, so that numeric indexes can be B-trees
+fn sortable(value: f64) -> u64 {
+    let bits = value.to_bits();
+    match bits >> 63 {
+        1 => !bits,
+        _ => bits | 1 << 63,
+    }
+}
+
+impl SearchClause {
+    fn field(&self) -> &str {
+        match self {
+            SearchClause::Tag { field, .. } | SearchClause::Numeric { field, .. } => field,
+        }
+    }
+
+    fn matches(&self, field: &SearchField, value: Option<&IndexedValue>) -> bool {
+        match (self, value) {
+            (SearchClause::Tag { tags, .. }, Some(IndexedValue::Tags(doc_tags))) => {
+                let case_sensitive = matches!(
+                    field.kind,
+                    SearchFieldType::Tag {
+                        case_sensitive: true,
+                        ..
+                    }
+                );
+                tags.iter()
+                    .any(|tag| doc_tags.contains(&normalize_tag(tag, case_sensitive)))
+            }
+            (SearchClause::Numeric { min, max, .. }, Some(IndexedValue::Number(v))) => {
+                let above = match min.exclusive {
+                    true => *v > min.value,
+                    false => *v >= min.value,
+                };
+                let below = match max.exclusive {
+                    true => *v < max.value,
+                    false => *v <= max.value,
+                };
+                above && below
+            }
+            _ => false,
+        }
+    }
+}
+
+impl SearchQuery {
+    /// Parses `*`, or clauses like `@status:{active | new} -@age:[(18 +inf]` separated by
+    /// whitespace. Tags may escape characters with a backslash.
+    pub fn parse(query: &str) -> Option<Self> {
+        let query = query.trim();
+        if query == "*" {
+            return Some(SearchQuery::default());
+        }
+        let mut chars = query.chars().peekable();
+        let mut clauses = Vec::new();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            if chars.peek().is_none() {
+                break;
+            }
+            let negated = chars.next_if_eq(&'-').is_some();
+            if chars.next()? != '@' {
+                return None;
+            }
+            let mut field = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                field.push(c);
+            }
+            if field.is_empty() || chars.next()? != ':' {
+                return None;
+            }
+            let clause = match chars.next()? {
+                '{' => {
+                    let (mut tags, mut tag) = (Vec::new(), String::new());
+                    loop {
+                        match chars.next()? {
+                            '\\' => tag.push(chars.next()?),
+                            '|' => tags.push(std::mem::take(&mut tag)),
+                            '}' => break,
+                            c => tag.push(c),
+                        }
+                    }
+                    tags.push(tag);
+                    let tags = tags
+                        .into_iter()
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect::<Vec<_>>();
+                    if tags.is_empty() {
+                        return None;
+                    }
+                    SearchClause::Tag { field, tags }
+                }
+                '[' => {
+                    let mut range = String::new();
+                    loop {
+                        match chars.next()? {
+                            ']' => break,
+                            c => range.push(c),
+                        }
+                    }
+                    let mut bounds = range
+                        .split(|c: char| c.is_whitespace() || c == ',')
+                        .filter(|s| !s.is_empty());
+                    let min = parse_bound(bounds.next()?)?;
+                    let max = parse_bound(bounds.next()?)?;
+                    if bounds.next().is_some() {
+                        return None;
+                    }
+                    SearchClause::Numeric { field, min, max }
+                }
+                _ => return None,
+            };
+            clauses.push((negated, clause));
+        }
+        match clauses.is_empty() {
+            true => None,
+            false => Some(SearchQuery { clauses }),
+        }
+    }
+}
+
+// `(` makes a bound exclusive, and `-inf`/`+inf` leave the range open
+fn parse_bound(bound: &str) -> Option<NumericBound> {
+    let (bound, exclusive) = match bound.strip_prefix('(') {
+        Some(bound) => (bound, true),
+        None => (bound, false),
+    };
+    let value = bound.parse::<f64>().ok().filter(|v| !v.is_nan())?;
+    Some(NumericBound { value, exclusive })
+}
+
+impl SearchIndex {
+    pub fn new(definition: IndexDefinition) -> Self {
+        SearchIndex {
+            definition,
+            tags: HashMap::new(),
+            numbers: HashMap::new(),
+            docs: HashMap::new(),
+        }
+    }
+
+    pub fn covers(&self, key: &str) -> bool {
+        let prefixes = &self.definition.prefixes;
+        prefixes.is_empty() || prefixes.iter().any(|p| key.starts_with(p.as_str()))
+    }
+
+    fn field(&self, alias: &str) -> Option<&SearchField> {
+        self.definition.fields.iter().find(|f| f.alias == alias)
+    }
+
+    /// Replaces what the index holds for `key` with the hash `doc`, or drops the key if the
+    /// hash is gone.
+    pub fn update(&mut self, key: &str, doc: Option<&HashMap<String, RespFrame>>) {
+        for (alias, value) in self.docs.remove(key).unwrap_or_default() {
+            match value {
+                IndexedValue::Tags(tags) => {
+                    let Some(index) = self.tags.get_mut(&alias) else {
+                        continue;
+                    };
+                    for tag in tags {
+                        if let Some(keys) = index.get_mut(&tag) {
+                            keys.remove(key);
+                            if keys.is_empty() {
+                                index.remove(&tag);
+                            }
+                        }
+                    }
+                }
+                IndexedValue::Number(v) => {
+                    if let Some(index) = self.numbers.get_mut(&alias) {
+                        index.remove(&(sortable(v), key.to_string()));
+                    }
+                }
+            }
+        }
+
+        let Some(doc) = doc else {
+            return;
+        };
+        // a NUMERIC field that does not hold a number keeps the whole hash out of the index
+        let unindexable = self.definition.fields.iter().any(|f| {
+            f.kind == SearchFieldType::Numeric
+                && doc.contains_key(&f.name)
+                && f.value(doc).is_none()
+        });
+        if unindexable {
+            return;
+        }
+        let values = self
+            .definition
+            .fields
+            .iter()
+            .filter_map(|f| Some((f.alias.clone(), f.value(doc)?)))
+            .collect::<Vec<_>>();
+        for (alias, value) in values.iter() {
+            match value {
+                IndexedValue::Tags(tags) => {
+                    let index = self.tags.entry(alias.clone()).or_default();
+                    for tag in tags {
+                        index
+                            .entry(tag.clone())
+                            .or_default()
+                            .insert(key.to_string());
+                    }
+                }
+                IndexedValue::Number(v) => {
+                    self.numbers
+                        .entry(alias.clone())
+                        .or_default()
+                        .insert((sortable(*v), key.to_string()));
+                }
+            }
+        }
+        self.docs.insert(key.to_string(), values);
+    }
+
+    fn check(&self, query: &SearchQuery) -> Result<(), BackendError> {
+        for (_, clause) in query.clauses.iter() {
+            let field = self
+                .field(clause.field())
+                .ok_or_else(|| BackendError::SearchUnknownField(clause.field().to_string()))?;
+            let kind = match clause {
+                SearchClause::Tag { .. } => "TAG",
+                SearchClause::Numeric { .. } => "NUMERIC",
+            };
+            match (clause, field.kind) {
+                (SearchClause::Tag { .. }, SearchFieldType::Tag { .. })
+                | (SearchClause::Numeric { .. }, SearchFieldType::Numeric) => {}
+                _ => return Err(BackendError::SearchFieldType(field.alias.clone(), kind)),
+            }
+        }
+        Ok(())
+    }
+
+    // keys the clause may match going by the index alone
+    fn candidates(&self, clause: &SearchClause) -> HashSet<String> {
+        let field = self.field(clause.field());
+        match clause {
+            SearchClause::Tag { field: alias, tags } => {
+                let case_sensitive = matches!(
+                    field.map(|f| f.kind),
+                    Some(SearchFieldType::Tag {
+                        case_sensitive: true,
+                        ..
+                    })
+                );
+                let Some(index) = self.tags.get(alias) else {
+                    return HashSet::new();
+                };
+                tags.iter()
+                    .filter_map(|tag| index.get(&normalize_tag(tag, case_sensitive)))
+                    .flatten()
+                    .cloned()
+                    .collect()
+            }
+            SearchClause::Numeric {
+                field: alias,
+                min,
+                max,
+            } => {
+                let Some(index) = self.numbers.get(alias) else {
+                    return HashSet::new();
+                };
+                let max = sortable(max.value);
+                index
+                    .range((sortable(min.value), String::new())..)
+                    .take_while(|(v, _)| *v <= max)
+                    .map(|(_, key)| key.clone())
+                    .collect()
+            }
+        }
+    }
+
+    fn matching(&self, query: &SearchQuery) -> Vec<String> {
+        let found = query
+            .clauses
+            .iter()
+            .filter(|(negated, _)| !negated)
+            .map(|(_, clause)| self.candidates(clause))
+            .reduce(|a, b| a.intersection(&b).cloned().collect());
+        match found {
+            Some(keys) => keys.into_iter().collect(),
+            // only negated clauses, which rule documents out rather than find them
+            None => self.docs.keys().cloned().collect(),
+        }
+    }
+
+    pub fn info(&self, name: &str) -> SearchIndexInfo {
+        SearchIndexInfo {
+            name: name.to_string(),
+            definition: self.definition.clone(),
+            num_docs: self.docs.len(),
+        }
+    }
+}
+
+// puts numbers first, by value, then other values as strings and missing values last; a
+// number never compares with a string, which keeps the order total
+fn compare_values(a: Option<&str>, b: Option<&str>, descending: bool) -> Ordering {
+    let (a, b) = match (a, b) {
+        (Some(a), Some(b)) => (a, b),
+        (a, b) => return a.is_none().cmp(&b.is_none()),
+    };
+    let number = |s: &str| s.parse::<f64>().ok().filter(|v| !v.is_nan());
+    let ord = match (number(a), number(b)) {
+        (Some(x), Some(y)) => x.total_cmp(&y),
+        (x, y) => x.is_none().cmp(&y.is_none()).then_with(|| a.cmp(b)),
+    };
+    match descending {
+        true => ord.reverse(),
+        false => ord,
+    }
+}
+
+impl Backend {
+    pub fn ft_create(&self, name: String, definition: IndexDefinition) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        let mut index = match self.search.entry(name) {
+            Entry::Occupied(_) => return Err(BackendError::SearchIndexExists),
+            Entry::Vacant(entry) => entry.insert(SearchIndex::new(definition)),
+        };
+        // hashes written from now on index themselves, the ones already there are indexed here
+        let keys = self
+            .hmap
+            .iter()
+            .map(|e| e.key().clone())
+            .filter(|key| index.covers(key))
+            .collect::<Vec<_>>();
+        for key in keys {
+            let doc = self.hgetall(&key);
+            index.update(&key, doc.as_ref());
+        }
+        Ok(())
+    }
+
+    /// Drops the index, and with `delete_docs` the hashes it indexed too.
+    pub fn ft_dropindex(&self, name: &str, delete_docs: bool) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        let (_, index) = self
+            .search
+            .remove(name)
+            .ok_or_else(|| BackendError::SearchNoIndex(name.to_string()))?;
+        if delete_docs {
+            for key in index.docs.keys() {
+                self.hmap.remove(key);
+                self.index_hash(key);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn ft_info(&self, name: &str) -> Result<SearchIndexInfo, BackendError> {
+        let index = self
+            .search
+            .get(name)
+            .ok_or_else(|| BackendError::SearchNoIndex(name.to_string()))?;
+        Ok(index.info(name))
+    }
+
+    /// Returns how many documents match the query, and the page of them `options` asks for.
+    pub fn ft_search(
+        &self,
+        name: &str,
+        query: &SearchQuery,
+        options: &SearchOptions,
+    ) -> Result<(usize, Vec<SearchDoc>), BackendError> {
+        let (fields, mut docs) = self.search_docs(name, query)?;
+        let field = |alias: &str| fields.iter().find(|f| f.alias == alias);
+        docs.sort_by(|a, b| a.0.cmp(&b.0));
+        if let Some((alias, descending)) = &options.sort_by {
+            let field =
+                field(alias).ok_or_else(|| BackendError::SearchUnknownField(alias.clone()))?;
+            let value =
+                |doc: &HashMap<String, RespFrame>| doc.get(&field.name).and_then(frame_to_string);
+            docs.sort_by(|a, b| {
+                compare_values(value(&a.1).as_deref(), value(&b.1).as_deref(), *descending)
+            });
+        }
+
+        let total = docs.len();
+        let page = docs
+            .into_iter()
+            .skip(options.offset)
+            .take(options.num)
+            .map(|(key, doc)| {
+                let values = match &options.return_fields {
+                    None => {
+                        let mut values = doc.into_iter().collect::<Vec<_>>();
+                        values.sort_by(|a, b| a.0.cmp(&b.0));
+                        values
+                    }
+                    Some(names) => names
+                        .iter()
+                        .filter_map(|name| {
+                            let field = field(name).map_or(name.as_str(), |f| f.name.as_str());
+                            Some((name.clone(), doc.get(field)?.clone()))
+                        })
+                        .collect(),
+                };
+                (key, values)
+            })
+            .collect();
+        Ok((total, page))
+    }
+
+    /// Runs the query and then the aggregation pipeline over the documents it matches,
+    /// returning the number of rows before LIMIT and the rows after it.
+    pub fn ft_aggregate(
+        &self,
+        name: &str,
+        query: &SearchQuery,
+        aggregate: &AggregateQuery,
+    ) -> Result<(usize, Vec<AggregateRow>), BackendError> {
+        let (fields, mut docs) = self.search_docs(name, query)?;
+        docs.sort_by(|a, b| a.0.cmp(&b.0));
+        // properties are field aliases, or hash fields outside the schema
+        let load = |doc: &HashMap<String, RespFrame>, property: &str| {
+            let field = fields
+                .iter()
+                .find(|f| f.alias == property)
+                .map_or(property, |f| f.name.as_str());
+            doc.get(field).and_then(frame_to_string)
+        };
+
+        let mut rows = match &aggregate.group_by {
+            None => docs
+                .iter()
+                .map(|(_, doc)| {
+                    aggregate
+                        .load
+                        .iter()
+                        .map(|p| (p.clone(), load(doc, p)))
+                        .collect::<AggregateRow>()
+                })
+                .collect::<Vec<_>>(),
+            Some(properties) => {
+                // groups in the order they were first seen, with their (count, sums)
+                let mut groups: Vec<(Vec<Option<String>>, u64, Vec<f64>)> = Vec::new();
+                let mut positions = HashMap::new();
+                for (_, doc) in docs.iter() {
+                    let values = properties.iter().map(|p| load(doc, p)).collect::<Vec<_>>();
+                    let pos = *positions.entry(values.clone()).or_insert_with(|| {
+                        groups.push((values, 0, vec![0.0; aggregate.reducers.len()]));
+                        groups.len() - 1
+                    });
+                    let group = &mut groups[pos];
+                    group.1 += 1;
+                    for (i, (reducer, _)) in aggregate.reducers.iter().enumerate() {
+                        if let Reducer::Sum(property) = reducer {
+                            group.2[i] += load(doc, property)
+                                .and_then(|v| v.parse::<f64>().ok())
+                                .filter(|v| !v.is_nan())
+                                .unwrap_or(0.0);
+                        }
+                    }
+                }
+                groups
+                    .into_iter()
+                    .map(|(values, count, sums)| {
+                        let mut row = properties
+                            .iter()
+                            .cloned()
+                            .zip(values)
+                            .collect::<AggregateRow>();
+                        for ((reducer, name), sum) in aggregate.reducers.iter().zip(sums) {
+                            let value = match reducer {
+                                Reducer::Count => count.to_string(),
+                                Reducer::Sum(_) => sum.to_string(),
+                            };
+                            row.push((name.clone(), Some(value)));
+                        }
+                        row
+                    })
+                    .collect()
+            }
+        };
+
+        if !aggregate.sort_by.is_empty() {
+            let get = |row: &AggregateRow, property: &str| {
+                row.iter()
+                    .find(|(name, _)| name == property)
+                    .and_then(|(_, v)| v.clone())
+            };
+            rows.sort_by(|a, b| {
+                aggregate
+                    .sort_by
+                    .iter()
+                    .map(|(p, descending)| {
+                        compare_values(get(a, p).as_deref(), get(b, p).as_deref(), *descending)
+                    })
+                    .find(|ord| ord.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        let total = rows.len();
+        if let Some((offset, num)) = aggregate.limit {
+            rows = rows.into_iter().skip(offset).take(num).collect();
+        }
+        Ok((total, rows))
+    }
+
+    // the schema and the documents matching the query, checked against the hashes as they are now
+    fn search_docs(
+        &self,
+        name: &str,
+        query: &SearchQuery,
+    ) -> Result<(Vec<SearchField>, Vec<MatchedDoc>), BackendError> {
+        let (fields, keys) = {
+            let index = self
+                .search
+                .get(name)
+                .ok_or_else(|| BackendError::SearchNoIndex(name.to_string()))?;
+            index.check(query)?;
+            (index.definition.fields.clone(), index.matching(query))
+        };
+        let docs = keys
+            .into_iter()
+            .filter_map(|key| {
+                let doc = self.hgetall(&key)?;
+                let matches = query.clauses.iter().all(|(negated, clause)| {
+                    fields
+                        .iter()
+                        .find(|f| f.alias == clause.field())
+                        .is_some_and(|f| clause.matches(f, f.value(&doc).as_ref()))
+                        != *negated
+                });
+                matches.then_some((key, doc))
+            })
+            .collect();
+        Ok((fields, docs))
+    }
+
+    /// Brings every index covering `key` up to date with the hash, called after each write to
+    /// it. The caller must not hold a guard on the hash.
+    pub(super) fn index_hash(&self, key: &str) {
+        if self.search.is_empty() {
+            return;
+        }
+        for mut index in self.search.iter_mut() {
+            if index.covers(key) {
+                // read under the index's guard, so that concurrent writes to the same hash
+                // leave it with the latest value
+                let doc = self.hgetall(key);
+                index.update(key, doc.as_ref());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    fn definition() -> IndexDefinition {
+        IndexDefinition {
+            prefixes: vec!["user:".to_string()],
+            fields: vec![
+                SearchField {
+                    name: "status".to_string(),
+                    alias: "status".to_string(),
+                    kind: SearchFieldType::Tag {
+                        separator: ',',
+                        case_sensitive: false,
+                    },
+                },
+                SearchField {
+                    name: "age".to_string(),
+                    alias: "age".to_string(),
+                    kind: SearchFieldType::Numeric,
+                },
+            ],
+        }
+    }
+
+    fn hset(backend: &Backend, key: &str, fields: &[(&str, &str)]) {
+        for (field, value) in fields {
+            backend.hset(
+                key.to_string(),
+                field.to_string(),
+                BulkString::from(*value).into(),
+            );
+        }
+    }
+
+    fn keys(backend: &Backend, query: &str) -> Vec<String> {
+        let query = SearchQuery::parse(query).unwrap();
+        let (_, docs) = backend
+            .ft_search("idx", &query, &SearchOptions::default())
+            .unwrap();
+        docs.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn test_search_query_parse() {
+        let query = SearchQuery::parse(r"@status:{ Active | on\-hold } -@age:[(18 +inf]").unwrap();
+        assert_eq!(
+            query.clauses,
+            vec![
+                (
+                    false,
+                    SearchClause::Tag {
+                        field: "status".to_string(),
+                        tags: vec!["Active".to_string(), "on-hold".to_string()],
+                    }
+                ),
+                (
+                    true,
+                    SearchClause::Numeric {
+                        field: "age".to_string(),
+                        min: NumericBound {
+                            value: 18.0,
+                            exclusive: true
+                        },
+                        max: NumericBound {
+                            value: f64::INFINITY,
+                            exclusive: false
+                        },
+                    }
+                ),
+            ]
+        );
+        assert_eq!(SearchQuery::parse("*"), Some(SearchQuery::default()));
+        assert_eq!(SearchQuery::parse("@age:[1]"), None);
+        assert_eq!(SearchQuery::parse("@status:{active"), None);
+        assert_eq!(SearchQuery::parse("hello"), None);
+        assert!(sortable(-1.5) < sortable(-0.5) && sortable(0.0) < sortable(2.0));
+    }
+
+    #[test]
+    fn test_index_follows_hash_writes() {
+        let backend = Backend::new();
+        hset(&backend, "user:1", &[("status", "active"), ("age", "30")]);
+        backend.ft_create("idx".to_string(), definition()).unwrap();
+        assert_eq!(
+            backend.ft_create("idx".to_string(), definition()),
+            Err(BackendError::SearchIndexExists)
+        );
+        hset(
+            &backend,
+            "user:2",
+            &[("status", "Active,new"), ("age", "17")],
+        );
+        hset(&backend, "order:1", &[("status", "active"), ("age", "40")]);
+
+        assert_eq!(keys(&backend, "@status:{active}"), vec!["user:1", "user:2"]);
+        assert_eq!(
+            keys(&backend, "@status:{active} @age:[18 65]"),
+            vec!["user:1"]
+        );
+        assert_eq!(keys(&backend, "-@age:[18 65]"), vec!["user:2"]);
+
+        backend.hset(
+            "user:1".to_string(),
+            "status".to_string(),
+            BulkString::from("inactive").into(),
+        );
+        backend
+            .hincrby("user:2".to_string(), "age".to_string(), 1)
+            .unwrap();
+        assert_eq!(keys(&backend, "@status:{active}"), vec!["user:2"]);
+        assert_eq!(keys(&backend, "@age:[18 18]"), vec!["user:2"]);
+
+        backend.hdel("user:2", &["status".to_string(), "age".to_string()]);
+        assert_eq!(keys(&backend, "*"), vec!["user:1"]);
+        assert_eq!(backend.ft_info("idx").unwrap().num_docs, 1);
+
+        assert_eq!(
+            backend.ft_search(
+                "idx",
+                &SearchQuery::parse("@name:{x}").unwrap(),
+                &SearchOptions::default()
+            ),
+            Err(BackendError::SearchUnknownField("name".to_string()))
+        );
+        backend.ft_dropindex("idx", true).unwrap();
+        assert_eq!(backend.hlen("user:1"), 0);
+        assert_eq!(backend.hlen("order:1"), 2);
+        assert_eq!(
+            backend.ft_info("idx"),
+            Err(BackendError::SearchNoIndex("idx".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_non_numeric_values_are_not_indexed() {
+        let backend = Backend::new();
+        backend.ft_create("idx".to_string(), definition()).unwrap();
+        hset(&backend, "user:1", &[("status", "active"), ("age", "abc")]);
+        hset(&backend, "user:2", &[("status", "active")]);
+        assert_eq!(keys(&backend, "*"), vec!["user:2"]);
+        assert_eq!(keys(&backend, "@status:{active}"), vec!["user:2"]);
+
+        hset(&backend, "user:1", &[("age", "30")]);
+        assert_eq!(keys(&backend, "*"), vec!["user:1", "user:2"]);
+
+        // numbers before strings before missing values, whichever pair is compared
+        let mut values = vec![None, Some("1x"), Some("9"), Some("10"), Some("-inf")];
+        values.sort_by(|a, b| compare_values(*a, *b, false));
+        assert_eq!(
+            values,
+            vec![Some("-inf"), Some("9"), Some("10"), Some("1x"), None]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_groups_and_sorts() {
+        let backend = Backend::new();
+        backend.ft_create("idx".to_string(), definition()).unwrap();
+        hset(&backend, "user:1", &[("status", "active"), ("age", "30")]);
+        hset(&backend, "user:2", &[("status", "new"), ("age", "20")]);
+        hset(&backend, "user:3", &[("status", "active"), ("age", "40.5")]);
+
+        let aggregate = AggregateQuery {
+            group_by: Some(vec!["status".to_string()]),
+            reducers: vec![
+                (Reducer::Count, "count".to_string()),
+                (Reducer::Sum("age".to_string()), "total".to_string()),
+            ],
+            sort_by: vec![("count".to_string(), true)],
+            limit: Some((0, 1)),
+            ..Default::default()
+        };
+        let (total, rows) = backend
+            .ft_aggregate("idx", &SearchQuery::default(), &aggregate)
+            .unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(
+            rows,
+            vec![vec![
+                ("status".to_string(), Some("active".to_string())),
+                ("count".to_string(), Some("2".to_string())),
+                ("total".to_string(), Some("70.5".to_string())),
+            ]]
+        );
+    }
+}
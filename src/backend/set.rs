@@ -0,0 +1,346 @@
+use std::collections::HashSet;
+
+use dashmap::mapref::one::Ref;
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use super::{intset::IntSet, Backend};
+
+// sets with more members than this always use the hashtable encoding
+const SET_MAX_INTSET_ENTRIES: usize = 512;
+
+/// A set value. Small sets holding only integers are packed into an `IntSet` and converted to
+/// a hashtable for good once they grow past `SET_MAX_INTSET_ENTRIES` or get a non-integer.
+#[derive(Debug, Clone)]
+pub enum Set {
+    IntSet(IntSet),
+    HashTable(HashSet<String>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl Default for Set {
+    fn default() -> Self {
+        Set::IntSet(IntSet::new())
+    }
+}
+
+impl FromIterator<String> for Set {
+    fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
+        let mut set = Set::default();
+        for member in iter {
+            set.insert(member);
+        }
+        set
+    }
+}
+
+impl Set {
+    pub fn len(&self) -> usize {
+        match self {
+            Set::IntSet(s) => s.len(),
+            Set::HashTable(s) => s.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, member: &str) -> bool {
+        match self {
+            Set::IntSet(s) => parse_int(member).is_some_and(|v| s.contains(v)),
+            Set::HashTable(s) => s.contains(member),
+        }
+    }
+
+    // returns true if the member was not already in the set
+    pub fn insert(&mut self, member: String) -> bool {
+        if let Set::IntSet(s) = self {
+            match parse_int(&member) {
+                Some(v) if s.contains(v) => return false,
+                Some(v) if s.len() < SET_MAX_INTSET_ENTRIES => return s.insert(v),
+                _ => self.convert_to_hashtable(),
+            }
+        }
+        match self {
+            Set::HashTable(s) => s.insert(member),
+            Set::IntSet(_) => unreachable!("set was converted to a hashtable"),
+        }
+    }
+
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self {
+            Set::IntSet(s) => parse_int(member).is_some_and(|v| s.remove(v)),
+            Set::HashTable(s) => s.remove(member),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        match self {
+            Set::IntSet(s) => Box::new(s.iter().map(|v| v.to_string())),
+            Set::HashTable(s) => Box::new(s.iter().cloned()),
+        }
+    }
+
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Set::IntSet(_) => "intset",
+            Set::HashTable(_) => "hashtable",
+        }
+    }
+
+    // estimated bytes used by the set, see `Backend::memory_usage`
+    pub fn memory_usage(&self) -> usize {
+        let members = match self {
+            Set::IntSet(s) => s.blob_len(),
+            Set::HashTable(s) => {
+                s.capacity() * (size_of::<String>() + 1) + s.iter().map(|m| m.len()).sum::<usize>()
+            }
+        };
+        size_of::<Set>() + members
+    }
+
+    fn convert_to_hashtable(&mut self) {
+        if let Set::IntSet(s) = self {
+            *self = Set::HashTable(s.iter().map(|v| v.to_string()).collect());
+        }
+    }
+}
+
+// only canonical integer strings can be stored in an intset, so "007" or "+1" round-trip
+fn parse_int(member: &str) -> Option<i64> {
+    member
+        .parse::<i64>()
+        .ok()
+        .filter(|v| v.to_string() == member)
+}
+
+impl Backend {
+    // returns the number of members that were not already in the set
+    pub fn sadd(&self, key: String, members: Vec<String>) -> usize {
+        let _guard = self.shared_lock();
+        let mut set = self.set.entry(key).or_default();
+        members.into_iter().map(|m| set.insert(m) as usize).sum()
+    }
+
+    pub fn sismember(&self, key: &str, value: &str) -> bool {
+        self.set.get(key).is_some_and(|set| set.contains(value))
+    }
+
+    pub fn smismember(&self, key: &str, members: &[String]) -> Vec<bool> {
+        let set = self.set.get(key);
+        members
+            .iter()
+            .map(|m| set.as_ref().is_some_and(|set| set.contains(m)))
+            .collect()
+    }
+
+    pub fn smembers(&self, key: &str) -> Vec<String> {
+        self.set.get(key).map_or(vec![], |set| set.iter().collect())
+    }
+
+    pub fn scard(&self, key: &str) -> usize {
+        self.set.get(key).map_or(0, |set| set.len())
+    }
+
+    // removes the set itself once its last member is gone
+    pub fn srem(&self, key: &str, members: &[String]) -> usize {
+        let _guard = self.shared_lock();
+        let removed = match self.set.get_mut(key) {
+            Some(mut set) => members.iter().filter(|m| set.remove(m)).count(),
+            None => return 0,
+        };
+        self.set.remove_if(key, |_, set| set.is_empty());
+        removed
+    }
+
+    pub fn spop(&self, key: &str, count: usize) -> Vec<String> {
+        let _guard = self.shared_lock();
+        let popped = match self.set.get_mut(key) {
+            Some(mut set) => {
+                let count = count.min(set.len());
+                let picked = set.iter().choose_multiple(&mut rand::thread_rng(), count);
+                for m in picked.iter() {
+                    set.remove(m);
+                }
+                picked
+            }
+            None => return vec![],
+        };
+        self.set.remove_if(key, |_, set| set.is_empty());
+        popped
+    }
+
+    // a positive count returns distinct members, a negative one may repeat them
+    pub fn srandmember(&self, key: &str, count: i64) -> Vec<String> {
+        let Some(set) = self.set.get(key) else {
+            return vec![];
+        };
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            let count = (count as usize).min(set.len());
+            return set.iter().choose_multiple(&mut rng, count);
+        }
+        let all = set.iter().collect::<Vec<_>>();
+        (0..count.unsigned_abs())
+            .filter_map(|_| all.choose(&mut rng).cloned())
+            .collect()
+    }
+
+    // no other writer can observe the member in neither or both sets
+    pub fn smove(&self, source: &str, destination: &str, member: &str) -> bool {
+        let _guard = self.exclusive_lock();
+        if !self.sismember(source, member) {
+            return false;
+        }
+        if source == destination {
+            return true;
+        }
+        if let Some(mut set) = self.set.get_mut(source) {
+            set.remove(member);
+        }
+        self.set.remove_if(source, |_, set| set.is_empty());
+        self.set
+            .entry(destination.to_string())
+            .or_default()
+            .insert(member.to_string());
+        true
+    }
+
+    /// Computes an intersection, union or difference over a consistent view of all the sets.
+    /// `limit` stops an intersection early once that many members were found (0 means no limit).
+    pub fn sop(&self, op: SetOp, keys: &[String], limit: usize) -> Vec<String> {
+        let _guard = self.exclusive_lock();
+        self.sop_locked(op, keys, limit)
+    }
+
+    // the destination is replaced, or deleted if the result is empty, in the same critical section
+    pub fn sopstore(&self, op: SetOp, destination: &str, keys: &[String]) -> usize {
+        let _guard = self.exclusive_lock();
+        let members = self.sop_locked(op, keys, 0);
+        let len = members.len();
+        if members.is_empty() {
+            self.set.remove(destination);
+        } else {
+            self.set
+                .insert(destination.to_string(), members.into_iter().collect());
+        }
+        len
+    }
+
+    // callers must hold the exclusive lock so no writer can interleave between the sets
+    fn sop_locked(&self, op: SetOp, keys: &[String], limit: usize) -> Vec<String> {
+        let sets = keys.iter().map(|k| self.set.get(k)).collect::<Vec<_>>();
+        match op {
+            SetOp::Inter => {
+                let Some(mut sets) = sets.into_iter().collect::<Option<Vec<_>>>() else {
+                    return vec![];
+                };
+                // probe the other sets with the members of the smallest one
+                sets.sort_by_key(|s| s.len());
+                let Some((smallest, rest)) = sets.split_first() else {
+                    return vec![];
+                };
+                let members = smallest
+                    .iter()
+                    .filter(|m| rest.iter().all(|s| s.contains(m)));
+                match limit {
+                    0 => members.collect(),
+                    n => members.take(n).collect(),
+                }
+            }
+            SetOp::Union => {
+                let mut members = HashSet::new();
+                for set in sets.iter().flatten() {
+                    members.extend(set.iter());
+                }
+                members.into_iter().collect()
+            }
+            SetOp::Diff => {
+                let mut sets = sets.into_iter();
+                let Some(Some(first)) = sets.next() else {
+                    return vec![];
+                };
+                let others = sets.flatten().collect::<Vec<Ref<_, _>>>();
+                first
+                    .iter()
+                    .filter(|m| !others.iter().any(|s| s.contains(m)))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let backend = Backend::new();
+        backend.sadd("a".to_string(), members(&["1", "2", "3", "4"]));
+        backend.sadd("b".to_string(), members(&["2", "3", "5"]));
+        backend.sadd("c".to_string(), members(&["3", "4"]));
+
+        let sorted = |mut v: Vec<String>| {
+            v.sort();
+            v
+        };
+        let keys = members(&["a", "b", "c"]);
+        assert_eq!(backend.sop(SetOp::Inter, &keys, 0), members(&["3"]));
+        assert_eq!(
+            sorted(backend.sop(SetOp::Union, &keys, 0)),
+            members(&["1", "2", "3", "4", "5"])
+        );
+        assert_eq!(backend.sop(SetOp::Diff, &keys, 0), members(&["1"]));
+        assert_eq!(backend.sop(SetOp::Inter, &members(&["a", "b"]), 1).len(), 1);
+        assert!(backend
+            .sop(SetOp::Inter, &members(&["a", "missing"]), 0)
+            .is_empty());
+
+        assert_eq!(
+            backend.sopstore(SetOp::Diff, "c", &members(&["a", "b", "c"])),
+            1
+        );
+        assert_eq!(backend.smembers("c"), members(&["1"]));
+        assert_eq!(
+            backend.sopstore(SetOp::Inter, "c", &members(&["c", "b"])),
+            0
+        );
+        assert!(!backend.set.contains_key("c"));
+    }
+
+    #[test]
+    fn test_set_encoding_conversion() {
+        let mut set = Set::default();
+        assert!(set.insert("1".to_string()));
+        assert!(set.insert("-20".to_string()));
+        assert!(!set.insert("1".to_string()));
+        assert_eq!(set.encoding(), "intset");
+        assert!(set.contains("-20"));
+        assert!(!set.contains("+1"));
+
+        // a non-canonical integer string forces the hashtable encoding
+        assert!(set.insert("007".to_string()));
+        assert_eq!(set.encoding(), "hashtable");
+        assert!(set.contains("1"));
+        assert!(set.contains("007"));
+
+        let mut set = (0..SET_MAX_INTSET_ENTRIES)
+            .map(|i| i.to_string())
+            .collect::<Set>();
+        assert_eq!(set.encoding(), "intset");
+        set.insert("512".to_string());
+        assert_eq!(set.encoding(), "hashtable");
+        assert_eq!(set.len(), SET_MAX_INTSET_ENTRIES + 1);
+    }
+}
@@ -0,0 +1,329 @@
+use std::cmp::Ordering;
+
+const MAX_LEVEL: usize = 32;
+// chance of a node reaching the next level
+const LEVEL_P: f64 = 0.25;
+
+// index of the header node, and of "no node"
+const HEAD: usize = 0;
+const NIL: usize = usize::MAX;
+
+/// A skiplist ordered by (score, member) where every link records how many nodes it spans,
+/// as in Redis' zskiplist, so ranks can be found in O(log n). Nodes live in an arena and link
+/// to each other by index.
+#[derive(Debug, Clone)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    // arena slots of removed nodes, reused by later inserts
+    free: Vec<usize>,
+    level: usize,
+    len: usize,
+    tail: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: String,
+    score: f64,
+    next: Vec<Link>,
+    prev: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Link {
+    to: usize,
+    // nodes between this one and `to`, counting `to`; for the last link, the nodes left
+    span: usize,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        let head = Node {
+            member: String::new(),
+            score: 0.0,
+            next: vec![Link { to: NIL, span: 0 }; MAX_LEVEL],
+            prev: NIL,
+        };
+        SkipList {
+            nodes: vec![head],
+            free: vec![],
+            level: 1,
+            len: 0,
+            tail: NIL,
+        }
+    }
+}
+
+fn cmp(score: f64, member: &str, other_score: f64, other_member: &str) -> Ordering {
+    score
+        .partial_cmp(&other_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| member.cmp(other_member))
+}
+
+fn random_level() -> usize {
+    let mut level = 1;
+    while level < MAX_LEVEL && rand::random::<f64>() < LEVEL_P {
+        level += 1;
+    }
+    level
+}
+
+impl SkipList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // the member must not be in the list already
+    pub fn insert(&mut self, score: f64, member: String) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i + 1 == self.level { 0 } else { rank[i + 1] };
+            loop {
+                let link = self.nodes[x].next[i];
+                if link.to == NIL || self.cmp_node(link.to, score, &member).is_ge() {
+                    break;
+                }
+                rank[i] += link.span;
+                x = link.to;
+            }
+            update[i] = x;
+        }
+
+        let level = random_level();
+        for i in self.level..level {
+            self.nodes[HEAD].next[i].span = self.len;
+        }
+        self.level = self.level.max(level);
+
+        let node = Node {
+            member,
+            score,
+            next: vec![Link { to: NIL, span: 0 }; level],
+            prev: if update[0] == HEAD { NIL } else { update[0] },
+        };
+        let new = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        for i in 0..level {
+            let before = self.nodes[update[i]].next[i];
+            let skipped = rank[0] - rank[i];
+            self.nodes[new].next[i] = Link {
+                to: before.to,
+                span: before.span - skipped,
+            };
+            self.nodes[update[i]].next[i] = Link {
+                to: new,
+                span: skipped + 1,
+            };
+        }
+        for (i, &x) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[x].next[i].span += 1;
+        }
+        match self.nodes[new].next[0].to {
+            NIL => self.tail = new,
+            next => self.nodes[next].prev = new,
+        }
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, score: f64, member: &str) -> bool {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[x].next[i];
+                if link.to == NIL || self.cmp_node(link.to, score, member).is_ge() {
+                    break;
+                }
+                x = link.to;
+            }
+            update[i] = x;
+        }
+        let target = self.nodes[x].next[0].to;
+        if target == NIL || self.cmp_node(target, score, member).is_ne() {
+            return false;
+        }
+
+        for (i, &x) in update.iter().enumerate().take(self.level) {
+            let link = self.nodes[x].next[i];
+            if link.to == target {
+                let after = self.nodes[target].next[i];
+                self.nodes[x].next[i] = Link {
+                    to: after.to,
+                    span: link.span + after.span - 1,
+                };
+            } else {
+                self.nodes[x].next[i].span -= 1;
+            }
+        }
+        let prev = self.nodes[target].prev;
+        match self.nodes[target].next[0].to {
+            NIL => self.tail = prev,
+            next => self.nodes[next].prev = prev,
+        }
+        while self.level > 1 && self.nodes[HEAD].next[self.level - 1].to == NIL {
+            self.level -= 1;
+        }
+        self.nodes[target].member = String::new();
+        self.nodes[target].next = vec![];
+        self.free.push(target);
+        self.len -= 1;
+        true
+    }
+
+    /// The number of leading elements for which `before` holds. `before` must hold for a prefix
+    /// of the list and for nothing after it, like "score < min".
+    pub fn count_while(&self, before: impl Fn(f64, &str) -> bool) -> usize {
+        let mut x = HEAD;
+        let mut rank = 0;
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[x].next[i];
+                if link.to == NIL {
+                    break;
+                }
+                let node = &self.nodes[link.to];
+                if !before(node.score, &node.member) {
+                    break;
+                }
+                rank += link.span;
+                x = link.to;
+            }
+        }
+        rank
+    }
+
+    // 0-based rank of an element that is in the list
+    pub fn rank(&self, score: f64, member: &str) -> usize {
+        self.count_while(|s, m| cmp(s, m, score, member).is_lt())
+    }
+
+    /// Elements in order starting at `rank`.
+    pub fn iter_from(&self, rank: usize) -> impl Iterator<Item = (&str, f64)> {
+        self.walk(self.node_at(rank), false)
+    }
+
+    /// Elements in reverse order starting at `rank`, counted from the head.
+    pub fn rev_iter_from(&self, rank: usize) -> impl Iterator<Item = (&str, f64)> {
+        self.walk(self.node_at(rank), true)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.walk(self.nodes[HEAD].next[0].to, false)
+    }
+
+    pub fn rev_iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.walk(self.tail, true)
+    }
+
+    fn walk(&self, mut x: usize, backward: bool) -> impl Iterator<Item = (&str, f64)> {
+        std::iter::from_fn(move || {
+            let node = self.nodes.get(x)?;
+            x = if backward { node.prev } else { node.next[0].to };
+            Some((node.member.as_str(), node.score))
+        })
+    }
+
+    // estimated bytes used by the nodes, not counting the member strings
+    pub fn memory_usage(&self) -> usize {
+        self.nodes.capacity() * size_of::<Node>()
+            + self
+                .nodes
+                .iter()
+                .map(|n| n.next.capacity() * size_of::<Link>())
+                .sum::<usize>()
+    }
+
+    // arena index of the node at a 0-based rank, NIL if out of range
+    fn node_at(&self, rank: usize) -> usize {
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[x].next[i];
+                if link.to == NIL || traversed + link.span > target {
+                    break;
+                }
+                traversed += link.span;
+                x = link.to;
+            }
+            if traversed == target {
+                return x;
+            }
+        }
+        NIL
+    }
+
+    fn cmp_node(&self, x: usize, score: f64, member: &str) -> Ordering {
+        let node = &self.nodes[x];
+        cmp(node.score, &node.member, score, member)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skiplist_order_and_rank() {
+        let mut list = SkipList::new();
+        for i in (0..200).rev() {
+            list.insert((i / 2) as f64, format!("m{:03}", i));
+        }
+        assert_eq!(list.len(), 200);
+        let all = list.iter().map(|(m, _)| m.to_string()).collect::<Vec<_>>();
+        let expected = (0..200).map(|i| format!("m{:03}", i)).collect::<Vec<_>>();
+        assert_eq!(all, expected);
+        for i in 0..200 {
+            assert_eq!(list.rank((i / 2) as f64, &format!("m{:03}", i)), i);
+        }
+        assert_eq!(list.count_while(|s, _| s < 10.0), 20);
+        assert_eq!(list.iter_from(199).count(), 1);
+        assert_eq!(list.iter_from(200).count(), 0);
+        assert_eq!(
+            list.rev_iter_from(1).map(|(m, _)| m).collect::<Vec<_>>(),
+            ["m001", "m000"]
+        );
+    }
+
+    #[test]
+    fn test_skiplist_remove() {
+        let mut list = SkipList::new();
+        for i in 0..100 {
+            list.insert(i as f64, i.to_string());
+        }
+        for i in (0..100).step_by(2) {
+            assert!(list.remove(i as f64, &i.to_string()));
+        }
+        assert!(!list.remove(0.0, "0"));
+        assert!(!list.remove(1.0, "2"));
+        assert_eq!(list.len(), 50);
+        assert_eq!(list.rank(51.0, "51"), 25);
+        assert_eq!(list.rev_iter_from(49).next(), Some(("99", 99.0)));
+        assert_eq!(list.rev_iter().nth(1), Some(("97", 97.0)));
+
+        // freed slots are reused
+        list.insert(0.5, "half".to_string());
+        assert_eq!(list.nodes.len(), 101);
+        assert_eq!(list.iter().next(), Some(("half", 0.5)));
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    ops::Bound::{self, Excluded, Included, Unbounded},
+};
+
+use crate::RespFrame;
+
+use super::{frame_heap_size, now_ms, stream_group::ConsumerGroup, Backend, BackendError};
+
+// entries per stream node; approximate (`~`) trimming only removes whole nodes
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+/// A stream entry id: a unix time in milliseconds and a sequence number that orders the
+/// entries added within the same millisecond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+/// The id requested by XADD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    // `*`
+    Auto,
+    // `<ms>-*`
+    Partial(u64),
+    Explicit(StreamId),
+}
+
+/// Which entries XADD and XTRIM evict from the head of a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrimStrategy {
+    MaxLen(usize),
+    // evicts entries with smaller ids
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    // `~`: only evict whole nodes, which may leave a few more entries than asked for
+    pub approximate: bool,
+    // at most this many entries are evicted; None for no limit
+    pub limit: Option<usize>,
+}
+
+pub type StreamFields = Vec<(String, RespFrame)>;
+
+/// An append-only log of entries ordered by id.
+#[derive(Debug, Clone, Default)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    // the id of the last entry ever added, which new ids must exceed
+    last_id: StreamId,
+    max_deleted_id: StreamId,
+    entries_added: u64,
+    pub(super) groups: BTreeMap<String, ConsumerGroup>,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// Parses `<ms>-<seq>`, or a bare `<ms>` with the sequence number `default_seq`.
+    pub fn parse(s: &str, default_seq: u64) -> Option<Self> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(StreamId::new(ms.parse().ok()?, seq.parse().ok()?)),
+            None => Some(StreamId::new(s.parse().ok()?, default_seq)),
+        }
+    }
+
+    // the smallest id greater than this one
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+// whether `start..end` selects nothing; BTreeMap::range panics on such ranges
+pub(super) fn is_empty_range(start: Bound<StreamId>, end: Bound<StreamId>) -> bool {
+    match (start, end) {
+        (Included(s), Included(e)) => s > e,
+        (Included(s) | Excluded(s), Included(e) | Excluded(e)) => s >= e,
+        _ => false,
+    }
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn first_entry(&self) -> Option<(StreamId, &StreamFields)> {
+        self.entries.first_key_value().map(|(id, f)| (*id, f))
+    }
+
+    pub fn last_entry(&self) -> Option<(StreamId, &StreamFields)> {
+        self.entries.last_key_value().map(|(id, f)| (*id, f))
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    /// Estimates how many entries were added up to and including `id`, as the `entries-read`
+    /// of a group that has read up to `id`. None if deletions make that impossible to tell.
+    pub(super) fn entries_read_at(&self, id: StreamId) -> Option<u64> {
+        if self.entries_added == 0 {
+            return Some(0);
+        }
+        if id >= self.last_id || self.is_empty() {
+            return (id <= self.last_id).then_some(self.entries_added);
+        }
+        let first = self.first_entry().map_or(self.last_id, |(first, _)| first);
+        // without deletions after the first entry, everything before it was trimmed away
+        if self.max_deleted_id == StreamId::MIN || self.max_deleted_id < first {
+            let trimmed = self.entries_added - self.len() as u64;
+            if id < first {
+                return Some(trimmed);
+            }
+            if id == first {
+                return Some(trimmed + 1);
+            }
+        }
+        None
+    }
+
+    // whether entries after `id` were deleted, which leaves gaps a read counter can't see
+    pub(super) fn has_tombstones_after(&self, id: StreamId) -> bool {
+        match self.first_entry() {
+            Some((first, _)) => self.max_deleted_id >= first && self.max_deleted_id > id,
+            None => false,
+        }
+    }
+
+    // the id XADD would give an entry, which must be greater than every id added before
+    fn next_id(&self, id: XAddId) -> Result<StreamId, BackendError> {
+        let last = self.last_id;
+        let id = match id {
+            XAddId::Auto => {
+                let now = now_ms();
+                if now > last.ms {
+                    StreamId::new(now, 0)
+                } else {
+                    last.next().ok_or(BackendError::StreamExhausted)?
+                }
+            }
+            // 0-* on a new stream gives 0-1, as 0-0 is never a valid id
+            XAddId::Partial(ms) if ms == last.ms => {
+                let seq = last.seq.checked_add(1);
+                StreamId::new(ms, seq.ok_or(BackendError::StreamIdTooSmall)?)
+            }
+            XAddId::Partial(ms) => StreamId::new(ms, 0),
+            XAddId::Explicit(id) => id,
+        };
+        if id == StreamId::MIN {
+            return Err(BackendError::StreamIdZero);
+        }
+        if id <= last {
+            return Err(BackendError::StreamIdTooSmall);
+        }
+        Ok(id)
+    }
+
+    fn add(&mut self, id: StreamId, fields: StreamFields) {
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    /// Entries with ids between `start` and `end`, from the end if `rev`, up to `count`.
+    pub fn range(
+        &self,
+        start: Bound<StreamId>,
+        end: Bound<StreamId>,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, StreamFields)> {
+        if is_empty_range(start, end) {
+            return vec![];
+        }
+        let count = count.unwrap_or(usize::MAX);
+        let range = self.entries.range((start, end));
+        let owned = |(id, fields): (&StreamId, &StreamFields)| (*id, fields.clone());
+        match rev {
+            true => range.rev().take(count).map(owned).collect(),
+            false => range.take(count).map(owned).collect(),
+        }
+    }
+
+    pub fn delete(&mut self, id: &StreamId) -> bool {
+        if self.entries.remove(id).is_none() {
+            return false;
+        }
+        self.max_deleted_id = self.max_deleted_id.max(*id);
+        true
+    }
+
+    // evicts entries from the head as `trim` asks, returning how many were evicted
+    pub fn trim(&mut self, trim: &StreamTrim) -> usize {
+        let mut n = match trim.strategy {
+            TrimStrategy::MaxLen(max) => self.len().saturating_sub(max),
+            TrimStrategy::MinId(min) => self.entries.range(..min).count(),
+        };
+        if let Some(limit) = trim.limit {
+            n = n.min(limit);
+        }
+        if trim.approximate {
+            n -= n % STREAM_NODE_MAX_ENTRIES;
+        }
+        for _ in 0..n {
+            if let Some((id, _)) = self.entries.pop_first() {
+                self.max_deleted_id = self.max_deleted_id.max(id);
+            }
+        }
+        n
+    }
+
+    // estimated bytes used by the stream, see `Backend::memory_usage`
+    pub fn memory_usage(&self) -> usize {
+        let entries = self
+            .entries
+            .values()
+            .map(|fields| {
+                size_of::<(StreamId, StreamFields)>()
+                    + fields.capacity() * size_of::<(String, RespFrame)>()
+                    + fields
+                        .iter()
+                        .map(|(f, v)| f.len() + frame_heap_size(v))
+                        .sum::<usize>()
+            })
+            .sum::<usize>();
+        size_of::<Stream>() + entries
+    }
+}
+
+impl Backend {
+    /// Appends an entry and trims the stream. Returns the new entry's id, or None if the stream
+    /// doesn't exist and `no_mkstream` is set.
+    pub fn xadd(
+        &self,
+        key: String,
+        id: XAddId,
+        fields: StreamFields,
+        no_mkstream: bool,
+        trim: Option<StreamTrim>,
+    ) -> Result<Option<StreamId>, BackendError> {
+        let _guard = self.shared_lock();
+        let id = {
+            let mut stream = match self.stream.get_mut(&key) {
+                Some(stream) => stream,
+                None if no_mkstream => return Ok(None),
+                None => {
+                    // a bad id must not leave an empty stream behind
+                    Stream::default().next_id(id)?;
+                    self.stream.entry(key.clone()).or_default()
+                }
+            };
+            let id = stream.next_id(id)?;
+            stream.add(id, fields);
+            if let Some(trim) = trim {
+                stream.trim(&trim);
+            }
+            id
+        };
+        self.signal_ready(&key);
+        Ok(Some(id))
+    }
+
+    pub fn xlen(&self, key: &str) -> usize {
+        self.stream.get(key).map_or(0, |s| s.len())
+    }
+
+    pub fn xrange(
+        &self,
+        key: &str,
+        start: Bound<StreamId>,
+        end: Bound<StreamId>,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(StreamId, StreamFields)> {
+        self.stream
+            .get(key)
+            .map_or(vec![], |s| s.range(start, end, count, rev))
+    }
+
+    pub fn xdel(&self, key: &str, ids: &[StreamId]) -> usize {
+        let _guard = self.shared_lock();
+        match self.stream.get_mut(key) {
+            Some(mut stream) => ids.iter().filter(|id| stream.delete(id)).count(),
+            None => 0,
+        }
+    }
+
+    pub fn xtrim(&self, key: &str, trim: &StreamTrim) -> usize {
+        let _guard = self.shared_lock();
+        self.stream.get_mut(key).map_or(0, |mut s| s.trim(trim))
+    }
+
+    // the id of the last entry added to the stream, 0-0 if there is none
+    pub fn stream_last_id(&self, key: &str) -> StreamId {
+        self.stream.get(key).map_or(StreamId::MIN, |s| s.last_id())
+    }
+
+    /// For each stream, its entries with ids greater than the one given, up to `count` per
+    /// stream. Streams without such entries are left out.
+    pub fn xread(
+        &self,
+        streams: &[(String, StreamId)],
+        count: Option<usize>,
+    ) -> Vec<(String, Vec<(StreamId, StreamFields)>)> {
+        streams
+            .iter()
+            .filter_map(|(key, id)| {
+                let entries = self.xrange(key, Excluded(*id), Unbounded, count, false);
+                (!entries.is_empty()).then(|| (key.clone(), entries))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BulkString;
+
+    use super::*;
+
+    fn fields(v: &[(&str, &str)]) -> StreamFields {
+        v.iter()
+            .map(|(f, v)| (f.to_string(), BulkString::from(*v).into()))
+            .collect()
+    }
+
+    fn add(backend: &Backend, key: &str, id: XAddId) -> Result<Option<StreamId>, BackendError> {
+        backend.xadd(key.to_string(), id, fields(&[("f", "v")]), false, None)
+    }
+
+    #[test]
+    fn test_xadd_ids() {
+        let backend = Backend::new();
+        assert_eq!(
+            add(&backend, "s", XAddId::Explicit(StreamId::MIN)),
+            Err(BackendError::StreamIdZero)
+        );
+        assert!(!backend.stream.contains_key("s"));
+
+        assert_eq!(
+            add(&backend, "s", XAddId::Partial(0)),
+            Ok(Some(StreamId::new(0, 1)))
+        );
+        assert_eq!(
+            add(&backend, "s", XAddId::Partial(5)),
+            Ok(Some(StreamId::new(5, 0)))
+        );
+        assert_eq!(
+            add(&backend, "s", XAddId::Partial(5)),
+            Ok(Some(StreamId::new(5, 1)))
+        );
+        assert_eq!(
+            add(&backend, "s", XAddId::Explicit(StreamId::new(5, 1))),
+            Err(BackendError::StreamIdTooSmall)
+        );
+        assert_eq!(
+            add(&backend, "s", XAddId::Partial(4)),
+            Err(BackendError::StreamIdTooSmall)
+        );
+        let auto = add(&backend, "s", XAddId::Auto).unwrap().unwrap();
+        assert!(auto > StreamId::new(5, 1));
+        assert_eq!(backend.xlen("s"), 4);
+
+        let max = XAddId::Explicit(StreamId::MAX);
+        assert_eq!(add(&backend, "max", max), Ok(Some(StreamId::MAX)));
+        assert_eq!(
+            add(&backend, "max", XAddId::Auto),
+            Err(BackendError::StreamExhausted)
+        );
+
+        assert_eq!(
+            backend.xadd("none".to_string(), XAddId::Auto, fields(&[]), true, None),
+            Ok(None)
+        );
+        assert!(!backend.stream.contains_key("none"));
+    }
+
+    #[test]
+    fn test_xrange_xdel_xtrim() {
+        let backend = Backend::new();
+        for i in 1..=250 {
+            add(&backend, "s", XAddId::Explicit(StreamId::new(i, 0))).unwrap();
+        }
+        let ids = |entries: Vec<(StreamId, StreamFields)>| {
+            entries.into_iter().map(|(id, _)| id.ms).collect::<Vec<_>>()
+        };
+
+        let range = backend.xrange(
+            "s",
+            Excluded(StreamId::new(3, 0)),
+            Unbounded,
+            Some(2),
+            false,
+        );
+        assert_eq!(ids(range), [4, 5]);
+        let range = backend.xrange("s", Unbounded, Included(StreamId::new(3, 0)), None, true);
+        assert_eq!(ids(range), [3, 2, 1]);
+        let id = StreamId::new(3, 0);
+        assert!(backend
+            .xrange("s", Excluded(id), Excluded(id), None, false)
+            .is_empty());
+
+        let deleted = [
+            StreamId::new(1, 0),
+            StreamId::new(1, 0),
+            StreamId::new(9, 9),
+        ];
+        assert_eq!(backend.xdel("s", &deleted), 1);
+
+        let approx = StreamTrim {
+            strategy: TrimStrategy::MaxLen(50),
+            approximate: true,
+            limit: None,
+        };
+        assert_eq!(backend.xtrim("s", &approx), 100);
+        let exact = StreamTrim {
+            strategy: TrimStrategy::MinId(StreamId::new(200, 0)),
+            approximate: false,
+            limit: None,
+        };
+        assert_eq!(backend.xtrim("s", &exact), 98);
+        assert_eq!(backend.xlen("s"), 51);
+
+        // streams stay around when empty
+        let all = StreamTrim {
+            strategy: TrimStrategy::MaxLen(0),
+            ..exact
+        };
+        backend.xtrim("s", &all);
+        assert_eq!(backend.xlen("s"), 0);
+        assert_eq!(backend.stream_last_id("s"), StreamId::new(250, 0));
+        let read = [("s".to_string(), StreamId::MIN)];
+        assert!(backend.xread(&read, None).is_empty());
+    }
+}
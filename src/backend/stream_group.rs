@@ -0,0 +1,762 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound::{self, Excluded, Unbounded},
+};
+
+use super::{now_ms, Backend, BackendError, Stream, StreamFields, StreamId};
+
+/// A consumer group: where its `>` reads continue, and the entries delivered to its consumers
+/// that were not acknowledged yet (the pending entries list, PEL).
+#[derive(Debug, Clone, Default)]
+pub struct ConsumerGroup {
+    last_id: StreamId,
+    // entries the group has read, None when deletions make that impossible to tell
+    entries_read: Option<u64>,
+    pel: BTreeMap<StreamId, PendingEntry>,
+    consumers: BTreeMap<String, Consumer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingEntry {
+    consumer: String,
+    // unix time in milliseconds of the last delivery
+    delivery_time: u64,
+    delivery_count: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Consumer {
+    // the last time the consumer read or claimed, whether or not it got anything
+    seen_time: u64,
+    // the last time it got entries, None if it never did
+    active_time: Option<u64>,
+    pending: BTreeSet<StreamId>,
+}
+
+/// XCLAIM's options.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    // sets the idle time instead of resetting it
+    pub idle: Option<u64>,
+    // sets the delivery time, as a unix time in milliseconds
+    pub time: Option<u64>,
+    pub retry_count: Option<u64>,
+    // claims entries that aren't pending, as long as they exist in the stream
+    pub force: bool,
+    // doesn't count as a delivery
+    pub just_id: bool,
+    // moves the group's last delivered id forward to this one
+    pub last_id: Option<StreamId>,
+}
+
+/// An entry of the extended XPENDING form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingInfo {
+    pub id: StreamId,
+    pub consumer: String,
+    pub idle: u64,
+    pub delivery_count: u64,
+}
+
+/// The summary form of XPENDING: the number of pending entries, the smallest and greatest of
+/// their ids, and how many of them each consumer holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub range: Option<(StreamId, StreamId)>,
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// XINFO STREAM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_generated_id: StreamId,
+    pub max_deleted_entry_id: StreamId,
+    pub entries_added: u64,
+    pub recorded_first_entry_id: StreamId,
+    pub groups: usize,
+    pub first_entry: Option<(StreamId, StreamFields)>,
+    pub last_entry: Option<(StreamId, StreamFields)>,
+}
+
+/// XINFO GROUPS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_delivered_id: StreamId,
+    pub entries_read: Option<u64>,
+    pub lag: Option<u64>,
+}
+
+/// XINFO CONSUMERS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    // milliseconds since the consumer was last seen
+    pub idle: u64,
+    // milliseconds since it last got entries, None if it never did
+    pub inactive: Option<u64>,
+}
+
+// an entry as handed to a consumer; None if it was deleted from the stream meanwhile
+pub type DeliveredEntry = (StreamId, Option<StreamFields>);
+
+// the cursor to continue from, the claimed entries and the ids of deleted ones
+pub type AutoClaimed = (StreamId, Vec<(StreamId, StreamFields)>, Vec<StreamId>);
+
+impl ConsumerGroup {
+    fn new(last_id: StreamId, entries_read: Option<u64>) -> Self {
+        ConsumerGroup {
+            last_id,
+            entries_read,
+            ..Default::default()
+        }
+    }
+
+    // the consumer, created if needed, marked as seen now
+    fn consumer(&mut self, name: &str, now: u64) -> &mut Consumer {
+        let consumer = self.consumers.entry(name.to_string()).or_default();
+        consumer.seen_time = now;
+        consumer
+    }
+
+    // makes `consumer` the owner of a pending entry, creating it if needed
+    fn assign(&mut self, id: StreamId, consumer: &str, delivery_time: u64, delivery_count: u64) {
+        if let Some(old) = self.pel.get(&id) {
+            if let Some(owner) = self.consumers.get_mut(&old.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        self.pel.insert(
+            id,
+            PendingEntry {
+                consumer: consumer.to_string(),
+                delivery_time,
+                delivery_count,
+            },
+        );
+        if let Some(owner) = self.consumers.get_mut(consumer) {
+            owner.pending.insert(id);
+        }
+    }
+
+    fn ack(&mut self, id: &StreamId) -> bool {
+        let Some(entry) = self.pel.remove(id) else {
+            return false;
+        };
+        if let Some(owner) = self.consumers.get_mut(&entry.consumer) {
+            owner.pending.remove(id);
+        }
+        true
+    }
+
+    // how many entries the group has yet to read, None if that can't be told
+    fn lag(&self, stream: &Stream) -> Option<u64> {
+        if stream.entries_added() == 0 {
+            return Some(0);
+        }
+        let read = match self.entries_read {
+            Some(read) if !stream.has_tombstones_after(self.last_id) => Some(read),
+            _ => stream.entries_read_at(self.last_id),
+        };
+        read.map(|read| stream.entries_added().saturating_sub(read))
+    }
+}
+
+impl Stream {
+    fn group_mut(&mut self, key: &str, group: &str) -> Result<&mut ConsumerGroup, BackendError> {
+        self.groups
+            .get_mut(group)
+            .ok_or_else(|| BackendError::NoGroup(key.to_string(), group.to_string()))
+    }
+
+    // delivers up to `count` entries newer than the group's last delivered id to `consumer`
+    fn read_new(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<DeliveredEntry>, BackendError> {
+        let now = now_ms();
+        let last_id = self.group_mut(key, group)?.last_id;
+        let entries = self.range(Excluded(last_id), Unbounded, count, false);
+        let mut read = self.group_mut(key, group)?.entries_read;
+        for (id, _) in &entries {
+            read = match read {
+                Some(read) if !self.has_tombstones_after(*id) => Some(read + 1),
+                _ => self.entries_read_at(*id),
+            };
+        }
+        let cg = self.group_mut(key, group)?;
+        let c = cg.consumer(consumer, now);
+        if !entries.is_empty() {
+            c.active_time = Some(now);
+        }
+        if let Some((id, _)) = entries.last() {
+            cg.last_id = *id;
+            cg.entries_read = read;
+        }
+        if !noack {
+            for (id, _) in &entries {
+                cg.assign(*id, consumer, now, 1);
+            }
+        }
+        Ok(entries.into_iter().map(|(id, f)| (id, Some(f))).collect())
+    }
+
+    // the consumer's own pending entries with ids greater than `after`, delivered once more
+    fn read_history(
+        &mut self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        after: StreamId,
+        count: Option<usize>,
+    ) -> Result<Vec<DeliveredEntry>, BackendError> {
+        let now = now_ms();
+        let cg = self.group_mut(key, group)?;
+        let ids = cg
+            .consumer(consumer, now)
+            .pending
+            .range((Excluded(after), Unbounded))
+            .take(count.unwrap_or(usize::MAX))
+            .copied()
+            .collect::<Vec<_>>();
+        for id in &ids {
+            if let Some(entry) = cg.pel.get_mut(id) {
+                entry.delivery_time = now;
+                entry.delivery_count += 1;
+            }
+        }
+        Ok(ids
+            .into_iter()
+            .map(|id| (id, self.get(&id).cloned()))
+            .collect())
+    }
+}
+
+impl Backend {
+    // runs `f` on the stream at `key`, failing if there is none
+    fn with_stream<T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&mut Stream) -> Result<T, BackendError>,
+    ) -> Result<T, BackendError> {
+        let _guard = self.shared_lock();
+        let mut stream = self.stream.get_mut(key).ok_or(BackendError::StreamNoKey)?;
+        f(&mut stream)
+    }
+
+    /// Creates a consumer group that delivers entries after `id`, or after the last entry if
+    /// `id` is None (`$`).
+    pub fn xgroup_create(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        mkstream: bool,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        if mkstream {
+            let _guard = self.shared_lock();
+            self.stream.entry(key.to_string()).or_default();
+        }
+        self.with_stream(key, |stream| {
+            if stream.groups.contains_key(group) {
+                return Err(BackendError::BusyGroup);
+            }
+            let id = id.unwrap_or(stream.last_id());
+            let entries_read = entries_read.or_else(|| stream.entries_read_at(id));
+            let cg = ConsumerGroup::new(id, entries_read);
+            stream.groups.insert(group.to_string(), cg);
+            Ok(())
+        })
+    }
+
+    pub fn xgroup_destroy(&self, key: &str, group: &str) -> Result<bool, BackendError> {
+        let destroyed = self.with_stream(key, |stream| Ok(stream.groups.remove(group).is_some()));
+        // readers blocked on the group get to see it's gone
+        if destroyed == Ok(true) {
+            self.signal_ready(key);
+        }
+        destroyed
+    }
+
+    pub fn xgroup_setid(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        entries_read: Option<u64>,
+    ) -> Result<(), BackendError> {
+        self.with_stream(key, |stream| {
+            let id = id.unwrap_or(stream.last_id());
+            let estimate = entries_read.or_else(|| stream.entries_read_at(id));
+            let cg = stream.group_mut(key, group)?;
+            cg.last_id = id;
+            cg.entries_read = estimate;
+            Ok(())
+        })
+    }
+
+    // returns whether the consumer was created
+    pub fn xgroup_createconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<bool, BackendError> {
+        self.with_stream(key, |stream| {
+            let cg = stream.group_mut(key, group)?;
+            if cg.consumers.contains_key(consumer) {
+                return Ok(false);
+            }
+            cg.consumer(consumer, now_ms());
+            Ok(true)
+        })
+    }
+
+    // returns the number of entries the consumer had pending, which are dropped with it
+    pub fn xgroup_delconsumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> Result<usize, BackendError> {
+        self.with_stream(key, |stream| {
+            let cg = stream.group_mut(key, group)?;
+            let Some(c) = cg.consumers.remove(consumer) else {
+                return Ok(0);
+            };
+            for id in &c.pending {
+                cg.pel.remove(id);
+            }
+            Ok(c.pending.len())
+        })
+    }
+
+    /// Reads from a stream as `consumer` of `group`. With `after` None (`>`) it delivers
+    /// entries never delivered to the group, adding them to the PEL unless `noack`; otherwise it
+    /// delivers the consumer's pending entries with greater ids again.
+    pub fn xreadgroup(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+    ) -> Result<Vec<DeliveredEntry>, BackendError> {
+        let nogroup = || BackendError::NoGroup(key.to_string(), group.to_string());
+        self.with_stream(key, |stream| match after {
+            None => stream.read_new(key, group, consumer, count, noack),
+            Some(after) => stream.read_history(key, group, consumer, after, count),
+        })
+        .map_err(|e| match e {
+            BackendError::StreamNoKey => nogroup(),
+            e => e,
+        })
+    }
+
+    pub fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> usize {
+        self.with_stream(key, |stream| {
+            let cg = stream.group_mut(key, group)?;
+            Ok(ids.iter().filter(|id| cg.ack(id)).count())
+        })
+        .unwrap_or(0)
+    }
+
+    pub fn xpending_summary(&self, key: &str, group: &str) -> Result<PendingSummary, BackendError> {
+        self.with_stream(key, |stream| {
+            let cg = stream.group_mut(key, group)?;
+            let range = cg
+                .pel
+                .first_key_value()
+                .zip(cg.pel.last_key_value())
+                .map(|((first, _), (last, _))| (*first, *last));
+            let consumers = cg
+                .consumers
+                .iter()
+                .filter(|(_, c)| !c.pending.is_empty())
+                .map(|(name, c)| (name.clone(), c.pending.len()))
+                .collect();
+            Ok(PendingSummary {
+                count: cg.pel.len(),
+                range,
+                consumers,
+            })
+        })
+    }
+
+    /// Pending entries with ids between `start` and `end`, idle for at least `min_idle`
+    /// milliseconds, optionally only those of `consumer`.
+    pub fn xpending(
+        &self,
+        key: &str,
+        group: &str,
+        (start, end): (Bound<StreamId>, Bound<StreamId>),
+        count: usize,
+        consumer: Option<&str>,
+        min_idle: u64,
+    ) -> Result<Vec<PendingInfo>, BackendError> {
+        let now = now_ms();
+        self.with_stream(key, |stream| {
+            let cg = stream.group_mut(key, group)?;
+            if super::stream::is_empty_range(start, end) {
+                return Ok(vec![]);
+            }
+            Ok(cg
+                .pel
+                .range((start, end))
+                .filter(|(_, e)| consumer.is_none_or(|c| e.consumer == c))
+                .map(|(id, e)| PendingInfo {
+                    id: *id,
+                    consumer: e.consumer.clone(),
+                    idle: now.saturating_sub(e.delivery_time),
+                    delivery_count: e.delivery_count,
+                })
+                .filter(|info| info.idle >= min_idle)
+                .take(count)
+                .collect())
+        })
+    }
+
+    /// Transfers the pending entries among `ids` that were idle for at least `min_idle`
+    /// milliseconds to `consumer` and returns them. Pending entries deleted from the stream are
+    /// dropped from the PEL instead.
+    pub fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        opts: ClaimOptions,
+    ) -> Result<Vec<(StreamId, StreamFields)>, BackendError> {
+        let now = now_ms();
+        let delivery_time = match (opts.time, opts.idle) {
+            (Some(time), _) => time,
+            (None, Some(idle)) => now.saturating_sub(idle),
+            (None, None) => now,
+        };
+        self.with_stream(key, |stream| {
+            let existing = ids
+                .iter()
+                .map(|id| stream.get(id).cloned())
+                .collect::<Vec<_>>();
+            let cg = stream.group_mut(key, group)?;
+            if let Some(last_id) = opts.last_id {
+                cg.last_id = cg.last_id.max(last_id);
+            }
+            cg.consumer(consumer, now);
+            let mut claimed = vec![];
+            for (id, fields) in ids.iter().zip(existing) {
+                let Some(fields) = fields else {
+                    cg.ack(id);
+                    continue;
+                };
+                let count = match cg.pel.get(id) {
+                    Some(e) if now.saturating_sub(e.delivery_time) < min_idle => continue,
+                    Some(e) => e.delivery_count,
+                    None if opts.force => 0,
+                    None => continue,
+                };
+                let count = match (opts.retry_count, opts.just_id) {
+                    (Some(retry_count), _) => retry_count,
+                    (None, true) => count,
+                    (None, false) => count + 1,
+                };
+                cg.assign(*id, consumer, delivery_time, count);
+                claimed.push((*id, fields));
+            }
+            if !claimed.is_empty() && !opts.just_id {
+                cg.consumer(consumer, now).active_time = Some(now);
+            }
+            Ok(claimed)
+        })
+    }
+
+    /// Claims like XCLAIM, scanning the PEL from `start` for up to `count` entries idle for at
+    /// least `min_idle` milliseconds. Returns the id to continue from (0-0 once the scan is
+    /// complete), the claimed entries, and the ids dropped because they were deleted.
+    #[allow(clippy::too_many_arguments)]
+    pub fn xautoclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> Result<AutoClaimed, BackendError> {
+        let now = now_ms();
+        self.with_stream(key, |stream| {
+            let cg = stream.group_mut(key, group)?;
+            cg.consumer(consumer, now);
+            // look at no more than ten times as many entries as asked for
+            let candidates = cg
+                .pel
+                .range(start..)
+                .take(count.saturating_mul(10))
+                .map(|(id, e)| (*id, now.saturating_sub(e.delivery_time) >= min_idle))
+                .collect::<Vec<_>>();
+            let mut next = StreamId::MIN;
+            let (mut claimed, mut deleted) = (vec![], vec![]);
+            for (i, &(id, idle)) in candidates.iter().enumerate() {
+                if claimed.len() == count {
+                    next = id;
+                    break;
+                }
+                if i + 1 == candidates.len() {
+                    let cg = stream.group_mut(key, group)?;
+                    next = cg
+                        .pel
+                        .range((Excluded(id), Unbounded))
+                        .next()
+                        .map_or(StreamId::MIN, |(id, _)| *id);
+                }
+                if !idle {
+                    continue;
+                }
+                let fields = stream.get(&id).cloned();
+                let cg = stream.group_mut(key, group)?;
+                let Some(fields) = fields else {
+                    cg.ack(&id);
+                    deleted.push(id);
+                    continue;
+                };
+                let delivery_count = cg.pel.get(&id).map_or(0, |e| e.delivery_count);
+                let delivery_count = delivery_count + (!just_id) as u64;
+                cg.assign(id, consumer, now, delivery_count);
+                claimed.push((id, fields));
+            }
+            if !claimed.is_empty() && !just_id {
+                stream
+                    .group_mut(key, group)?
+                    .consumer(consumer, now)
+                    .active_time = Some(now);
+            }
+            Ok((next, claimed, deleted))
+        })
+    }
+
+    pub fn xinfo_stream(&self, key: &str) -> Result<StreamInfo, BackendError> {
+        let stream = self.stream.get(key).ok_or(BackendError::NoSuchKey)?;
+        let owned = |(id, fields): (StreamId, &StreamFields)| (id, fields.clone());
+        Ok(StreamInfo {
+            length: stream.len(),
+            last_generated_id: stream.last_id(),
+            max_deleted_entry_id: stream.max_deleted_id(),
+            entries_added: stream.entries_added(),
+            recorded_first_entry_id: stream.first_entry().map_or(StreamId::MIN, |(id, _)| id),
+            groups: stream.groups.len(),
+            first_entry: stream.first_entry().map(owned),
+            last_entry: stream.last_entry().map(owned),
+        })
+    }
+
+    pub fn xinfo_groups(&self, key: &str) -> Result<Vec<GroupInfo>, BackendError> {
+        let stream = self.stream.get(key).ok_or(BackendError::NoSuchKey)?;
+        Ok(stream
+            .groups
+            .iter()
+            .map(|(name, cg)| GroupInfo {
+                name: name.clone(),
+                consumers: cg.consumers.len(),
+                pending: cg.pel.len(),
+                last_delivered_id: cg.last_id,
+                entries_read: cg.entries_read,
+                lag: cg.lag(&stream),
+            })
+            .collect())
+    }
+
+    pub fn xinfo_consumers(
+        &self,
+        key: &str,
+        group: &str,
+    ) -> Result<Vec<ConsumerInfo>, BackendError> {
+        let now = now_ms();
+        let stream = self.stream.get(key).ok_or(BackendError::NoSuchKey)?;
+        let cg = stream
+            .groups
+            .get(group)
+            .ok_or_else(|| BackendError::NoGroup(key.to_string(), group.to_string()))?;
+        Ok(cg
+            .consumers
+            .iter()
+            .map(|(name, c)| ConsumerInfo {
+                name: name.clone(),
+                pending: c.pending.len(),
+                idle: now.saturating_sub(c.seen_time),
+                inactive: c.active_time.map(|t| now.saturating_sub(t)),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BulkString, XAddId};
+
+    use super::*;
+
+    fn add(backend: &Backend, key: &str, ms: u64) {
+        let fields = vec![("f".to_string(), BulkString::from("v").into())];
+        let id = XAddId::Explicit(StreamId::new(ms, 0));
+        backend
+            .xadd(key.to_string(), id, fields, false, None)
+            .unwrap();
+    }
+
+    fn ids(entries: &[DeliveredEntry]) -> Vec<u64> {
+        entries.iter().map(|(id, _)| id.ms).collect()
+    }
+
+    #[test]
+    fn test_xreadgroup_and_xack() {
+        let backend = Backend::new();
+        assert_eq!(
+            backend.xgroup_create("s", "g", Some(StreamId::MIN), false, None),
+            Err(BackendError::StreamNoKey)
+        );
+        backend
+            .xgroup_create("s", "g", Some(StreamId::MIN), true, None)
+            .unwrap();
+        assert_eq!(
+            backend.xgroup_create("s", "g", None, false, None),
+            Err(BackendError::BusyGroup)
+        );
+        for ms in 1..=4 {
+            add(&backend, "s", ms);
+        }
+
+        let read = backend.xreadgroup("s", "g", "alice", None, Some(3), false);
+        assert_eq!(ids(&read.unwrap()), [1, 2, 3]);
+        let read = backend.xreadgroup("s", "g", "bob", None, None, false);
+        assert_eq!(ids(&read.unwrap()), [4]);
+        let read = backend.xreadgroup("s", "g", "bob", None, None, false);
+        assert!(read.unwrap().is_empty());
+
+        // history reads only see the consumer's own pending entries, deleted ones as None
+        backend.xdel("s", &[StreamId::new(2, 0)]);
+        let read = backend
+            .xreadgroup("s", "g", "alice", Some(StreamId::MIN), None, false)
+            .unwrap();
+        assert_eq!(ids(&read), [1, 2, 3]);
+        assert_eq!(read[1].1, None);
+
+        let acked = [
+            StreamId::new(1, 0),
+            StreamId::new(4, 0),
+            StreamId::new(9, 0),
+        ];
+        assert_eq!(backend.xack("s", "g", &acked), 2);
+        let summary = backend.xpending_summary("s", "g").unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(
+            summary.range,
+            Some((StreamId::new(2, 0), StreamId::new(3, 0)))
+        );
+        assert_eq!(summary.consumers, vec![("alice".to_string(), 2)]);
+
+        let pending = backend
+            .xpending("s", "g", (Unbounded, Unbounded), 10, Some("alice"), 0)
+            .unwrap();
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].delivery_count, 2);
+
+        assert_eq!(backend.xgroup_delconsumer("s", "g", "alice"), Ok(2));
+        assert_eq!(backend.xpending_summary("s", "g").unwrap().count, 0);
+        assert_eq!(
+            backend.xreadgroup("s", "nope", "alice", None, None, false),
+            Err(BackendError::NoGroup("s".to_string(), "nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_xclaim_and_xautoclaim() {
+        let backend = Backend::new();
+        for ms in 1..=5 {
+            add(&backend, "s", ms);
+        }
+        backend
+            .xgroup_create("s", "g", Some(StreamId::MIN), false, None)
+            .unwrap();
+        backend
+            .xreadgroup("s", "g", "alice", None, None, false)
+            .unwrap();
+
+        let id = |ms| StreamId::new(ms, 0);
+        let claimed = backend.xclaim("s", "g", "bob", 60_000, &[id(1)], ClaimOptions::default());
+        assert!(claimed.unwrap().is_empty());
+        let opts = ClaimOptions {
+            retry_count: Some(7),
+            ..Default::default()
+        };
+        let claimed = backend.xclaim("s", "g", "bob", 0, &[id(1), id(9)], opts);
+        assert_eq!(claimed.unwrap().len(), 1);
+        let pending = backend
+            .xpending("s", "g", (Unbounded, Unbounded), 1, None, 0)
+            .unwrap();
+        assert_eq!(
+            (pending[0].consumer.as_str(), pending[0].delivery_count),
+            ("bob", 7)
+        );
+
+        backend.xdel("s", &[id(3)]);
+        let (next, claimed, deleted) = backend
+            .xautoclaim("s", "g", "carol", 0, id(2), 2, false)
+            .unwrap();
+        assert_eq!(next, id(5));
+        assert_eq!(
+            claimed.iter().map(|(id, _)| id.ms).collect::<Vec<_>>(),
+            [2, 4]
+        );
+        assert_eq!(deleted, [id(3)]);
+        let (next, claimed, _) = backend
+            .xautoclaim("s", "g", "carol", 0, next, 2, true)
+            .unwrap();
+        assert_eq!((next, claimed.len()), (StreamId::MIN, 1));
+
+        let consumers = backend.xinfo_consumers("s", "g").unwrap();
+        let pending = consumers.iter().map(|c| c.pending).collect::<Vec<_>>();
+        assert_eq!(pending, [0, 1, 3]);
+    }
+
+    #[test]
+    fn test_group_lag() {
+        let backend = Backend::new();
+        for ms in 1..=5 {
+            add(&backend, "s", ms);
+        }
+        backend
+            .xgroup_create("s", "g", Some(StreamId::MIN), false, None)
+            .unwrap();
+        backend
+            .xreadgroup("s", "g", "c", None, Some(2), true)
+            .unwrap();
+        let groups = backend.xinfo_groups("s").unwrap();
+        assert_eq!((groups[0].entries_read, groups[0].lag), (Some(2), Some(3)));
+
+        // a deletion ahead of the group makes the lag unknown
+        backend.xdel("s", &[StreamId::new(4, 0)]);
+        let groups = backend.xinfo_groups("s").unwrap();
+        assert_eq!(groups[0].lag, None);
+        backend.xreadgroup("s", "g", "c", None, None, true).unwrap();
+        let groups = backend.xinfo_groups("s").unwrap();
+        assert_eq!((groups[0].entries_read, groups[0].lag), (Some(5), Some(0)));
+
+        let info = backend.xinfo_stream("s").unwrap();
+        assert_eq!(info.length, 4);
+        assert_eq!(info.max_deleted_entry_id, StreamId::new(4, 0));
+        assert_eq!(info.groups, 1);
+    }
+}
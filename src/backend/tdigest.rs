@@ -0,0 +1,492 @@
+use std::f64::consts::PI;
+
+use dashmap::mapref::entry::Entry;
+
+use super::{Backend, BackendError};
+
+// (mean, weight)
+type Centroid = (f64, f64);
+
+/// A merging t-digest: centroids that are small near the tails and large in the middle, so
+/// extreme quantiles stay accurate with a bounded number of centroids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TDigest {
+    compression: f64,
+    // sorted by mean and compressed after every write
+    centroids: Vec<Centroid>,
+    total: f64,
+    min: f64,
+    max: f64,
+}
+
+impl TDigest {
+    pub fn new(compression: f64) -> Self {
+        TDigest {
+            compression,
+            centroids: Vec::new(),
+            total: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn compression(&self) -> f64 {
+        self.compression
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.centroids.is_empty()
+    }
+
+    pub fn add(&mut self, values: &[f64]) {
+        let centroids = values.iter().map(|v| (*v, 1.0)).collect::<Vec<_>>();
+        self.absorb(centroids);
+    }
+
+    /// Adds all of `other`'s observations.
+    pub fn merge(&mut self, other: &TDigest) {
+        self.absorb(other.centroids.clone());
+    }
+
+    pub fn reset(&mut self) {
+        *self = TDigest::new(self.compression);
+    }
+
+    fn absorb(&mut self, centroids: Vec<Centroid>) {
+        if centroids.is_empty() {
+            return;
+        }
+        for (mean, weight) in &centroids {
+            self.min = self.min.min(*mean);
+            self.max = self.max.max(*mean);
+            self.total += weight;
+        }
+        let mut all = std::mem::take(&mut self.centroids);
+        all.extend(centroids);
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.compress(all);
+    }
+
+    // the k1 scale function and its inverse, which limit a centroid to one unit of k
+    fn k(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin()
+    }
+
+    fn q(&self, k: f64) -> f64 {
+        let k = k.min(self.compression / 4.0);
+        ((k * 2.0 * PI / self.compression).sin() + 1.0) / 2.0
+    }
+
+    fn compress(&mut self, sorted: Vec<Centroid>) {
+        let mut sorted = sorted.into_iter();
+        let Some(mut cur) = sorted.next() else {
+            return;
+        };
+        let mut weight_so_far = 0.0;
+        let mut limit = self.total * self.q(self.k(0.0) + 1.0);
+        for next in sorted {
+            if weight_so_far + cur.1 + next.1 <= limit {
+                let weight = cur.1 + next.1;
+                cur = (cur.0 + (next.0 - cur.0) * next.1 / weight, weight);
+            } else {
+                weight_so_far += cur.1;
+                self.centroids.push(cur);
+                cur = next;
+                limit = self.total * self.q(self.k(weight_so_far / self.total) + 1.0);
+            }
+        }
+        self.centroids.push(cur);
+    }
+
+    pub fn min(&self) -> f64 {
+        match self.is_empty() {
+            true => f64::NAN,
+            false => self.min,
+        }
+    }
+
+    pub fn max(&self) -> f64 {
+        match self.is_empty() {
+            true => f64::NAN,
+            false => self.max,
+        }
+    }
+
+    /// The estimated value below which a fraction `q` of the observations fall.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let c = &self.centroids;
+        let n = c.len();
+        match n {
+            0 => return f64::NAN,
+            1 => return c[0].0,
+            _ => {}
+        }
+        // the offset `q` points at if the observations were in a sorted array
+        let index = q * self.total;
+        if index < 1.0 {
+            return self.min;
+        }
+        let (first, last) = (c[0], c[n - 1]);
+        // a centroid at the edge still has one observation exactly at min or max
+        if first.1 > 1.0 && index < first.1 / 2.0 {
+            return self.min + (index - 1.0) / (first.1 / 2.0 - 1.0) * (first.0 - self.min);
+        }
+        if index > self.total - 1.0 {
+            return self.max;
+        }
+        if last.1 > 1.0 && self.total - index <= last.1 / 2.0 {
+            return self.max
+                - (self.total - index - 1.0) / (last.1 / 2.0 - 1.0) * (self.max - last.0);
+        }
+
+        let mut weight_so_far = first.1 / 2.0;
+        for pair in c.windows(2) {
+            let ((left, left_w), (right, right_w)) = (pair[0], pair[1]);
+            let dw = (left_w + right_w) / 2.0;
+            if weight_so_far + dw > index {
+                // a singleton's whole weight sits at its mean, so it is not interpolated over
+                let mut left_unit = 0.0;
+                if left_w == 1.0 {
+                    if index - weight_so_far < 0.5 {
+                        return left;
+                    }
+                    left_unit = 0.5;
+                }
+                let mut right_unit = 0.0;
+                if right_w == 1.0 {
+                    if weight_so_far + dw - index <= 0.5 {
+                        return right;
+                    }
+                    right_unit = 0.5;
+                }
+                let z1 = index - weight_so_far - left_unit;
+                let z2 = weight_so_far + dw - index - right_unit;
+                return weighted_average(left, z2, right, z1);
+            }
+            weight_so_far += dw;
+        }
+        let z1 = index - weight_so_far;
+        let z2 = last.1 / 2.0 - z1;
+        weighted_average(last.0, z1, self.max, z2)
+    }
+
+    /// The estimated fraction of observations below `value`, counting half of those equal
+    /// to it.
+    pub fn cdf(&self, value: f64) -> f64 {
+        let c = &self.centroids;
+        let n = c.len();
+        if n == 0 {
+            return f64::NAN;
+        }
+        if value < self.min {
+            return 0.0;
+        }
+        if value > self.max {
+            return 1.0;
+        }
+        if n == 1 {
+            return match self.max - self.min {
+                width if width < f64::EPSILON => 0.5,
+                width => (value - self.min) / width,
+            };
+        }
+        let total = self.total;
+        let (first, last) = (c[0], c[n - 1]);
+        if value < first.0 {
+            let width = first.0 - self.min;
+            if width <= 0.0 {
+                return 0.0;
+            }
+            if value == self.min {
+                return 0.5 / total;
+            }
+            return (1.0 + (value - self.min) / width * (first.1 / 2.0 - 1.0)) / total;
+        }
+        if value > last.0 {
+            let width = self.max - last.0;
+            if width <= 0.0 {
+                return 1.0;
+            }
+            if value == self.max {
+                return 1.0 - 0.5 / total;
+            }
+            return 1.0 - (1.0 + (self.max - value) / width * (last.1 / 2.0 - 1.0)) / total;
+        }
+
+        let mut weight_so_far = 0.0;
+        for (i, pair) in c.windows(2).enumerate() {
+            let ((left, left_w), (right, right_w)) = (pair[0], pair[1]);
+            if left == value {
+                // centroids exactly at `value` count as one
+                let dw = c[i..]
+                    .iter()
+                    .take_while(|(mean, _)| *mean == value)
+                    .map(|(_, w)| w)
+                    .sum::<f64>();
+                return (weight_so_far + dw / 2.0) / total;
+            }
+            if left < value && value < right {
+                let dw = (left_w + right_w) / 2.0;
+                if right - left <= 0.0 {
+                    return (weight_so_far + dw) / total;
+                }
+                let (mut left_excluded, mut right_excluded) = (0.0, 0.0);
+                if left_w == 1.0 {
+                    if right_w == 1.0 {
+                        // two singletons leave nothing to interpolate
+                        return (weight_so_far + 1.0) / total;
+                    }
+                    left_excluded = 0.5;
+                } else if right_w == 1.0 {
+                    right_excluded = 0.5;
+                }
+                let base = weight_so_far + left_w / 2.0 + left_excluded;
+                let span = dw - left_excluded - right_excluded;
+                return (base + span * (value - left) / (right - left)) / total;
+            }
+            weight_so_far += left_w;
+        }
+        1.0 - 0.5 / total
+    }
+
+    /// How many observations are below `value` (or above it if `reverse`), counting half
+    /// of those equal to it: -1 past the wrong end, and -2 for an empty sketch.
+    pub fn rank(&self, value: f64, reverse: bool) -> i64 {
+        if self.is_empty() {
+            return -2;
+        }
+        let total = self.total;
+        let below = match value {
+            v if v < self.min => return if reverse { total as i64 } else { -1 },
+            v if v > self.max => return if reverse { -1 } else { total as i64 },
+            v => self.cdf(v) * total,
+        };
+        match reverse {
+            true => (total - below - 0.5).round() as i64,
+            false => (below - 0.5).round() as i64,
+        }
+    }
+
+    /// The mean of the observations between the `low` and `high` quantiles.
+    pub fn trimmed_mean(&self, low: f64, high: f64) -> f64 {
+        let (low, high) = ((self.total * low).floor(), (self.total * high).ceil());
+        let (mut start, mut sum, mut count) = (0.0, 0.0, 0.0);
+        for (mean, weight) in &self.centroids {
+            let end = start + weight;
+            let overlap = end.min(high) - start.max(low);
+            if overlap > 0.0 {
+                sum += mean * overlap;
+                count += overlap;
+            }
+            start = end;
+        }
+        match count {
+            0.0 => f64::NAN,
+            _ => sum / count,
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>() + self.centroids.len() * size_of::<Centroid>()
+    }
+}
+
+fn weighted_average(x1: f64, w1: f64, x2: f64, w2: f64) -> f64 {
+    let (lo, hi) = (x1.min(x2), x1.max(x2));
+    ((x1 * w1 + x2 * w2) / (w1 + w2)).clamp(lo, hi)
+}
+
+impl Backend {
+    pub fn tdigest_create(&self, key: String, compression: f64) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        match self.tdigest.entry(key) {
+            Entry::Occupied(_) => Err(BackendError::TDigestKeyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(TDigest::new(compression));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn tdigest_add(&self, key: &str, values: &[f64]) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        let mut digest = self
+            .tdigest
+            .get_mut(key)
+            .ok_or(BackendError::TDigestNoKey)?;
+        digest.add(values);
+        Ok(())
+    }
+
+    pub fn tdigest_reset(&self, key: &str) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        let mut digest = self
+            .tdigest
+            .get_mut(key)
+            .ok_or(BackendError::TDigestNoKey)?;
+        digest.reset();
+        Ok(())
+    }
+
+    pub fn tdigest_quantile(&self, key: &str, quantiles: &[f64]) -> Result<Vec<f64>, BackendError> {
+        let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+        Ok(quantiles.iter().map(|q| digest.quantile(*q)).collect())
+    }
+
+    pub fn tdigest_cdf(&self, key: &str, values: &[f64]) -> Result<Vec<f64>, BackendError> {
+        let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+        Ok(values.iter().map(|v| digest.cdf(*v)).collect())
+    }
+
+    pub fn tdigest_rank(
+        &self,
+        key: &str,
+        values: &[f64],
+        reverse: bool,
+    ) -> Result<Vec<i64>, BackendError> {
+        let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+        Ok(values.iter().map(|v| digest.rank(*v, reverse)).collect())
+    }
+
+    pub fn tdigest_trimmed_mean(
+        &self,
+        key: &str,
+        low: f64,
+        high: f64,
+    ) -> Result<f64, BackendError> {
+        let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+        Ok(digest.trimmed_mean(low, high))
+    }
+
+    pub fn tdigest_min(&self, key: &str) -> Result<f64, BackendError> {
+        let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+        Ok(digest.min())
+    }
+
+    pub fn tdigest_max(&self, key: &str) -> Result<f64, BackendError> {
+        let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+        Ok(digest.max())
+    }
+
+    /// Merges the sources into `dest`, along with what `dest` already holds unless
+    /// `override_dest`. Without a `compression`, it is the largest of the merged sketches'.
+    pub fn tdigest_merge(
+        &self,
+        dest: String,
+        sources: &[String],
+        compression: Option<f64>,
+        override_dest: bool,
+    ) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        let mut digests = sources
+            .iter()
+            .map(|key| {
+                let digest = self.tdigest.get(key).ok_or(BackendError::TDigestNoKey)?;
+                Ok(digest.clone())
+            })
+            .collect::<Result<Vec<_>, BackendError>>()?;
+        if !override_dest {
+            digests.extend(self.tdigest.get(&dest).map(|digest| digest.clone()));
+        }
+        let compression = compression
+            .unwrap_or_else(|| digests.iter().map(TDigest::compression).fold(0.0, f64::max));
+        let mut merged = TDigest::new(compression);
+        for digest in &digests {
+            merged.merge(digest);
+        }
+        self.tdigest.insert(dest, merged);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tdigest_small_sets_are_exact() {
+        let mut digest = TDigest::new(100.0);
+        digest.add(&[1.0, 2.0, 2.0, 3.0, 3.0, 3.0, 4.0, 4.0, 4.0, 4.0]);
+        digest.add(&[5.0, 5.0, 5.0, 5.0, 5.0]);
+        let quantiles =
+            [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0].map(|q| digest.quantile(q));
+        assert_eq!(
+            quantiles,
+            [1.0, 2.0, 3.0, 3.0, 4.0, 4.0, 4.0, 5.0, 5.0, 5.0, 5.0]
+        );
+
+        let mut digest = TDigest::new(100.0);
+        digest.add(&[10.0, 20.0, 30.0, 40.0, 50.0, 60.0]);
+        let ranks = [0.0, 10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0]
+            .map(|v| (digest.rank(v, false), digest.rank(v, true)));
+        assert_eq!(
+            ranks,
+            [
+                (-1, 6),
+                (0, 5),
+                (1, 4),
+                (2, 3),
+                (3, 2),
+                (4, 1),
+                (5, 0),
+                (6, -1)
+            ]
+        );
+        assert_eq!(digest.cdf(35.0), 0.5);
+        assert_eq!(digest.trimmed_mean(0.0, 1.0), 35.0);
+        assert_eq!(digest.trimmed_mean(0.2, 0.8), 35.0);
+        assert!(TDigest::new(100.0).quantile(0.5).is_nan());
+    }
+
+    #[test]
+    fn test_tdigest_large_sets_are_close() {
+        let mut digest = TDigest::new(100.0);
+        let values = (0..100_000).map(|i| i as f64).collect::<Vec<_>>();
+        for chunk in values.chunks(1000) {
+            digest.add(chunk);
+        }
+        assert!(digest.centroids.len() < 200);
+        for q in [0.001, 0.01, 0.5, 0.99, 0.999] {
+            let expected = q * 100_000.0;
+            assert!((digest.quantile(q) - expected).abs() < 100_000.0 * 0.005);
+            assert!((digest.cdf(expected) - q).abs() < 0.005);
+        }
+        assert_eq!((digest.min(), digest.max()), (0.0, 99_999.0));
+    }
+
+    #[test]
+    fn test_tdigest_merge() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        backend.tdigest_create("a".to_string(), 100.0)?;
+        backend.tdigest_create("b".to_string(), 200.0)?;
+        backend.tdigest_add("a", &[1.0, 2.0])?;
+        backend.tdigest_add("b", &[3.0])?;
+        backend.tdigest_create("dest".to_string(), 50.0)?;
+        backend.tdigest_add("dest", &[100.0])?;
+
+        backend.tdigest_merge(
+            "dest".to_string(),
+            &["a".to_string(), "b".to_string()],
+            None,
+            false,
+        )?;
+        assert_eq!(backend.tdigest_max("dest")?, 100.0);
+        assert_eq!(
+            backend.tdigest.get("dest").map(|d| d.compression()),
+            Some(200.0)
+        );
+
+        let sources = ["a".to_string()];
+        backend.tdigest_merge("dest".to_string(), &sources, Some(80.0), true)?;
+        assert_eq!(backend.tdigest_max("dest")?, 2.0);
+        assert_eq!(
+            backend.tdigest.get("dest").map(|d| d.compression()),
+            Some(80.0)
+        );
+        assert_eq!(
+            backend.tdigest_merge("dest".to_string(), &["x".to_string()], None, false),
+            Err(BackendError::TDigestNoKey)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,720 @@
+use std::collections::BTreeMap;
+
+use dashmap::mapref::entry::Entry;
+
+use super::{Backend, BackendError};
+
+/// What TS.ADD does with a sample whose timestamp is already in the series.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    // reject the sample
+    #[default]
+    Block,
+    // keep the existing value
+    First,
+    Last,
+    Min,
+    Max,
+    Sum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsAggregator {
+    Avg,
+    Sum,
+    Min,
+    Max,
+    Count,
+    First,
+    Last,
+}
+
+/// Folds the samples of each `bucket` milliseconds into one, with buckets starting at
+/// `align` plus a multiple of `bucket`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TsAggregation {
+    pub aggregator: TsAggregator,
+    pub bucket: u64,
+    pub align: u64,
+}
+
+/// The options of TS.CREATE, which TS.ADD and TS.INCRBY also take to create a missing series.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TsOptions {
+    // milliseconds before the newest sample that samples are kept for; 0 keeps them all
+    pub retention: Option<u64>,
+    pub duplicate_policy: Option<DuplicatePolicy>,
+    pub labels: Option<Labels>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TsRangeQuery {
+    pub from: u64,
+    pub to: u64,
+    pub rev: bool,
+    pub filter_ts: Option<Vec<u64>>,
+    pub filter_value: Option<(f64, f64)>,
+    pub count: Option<usize>,
+    pub aggregation: Option<TsAggregation>,
+}
+
+/// A TS.MRANGE FILTER expression. `Eq` matches series whose label has one of the values,
+/// or lacks the label when there are none; `Ne` is the opposite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelMatcher {
+    Eq(String, Vec<String>),
+    Ne(String, Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TsRule {
+    pub dest: String,
+    pub aggregation: TsAggregation,
+    // the start of the bucket the source's newest samples fall in, written once it closes
+    open_bucket: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeriesInfo {
+    pub total_samples: usize,
+    pub first_timestamp: u64,
+    pub last_timestamp: u64,
+    pub retention: u64,
+    pub duplicate_policy: DuplicatePolicy,
+    pub labels: Labels,
+    pub source_key: Option<String>,
+    pub rules: Vec<TsRule>,
+}
+
+pub type Sample = (u64, f64);
+
+pub type Labels = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Default)]
+pub struct TimeSeries {
+    samples: BTreeMap<u64, f64>,
+    retention: u64,
+    duplicate_policy: DuplicatePolicy,
+    labels: Labels,
+    rules: Vec<TsRule>,
+    // the series this one is a compaction of
+    source: Option<String>,
+}
+
+impl DuplicatePolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "block" => Some(DuplicatePolicy::Block),
+            "first" => Some(DuplicatePolicy::First),
+            "last" => Some(DuplicatePolicy::Last),
+            "min" => Some(DuplicatePolicy::Min),
+            "max" => Some(DuplicatePolicy::Max),
+            "sum" => Some(DuplicatePolicy::Sum),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DuplicatePolicy::Block => "block",
+            DuplicatePolicy::First => "first",
+            DuplicatePolicy::Last => "last",
+            DuplicatePolicy::Min => "min",
+            DuplicatePolicy::Max => "max",
+            DuplicatePolicy::Sum => "sum",
+        }
+    }
+}
+
+impl TsAggregator {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "avg" => Some(TsAggregator::Avg),
+            "sum" => Some(TsAggregator::Sum),
+            "min" => Some(TsAggregator::Min),
+            "max" => Some(TsAggregator::Max),
+            "count" => Some(TsAggregator::Count),
+            "first" => Some(TsAggregator::First),
+            "last" => Some(TsAggregator::Last),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TsAggregator::Avg => "avg",
+            TsAggregator::Sum => "sum",
+            TsAggregator::Min => "min",
+            TsAggregator::Max => "max",
+            TsAggregator::Count => "count",
+            TsAggregator::First => "first",
+            TsAggregator::Last => "last",
+        }
+    }
+
+    // the aggregate of a non-empty run of values in timestamp order
+    fn apply(&self, values: impl Iterator<Item = f64>) -> f64 {
+        let (mut count, mut sum, mut first, mut last) = (0usize, 0.0, f64::NAN, f64::NAN);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for v in values {
+            if count == 0 {
+                first = v;
+            }
+            count += 1;
+            sum += v;
+            last = v;
+            min = min.min(v);
+            max = max.max(v);
+        }
+        match self {
+            TsAggregator::Avg => sum / count as f64,
+            TsAggregator::Sum => sum,
+            TsAggregator::Min => min,
+            TsAggregator::Max => max,
+            TsAggregator::Count => count as f64,
+            TsAggregator::First => first,
+            TsAggregator::Last => last,
+        }
+    }
+}
+
+impl TsAggregation {
+    // the start of the bucket `ts` falls in
+    fn bucket_start(&self, ts: u64) -> u64 {
+        let offset = (ts as i128 - self.align as i128).rem_euclid(self.bucket as i128);
+        (ts as i128 - offset).max(0) as u64
+    }
+
+    // aggregates samples in timestamp order into (bucket start, aggregate) pairs
+    fn aggregate(&self, samples: impl Iterator<Item = Sample>) -> Vec<Sample> {
+        let mut ret = Vec::new();
+        let mut bucket: Option<(u64, Vec<f64>)> = None;
+        for (ts, value) in samples {
+            let start = self.bucket_start(ts);
+            match bucket.as_mut() {
+                Some((current, values)) if *current == start => values.push(value),
+                _ => {
+                    if let Some((current, values)) = bucket.take() {
+                        ret.push((current, self.aggregator.apply(values.into_iter())));
+                    }
+                    bucket = Some((start, vec![value]));
+                }
+            }
+        }
+        if let Some((current, values)) = bucket {
+            ret.push((current, self.aggregator.apply(values.into_iter())));
+        }
+        ret
+    }
+}
+
+impl LabelMatcher {
+    fn matches(&self, labels: &[(String, String)]) -> bool {
+        let (LabelMatcher::Eq(label, values) | LabelMatcher::Ne(label, values)) = self;
+        let eq = match labels.iter().find(|(l, _)| l == label) {
+            Some((_, v)) => values.contains(v),
+            None => values.is_empty(),
+        };
+        eq == matches!(self, LabelMatcher::Eq(..))
+    }
+}
+
+impl TimeSeries {
+    fn new(options: TsOptions) -> Self {
+        TimeSeries {
+            retention: options.retention.unwrap_or_default(),
+            duplicate_policy: options.duplicate_policy.unwrap_or_default(),
+            labels: options.labels.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn labels(&self) -> &[(String, String)] {
+        &self.labels
+    }
+
+    pub fn last(&self) -> Option<Sample> {
+        self.samples.last_key_value().map(|(ts, v)| (*ts, *v))
+    }
+
+    // estimated bytes used by the series, see `Backend::memory_usage`
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.samples.len() * size_of::<Sample>()
+            + self
+                .labels
+                .iter()
+                .map(|(k, v)| size_of::<(String, String)>() + k.len() + v.len())
+                .sum::<usize>()
+            + self
+                .rules
+                .iter()
+                .map(|rule| size_of::<TsRule>() + rule.dest.len())
+                .sum::<usize>()
+    }
+
+    // samples at or before this timestamp have fallen out of the retention window
+    fn retention_floor(&self) -> Option<u64> {
+        let (last, _) = self.last()?;
+        (self.retention > 0).then(|| last.saturating_sub(self.retention))
+    }
+
+    /// Adds a sample, resolving a duplicate timestamp with `policy` (or the series' own),
+    /// and returns the value stored.
+    fn upsert(
+        &mut self,
+        ts: u64,
+        value: f64,
+        policy: Option<DuplicatePolicy>,
+    ) -> Result<f64, BackendError> {
+        if self.retention_floor().is_some_and(|floor| ts < floor) {
+            return Err(BackendError::TsTooOld);
+        }
+        let stored = match self.samples.get(&ts) {
+            None => value,
+            Some(&old) => match policy.unwrap_or(self.duplicate_policy) {
+                DuplicatePolicy::Block => return Err(BackendError::TsDuplicate),
+                DuplicatePolicy::First => old,
+                DuplicatePolicy::Last => value,
+                DuplicatePolicy::Min => old.min(value),
+                DuplicatePolicy::Max => old.max(value),
+                DuplicatePolicy::Sum => old + value,
+            },
+        };
+        self.samples.insert(ts, stored);
+        if let Some(floor) = self.retention_floor() {
+            self.samples = self.samples.split_off(&floor);
+        }
+        Ok(stored)
+    }
+
+    /// The samples between two timestamps, filtered and aggregated as the query asks.
+    pub fn range(&self, query: &TsRangeQuery) -> Vec<Sample> {
+        if query.from > query.to {
+            return Vec::new();
+        }
+        let samples = self
+            .samples
+            .range(query.from..=query.to)
+            .map(|(ts, v)| (*ts, *v))
+            .filter(|(ts, _)| query.filter_ts.as_ref().is_none_or(|f| f.contains(ts)))
+            .filter(|(_, v)| {
+                query
+                    .filter_value
+                    .is_none_or(|(min, max)| (min..=max).contains(v))
+            });
+        let mut ret = match &query.aggregation {
+            Some(aggregation) => aggregation.aggregate(samples),
+            None => samples.collect(),
+        };
+        if query.rev {
+            ret.reverse();
+        }
+        if let Some(count) = query.count {
+            ret.truncate(count);
+        }
+        ret
+    }
+
+    // the compacted samples a write at `ts` settles: a bucket that just closed, or an
+    // already closed bucket the write changed
+    fn compactions(&mut self, ts: u64) -> Vec<(String, Sample)> {
+        let mut ret = Vec::new();
+        for i in 0..self.rules.len() {
+            let aggregation = self.rules[i].aggregation;
+            let start = aggregation.bucket_start(ts);
+            let settled = match self.rules[i].open_bucket {
+                Some(open) if start < open => Some(start),
+                Some(open) if start == open => None,
+                open => {
+                    self.rules[i].open_bucket = Some(start);
+                    open
+                }
+            };
+            let Some(bucket) = settled else {
+                continue;
+            };
+            let end = bucket.saturating_add(aggregation.bucket);
+            let mut values = self.samples.range(bucket..end).map(|(_, v)| *v).peekable();
+            if values.peek().is_some() {
+                let value = aggregation.aggregator.apply(values);
+                ret.push((self.rules[i].dest.clone(), (bucket, value)));
+            }
+        }
+        ret
+    }
+}
+
+impl Backend {
+    pub fn ts_create(&self, key: String, options: TsOptions) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        match self.timeseries.entry(key) {
+            Entry::Occupied(_) => Err(BackendError::TsKeyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(TimeSeries::new(options));
+                Ok(())
+            }
+        }
+    }
+
+    /// Adds a sample, creating the series with `options` when it is missing, and updates the
+    /// series compacted from it. Returns the sample's timestamp.
+    pub fn ts_add(
+        &self,
+        key: String,
+        ts: u64,
+        value: f64,
+        on_duplicate: Option<DuplicatePolicy>,
+        options: TsOptions,
+    ) -> Result<u64, BackendError> {
+        let _guard = self.shared_lock();
+        let compactions = {
+            let mut series = self
+                .timeseries
+                .entry(key)
+                .or_insert_with(|| TimeSeries::new(options));
+            series.upsert(ts, value, on_duplicate)?;
+            series.compactions(ts)
+        };
+        self.ts_compact(compactions);
+        Ok(ts)
+    }
+
+    /// TS.ADD for each (key, timestamp, value); the series must exist.
+    pub fn ts_madd(&self, samples: Vec<(String, u64, f64)>) -> Vec<Result<u64, BackendError>> {
+        samples
+            .into_iter()
+            .map(|(key, ts, value)| {
+                if !self.timeseries.contains_key(&key) {
+                    return Err(BackendError::TsNoKey);
+                }
+                self.ts_add(key, ts, value, None, TsOptions::default())
+            })
+            .collect()
+    }
+
+    /// Adds `by` to the newest value as a new sample at `ts`, or in place when `ts` is the
+    /// newest timestamp. Returns the sample's timestamp.
+    pub fn ts_incrby(
+        &self,
+        key: String,
+        by: f64,
+        ts: u64,
+        options: TsOptions,
+    ) -> Result<u64, BackendError> {
+        let _guard = self.shared_lock();
+        let compactions = {
+            let mut series = self
+                .timeseries
+                .entry(key)
+                .or_insert_with(|| TimeSeries::new(options));
+            let (last_ts, last) = series.last().unwrap_or((0, 0.0));
+            if ts < last_ts {
+                return Err(BackendError::TsIncrTooOld);
+            }
+            series.upsert(ts, last + by, Some(DuplicatePolicy::Last))?;
+            series.compactions(ts)
+        };
+        self.ts_compact(compactions);
+        Ok(ts)
+    }
+
+    pub fn ts_get(&self, key: &str) -> Result<Option<Sample>, BackendError> {
+        let series = self.timeseries.get(key).ok_or(BackendError::TsNoKey)?;
+        Ok(series.last())
+    }
+
+    pub fn ts_range(&self, key: &str, query: &TsRangeQuery) -> Result<Vec<Sample>, BackendError> {
+        let series = self.timeseries.get(key).ok_or(BackendError::TsNoKey)?;
+        Ok(series.range(query))
+    }
+
+    /// TS.RANGE on every series whose labels satisfy all the matchers, sorted by key.
+    pub fn ts_mrange(
+        &self,
+        matchers: &[LabelMatcher],
+        query: &TsRangeQuery,
+    ) -> Vec<(String, Labels, Vec<Sample>)> {
+        let mut ret = self
+            .timeseries
+            .iter()
+            .filter(|series| matchers.iter().all(|m| m.matches(series.labels())))
+            .map(|series| {
+                let labels = series.labels().to_vec();
+                (series.key().clone(), labels, series.range(query))
+            })
+            .collect::<Vec<_>>();
+        ret.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        ret
+    }
+
+    pub fn ts_info(&self, key: &str) -> Result<TimeSeriesInfo, BackendError> {
+        let series = self.timeseries.get(key).ok_or(BackendError::TsNoKey)?;
+        let first = series.samples.first_key_value().map(|(ts, _)| *ts);
+        Ok(TimeSeriesInfo {
+            total_samples: series.len(),
+            first_timestamp: first.unwrap_or_default(),
+            last_timestamp: series.last().map(|(ts, _)| ts).unwrap_or_default(),
+            retention: series.retention,
+            duplicate_policy: series.duplicate_policy,
+            labels: series.labels.clone(),
+            source_key: series.source.clone(),
+            rules: series.rules.clone(),
+        })
+    }
+
+    /// Compacts `source` into `dest` from now on. A series can only be compacted from one
+    /// source, and a compaction cannot be compacted further.
+    pub fn ts_createrule(
+        &self,
+        source: &str,
+        dest: &str,
+        aggregation: TsAggregation,
+    ) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        if source == dest {
+            return Err(BackendError::TsSameRuleKey);
+        }
+        let (Some(src), Some(dst)) = (self.timeseries.get(source), self.timeseries.get(dest))
+        else {
+            return Err(BackendError::TsNoKey);
+        };
+        if src.source.is_some() || dst.source.is_some() || !dst.rules.is_empty() {
+            return Err(BackendError::TsRuleExists);
+        }
+        drop((src, dst));
+        if let Some(mut src) = self.timeseries.get_mut(source) {
+            let open_bucket = src.last().map(|(ts, _)| aggregation.bucket_start(ts));
+            src.rules.push(TsRule {
+                dest: dest.to_string(),
+                aggregation,
+                open_bucket,
+            });
+        }
+        if let Some(mut dst) = self.timeseries.get_mut(dest) {
+            dst.source = Some(source.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn ts_deleterule(&self, source: &str, dest: &str) -> Result<(), BackendError> {
+        let _guard = self.exclusive_lock();
+        let mut src = self
+            .timeseries
+            .get_mut(source)
+            .ok_or(BackendError::TsNoKey)?;
+        let len = src.rules.len();
+        src.rules.retain(|rule| rule.dest != dest);
+        if src.rules.len() == len {
+            return Err(BackendError::TsNoRule);
+        }
+        drop(src);
+        if let Some(mut dst) = self.timeseries.get_mut(dest) {
+            dst.source = None;
+        }
+        Ok(())
+    }
+
+    // writes compacted samples to their series, overwriting earlier values of the bucket
+    fn ts_compact(&self, compactions: Vec<(String, Sample)>) {
+        for (dest, (ts, value)) in compactions {
+            if let Some(mut series) = self.timeseries.get_mut(&dest) {
+                // a bucket older than the destination's retention is dropped
+                let _ = series.upsert(ts, value, Some(DuplicatePolicy::Last));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(from: u64, to: u64) -> TsRangeQuery {
+        TsRangeQuery {
+            from,
+            to,
+            rev: false,
+            filter_ts: None,
+            filter_value: None,
+            count: None,
+            aggregation: None,
+        }
+    }
+
+    fn aggregation(aggregator: TsAggregator, bucket: u64) -> TsAggregation {
+        TsAggregation {
+            aggregator,
+            bucket,
+            align: 0,
+        }
+    }
+
+    #[test]
+    fn test_ts_add_duplicates_and_retention() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let options = TsOptions {
+            retention: Some(100),
+            ..Default::default()
+        };
+        backend.ts_create("ts".to_string(), options)?;
+        assert_eq!(
+            backend.ts_create("ts".to_string(), TsOptions::default()),
+            Err(BackendError::TsKeyExists)
+        );
+        backend.ts_add("ts".to_string(), 10, 1.0, None, TsOptions::default())?;
+        assert_eq!(
+            backend.ts_add("ts".to_string(), 10, 2.0, None, TsOptions::default()),
+            Err(BackendError::TsDuplicate)
+        );
+        let sum = Some(DuplicatePolicy::Sum);
+        backend.ts_add("ts".to_string(), 10, 2.0, sum, TsOptions::default())?;
+        assert_eq!(backend.ts_get("ts")?, Some((10, 3.0)));
+
+        backend.ts_add("ts".to_string(), 150, 5.0, None, TsOptions::default())?;
+        assert_eq!(
+            backend.ts_range("ts", &query(0, u64::MAX))?,
+            vec![(150, 5.0)]
+        );
+        assert_eq!(
+            backend.ts_add("ts".to_string(), 20, 1.0, None, TsOptions::default()),
+            Err(BackendError::TsTooOld)
+        );
+
+        assert_eq!(
+            backend.ts_incrby("ts".to_string(), 2.5, 160, TsOptions::default())?,
+            160
+        );
+        assert_eq!(
+            backend.ts_incrby("ts".to_string(), 1.0, 160, TsOptions::default())?,
+            160
+        );
+        assert_eq!(backend.ts_get("ts")?, Some((160, 8.5)));
+        assert_eq!(
+            backend.ts_incrby("ts".to_string(), 1.0, 155, TsOptions::default()),
+            Err(BackendError::TsIncrTooOld)
+        );
+
+        let added = backend.ts_madd(vec![
+            ("ts".to_string(), 170, 1.0),
+            ("missing".to_string(), 170, 1.0),
+        ]);
+        assert_eq!(added, vec![Ok(170), Err(BackendError::TsNoKey)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_range_aggregation() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for (ts, v) in [(1, 1.0), (5, 3.0), (10, 10.0), (12, 2.0), (25, 4.0)] {
+            backend.ts_add("ts".to_string(), ts, v, None, TsOptions::default())?;
+        }
+        let mut q = query(0, 20);
+        assert_eq!(backend.ts_range("ts", &q)?.len(), 4);
+        q.filter_value = Some((2.0, 5.0));
+        assert_eq!(backend.ts_range("ts", &q)?, vec![(5, 3.0), (12, 2.0)]);
+
+        let mut q = query(0, u64::MAX);
+        q.aggregation = Some(aggregation(TsAggregator::Avg, 10));
+        assert_eq!(
+            backend.ts_range("ts", &q)?,
+            vec![(0, 2.0), (10, 6.0), (20, 4.0)]
+        );
+        q.aggregation = Some(TsAggregation {
+            align: 5,
+            ..aggregation(TsAggregator::Count, 10)
+        });
+        q.rev = true;
+        q.count = Some(2);
+        assert_eq!(backend.ts_range("ts", &q)?, vec![(25, 1.0), (5, 3.0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_compaction_rules() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        for key in ["src", "dst"] {
+            backend.ts_create(key.to_string(), TsOptions::default())?;
+        }
+        backend.ts_createrule("src", "dst", aggregation(TsAggregator::Max, 10))?;
+        assert_eq!(
+            backend.ts_createrule("dst", "src", aggregation(TsAggregator::Max, 10)),
+            Err(BackendError::TsRuleExists)
+        );
+
+        for (ts, v) in [(1, 1.0), (4, 7.0), (9, 2.0), (12, 3.0)] {
+            backend.ts_add("src".to_string(), ts, v, None, TsOptions::default())?;
+        }
+        // the bucket at 10 is still open
+        assert_eq!(
+            backend.ts_range("dst", &query(0, u64::MAX))?,
+            vec![(0, 7.0)]
+        );
+        let last = Some(DuplicatePolicy::Last);
+        backend.ts_add("src".to_string(), 4, 8.0, last, TsOptions::default())?;
+        backend.ts_add("src".to_string(), 31, 1.0, None, TsOptions::default())?;
+        assert_eq!(
+            backend.ts_range("dst", &query(0, u64::MAX))?,
+            vec![(0, 8.0), (10, 3.0)]
+        );
+
+        let info = backend.ts_info("dst")?;
+        assert_eq!(info.source_key.as_deref(), Some("src"));
+        backend.ts_deleterule("src", "dst")?;
+        assert!(backend.ts_info("src")?.rules.is_empty());
+        assert_eq!(
+            backend.ts_deleterule("src", "dst"),
+            Err(BackendError::TsNoRule)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_mrange_filter() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let labels = |pairs: &[(&str, &str)]| TsOptions {
+            labels: Some(
+                pairs
+                    .iter()
+                    .map(|(l, v)| (l.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            ..Default::default()
+        };
+        backend.ts_create("a".to_string(), labels(&[("host", "h1"), ("dc", "x")]))?;
+        backend.ts_create("b".to_string(), labels(&[("host", "h2")]))?;
+        backend.ts_create("c".to_string(), labels(&[("host", "h3"), ("dc", "y")]))?;
+        let keys = |matchers: &[LabelMatcher]| -> Vec<String> {
+            let ret = backend.ts_mrange(matchers, &query(0, u64::MAX));
+            ret.into_iter().map(|(key, _, _)| key).collect()
+        };
+        let eq = |l: &str, vs: &[&str]| {
+            LabelMatcher::Eq(l.to_string(), vs.iter().map(|v| v.to_string()).collect())
+        };
+        let ne = |l: &str, vs: &[&str]| {
+            LabelMatcher::Ne(l.to_string(), vs.iter().map(|v| v.to_string()).collect())
+        };
+        assert_eq!(keys(&[eq("host", &["h1", "h2"])]), ["a", "b"]);
+        assert_eq!(
+            keys(&[eq("host", &["h1", "h2", "h3"]), eq("dc", &[])]),
+            ["b"]
+        );
+        assert_eq!(
+            keys(&[eq("host", &["h1", "h2", "h3"]), ne("dc", &[])]),
+            ["a", "c"]
+        );
+        assert_eq!(
+            keys(&[eq("host", &["h1", "h2", "h3"]), ne("dc", &["x"])]),
+            ["b", "c"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use dashmap::mapref::entry::Entry;
+use rand::Rng;
+
+use super::{bloom::MAX_LAYER_BYTES, hyperloglog::murmurhash64a, Backend, BackendError};
+
+const TOPK_FINGERPRINT_SEED: u64 = 0x5bd1_e995;
+
+/// The parameters TOPK.RESERVE creates a Top-K with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TopKOptions {
+    pub k: u64,
+    pub width: u64,
+    pub depth: u64,
+    // how likely a colliding item wears down a counter, as `decay ^ count`
+    pub decay: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TopKSketchInfo {
+    pub k: u64,
+    pub width: u64,
+    pub depth: u64,
+    pub decay: f64,
+}
+
+/// A HeavyKeeper Top-K: a sketch of fingerprinted counters that decay under collisions,
+/// and the `k` items with the highest estimated counts.
+#[derive(Debug, Clone)]
+pub struct TopK {
+    options: TopKOptions,
+    // (fingerprint, count) for each of `width` buckets in `depth` rows
+    buckets: Vec<(u64, u64)>,
+    // at most `k` items with their counts
+    heap: Vec<(Vec<u8>, u64)>,
+}
+
+impl Default for TopKOptions {
+    fn default() -> Self {
+        TopKOptions {
+            k: 10,
+            width: 8,
+            depth: 7,
+            decay: 0.9,
+        }
+    }
+}
+
+impl TopK {
+    /// Fails if the buckets would take more than a filter layer may. The heap grows with the
+    /// items counted, so `k` is not allocated up front.
+    pub fn new(options: TopKOptions) -> Result<Self, BackendError> {
+        let buckets = options
+            .width
+            .checked_mul(options.depth)
+            .filter(|n| n.saturating_mul(size_of::<(u64, u64)>() as u64) <= MAX_LAYER_BYTES)
+            .ok_or(BackendError::SketchTooLarge)?;
+        Ok(TopK {
+            options,
+            buckets: vec![(0, 0); buckets as usize],
+            heap: Vec::new(),
+        })
+    }
+
+    /// Counts the item `incr` times, returning the item it pushed out of the top `k`.
+    pub fn incr(&mut self, item: &[u8], incr: u64) -> Option<Vec<u8>> {
+        let fp = murmurhash64a(item, TOPK_FINGERPRINT_SEED);
+        let (width, decay) = (self.options.width, self.options.decay);
+        let mut rng = rand::thread_rng();
+        let mut max = 0;
+        for row in 0..self.options.depth {
+            let col = murmurhash64a(item, row) % width;
+            let bucket = &mut self.buckets[(row * width + col) as usize];
+            if bucket.1 == 0 {
+                *bucket = (fp, incr);
+            } else if bucket.0 == fp {
+                bucket.1 = bucket.1.saturating_add(incr);
+            } else {
+                // each count may wear the other item down, and take the bucket once it is gone
+                for left in (1..=incr).rev() {
+                    if rng.gen::<f64>() < decay.powf(bucket.1 as f64) {
+                        bucket.1 -= 1;
+                        if bucket.1 == 0 {
+                            *bucket = (fp, left);
+                            break;
+                        }
+                    }
+                }
+            }
+            if bucket.0 == fp {
+                max = max.max(bucket.1);
+            }
+        }
+
+        if let Some(entry) = self.heap.iter_mut().find(|(i, _)| i == item) {
+            entry.1 = max;
+            return None;
+        }
+        if max == 0 {
+            return None;
+        }
+        if self.heap.len() < self.options.k as usize {
+            self.heap.push((item.to_vec(), max));
+            return None;
+        }
+        let (min, _) = self
+            .heap
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, count))| *count)?;
+        if self.heap[min].1 >= max {
+            return None;
+        }
+        let expelled = std::mem::replace(&mut self.heap[min], (item.to_vec(), max));
+        Some(expelled.0)
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        self.heap.iter().any(|(i, _)| i == item)
+    }
+
+    /// The top items with their counts, highest first.
+    pub fn list(&self) -> Vec<(Vec<u8>, u64)> {
+        let mut list = self.heap.clone();
+        list.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        list
+    }
+
+    pub fn info(&self) -> TopKSketchInfo {
+        TopKSketchInfo {
+            k: self.options.k,
+            width: self.options.width,
+            depth: self.options.depth,
+            decay: self.options.decay,
+        }
+    }
+
+    pub fn memory_usage(&self) -> usize {
+        size_of::<Self>()
+            + self.buckets.len() * size_of::<(u64, u64)>()
+            + self
+                .heap
+                .iter()
+                .map(|(item, _)| size_of::<(Vec<u8>, u64)>() + item.len())
+                .sum::<usize>()
+    }
+}
+
+impl Backend {
+    pub fn topk_reserve(&self, key: String, options: TopKOptions) -> Result<(), BackendError> {
+        let _guard = self.shared_lock();
+        match self.topk.entry(key) {
+            Entry::Occupied(_) => Err(BackendError::TopKKeyExists),
+            Entry::Vacant(entry) => {
+                entry.insert(TopK::new(options)?);
+                Ok(())
+            }
+        }
+    }
+
+    /// Counts each item, returning what each pushed out of the top `k`.
+    pub fn topk_incrby(
+        &self,
+        key: &str,
+        items: &[(Vec<u8>, u64)],
+    ) -> Result<Vec<Option<Vec<u8>>>, BackendError> {
+        let _guard = self.shared_lock();
+        let mut topk = self.topk.get_mut(key).ok_or(BackendError::TopKNoKey)?;
+        Ok(items
+            .iter()
+            .map(|(item, incr)| topk.incr(item, *incr))
+            .collect())
+    }
+
+    pub fn topk_query(&self, key: &str, items: &[Vec<u8>]) -> Result<Vec<bool>, BackendError> {
+        let topk = self.topk.get(key).ok_or(BackendError::TopKNoKey)?;
+        Ok(items.iter().map(|item| topk.contains(item)).collect())
+    }
+
+    pub fn topk_list(&self, key: &str) -> Result<Vec<(Vec<u8>, u64)>, BackendError> {
+        let topk = self.topk.get(key).ok_or(BackendError::TopKNoKey)?;
+        Ok(topk.list())
+    }
+
+    pub fn topk_info(&self, key: &str) -> Result<TopKSketchInfo, BackendError> {
+        let topk = self.topk.get(key).ok_or(BackendError::TopKNoKey)?;
+        Ok(topk.info())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topk_finds_heavy_hitters() {
+        let mut topk = TopK::new(TopKOptions {
+            k: 3,
+            width: 50,
+            depth: 5,
+            decay: 0.9,
+        })
+        .unwrap();
+        let mut expelled = Vec::new();
+        // a long tail of single items around three heavy ones
+        for i in 0..2000u64 {
+            for heavy in [b"a", b"b", b"c"] {
+                if i % 4 == 0 {
+                    expelled.extend(topk.incr(heavy, 1));
+                }
+            }
+            expelled.extend(topk.incr(format!("tail{}", i).as_bytes(), 1));
+        }
+        let list = topk.list();
+        let mut items = list
+            .iter()
+            .map(|(item, _)| item.clone())
+            .collect::<Vec<_>>();
+        items.sort();
+        assert_eq!(items, vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]);
+        assert!(list.iter().all(|(_, count)| *count <= 500));
+        assert!(list.windows(2).all(|w| w[0].1 >= w[1].1));
+        // the tail items that made it in early were pushed out again
+        assert!(expelled.iter().all(|item| item.starts_with(b"tail")));
+    }
+
+    #[test]
+    fn test_topk_backend() -> Result<(), BackendError> {
+        let backend = Backend::new();
+        let options = TopKOptions {
+            k: 1,
+            ..Default::default()
+        };
+        backend.topk_reserve("topk".to_string(), options)?;
+        assert_eq!(
+            backend.topk_reserve("topk".to_string(), options),
+            Err(BackendError::TopKKeyExists)
+        );
+        assert_eq!(
+            backend.topk_incrby("topk", &[(b"x".to_vec(), 1), (b"y".to_vec(), 5)])?,
+            vec![None, Some(b"x".to_vec())]
+        );
+        assert_eq!(
+            backend.topk_query("topk", &[b"x".to_vec(), b"y".to_vec()])?,
+            vec![false, true]
+        );
+        assert_eq!(backend.topk_list("topk")?, vec![(b"y".to_vec(), 5)]);
+        assert_eq!(backend.topk_list("missing"), Err(BackendError::TopKNoKey));
+        Ok(())
+    }
+}
@@ -0,0 +1,839 @@
+use std::collections::HashMap;
+
+use dashmap::mapref::one::Ref;
+use rand::seq::{IteratorRandom, SliceRandom};
+
+use super::{list::resolve_range, skiplist::SkipList, Backend, BackendError, Set, SetOp};
+
+/// A sorted set: a member to score map for lookups plus a skiplist for ordered access.
+#[derive(Debug, Clone, Default)]
+pub struct ZSet {
+    scores: HashMap<String, f64>,
+    list: SkipList,
+}
+
+/// A `min`/`max` score bound, as in ZCOUNT or ZRANGE BYSCORE.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+/// A `min`/`max` member bound for lexicographic ranges, as in ZRANGE BYLEX.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    // `-`
+    Min,
+    // `+`
+    Max,
+    // `[member`
+    Inclusive(String),
+    // `(member`
+    Exclusive(String),
+}
+
+/// What a ZRANGE style command selects, in ascending order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeBy {
+    // inclusive, possibly negative ranks; counted from the highest score if the range is `rev`
+    Rank(i64, i64),
+    Score(ScoreBound, ScoreBound),
+    Lex(LexBound, LexBound),
+}
+
+/// A ZRANGE query: the elements selected by `by`, optionally reversed, then paged by
+/// `offset` and `count` (LIMIT; a negative count means all).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZRangeSpec {
+    pub by: RangeBy,
+    pub rev: bool,
+    pub offset: i64,
+    pub count: i64,
+}
+
+/// How ZUNION and ZINTER combine the scores of a member found in several sets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregate {
+    #[default]
+    Sum,
+    Min,
+    Max,
+}
+
+/// ZADD's NX/XX and GT/LT flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddFlags {
+    // Some(true) for XX (only update), Some(false) for NX (only add)
+    pub only_if: Option<bool>,
+    pub gt: bool,
+    pub lt: bool,
+}
+
+impl ScoreBound {
+    // whether a score lies before a `min` bound
+    fn below_min(&self, score: f64) -> bool {
+        if self.exclusive {
+            score <= self.value
+        } else {
+            score < self.value
+        }
+    }
+
+    // whether a score lies within a `max` bound
+    fn within_max(&self, score: f64) -> bool {
+        if self.exclusive {
+            score < self.value
+        } else {
+            score <= self.value
+        }
+    }
+}
+
+impl LexBound {
+    fn below_min(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(m) => member < m.as_str(),
+            LexBound::Exclusive(m) => member <= m.as_str(),
+        }
+    }
+
+    fn within_max(&self, member: &str) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(m) => member <= m.as_str(),
+            LexBound::Exclusive(m) => member < m.as_str(),
+        }
+    }
+}
+
+impl Aggregate {
+    fn apply(self, a: f64, b: f64) -> f64 {
+        match self {
+            Aggregate::Sum => nan_to_zero(a + b),
+            Aggregate::Min => a.min(b),
+            Aggregate::Max => a.max(b),
+        }
+    }
+}
+
+// the members of a ZINTERCARD source, which may also be a plain set
+enum Members<'a> {
+    ZSet(Ref<'a, String, ZSet>),
+    Set(Ref<'a, String, Set>),
+}
+
+impl Members<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Members::ZSet(zset) => zset.scores.len(),
+            Members::Set(set) => set.len(),
+        }
+    }
+
+    fn contains(&self, member: &str) -> bool {
+        match self {
+            Members::ZSet(zset) => zset.scores.contains_key(member),
+            Members::Set(set) => set.contains(member),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = String> + '_> {
+        match self {
+            Members::ZSet(zset) => Box::new(zset.scores.keys().cloned()),
+            Members::Set(set) => set.iter(),
+        }
+    }
+}
+
+// inf - inf and inf * 0 count as 0, as they do in Redis
+fn nan_to_zero(score: f64) -> f64 {
+    if score.is_nan() {
+        0.0
+    } else {
+        score
+    }
+}
+
+impl ZRangeSpec {
+    pub fn new(by: RangeBy) -> Self {
+        ZRangeSpec {
+            by,
+            rev: false,
+            offset: 0,
+            count: -1,
+        }
+    }
+}
+
+impl ZAddFlags {
+    // whether `member`, currently at `current`, may be set to `score`
+    fn allows(&self, current: Option<f64>, score: f64) -> bool {
+        match (current, self.only_if) {
+            (Some(_), Some(false)) | (None, Some(true)) => false,
+            (Some(current), _) => !(self.gt && score <= current || self.lt && score >= current),
+            (None, _) => true,
+        }
+    }
+}
+
+impl ZSet {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &str) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    // adds the member or moves it to a new score; returns true if it is new
+    pub fn insert(&mut self, member: String, score: f64) -> bool {
+        // -0.0 and 0.0 are the same score
+        let score = if score == 0.0 { 0.0 } else { score };
+        match self.scores.insert(member.clone(), score) {
+            Some(old) if old == score => false,
+            Some(old) => {
+                self.list.remove(old, &member);
+                self.list.insert(score, member);
+                false
+            }
+            None => {
+                self.list.insert(score, member);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, member: &str) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.list.remove(score, member),
+            None => false,
+        }
+    }
+
+    // 0-based rank in ascending order
+    pub fn rank(&self, member: &str) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.list.rank(score, member))
+    }
+
+    pub fn count(&self, min: ScoreBound, max: ScoreBound) -> usize {
+        let start = self.list.count_while(|s, _| min.below_min(s));
+        let end = self.list.count_while(|s, _| max.within_max(s));
+        end.saturating_sub(start)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.list.iter()
+    }
+
+    // the ascending ranks selected by `by`, as a half-open range
+    fn span(&self, by: &RangeBy, rev: bool) -> (usize, usize) {
+        match by {
+            RangeBy::Rank(start, stop) => match resolve_range(*start, *stop, self.len()) {
+                Some((start, stop)) if rev => (self.len() - 1 - stop, self.len() - start),
+                Some((start, stop)) => (start, stop + 1),
+                None => (0, 0),
+            },
+            RangeBy::Score(min, max) => (
+                self.list.count_while(|s, _| min.below_min(s)),
+                self.list.count_while(|s, _| max.within_max(s)),
+            ),
+            // lexicographic ranges are only meaningful when all scores are equal
+            RangeBy::Lex(min, max) => (
+                self.list.count_while(|_, m| min.below_min(m)),
+                self.list.count_while(|_, m| max.within_max(m)),
+            ),
+        }
+    }
+
+    pub fn range(&self, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        let (start, end) = self.span(&spec.by, spec.rev);
+        let len = end.saturating_sub(start);
+        if spec.offset < 0 || spec.offset as usize >= len {
+            return vec![];
+        }
+        let offset = spec.offset as usize;
+        let take = match spec.count {
+            n if n < 0 => len - offset,
+            n => (n as usize).min(len - offset),
+        };
+        let owned = |(m, s): (&str, f64)| (m.to_string(), s);
+        if spec.rev {
+            let from = end - 1 - offset;
+            self.list
+                .rev_iter_from(from)
+                .take(take)
+                .map(owned)
+                .collect()
+        } else {
+            let from = start + offset;
+            self.list.iter_from(from).take(take).map(owned).collect()
+        }
+    }
+
+    // removes up to `count` members with the lowest scores, or the highest if `max`
+    pub fn pop(&mut self, max: bool, count: usize) -> Vec<(String, f64)> {
+        let owned = |(m, s): (&str, f64)| (m.to_string(), s);
+        let popped = match max {
+            true => self
+                .list
+                .rev_iter()
+                .take(count)
+                .map(owned)
+                .collect::<Vec<_>>(),
+            false => self.list.iter().take(count).map(owned).collect(),
+        };
+        for (member, _) in &popped {
+            self.remove(member);
+        }
+        popped
+    }
+
+    pub fn remove_range(&mut self, by: &RangeBy) -> usize {
+        let members = self.range(&ZRangeSpec::new(by.clone()));
+        for (member, _) in &members {
+            self.remove(member);
+        }
+        members.len()
+    }
+
+    // estimated bytes used by the sorted set, see `Backend::memory_usage`
+    pub fn memory_usage(&self) -> usize {
+        let members = self.scores.keys().map(|m| m.len()).sum::<usize>();
+        // one control byte per bucket on top of the entry itself
+        let map = self.scores.capacity() * (size_of::<(String, f64)>() + 1);
+        size_of::<ZSet>() + map + self.list.memory_usage() + members
+    }
+}
+
+impl Backend {
+    /// Adds or updates members according to `flags`. Returns the number of members added and
+    /// the number whose score changed (including those added).
+    pub fn zadd(
+        &self,
+        key: String,
+        members: Vec<(f64, String)>,
+        flags: ZAddFlags,
+    ) -> (usize, usize) {
+        let _guard = self.shared_lock();
+        let (mut added, mut changed) = (0, 0);
+        {
+            let mut zset = self.zset.entry(key.clone()).or_default();
+            for (score, member) in members {
+                let current = zset.score(&member);
+                if !flags.allows(current, score) || current == Some(score) {
+                    continue;
+                }
+                added += zset.insert(member, score) as usize;
+                changed += 1;
+            }
+        }
+        self.zset.remove_if(&key, |_, zset| zset.is_empty());
+        if added > 0 {
+            self.signal_ready(&key);
+        }
+        (added, changed)
+    }
+
+    // ZINCRBY and ZADD INCR; returns None if the flags prevented the update
+    pub fn zincrby(
+        &self,
+        key: String,
+        member: String,
+        increment: f64,
+        flags: ZAddFlags,
+    ) -> Result<Option<f64>, BackendError> {
+        let _guard = self.shared_lock();
+        let ret = {
+            let mut zset = self.zset.entry(key.clone()).or_default();
+            let current = zset.score(&member);
+            let score = current.unwrap_or(0.0) + increment;
+            if score.is_nan() {
+                Err(BackendError::ScoreNan)
+            } else if flags.allows(current, score) {
+                zset.insert(member, score);
+                self.signal_ready(&key);
+                Ok(Some(score))
+            } else {
+                Ok(None)
+            }
+        };
+        self.zset.remove_if(&key, |_, zset| zset.is_empty());
+        ret
+    }
+
+    pub fn zrem(&self, key: &str, members: &[String]) -> usize {
+        let _guard = self.shared_lock();
+        let removed = match self.zset.get_mut(key) {
+            Some(mut zset) => members.iter().filter(|m| zset.remove(m)).count(),
+            None => return 0,
+        };
+        self.zset.remove_if(key, |_, zset| zset.is_empty());
+        removed
+    }
+
+    pub fn zscore(&self, key: &str, member: &str) -> Option<f64> {
+        self.zset.get(key).and_then(|zset| zset.score(member))
+    }
+
+    pub fn zmscore(&self, key: &str, members: &[String]) -> Vec<Option<f64>> {
+        let zset = self.zset.get(key);
+        members
+            .iter()
+            .map(|m| zset.as_ref().and_then(|zset| zset.score(m)))
+            .collect()
+    }
+
+    pub fn zcard(&self, key: &str) -> usize {
+        self.zset.get(key).map_or(0, |zset| zset.len())
+    }
+
+    pub fn zcount(&self, key: &str, min: ScoreBound, max: ScoreBound) -> usize {
+        self.zset.get(key).map_or(0, |zset| zset.count(min, max))
+    }
+
+    pub fn zlexcount(&self, key: &str, min: &LexBound, max: &LexBound) -> usize {
+        let Some(zset) = self.zset.get(key) else {
+            return 0;
+        };
+        let (start, end) = zset.span(&RangeBy::Lex(min.clone(), max.clone()), false);
+        end.saturating_sub(start)
+    }
+
+    pub fn zrange(&self, key: &str, spec: &ZRangeSpec) -> Vec<(String, f64)> {
+        self.zset.get(key).map_or(vec![], |zset| zset.range(spec))
+    }
+
+    /// Stores the result of a ZRANGE query on `source` at `destination`, replacing it, and
+    /// returns its size. An empty result deletes `destination`.
+    pub fn zrangestore(&self, destination: &str, source: &str, spec: &ZRangeSpec) -> usize {
+        let _guard = self.exclusive_lock();
+        let members = self.zrange(source, spec);
+        let len = members.len();
+        if members.is_empty() {
+            self.zset.remove(destination);
+        } else {
+            let mut zset = ZSet::default();
+            for (member, score) in members {
+                zset.insert(member, score);
+            }
+            self.zset.insert(destination.to_string(), zset);
+            self.signal_ready(destination);
+        }
+        len
+    }
+
+    /// Computes a union, intersection or difference of sorted sets over a consistent view of
+    /// them, sorted by score. Scores are multiplied by `weights` (1 for keys without one) and
+    /// combined by `aggregate`; a difference keeps the scores of the first set.
+    pub fn zop(
+        &self,
+        op: SetOp,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Vec<(String, f64)> {
+        let _guard = self.exclusive_lock();
+        self.zop_locked(op, keys, weights, aggregate)
+    }
+
+    // stores the result of `zop` at `destination`, replacing it, and returns its size
+    pub fn zopstore(
+        &self,
+        op: SetOp,
+        destination: &str,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> usize {
+        let _guard = self.exclusive_lock();
+        let members = self.zop_locked(op, keys, weights, aggregate);
+        let len = members.len();
+        if members.is_empty() {
+            self.zset.remove(destination);
+        } else {
+            let mut zset = ZSet::default();
+            for (member, score) in members {
+                zset.insert(member, score);
+            }
+            self.zset.insert(destination.to_string(), zset);
+            self.signal_ready(destination);
+        }
+        len
+    }
+
+    // the size of the intersection, counting at most `limit` members (0 means no limit)
+    pub fn zintercard(&self, keys: &[String], limit: usize) -> usize {
+        let _guard = self.exclusive_lock();
+        let mut sets = Vec::with_capacity(keys.len());
+        for key in keys {
+            let members = match (self.zset.get(key), self.set.get(key)) {
+                (Some(zset), _) => Members::ZSet(zset),
+                (None, Some(set)) => Members::Set(set),
+                (None, None) => return 0,
+            };
+            sets.push(members);
+        }
+        // probe the other sets with the members of the smallest one, stopping at the limit
+        sets.sort_by_key(|s| s.len());
+        let Some((smallest, rest)) = sets.split_first() else {
+            return 0;
+        };
+        let members = smallest
+            .iter()
+            .filter(|m| rest.iter().all(|s| s.contains(m)));
+        match limit {
+            0 => members.count(),
+            n => members.take(n).count(),
+        }
+    }
+
+    // callers must hold the exclusive lock so no writer can interleave between the sets
+    fn zop_locked(
+        &self,
+        op: SetOp,
+        keys: &[String],
+        weights: &[f64],
+        aggregate: Aggregate,
+    ) -> Vec<(String, f64)> {
+        let weighted = |i: usize| {
+            let weight = weights.get(i).copied().unwrap_or(1.0);
+            move |(m, s): (&String, &f64)| (m.clone(), nan_to_zero(s * weight))
+        };
+        let sets = keys.iter().map(|k| self.zset_scores(k)).collect::<Vec<_>>();
+        let result = match op {
+            SetOp::Union => {
+                let mut result = HashMap::new();
+                for (i, set) in sets.iter().enumerate() {
+                    for (member, score) in set.iter().flatten().map(weighted(i)) {
+                        result
+                            .entry(member)
+                            .and_modify(|s| *s = aggregate.apply(*s, score))
+                            .or_insert(score);
+                    }
+                }
+                result
+            }
+            SetOp::Inter => {
+                let Some(sets) = sets.into_iter().collect::<Option<Vec<_>>>() else {
+                    return vec![];
+                };
+                let Some((first, rest)) = sets.split_first() else {
+                    return vec![];
+                };
+                let mut result = first.iter().map(weighted(0)).collect::<HashMap<_, _>>();
+                for (i, set) in rest.iter().enumerate() {
+                    result.retain(|member, score| match set.get(member) {
+                        Some(s) => {
+                            *score = aggregate.apply(*score, weighted(i + 1)((member, s)).1);
+                            true
+                        }
+                        None => false,
+                    });
+                }
+                result
+            }
+            SetOp::Diff => {
+                let mut sets = sets.into_iter();
+                let Some(Some(mut result)) = sets.next() else {
+                    return vec![];
+                };
+                for set in sets.flatten() {
+                    result.retain(|member, _| !set.contains_key(member));
+                }
+                result
+            }
+        };
+        let mut result = result.into_iter().collect::<Vec<_>>();
+        result.sort_by(|(m1, s1), (m2, s2)| s1.total_cmp(s2).then_with(|| m1.cmp(m2)));
+        result
+    }
+
+    // the members and scores of a sorted set; plain sets can be used as input with every
+    // score set to 1, as in Redis
+    fn zset_scores(&self, key: &str) -> Option<HashMap<String, f64>> {
+        if let Some(zset) = self.zset.get(key) {
+            return Some(zset.scores.clone());
+        }
+        let set = self.set.get(key)?;
+        Some(set.iter().map(|m| (m, 1.0)).collect())
+    }
+
+    // ZPOPMIN and ZPOPMAX
+    pub fn zpop(&self, key: &str, max: bool, count: usize) -> Vec<(String, f64)> {
+        let _guard = self.shared_lock();
+        self.zpop_locked(key, max, count).unwrap_or_default()
+    }
+
+    // pops from the first non-empty sorted set among `keys`, returning its key and the members
+    pub fn zmpop(
+        &self,
+        keys: &[String],
+        max: bool,
+        count: usize,
+    ) -> Option<(String, Vec<(String, f64)>)> {
+        let _guard = self.exclusive_lock();
+        keys.iter().find_map(|key| {
+            self.zpop_locked(key, max, count)
+                .map(|members| (key.clone(), members))
+        })
+    }
+
+    fn zpop_locked(&self, key: &str, max: bool, count: usize) -> Option<Vec<(String, f64)>> {
+        let popped = self.zset.get_mut(key)?.pop(max, count);
+        self.zset.remove_if(key, |_, zset| zset.is_empty());
+        Some(popped)
+    }
+
+    /// Random members with their scores: up to `count` distinct ones, or exactly `-count`
+    /// possibly repeated ones if `count` is negative.
+    pub fn zrandmember(&self, key: &str, count: i64) -> Vec<(String, f64)> {
+        let Some(zset) = self.zset.get(key) else {
+            return vec![];
+        };
+        let mut rng = rand::thread_rng();
+        let owned = |(m, s): (&String, &f64)| (m.clone(), *s);
+        if count >= 0 {
+            let count = (count as usize).min(zset.scores.len());
+            return zset
+                .scores
+                .iter()
+                .choose_multiple(&mut rng, count)
+                .into_iter()
+                .map(owned)
+                .collect();
+        }
+        let all = zset.scores.iter().collect::<Vec<_>>();
+        (0..count.unsigned_abs())
+            .filter_map(|_| all.choose(&mut rng).map(|&e| owned(e)))
+            .collect()
+    }
+
+    // ZREMRANGEBYRANK, ZREMRANGEBYSCORE and ZREMRANGEBYLEX
+    pub fn zremrange(&self, key: &str, by: &RangeBy) -> usize {
+        let _guard = self.shared_lock();
+        let removed = match self.zset.get_mut(key) {
+            Some(mut zset) => zset.remove_range(by),
+            None => return 0,
+        };
+        self.zset.remove_if(key, |_, zset| zset.is_empty());
+        removed
+    }
+
+    // the member's rank, counted from the highest score if `rev`, along with its score
+    pub fn zrank(&self, key: &str, member: &str, rev: bool) -> Option<(usize, f64)> {
+        let zset = self.zset.get(key)?;
+        let rank = zset.rank(member)?;
+        let rank = if rev { zset.len() - 1 - rank } else { rank };
+        Some((rank, zset.score(member)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(v: &[(f64, &str)]) -> Vec<(f64, String)> {
+        v.iter().map(|(s, m)| (*s, m.to_string())).collect()
+    }
+
+    fn scored(v: &[(&str, f64)]) -> Vec<(String, f64)> {
+        v.iter().map(|(m, s)| (m.to_string(), *s)).collect()
+    }
+
+    #[test]
+    fn test_zadd_flags() {
+        let backend = Backend::new();
+        let members = pairs(&[(1.0, "a"), (2.0, "b")]);
+        assert_eq!(
+            backend.zadd("z".to_string(), members, ZAddFlags::default()),
+            (2, 2)
+        );
+
+        let nx = ZAddFlags {
+            only_if: Some(false),
+            ..Default::default()
+        };
+        let members = pairs(&[(5.0, "a"), (3.0, "c")]);
+        assert_eq!(backend.zadd("z".to_string(), members, nx), (1, 1));
+        assert_eq!(backend.zscore("z", "a"), Some(1.0));
+
+        let xx_gt = ZAddFlags {
+            only_if: Some(true),
+            gt: true,
+            ..Default::default()
+        };
+        let members = pairs(&[(0.5, "a"), (4.0, "b"), (9.0, "d")]);
+        assert_eq!(backend.zadd("z".to_string(), members, xx_gt), (0, 1));
+        assert_eq!(
+            backend.zmscore("z", &["a".to_string(), "b".to_string(), "d".to_string()]),
+            vec![Some(1.0), Some(4.0), None]
+        );
+
+        let lt = ZAddFlags {
+            lt: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            backend.zincrby("z".to_string(), "b".to_string(), 1.0, lt),
+            Ok(None)
+        );
+        assert_eq!(
+            backend.zincrby("z".to_string(), "b".to_string(), -1.5, lt),
+            Ok(Some(2.5))
+        );
+        assert_eq!(backend.zcard("z"), 3);
+
+        // an aborted add to a missing key must not leave an empty set behind
+        let xx = ZAddFlags {
+            only_if: Some(true),
+            ..Default::default()
+        };
+        backend.zadd("empty".to_string(), pairs(&[(1.0, "a")]), xx);
+        assert!(!backend.zset.contains_key("empty"));
+    }
+
+    #[test]
+    fn test_zrank_and_zcount() {
+        let backend = Backend::new();
+        let members = pairs(&[(1.0, "a"), (2.0, "b"), (2.0, "c"), (3.0, "d")]);
+        backend.zadd("z".to_string(), members, ZAddFlags::default());
+
+        assert_eq!(backend.zrank("z", "c", false), Some((2, 2.0)));
+        assert_eq!(backend.zrank("z", "c", true), Some((1, 2.0)));
+        assert_eq!(backend.zrank("z", "x", false), None);
+
+        let bound = |value, exclusive| ScoreBound { value, exclusive };
+        assert_eq!(backend.zcount("z", bound(2.0, false), bound(3.0, false)), 3);
+        assert_eq!(backend.zcount("z", bound(2.0, true), bound(3.0, false)), 1);
+        assert_eq!(
+            backend.zcount("z", bound(f64::NEG_INFINITY, false), bound(3.0, true)),
+            3
+        );
+        assert_eq!(backend.zcount("z", bound(5.0, false), bound(1.0, false)), 0);
+
+        assert_eq!(backend.zrem("z", &["a".to_string(), "x".to_string()]), 1);
+        assert_eq!(backend.zrank("z", "d", false), Some((2, 3.0)));
+    }
+
+    #[test]
+    fn test_zrange() {
+        let backend = Backend::new();
+        let members = (0..10).map(|i| (i as f64, format!("m{}", i))).collect();
+        backend.zadd("z".to_string(), members, ZAddFlags::default());
+        let names = |spec: ZRangeSpec| {
+            backend
+                .zrange("z", &spec)
+                .into_iter()
+                .map(|(m, _)| m)
+                .collect::<Vec<_>>()
+        };
+        let bound = |value, exclusive| ScoreBound { value, exclusive };
+
+        assert_eq!(names(ZRangeSpec::new(RangeBy::Rank(-2, 100))), ["m8", "m9"]);
+        let rev = ZRangeSpec {
+            rev: true,
+            ..ZRangeSpec::new(RangeBy::Rank(0, 1))
+        };
+        assert_eq!(names(rev), ["m9", "m8"]);
+        assert!(names(ZRangeSpec::new(RangeBy::Rank(5, 2))).is_empty());
+
+        let by_score = RangeBy::Score(bound(2.0, true), bound(f64::INFINITY, false));
+        let limited = ZRangeSpec {
+            offset: 1,
+            count: 2,
+            ..ZRangeSpec::new(by_score.clone())
+        };
+        assert_eq!(names(limited), ["m4", "m5"]);
+        let rev_limited = ZRangeSpec {
+            rev: true,
+            offset: 6,
+            count: 5,
+            ..ZRangeSpec::new(by_score)
+        };
+        assert_eq!(names(rev_limited), ["m3"]);
+
+        let by_lex = RangeBy::Lex(LexBound::Exclusive("m7".to_string()), LexBound::Max);
+        assert_eq!(names(ZRangeSpec::new(by_lex)), ["m8", "m9"]);
+        let min = LexBound::Inclusive("m2".to_string());
+        assert_eq!(backend.zlexcount("z", &min, &LexBound::Max), 8);
+        assert_eq!(backend.zlexcount("z", &LexBound::Max, &LexBound::Min), 0);
+
+        let stored = backend.zrangestore("dst", "z", &ZRangeSpec::new(RangeBy::Rank(0, 2)));
+        assert_eq!(stored, 3);
+        assert_eq!(backend.zcard("dst"), 3);
+        assert_eq!(
+            backend.zrangestore("dst", "missing", &ZRangeSpec::new(RangeBy::Rank(0, -1))),
+            0
+        );
+        assert!(!backend.zset.contains_key("dst"));
+
+        let by_score = RangeBy::Score(bound(f64::NEG_INFINITY, false), bound(4.0, true));
+        assert_eq!(backend.zremrange("z", &by_score), 4);
+        assert_eq!(backend.zremrange("z", &RangeBy::Rank(0, -1)), 6);
+        assert!(!backend.zset.contains_key("z"));
+    }
+
+    #[test]
+    fn test_zop() {
+        let backend = Backend::new();
+        let members = pairs(&[(1.0, "x"), (2.0, "y")]);
+        backend.zadd("a".to_string(), members, ZAddFlags::default());
+        let members = pairs(&[(10.0, "y"), (20.0, "z")]);
+        backend.zadd("b".to_string(), members, ZAddFlags::default());
+        backend.sadd("s".to_string(), vec!["z".to_string()]);
+        let keys = |k: &[&str]| k.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+        let union = backend.zop(SetOp::Union, &keys(&["a", "b"]), &[2.0], Aggregate::Sum);
+        assert_eq!(union, scored(&[("x", 2.0), ("y", 14.0), ("z", 20.0)]));
+        let inter = backend.zop(SetOp::Inter, &keys(&["a", "b"]), &[], Aggregate::Max);
+        assert_eq!(inter, scored(&[("y", 10.0)]));
+        let inter = backend.zop(SetOp::Inter, &keys(&["b", "s"]), &[], Aggregate::Min);
+        assert_eq!(inter, scored(&[("z", 1.0)]));
+        assert!(backend
+            .zop(SetOp::Inter, &keys(&["a", "missing"]), &[], Aggregate::Sum)
+            .is_empty());
+        let diff = backend.zop(SetOp::Diff, &keys(&["b", "a"]), &[], Aggregate::Sum);
+        assert_eq!(diff, scored(&[("z", 20.0)]));
+
+        // inf * 0 counts as 0
+        let members = pairs(&[(f64::INFINITY, "x")]);
+        backend.zadd("inf".to_string(), members, ZAddFlags::default());
+        let union = backend.zop(SetOp::Union, &keys(&["inf", "a"]), &[0.0], Aggregate::Sum);
+        assert_eq!(union[0], ("x".to_string(), 1.0));
+
+        assert_eq!(backend.zintercard(&keys(&["a", "b"]), 0), 1);
+        assert_eq!(backend.zintercard(&keys(&["a", "a"]), 0), 2);
+        assert_eq!(backend.zintercard(&keys(&["a", "a"]), 1), 1);
+        assert_eq!(backend.zintercard(&keys(&["a", "missing"]), 0), 0);
+        let keys = keys(&["a", "b"]);
+        let stored = backend.zopstore(SetOp::Union, "dst", &keys, &[], Aggregate::Sum);
+        assert_eq!(stored, 3);
+        assert_eq!(backend.zscore("dst", "y"), Some(12.0));
+    }
+
+    #[test]
+    fn test_zpop_and_zrandmember() {
+        let backend = Backend::new();
+        let members = pairs(&[(1.0, "a"), (2.0, "b"), (3.0, "c")]);
+        backend.zadd("z".to_string(), members, ZAddFlags::default());
+
+        assert_eq!(backend.zpop("z", true, 1), scored(&[("c", 3.0)]));
+        assert_eq!(backend.zrandmember("z", 5).len(), 2);
+        assert_eq!(backend.zrandmember("z", -5).len(), 5);
+        let keys = ["missing".to_string(), "z".to_string()];
+        assert_eq!(
+            backend.zmpop(&keys, false, 5),
+            Some(("z".to_string(), scored(&[("a", 1.0), ("b", 2.0)])))
+        );
+        assert!(!backend.zset.contains_key("z"));
+        assert_eq!(backend.zmpop(&keys, false, 1), None);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{Backend, BulkString, KeyType, RespArray, RespFrame, RespNull};
+
+use super::{
+    extract_args, extract_i64, extract_string, validate_command, CmpType, CommandError,
+    CommandExecutor, RESP_WRONGTYPE,
+};
+
+// a string value can be at most 512MB, so bit offsets must fit in 32 bits
+const MAX_BIT_OFFSET: u64 = 512 * 1024 * 1024 * 8;
+
+#[derive(Debug)]
+pub struct BitField {
+    key: String,
+    ops: Vec<BitFieldOp>,
+}
+
+#[derive(Debug)]
+pub struct BitFieldRo {
+    key: String,
+    ops: Vec<BitFieldOp>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BitFieldType {
+    signed: bool,
+    bits: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Overflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+#[derive(Debug, PartialEq)]
+enum BitFieldOp {
+    Get(BitFieldType, u64),
+    Set(BitFieldType, u64, i64),
+    IncrBy(BitFieldType, u64, i64),
+    Overflow(Overflow),
+}
+
+impl CommandExecutor for BitField {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if !self.ops.iter().any(BitFieldOp::is_write) {
+            return read_bitfield(backend, &self.key, &self.ops);
+        }
+        if backend.holds_other_type(&self.key, KeyType::String) {
+            return RESP_WRONGTYPE.clone();
+        }
+
+        let mut entry = backend
+            .map
+            .entry(self.key)
+            .or_insert_with(|| BulkString::new(vec![]).into());
+        match entry.value_mut() {
+            RespFrame::BulkString(s) => RespArray::new(run_ops(&mut s.0, &self.ops)).into(),
+            _ => RESP_WRONGTYPE.clone(),
+        }
+    }
+}
+
+impl CommandExecutor for BitFieldRo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        read_bitfield(backend, &self.key, &self.ops)
+    }
+}
+
+fn read_bitfield(backend: &Backend, key: &str, ops: &[BitFieldOp]) -> RespFrame {
+    let mut buf = match backend.map.get(key) {
+        Some(v) => match v.value() {
+            RespFrame::BulkString(s) => s.0.clone(),
+            _ => return RESP_WRONGTYPE.clone(),
+        },
+        None => vec![],
+    };
+    RespArray::new(run_ops(&mut buf, ops)).into()
+}
+
+fn run_ops(buf: &mut Vec<u8>, ops: &[BitFieldOp]) -> Vec<RespFrame> {
+    let mut overflow = Overflow::Wrap;
+    let mut ret = Vec::with_capacity(ops.len());
+    for op in ops {
+        match *op {
+            BitFieldOp::Get(ty, offset) => ret.push(ty.read(buf, offset).into()),
+            BitFieldOp::Set(ty, offset, value) => {
+                let old = ty.read(buf, offset);
+                match ty.check_overflow(value as i128, overflow) {
+                    Some(v) => {
+                        ty.write(buf, offset, v);
+                        ret.push(old.into());
+                    }
+                    None => ret.push(RespFrame::Null(RespNull)),
+                }
+            }
+            BitFieldOp::IncrBy(ty, offset, incr) => {
+                let old = ty.read(buf, offset);
+                match ty.check_overflow(old as i128 + incr as i128, overflow) {
+                    Some(v) => {
+                        ty.write(buf, offset, v);
+                        ret.push(v.into());
+                    }
+                    None => ret.push(RespFrame::Null(RespNull)),
+                }
+            }
+            BitFieldOp::Overflow(o) => overflow = o,
+        }
+    }
+    ret
+}
+
+impl BitFieldOp {
+    fn is_write(&self) -> bool {
+        matches!(self, BitFieldOp::Set(..) | BitFieldOp::IncrBy(..))
+    }
+}
+
+impl BitFieldType {
+    fn range(&self) -> (i128, i128) {
+        if self.signed {
+            (-(1i128 << (self.bits - 1)), (1i128 << (self.bits - 1)) - 1)
+        } else {
+            (0, (1i128 << self.bits) - 1)
+        }
+    }
+
+    // returns the value to store, or None if the operation must fail
+    fn check_overflow(&self, value: i128, overflow: Overflow) -> Option<i64> {
+        let (min, max) = self.range();
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+        match overflow {
+            Overflow::Wrap => Some(self.wrap(value as u128 as u64)),
+            Overflow::Sat if value > max => Some(max as i64),
+            Overflow::Sat => Some(min as i64),
+            Overflow::Fail => None,
+        }
+    }
+
+    // keep the low `bits` bits of raw, sign-extending them for signed types
+    fn wrap(&self, raw: u64) -> i64 {
+        let shift = 64 - self.bits;
+        if self.signed {
+            ((raw << shift) as i64) >> shift
+        } else {
+            ((raw << shift) >> shift) as i64
+        }
+    }
+
+    fn read(&self, buf: &[u8], offset: u64) -> i64 {
+        let mut raw = 0u64;
+        for pos in offset..offset + self.bits as u64 {
+            let bit = buf
+                .get((pos >> 3) as usize)
+                .map_or(0, |b| (b >> (7 - (pos & 7))) & 1);
+            raw = (raw << 1) | bit as u64;
+        }
+        self.wrap(raw)
+    }
+
+    fn write(&self, buf: &mut Vec<u8>, offset: u64, value: i64) {
+        let end = ((offset + self.bits as u64 + 7) >> 3) as usize;
+        if buf.len() < end {
+            buf.resize(end, 0);
+        }
+        for i in 0..self.bits as u64 {
+            let bit = ((value as u64) >> (self.bits as u64 - 1 - i)) & 1;
+            let pos = offset + i;
+            let mask = 1u8 << (7 - (pos & 7));
+            let byte = &mut buf[(pos >> 3) as usize];
+            if bit == 1 {
+                *byte |= mask;
+            } else {
+                *byte &= !mask;
+            }
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BitField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitfield"], 1, CmpType::LEAST)?;
+
+        let (key, ops) = parse_bitfield(value)?;
+        Ok(BitField { key, ops })
+    }
+}
+
+impl TryFrom<RespArray> for BitFieldRo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bitfield_ro"], 1, CmpType::LEAST)?;
+
+        let (key, ops) = parse_bitfield(value)?;
+        if ops.iter().any(|op| !matches!(op, BitFieldOp::Get(..))) {
+            return Err(CommandError::InvalidArgument(
+                "BITFIELD_RO only supports the GET subcommand".to_string(),
+            ));
+        }
+        Ok(BitFieldRo { key, ops })
+    }
+}
+
+fn parse_bitfield(value: RespArray) -> Result<(String, Vec<BitFieldOp>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+
+    let mut ops = Vec::new();
+    while let Some(arg) = args.next() {
+        let sub = extract_string(Some(arg))?.to_ascii_lowercase();
+        let op = match sub.as_str() {
+            "get" => {
+                let ty = parse_type(args.next())?;
+                BitFieldOp::Get(ty, parse_offset(args.next(), ty)?)
+            }
+            "set" => {
+                let ty = parse_type(args.next())?;
+                let offset = parse_offset(args.next(), ty)?;
+                BitFieldOp::Set(ty, offset, extract_i64(args.next())?)
+            }
+            "incrby" => {
+                let ty = parse_type(args.next())?;
+                let offset = parse_offset(args.next(), ty)?;
+                BitFieldOp::IncrBy(ty, offset, extract_i64(args.next())?)
+            }
+            "overflow" => {
+                let overflow = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                    "wrap" => Overflow::Wrap,
+                    "sat" => Overflow::Sat,
+                    "fail" => Overflow::Fail,
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "Invalid OVERFLOW type specified".to_string(),
+                        ))
+                    }
+                };
+                BitFieldOp::Overflow(overflow)
+            }
+            _ => {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Invalid BITFIELD subcommand: {}",
+                    sub
+                )))
+            }
+        };
+        ops.push(op);
+    }
+    Ok((key, ops))
+}
+
+fn parse_type(arg: Option<RespFrame>) -> Result<BitFieldType, CommandError> {
+    let err = || {
+        CommandError::InvalidArgument(
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string(),
+        )
+    };
+    let s = extract_string(arg)?.to_ascii_lowercase();
+    let (signed, bits) = match s.split_at_checked(1) {
+        Some(("i", bits)) => (true, bits),
+        Some(("u", bits)) => (false, bits),
+        _ => return Err(err()),
+    };
+    let bits: u32 = bits.parse().map_err(|_| err())?;
+    let max = if signed { 64 } else { 63 };
+    if bits == 0 || bits > max {
+        return Err(err());
+    }
+    Ok(BitFieldType { signed, bits })
+}
+
+// offsets prefixed with `#` are multiplied by the type width
+fn parse_offset(arg: Option<RespFrame>, ty: BitFieldType) -> Result<u64, CommandError> {
+    let err =
+        || CommandError::InvalidArgument("bit offset is not an integer or out of range".into());
+    let s = extract_string(arg)?;
+    let offset = match s.strip_prefix('#') {
+        Some(n) => n
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(ty.bits as u64)),
+        None => s.parse::<u64>().ok(),
+    }
+    .ok_or_else(err)?;
+    match offset.checked_add(ty.bits as u64) {
+        Some(end) if end <= MAX_BIT_OFFSET => Ok(offset),
+        _ => Err(err()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    #[test]
+    fn test_bitfield_from_resp_array() -> Result<()> {
+        let result: BitField = args(&[
+            "bitfield", "mykey", "INCRBY", "i5", "100", "1", "OVERFLOW", "SAT", "GET", "u4", "#2",
+        ])
+        .try_into()?;
+        assert_eq!(result.key, "mykey");
+        assert_eq!(
+            result.ops,
+            vec![
+                BitFieldOp::IncrBy(
+                    BitFieldType {
+                        signed: true,
+                        bits: 5
+                    },
+                    100,
+                    1
+                ),
+                BitFieldOp::Overflow(Overflow::Sat),
+                BitFieldOp::Get(
+                    BitFieldType {
+                        signed: false,
+                        bits: 4
+                    },
+                    8
+                ),
+            ]
+        );
+
+        let result: Result<BitField, _> =
+            args(&["bitfield", "mykey", "GET", "u64", "0"]).try_into();
+        assert!(result.is_err());
+
+        let result: Result<BitField, _> = args(&[
+            "bitfield",
+            "mykey",
+            "SET",
+            "u8",
+            "18446744073709551615",
+            "1",
+        ])
+        .try_into();
+        assert!(result.is_err());
+
+        let result: Result<BitFieldRo, _> =
+            args(&["bitfield_ro", "mykey", "SET", "u8", "0", "1"]).try_into();
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_set_get() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: BitField = args(&[
+            "bitfield", "mykey", "SET", "u8", "0", "255", "GET", "u8", "0", "GET", "i4", "0",
+        ])
+        .try_into()?;
+        let result = cmd.execute(&backend);
+        assert_eq!(
+            result,
+            RespArray::new([0.into(), 255.into(), (-1).into()]).into()
+        );
+        assert_eq!(
+            backend.get("mykey"),
+            Some(BulkString::new(vec![0xff]).into())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_overflow() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: BitField = args(&[
+            "bitfield", "mykey", "INCRBY", "u2", "100", "1", "OVERFLOW", "SAT", "INCRBY", "u2",
+            "102", "1",
+        ])
+        .try_into()?;
+        let result = cmd.execute(&backend);
+        assert_eq!(result, RespArray::new([1.into(), 1.into()]).into());
+
+        let incr = |overflow: &str| -> Result<RespFrame> {
+            let cmd: BitField = args(&[
+                "bitfield", "mykey", "OVERFLOW", overflow, "INCRBY", "u2", "100", "2",
+            ])
+            .try_into()?;
+            Ok(cmd.execute(&backend))
+        };
+        assert_eq!(incr("WRAP")?, RespArray::new([3.into()]).into());
+        assert_eq!(incr("WRAP")?, RespArray::new([1.into()]).into());
+        assert_eq!(incr("SAT")?, RespArray::new([3.into()]).into());
+        assert_eq!(
+            incr("FAIL")?,
+            RespArray::new([RespFrame::Null(RespNull)]).into()
+        );
+
+        let cmd: BitField = args(&[
+            "bitfield", "mykey", "OVERFLOW", "SAT", "INCRBY", "i8", "0", "-200",
+        ])
+        .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([(-128).into()]).into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitfield_ro_missing_key() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: BitFieldRo = args(&["bitfield_ro", "mykey", "GET", "i64", "0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespArray::new([0.into()]).into());
+        assert_eq!(backend.get("mykey"), None);
+        Ok(())
+    }
+}
@@ -0,0 +1,538 @@
+use crate::{
+    Backend, BloomOptions, BulkString, KeyType, RespArray, RespFrame, RespMap, RespNull,
+    SimpleError,
+};
+
+use super::{
+    command_name, extract_args, extract_bytes, extract_f64, extract_i64, extract_string,
+    validate_command, CmpType, CommandError, CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct BfReserve {
+    key: String,
+    options: BloomOptions,
+}
+
+// BF.ADD and BF.MADD, which only differ in the reply
+#[derive(Debug)]
+pub struct BfAdd {
+    key: String,
+    items: Vec<Vec<u8>>,
+    multi: bool,
+}
+
+#[derive(Debug)]
+pub struct BfInsert {
+    key: String,
+    items: Vec<Vec<u8>>,
+    // None for NOCREATE
+    options: Option<BloomOptions>,
+}
+
+// BF.EXISTS and BF.MEXISTS
+#[derive(Debug)]
+pub struct BfExists {
+    key: String,
+    items: Vec<Vec<u8>>,
+    multi: bool,
+}
+
+#[derive(Debug)]
+pub struct BfCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct BfInfo {
+    key: String,
+    field: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct BfScanDump {
+    key: String,
+    iter: u64,
+}
+
+#[derive(Debug)]
+pub struct BfLoadChunk {
+    key: String,
+    iter: u64,
+    data: Vec<u8>,
+}
+
+impl CommandExecutor for BfReserve {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Bloom) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.bf_reserve(self.key, self.options) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Bloom) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let added = match backend.bf_insert(self.key, &self.items, Some(BloomOptions::default())) {
+            Ok(added) => added,
+            Err(e) => return e.into(),
+        };
+        let mut ret = added.into_iter().map(|added| match added {
+            Ok(added) => RespFrame::Integer(added as i64),
+            Err(e) => e.into(),
+        });
+        match self.multi {
+            true => RespArray::new(ret.collect::<Vec<_>>()).into(),
+            false => ret.next().unwrap_or(RespFrame::Integer(0)),
+        }
+    }
+}
+
+impl CommandExecutor for BfInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Bloom) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.bf_insert(self.key, &self.items, self.options) {
+            Ok(added) => RespArray::new(
+                added
+                    .into_iter()
+                    .map(|added| match added {
+                        Ok(added) => RespFrame::Integer(added as i64),
+                        Err(e) => e.into(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BfExists {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let mut ret = backend
+            .bf_exists(&self.key, &self.items)
+            .into_iter()
+            .map(|found| RespFrame::Integer(found as i64));
+        match self.multi {
+            true => RespArray::new(ret.collect::<Vec<_>>()).into(),
+            false => ret.next().unwrap_or(RespFrame::Integer(0)),
+        }
+    }
+}
+
+impl CommandExecutor for BfCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.bf_card(&self.key) as i64)
+    }
+}
+
+impl CommandExecutor for BfInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.bf_info(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        // a nonscaling filter has no expansion rate
+        let expansion = match info.expansion {
+            0 => RespFrame::Null(RespNull),
+            e => RespFrame::Integer(e as i64),
+        };
+        let fields = [
+            (
+                "capacity",
+                "Capacity",
+                RespFrame::Integer(info.capacity as i64),
+            ),
+            ("size", "Size", RespFrame::Integer(info.size as i64)),
+            (
+                "filters",
+                "Number of filters",
+                RespFrame::Integer(info.filters as i64),
+            ),
+            (
+                "items",
+                "Number of items inserted",
+                RespFrame::Integer(info.items as i64),
+            ),
+            ("expansion", "Expansion rate", expansion),
+        ];
+        match self.field {
+            Some(field) => match fields.into_iter().find(|(name, _, _)| *name == field) {
+                Some((_, _, value)) => RespArray::new([value]).into(),
+                None => SimpleError::new("ERR Invalid information value").into(),
+            },
+            None => {
+                let mut map = RespMap::new();
+                for (_, name, value) in fields {
+                    map.insert(name.to_string(), value);
+                }
+                map.into()
+            }
+        }
+    }
+}
+
+impl CommandExecutor for BfScanDump {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.bf_scandump(&self.key, self.iter) {
+            Ok((iter, data)) => RespArray::new([
+                RespFrame::Integer(iter as i64),
+                BulkString::new(data).into(),
+            ])
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for BfLoadChunk {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Bloom) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.bf_loadchunk(self.key, self.iter, &self.data) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for BfReserve {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.reserve"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut options = BloomOptions {
+            error_rate: extract_error_rate(args.next())?,
+            capacity: extract_capacity(args.next())?,
+            ..Default::default()
+        };
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "expansion" => options.expansion = extract_expansion(args.next())?,
+                "nonscaling" => options.expansion = 0,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(BfReserve { key, options })
+    }
+}
+
+impl TryFrom<RespArray> for BfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, multi) = match command_name(&value).as_str() {
+            "bf.add" => ("bf.add", false),
+            "bf.madd" => ("bf.madd", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        match multi {
+            true => validate_command(&value, &[name], 2, CmpType::LEAST)?,
+            false => validate_command(&value, &[name], 2, CmpType::EQ)?,
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BfAdd { key, items, multi })
+    }
+}
+
+impl TryFrom<RespArray> for BfInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.insert"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (mut options, mut nocreate) = (BloomOptions::default(), false);
+        loop {
+            match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                "capacity" => options.capacity = extract_capacity(args.next())?,
+                "error" => options.error_rate = extract_error_rate(args.next())?,
+                "expansion" => options.expansion = extract_expansion(args.next())?,
+                "nocreate" => nocreate = true,
+                "nonscaling" => options.expansion = 0,
+                "items" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if items.is_empty() {
+            return Err(syntax_error());
+        }
+        Ok(BfInsert {
+            key,
+            items,
+            options: (!nocreate).then_some(options),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BfExists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, multi) = match command_name(&value).as_str() {
+            "bf.exists" => ("bf.exists", false),
+            "bf.mexists" => ("bf.mexists", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        match multi {
+            true => validate_command(&value, &[name], 2, CmpType::LEAST)?,
+            false => validate_command(&value, &[name], 2, CmpType::EQ)?,
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BfExists { key, items, multi })
+    }
+}
+
+impl TryFrom<RespArray> for BfCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.card"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(BfCard { key })
+    }
+}
+
+impl TryFrom<RespArray> for BfInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.info"], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(syntax_error());
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let field = match args.next() {
+            Some(field) => Some(extract_string(Some(field))?.to_ascii_lowercase()),
+            None => None,
+        };
+        Ok(BfInfo { key, field })
+    }
+}
+
+impl TryFrom<RespArray> for BfScanDump {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.scandump"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let iter = extract_iter(args.next())?;
+        Ok(BfScanDump { key, iter })
+    }
+}
+
+impl TryFrom<RespArray> for BfLoadChunk {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bf.loadchunk"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let iter = extract_iter(args.next())?;
+        let data = extract_bytes(args.next())?;
+        Ok(BfLoadChunk { key, iter, data })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+fn extract_error_rate(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    match extract_f64(arg)? {
+        rate if rate > 0.0 && rate < 1.0 => Ok(rate),
+        _ => Err(CommandError::InvalidArgument(
+            "(0 < error rate range < 1)".to_string(),
+        )),
+    }
+}
+
+fn extract_capacity(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_i64(arg)? {
+        capacity if capacity > 0 => Ok(capacity as u64),
+        _ => Err(CommandError::InvalidArgument(
+            "(capacity should be larger than 0)".to_string(),
+        )),
+    }
+}
+
+// capped as CF.RESERVE caps it; the layers it grows are bounded in the backend
+fn extract_expansion(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_i64(arg)? {
+        expansion if (1..=32768).contains(&expansion) => Ok(expansion as u64),
+        _ => Err(CommandError::InvalidArgument(
+            "expansion should be in the range 1..32768".to_string(),
+        )),
+    }
+}
+
+// the cursor of SCANDUMP and LOADCHUNK
+fn extract_iter(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_i64(arg)? {
+        iter if iter >= 0 => Ok(iter as u64),
+        _ => Err(CommandError::InvalidArgument(
+            "invalid iterator".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    #[test]
+    fn test_bloom_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: BfReserve = args(&["bf.reserve", "bf", "0.001", "10", "nonscaling"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: BfReserve = args(&["bf.reserve", "bf", "0.01", "10"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::from(SimpleError::new("ERR item exists"))
+        );
+
+        let cmd: BfAdd = args(&["bf.add", "bf", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: BfAdd = args(&["bf.madd", "bf", "a", "b"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(0), RespFrame::Integer(1)]).into()
+        );
+        let cmd: BfExists = args(&["bf.mexists", "bf", "a", "b"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(1), RespFrame::Integer(1)]).into()
+        );
+        let cmd: BfCard = args(&["bf.card", "bf"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        backend.cms_init("sketch".to_string(), 10, 2)?;
+        let cmd: BfAdd = args(&["bf.add", "sketch", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        assert!(!backend.bloom.contains_key("sketch"));
+        let cmd: BfInfo = args(&["bf.info", "bf", "capacity"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(10)]).into()
+        );
+
+        let cmd: BfInsert = args(&["bf.insert", "new", "nocreate", "items", "a"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::from(SimpleError::new("ERR not found"))
+        );
+        let cmd: BfInsert = args(&[
+            "bf.insert",
+            "new",
+            "capacity",
+            "1",
+            "nonscaling",
+            "items",
+            "a",
+            "b",
+        ])
+        .try_into()?;
+        let RespFrame::Array(added) = cmd.execute(&backend) else {
+            panic!("BF.INSERT should reply with an array");
+        };
+        assert_eq!(added.first(), Some(&RespFrame::Integer(1)));
+        assert_eq!(
+            added.get(1),
+            Some(&RespFrame::from(SimpleError::new(
+                "ERR non scaling filter is full"
+            )))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bloom_size_limits() -> Result<()> {
+        let backend = Backend::new();
+        let too_large = RespFrame::from(SimpleError::new("ERR filter would be too large"));
+        let cmd: BfReserve =
+            args(&["bf.reserve", "k", "0.01", "9223372036854775807"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), too_large);
+        let cmd: BfInsert =
+            args(&["bf.insert", "k", "capacity", "1000000000", "items", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), too_large);
+        assert!(!backend.bloom.contains_key("k"));
+
+        let expansion = [
+            "bf.reserve",
+            "k",
+            "0.5",
+            "1",
+            "expansion",
+            "9223372036854775807",
+        ];
+        assert!(BfReserve::try_from(args(&expansion)).is_err());
+        assert!(
+            BfReserve::try_from(args(&["bf.reserve", "k", "0.5", "1", "expansion", "0"])).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bloom_scandump_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: BfAdd = args(&["bf.madd", "bf", "x", "y"]).try_into()?;
+        cmd.execute(&backend);
+
+        let mut iter = 0;
+        loop {
+            let cmd: BfScanDump = args(&["bf.scandump", "bf", &iter.to_string()]).try_into()?;
+            let RespFrame::Array(reply) = cmd.execute(&backend) else {
+                panic!("BF.SCANDUMP should reply with an array");
+            };
+            let (Some(RespFrame::Integer(next)), Some(RespFrame::BulkString(data))) =
+                (reply.first(), reply.get(1))
+            else {
+                panic!("BF.SCANDUMP should reply with an iterator and data");
+            };
+            if *next == 0 {
+                break;
+            }
+            let cmd: BfLoadChunk = RespArray::new([
+                BulkString::from("bf.loadchunk").into(),
+                BulkString::from("copy").into(),
+                BulkString::from(next.to_string()).into(),
+                data.clone().into(),
+            ])
+            .try_into()?;
+            assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+            iter = *next;
+        }
+        let cmd: BfExists = args(&["bf.exists", "copy", "y"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert!(BfReserve::try_from(args(&["bf.reserve", "x", "1.5", "10"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{Backend, CountMinSketch, KeyType, RespArray, RespFrame, RespMap};
+
+use super::{
+    command_name, extract_args, extract_bytes, extract_f64, extract_i64, extract_numkeys,
+    extract_string, validate_command, CmpType, CommandError, CommandExecutor, RESP_OK,
+    RESP_WRONGTYPE,
+};
+
+// CMS.INITBYDIM and CMS.INITBYPROB, which only differ in how the dimensions are given
+#[derive(Debug)]
+pub struct CmsInit {
+    key: String,
+    width: u64,
+    depth: u64,
+}
+
+#[derive(Debug)]
+pub struct CmsIncrBy {
+    key: String,
+    items: Vec<(Vec<u8>, u64)>,
+}
+
+#[derive(Debug)]
+pub struct CmsQuery {
+    key: String,
+    items: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct CmsMerge {
+    dest: String,
+    sources: Vec<(String, i64)>,
+}
+
+#[derive(Debug)]
+pub struct CmsInfo {
+    key: String,
+}
+
+impl CommandExecutor for CmsInit {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Cms) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.cms_init(self.key, self.width, self.depth) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CmsIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.cms_incrby(&self.key, &self.items) {
+            Ok(counts) => counts_reply(counts),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CmsQuery {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.cms_query(&self.key, &self.items) {
+            Ok(counts) => counts_reply(counts),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CmsMerge {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.dest, KeyType::Cms) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.cms_merge(&self.dest, &self.sources) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CmsInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.cms_info(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let mut map = RespMap::new();
+        map.insert("width".to_string(), RespFrame::Integer(info.width as i64));
+        map.insert("depth".to_string(), RespFrame::Integer(info.depth as i64));
+        map.insert("count".to_string(), RespFrame::Integer(info.count as i64));
+        map.into()
+    }
+}
+
+fn counts_reply(counts: Vec<u64>) -> RespFrame {
+    RespArray::new(
+        counts
+            .into_iter()
+            .map(|count| RespFrame::Integer(count as i64))
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
+impl TryFrom<RespArray> for CmsInit {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, by_prob) = match command_name(&value).as_str() {
+            "cms.initbydim" => ("cms.initbydim", false),
+            "cms.initbyprob" => ("cms.initbyprob", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (width, depth) = match by_prob {
+            true => {
+                let error = extract_probability(args.next(), "invalid overestimation value")?;
+                let probability = extract_probability(args.next(), "invalid prob value")?;
+                CountMinSketch::dimensions(error, probability)
+            }
+            false => (
+                extract_positive(args.next(), "invalid width")?,
+                extract_positive(args.next(), "invalid depth")?,
+            ),
+        };
+        Ok(CmsInit { key, width, depth })
+    }
+}
+
+impl TryFrom<RespArray> for CmsIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.incrby"], 3, CmpType::LEAST)?;
+        if !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'cms.incrby' command".to_string(),
+            ));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut items = Vec::new();
+        while let Some(item) = args.next() {
+            let item = extract_bytes(Some(item))?;
+            let incr = match extract_i64(args.next()) {
+                Ok(incr) if incr >= 0 => incr as u64,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "CMS: Cannot parse number".to_string(),
+                    ))
+                }
+            };
+            items.push((item, incr));
+        }
+        Ok(CmsIncrBy { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for CmsQuery {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.query"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CmsQuery { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for CmsMerge {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.merge"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dest = extract_string(args.next())?;
+        let keys = extract_numkeys(&mut args)?;
+        let weights = match args.next() {
+            None => vec![1; keys.len()],
+            Some(opt) => {
+                if !extract_string(Some(opt))?.eq_ignore_ascii_case("weights") {
+                    return Err(syntax_error());
+                }
+                let weights = args
+                    .map(|arg| extract_i64(Some(arg)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if weights.len() != keys.len() {
+                    return Err(syntax_error());
+                }
+                weights
+            }
+        };
+        Ok(CmsMerge {
+            dest,
+            sources: keys.into_iter().zip(weights).collect(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for CmsInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cms.info"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(CmsInfo { key })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+fn extract_positive(arg: Option<RespFrame>, msg: &str) -> Result<u64, CommandError> {
+    match extract_i64(arg) {
+        Ok(v) if v > 0 => Ok(v as u64),
+        _ => Err(CommandError::InvalidArgument(format!("CMS: {}", msg))),
+    }
+}
+
+fn extract_probability(arg: Option<RespFrame>, msg: &str) -> Result<f64, CommandError> {
+    match extract_f64(arg) {
+        Ok(v) if v > 0.0 && v < 1.0 => Ok(v),
+        _ => Err(CommandError::InvalidArgument(format!("CMS: {}", msg))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+    use crate::SimpleError;
+
+    use super::*;
+
+    fn counts(counts: &[i64]) -> RespFrame {
+        RespArray::new(
+            counts
+                .iter()
+                .map(|c| RespFrame::Integer(*c))
+                .collect::<Vec<_>>(),
+        )
+        .into()
+    }
+
+    #[test]
+    fn test_cms_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: CmsInit = args(&["cms.initbydim", "a", "100", "5"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: CmsInit = args(&["cms.initbyprob", "b", "0.01", "0.01"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: CmsInfo = args(&["cms.info", "b"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend) else {
+            panic!("CMS.INFO should reply with a map");
+        };
+        assert_eq!(info.get("width"), Some(&RespFrame::Integer(200)));
+        assert_eq!(info.get("depth"), Some(&RespFrame::Integer(7)));
+
+        let cmd: CmsIncrBy = args(&["cms.incrby", "a", "x", "3", "y", "1", "x", "2"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), counts(&[3, 1, 5]));
+        let cmd: CmsQuery = args(&["cms.query", "a", "x", "y", "z"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), counts(&[5, 1, 0]));
+
+        assert!(CmsIncrBy::try_from(args(&["cms.incrby", "a", "x", "-1"])).is_err());
+        assert!(CmsInit::try_from(args(&["cms.initbyprob", "c", "1", "0.5"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cms_size_limits() -> Result<()> {
+        let backend = Backend::new();
+        let too_large = RespFrame::from(SimpleError::new("ERR sketch would be too large"));
+        let cmd: CmsInit = args(&["cms.initbydim", "c", "4294967296", "4294967296"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), too_large);
+        let cmd: CmsInit =
+            args(&["cms.initbyprob", "c", "0.0000000000000000001", "0.01"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), too_large);
+        assert!(!backend.cms.contains_key("c"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cms_merge_command() -> Result<()> {
+        let backend = Backend::new();
+        for key in ["a", "b", "dest"] {
+            let cmd: CmsInit = args(&["cms.initbydim", key, "50", "4"]).try_into()?;
+            cmd.execute(&backend);
+        }
+        let cmd: CmsIncrBy = args(&["cms.incrby", "a", "x", "4"]).try_into()?;
+        cmd.execute(&backend);
+        let cmd: CmsIncrBy = args(&["cms.incrby", "b", "x", "1"]).try_into()?;
+        cmd.execute(&backend);
+
+        let cmd: CmsMerge =
+            args(&["cms.merge", "dest", "2", "a", "b", "weights", "1", "3"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: CmsQuery = args(&["cms.query", "dest", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), counts(&[7]));
+
+        let cmd: CmsMerge = args(&["cms.merge", "missing", "1", "a"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        assert!(
+            CmsMerge::try_from(args(&["cms.merge", "dest", "2", "a", "b", "weights", "1"]))
+                .is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,517 @@
+use crate::{Backend, BulkString, CuckooOptions, KeyType, RespArray, RespFrame, RespMap};
+
+use super::{
+    command_name, extract_args, extract_bytes, extract_i64, extract_string, validate_command,
+    CmpType, CommandError, CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct CfReserve {
+    key: String,
+    options: CuckooOptions,
+}
+
+// CF.ADD and CF.ADDNX
+#[derive(Debug)]
+pub struct CfAdd {
+    key: String,
+    item: Vec<u8>,
+    nx: bool,
+}
+
+// CF.INSERT and CF.INSERTNX
+#[derive(Debug)]
+pub struct CfInsert {
+    key: String,
+    items: Vec<Vec<u8>>,
+    nx: bool,
+    // None for NOCREATE
+    options: Option<CuckooOptions>,
+}
+
+// CF.EXISTS and CF.MEXISTS
+#[derive(Debug)]
+pub struct CfExists {
+    key: String,
+    items: Vec<Vec<u8>>,
+    multi: bool,
+}
+
+#[derive(Debug)]
+pub struct CfDel {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct CfCount {
+    key: String,
+    item: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct CfInfo {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct CfScanDump {
+    key: String,
+    iter: u64,
+}
+
+#[derive(Debug)]
+pub struct CfLoadChunk {
+    key: String,
+    iter: u64,
+    data: Vec<u8>,
+}
+
+impl CommandExecutor for CfReserve {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Cuckoo) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.cf_reserve(self.key, self.options) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Cuckoo) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let items = [self.item];
+        let options = Some(CuckooOptions::default());
+        match backend.cf_insert(self.key, &items, self.nx, options) {
+            Ok(added) => match added.into_iter().next() {
+                Some(Ok(added)) => RespFrame::Integer(added as i64),
+                Some(Err(e)) => e.into(),
+                None => RespFrame::Integer(0),
+            },
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CfInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Cuckoo) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.cf_insert(self.key, &self.items, self.nx, self.options) {
+            // -1 for an item that did not fit
+            Ok(added) => RespArray::new(
+                added
+                    .into_iter()
+                    .map(|added| RespFrame::Integer(added.map_or(-1, |added| added as i64)))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CfExists {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let mut ret = backend
+            .cf_exists(&self.key, &self.items)
+            .into_iter()
+            .map(|found| RespFrame::Integer(found as i64));
+        match self.multi {
+            true => RespArray::new(ret.collect::<Vec<_>>()).into(),
+            false => ret.next().unwrap_or(RespFrame::Integer(0)),
+        }
+    }
+}
+
+impl CommandExecutor for CfDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.cf_del(&self.key, &self.item) {
+            Ok(deleted) => RespFrame::Integer(deleted as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CfCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.cf_count(&self.key, &self.item) as i64)
+    }
+}
+
+impl CommandExecutor for CfInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.cf_info(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let fields = [
+            ("Size", info.size as i64),
+            ("Number of buckets", info.buckets as i64),
+            ("Number of filters", info.filters as i64),
+            ("Number of items inserted", info.inserted as i64),
+            ("Number of items deleted", info.deleted as i64),
+            ("Bucket size", info.bucket_size as i64),
+            ("Expansion rate", info.expansion as i64),
+            ("Max iterations", info.max_iterations as i64),
+        ];
+        let mut map = RespMap::new();
+        for (name, value) in fields {
+            map.insert(name.to_string(), RespFrame::Integer(value));
+        }
+        map.into()
+    }
+}
+
+impl CommandExecutor for CfScanDump {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.cf_scandump(&self.key, self.iter) {
+            Ok((iter, data)) => RespArray::new([
+                RespFrame::Integer(iter as i64),
+                BulkString::new(data).into(),
+            ])
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for CfLoadChunk {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Cuckoo) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.cf_loadchunk(self.key, self.iter, &self.data) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for CfReserve {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.reserve"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut options = CuckooOptions {
+            capacity: extract_capacity(args.next())?,
+            ..Default::default()
+        };
+        while let Some(opt) = args.next() {
+            let opt = extract_string(Some(opt))?.to_ascii_lowercase();
+            let (value, range) = match opt.as_str() {
+                "bucketsize" => (&mut options.bucket_size, 1..=255),
+                "maxiterations" => (&mut options.max_iterations, 1..=65535),
+                "expansion" => (&mut options.expansion, 0..=32768),
+                _ => return Err(syntax_error()),
+            };
+            *value = match extract_i64(args.next())? {
+                v if range.contains(&v) => v as u64,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "{} should be in the range {}..{}",
+                        opt,
+                        range.start(),
+                        range.end()
+                    )))
+                }
+            };
+        }
+        Ok(CfReserve { key, options })
+    }
+}
+
+impl TryFrom<RespArray> for CfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, nx) = match command_name(&value).as_str() {
+            "cf.add" => ("cf.add", false),
+            "cf.addnx" => ("cf.addnx", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let item = extract_bytes(args.next())?;
+        Ok(CfAdd { key, item, nx })
+    }
+}
+
+impl TryFrom<RespArray> for CfInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, nx) = match command_name(&value).as_str() {
+            "cf.insert" => ("cf.insert", false),
+            "cf.insertnx" => ("cf.insertnx", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (mut options, mut nocreate) = (CuckooOptions::default(), false);
+        loop {
+            match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                "capacity" => options.capacity = extract_capacity(args.next())?,
+                "nocreate" => nocreate = true,
+                "items" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if items.is_empty() {
+            return Err(syntax_error());
+        }
+        Ok(CfInsert {
+            key,
+            items,
+            nx,
+            options: (!nocreate).then_some(options),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for CfExists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, multi) = match command_name(&value).as_str() {
+            "cf.exists" => ("cf.exists", false),
+            "cf.mexists" => ("cf.mexists", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        match multi {
+            true => validate_command(&value, &[name], 2, CmpType::LEAST)?,
+            false => validate_command(&value, &[name], 2, CmpType::EQ)?,
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CfExists { key, items, multi })
+    }
+}
+
+impl TryFrom<RespArray> for CfDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.del"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let item = extract_bytes(args.next())?;
+        Ok(CfDel { key, item })
+    }
+}
+
+impl TryFrom<RespArray> for CfCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.count"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let item = extract_bytes(args.next())?;
+        Ok(CfCount { key, item })
+    }
+}
+
+impl TryFrom<RespArray> for CfInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.info"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(CfInfo { key })
+    }
+}
+
+impl TryFrom<RespArray> for CfScanDump {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.scandump"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let iter = extract_iter(args.next())?;
+        Ok(CfScanDump { key, iter })
+    }
+}
+
+impl TryFrom<RespArray> for CfLoadChunk {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["cf.loadchunk"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let iter = extract_iter(args.next())?;
+        let data = extract_bytes(args.next())?;
+        Ok(CfLoadChunk { key, iter, data })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+fn extract_capacity(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_i64(arg)? {
+        capacity if capacity > 0 => Ok(capacity as u64),
+        _ => Err(CommandError::InvalidArgument(
+            "(capacity should be larger than 0)".to_string(),
+        )),
+    }
+}
+
+// the cursor of SCANDUMP and LOADCHUNK
+fn extract_iter(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_i64(arg)? {
+        iter if iter >= 0 => Ok(iter as u64),
+        _ => Err(CommandError::InvalidArgument(
+            "invalid iterator".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+    use crate::SimpleError;
+
+    use super::*;
+
+    #[test]
+    fn test_cuckoo_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: CfReserve =
+            args(&["cf.reserve", "cf", "4", "bucketsize", "1", "expansion", "0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd: CfAdd = args(&["cf.add", "cf", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: CfAdd = args(&["cf.addnx", "cf", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd: CfCount = args(&["cf.count", "cf", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: CfExists = args(&["cf.mexists", "cf", "a", "b"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(1), RespFrame::Integer(0)]).into()
+        );
+        let cmd: CfDel = args(&["cf.del", "cf", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: CfDel = args(&["cf.del", "cf", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+
+        // four slots with no room to grow
+        let items = (0..8).map(|i| i.to_string()).collect::<Vec<_>>();
+        let mut insert = vec!["cf.insert", "cf", "items"];
+        insert.extend(items.iter().map(String::as_str));
+        let cmd: CfInsert = args(&insert).try_into()?;
+        let RespFrame::Array(added) = cmd.execute(&backend) else {
+            panic!("CF.INSERT should reply with an array");
+        };
+        assert!(added.contains(&RespFrame::Integer(-1)));
+
+        let cmd: CfInsert = args(&["cf.insert", "new", "nocreate", "items", "a"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::from(SimpleError::new("ERR not found"))
+        );
+        assert!(CfReserve::try_from(args(&["cf.reserve", "x", "10", "bucketsize", "0"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cuckoo_size_limits() -> Result<()> {
+        let backend = Backend::new();
+        let too_large = RespFrame::from(SimpleError::new("ERR filter would be too large"));
+        for capacity in ["9223372036854775807", "1000000000"] {
+            let cmd: CfReserve = args(&["cf.reserve", "k", capacity]).try_into()?;
+            assert_eq!(cmd.execute(&backend), too_large);
+        }
+        let cmd: CfInsert = args(&[
+            "cf.insert",
+            "k",
+            "capacity",
+            "9223372036854775807",
+            "items",
+            "a",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend), too_large);
+        assert!(!backend.cuckoo.contains_key("k"));
+
+        let bucket_size = ["cf.reserve", "k", "10", "bucketsize", "256"];
+        assert!(CfReserve::try_from(args(&bucket_size)).is_err());
+        let expansion = ["cf.reserve", "k", "10", "expansion", "9223372036854775807"];
+        assert!(CfReserve::try_from(args(&expansion)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cuckoo_scandump_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: CfInsert = args(&["cf.insertnx", "cf", "items", "x", "y", "x"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(1),
+                RespFrame::Integer(1),
+                RespFrame::Integer(0)
+            ])
+            .into()
+        );
+
+        let mut iter = 0;
+        loop {
+            let cmd: CfScanDump = args(&["cf.scandump", "cf", &iter.to_string()]).try_into()?;
+            let RespFrame::Array(reply) = cmd.execute(&backend) else {
+                panic!("CF.SCANDUMP should reply with an array");
+            };
+            let (Some(RespFrame::Integer(next)), Some(RespFrame::BulkString(data))) =
+                (reply.first(), reply.get(1))
+            else {
+                panic!("CF.SCANDUMP should reply with an iterator and data");
+            };
+            if *next == 0 {
+                break;
+            }
+            let cmd: CfLoadChunk = RespArray::new([
+                BulkString::from("cf.loadchunk").into(),
+                BulkString::from("copy").into(),
+                BulkString::from(next.to_string()).into(),
+                data.clone().into(),
+            ])
+            .try_into()?;
+            assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+            iter = *next;
+        }
+        let cmd: CfExists = args(&["cf.exists", "copy", "y"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: CfInfo = args(&["cf.info", "copy"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend) else {
+            panic!("CF.INFO should reply with a map");
+        };
+        assert_eq!(
+            info.get("Number of items inserted"),
+            Some(&RespFrame::Integer(2))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,588 @@
+use crate::{
+    Backend, BulkString, GeoMatch, GeoOrigin, GeoQuery, GeoShape, KeyType, RespArray, RespFrame,
+    RespNull, ZAddFlags,
+};
+
+use super::{
+    extract_args, extract_f64, extract_i64, extract_string, validate_command,
+    zset::parse_key_members, CmpType, CommandError, CommandExecutor, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct GeoAdd {
+    key: String,
+    flags: ZAddFlags,
+    // count changed members rather than only added ones
+    ch: bool,
+    // longitude, latitude, member
+    items: Vec<(f64, f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct GeoPos {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoDist {
+    key: String,
+    member1: String,
+    member2: String,
+    // meters per unit
+    unit: f64,
+}
+
+#[derive(Debug)]
+pub struct GeoHash {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GeoSearch {
+    key: String,
+    query: GeoQuery,
+    with: WithOptions,
+}
+
+#[derive(Debug)]
+pub struct GeoSearchStore {
+    destination: String,
+    source: String,
+    query: GeoQuery,
+    store_dist: bool,
+}
+
+// what GEOSEARCH replies with besides the members
+#[derive(Debug, Default, PartialEq)]
+struct WithOptions {
+    coord: bool,
+    dist: bool,
+    hash: bool,
+}
+
+impl CommandExecutor for GeoAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::ZSet) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.geoadd(self.key, self.items, self.flags) {
+            Ok((added, changed)) => {
+                RespFrame::Integer(if self.ch { changed } else { added } as i64)
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for GeoPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .geopos(&self.key, &self.members)
+            .into_iter()
+            .map(|pos| match pos {
+                Some(pos) => coord_reply(pos),
+                None => RespFrame::Null(RespNull),
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for GeoDist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.geodist(&self.key, &self.member1, &self.member2) {
+            Some(dist) => dist_reply(dist / self.unit),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for GeoHash {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .geohash(&self.key, &self.members)
+            .into_iter()
+            .map(|hash| match hash {
+                Some(hash) => BulkString::from(hash).into(),
+                None => RespFrame::Null(RespNull),
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for GeoSearch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let matches = match backend.geosearch(&self.key, &self.query) {
+            Ok(matches) => matches,
+            Err(e) => return e.into(),
+        };
+        let ret = matches
+            .into_iter()
+            .map(|m| match_reply(m, &self.with))
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for GeoSearchStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::ZSet) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let stored = backend.geosearchstore(
+            &self.destination,
+            &self.source,
+            &self.query,
+            self.store_dist,
+        );
+        match stored {
+            Ok(len) => RespFrame::Integer(len as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn coord_reply((lon, lat): (f64, f64)) -> RespFrame {
+    RespArray::new([lon.into(), lat.into()]).into()
+}
+
+// distances are replied as strings with 4 decimals
+fn dist_reply(dist: f64) -> RespFrame {
+    BulkString::from(format!("{:.4}", dist)).into()
+}
+
+// the member alone, or [member, dist, hash, [lon, lat]] with the requested parts
+fn match_reply(m: GeoMatch, with: &WithOptions) -> RespFrame {
+    if *with == WithOptions::default() {
+        return BulkString::from(m.member).into();
+    }
+    let mut ret = vec![BulkString::from(m.member).into()];
+    if with.dist {
+        ret.push(dist_reply(m.dist));
+    }
+    if with.hash {
+        ret.push(RespFrame::Integer(m.hash as i64));
+    }
+    if with.coord {
+        ret.push(coord_reply((m.lon, m.lat)));
+    }
+    RespArray::new(ret).into()
+}
+
+// meters per unit of m, km, ft or mi
+fn extract_unit(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    match extract_string(arg)?.to_ascii_lowercase().as_str() {
+        "m" => Ok(1.0),
+        "km" => Ok(1000.0),
+        "ft" => Ok(0.3048),
+        "mi" => Ok(1609.34),
+        _ => Err(CommandError::InvalidArgument(
+            "unsupported unit provided. please use M, KM, FT, MI".to_string(),
+        )),
+    }
+}
+
+fn extract_lon_lat(args: &mut impl Iterator<Item = RespFrame>) -> Result<(f64, f64), CommandError> {
+    let lon = extract_f64(args.next())?;
+    let lat = extract_f64(args.next())?;
+    Ok((lon, lat))
+}
+
+fn extract_distance(arg: Option<RespFrame>, what: &str) -> Result<f64, CommandError> {
+    match extract_f64(arg)? {
+        d if d < 0.0 => Err(CommandError::InvalidArgument(format!(
+            "{} cannot be negative",
+            what
+        ))),
+        d => Ok(d),
+    }
+}
+
+// parses the GEOSEARCH options after the key(s); STOREDIST is only accepted with `store`
+fn parse_search(
+    name: &str,
+    args: impl Iterator<Item = RespFrame>,
+    store: bool,
+) -> Result<(GeoQuery, WithOptions, bool), CommandError> {
+    let mut args = args.peekable();
+    let (mut origin, mut shape, mut unit) = (None, None, 1.0);
+    let (mut desc, mut count, mut any) = (None, None, false);
+    let (mut with, mut store_dist) = (WithOptions::default(), false);
+    let exactly_one = |what: &str| {
+        CommandError::InvalidArgument(format!(
+            "exactly one of {} can be specified for {}",
+            what, name
+        ))
+    };
+    while let Some(opt) = args.next() {
+        match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+            "frommember" if origin.is_none() => {
+                origin = Some(GeoOrigin::Member(extract_string(args.next())?))
+            }
+            "fromlonlat" if origin.is_none() => {
+                let (lon, lat) = extract_lon_lat(&mut args)?;
+                origin = Some(GeoOrigin::LonLat(lon, lat));
+            }
+            "frommember" | "fromlonlat" => {
+                return Err(exactly_one("FROMMEMBER or FROMLONLAT"));
+            }
+            "byradius" if shape.is_none() => {
+                shape = Some(GeoShape::Radius(extract_distance(args.next(), "radius")?));
+                unit = extract_unit(args.next())?;
+            }
+            "bybox" if shape.is_none() => {
+                let width = extract_distance(args.next(), "height or width")?;
+                let height = extract_distance(args.next(), "height or width")?;
+                shape = Some(GeoShape::Box(width, height));
+                unit = extract_unit(args.next())?;
+            }
+            "byradius" | "bybox" => return Err(exactly_one("BYRADIUS and BYBOX")),
+            "asc" => desc = Some(false),
+            "desc" => desc = Some(true),
+            "count" => {
+                count = match extract_i64(args.next())? {
+                    n if n > 0 => Some(n as usize),
+                    _ => {
+                        return Err(CommandError::InvalidArgument(
+                            "COUNT must be > 0".to_string(),
+                        ))
+                    }
+                };
+                let is_any = |f: &RespFrame| matches!(f, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"any"));
+                any = args.next_if(is_any).is_some();
+            }
+            "withcoord" if !store => with.coord = true,
+            "withdist" if !store => with.dist = true,
+            "withhash" if !store => with.hash = true,
+            "storedist" if store => store_dist = true,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        }
+    }
+    let origin = origin.ok_or_else(|| exactly_one("FROMMEMBER or FROMLONLAT"))?;
+    let shape = shape.ok_or_else(|| exactly_one("BYRADIUS and BYBOX"))?;
+    let query = GeoQuery {
+        origin,
+        shape,
+        unit,
+        desc,
+        count,
+        any,
+    };
+    Ok((query, with, store_dist))
+}
+
+impl TryFrom<RespArray> for GeoAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geoadd"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let (mut flags, mut ch) = (ZAddFlags::default(), false);
+        while let Some(RespFrame::BulkString(opt)) = args.peek() {
+            match opt.to_ascii_lowercase().as_slice() {
+                b"nx" if flags.only_if != Some(true) => flags.only_if = Some(false),
+                b"xx" if flags.only_if != Some(false) => flags.only_if = Some(true),
+                b"nx" | b"xx" => {
+                    return Err(CommandError::InvalidArgument(
+                        "XX and NX options at the same time are not compatible".to_string(),
+                    ))
+                }
+                b"ch" => ch = true,
+                _ => break,
+            }
+            args.next();
+        }
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(3) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let mut items = Vec::with_capacity(args.len() / 3);
+        let mut args = args.into_iter();
+        while args.len() > 0 {
+            let (lon, lat) = extract_lon_lat(&mut args)?;
+            items.push((lon, lat, extract_string(args.next())?));
+        }
+        Ok(GeoAdd {
+            key,
+            flags,
+            ch,
+            items,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geopos"], 1, CmpType::LEAST)?;
+
+        let (key, members) = parse_key_members(value)?;
+        Ok(GeoPos { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoDist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geodist"], 3, CmpType::LEAST)?;
+        if value.len() > 5 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let member1 = extract_string(args.next())?;
+        let member2 = extract_string(args.next())?;
+        let unit = match args.next() {
+            Some(unit) => extract_unit(Some(unit))?,
+            None => 1.0,
+        };
+        Ok(GeoDist {
+            key,
+            member1,
+            member2,
+            unit,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for GeoHash {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geohash"], 1, CmpType::LEAST)?;
+
+        let (key, members) = parse_key_members(value)?;
+        Ok(GeoHash { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geosearch"], 5, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (query, with, _) = parse_search("GEOSEARCH", args, false)?;
+        Ok(GeoSearch { key, query, with })
+    }
+}
+
+impl TryFrom<RespArray> for GeoSearchStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["geosearchstore"], 6, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = extract_string(args.next())?;
+        let source = extract_string(args.next())?;
+        let (query, _, store_dist) = parse_search("GEOSEARCHSTORE", args, true)?;
+        Ok(GeoSearchStore {
+            destination,
+            source,
+            query,
+            store_dist,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    #[test]
+    fn test_geosearch_from_resp_array() -> Result<()> {
+        let result: GeoSearch = args(&[
+            "geosearch",
+            "k",
+            "frommember",
+            "m",
+            "bybox",
+            "2",
+            "3",
+            "KM",
+            "count",
+            "5",
+            "any",
+            "desc",
+            "withhash",
+        ])
+        .try_into()?;
+        assert_eq!(result.query.origin, GeoOrigin::Member("m".to_string()));
+        assert_eq!(result.query.shape, GeoShape::Box(2.0, 3.0));
+        assert_eq!(result.query.unit, 1000.0);
+        assert_eq!(
+            (result.query.count, result.query.any, result.query.desc),
+            (Some(5), true, Some(true))
+        );
+        assert!(result.with.hash && !result.with.dist);
+
+        for bad in [
+            &["geosearch", "k", "byradius", "1", "m", "asc"][..],
+            &[
+                "geosearch",
+                "k",
+                "fromlonlat",
+                "1",
+                "2",
+                "byradius",
+                "1",
+                "yd",
+            ],
+            &[
+                "geosearch",
+                "k",
+                "fromlonlat",
+                "1",
+                "2",
+                "byradius",
+                "-1",
+                "m",
+            ],
+            &[
+                "geosearch",
+                "k",
+                "fromlonlat",
+                "1",
+                "2",
+                "byradius",
+                "1",
+                "m",
+                "count",
+                "0",
+            ],
+            &[
+                "geosearch",
+                "k",
+                "fromlonlat",
+                "1",
+                "2",
+                "byradius",
+                "1",
+                "m",
+                "storedist",
+            ],
+        ] {
+            assert!(GeoSearch::try_from(args(bad)).is_err(), "{:?}", bad);
+        }
+        let result: GeoSearchStore = args(&[
+            "geosearchstore",
+            "d",
+            "k",
+            "fromlonlat",
+            "1",
+            "2",
+            "byradius",
+            "1",
+            "mi",
+            "storedist",
+        ])
+        .try_into()?;
+        assert!(result.store_dist);
+        assert!(GeoSearchStore::try_from(args(&[
+            "geosearchstore",
+            "d",
+            "k",
+            "fromlonlat",
+            "1",
+            "2",
+            "byradius",
+            "1",
+            "m",
+            "withdist",
+        ]))
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: GeoAdd = args(&[
+            "geoadd",
+            "Sicily",
+            "13.361389",
+            "38.115556",
+            "Palermo",
+            "15.087269",
+            "37.502669",
+            "Catania",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd: GeoAdd =
+            args(&["geoadd", "Sicily", "xx", "ch", "13.4", "38.1", "Palermo"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: GeoAdd =
+            args(&["geoadd", "Sicily", "13.361389", "38.115556", "Palermo"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd: GeoAdd = args(&["geoadd", "Sicily", "200", "0", "nowhere"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        let cmd: GeoDist = args(&["geodist", "Sicily", "Palermo", "Catania", "km"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("166.2742").into());
+        let cmd: GeoHash = args(&["geohash", "Sicily", "Palermo", "nope"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("sqc8b49rny0").into(),
+                RespFrame::Null(RespNull)
+            ])
+            .into()
+        );
+
+        let cmd: GeoSearch = args(&[
+            "geosearch",
+            "Sicily",
+            "fromlonlat",
+            "15",
+            "37",
+            "byradius",
+            "200",
+            "km",
+            "asc",
+            "withdist",
+        ])
+        .try_into()?;
+        let reply = |member: &str, dist: &str| {
+            RespArray::new([
+                BulkString::from(member).into(),
+                BulkString::from(dist).into(),
+            ])
+            .into()
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([reply("Catania", "56.4413"), reply("Palermo", "190.4424")]).into()
+        );
+
+        let cmd: GeoSearchStore = args(&[
+            "geosearchstore",
+            "near",
+            "Sicily",
+            "frommember",
+            "Catania",
+            "byradius",
+            "100",
+            "km",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        assert_eq!(
+            backend.zscore("near", "Catania"),
+            backend.zscore("Sicily", "Catania")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,427 @@
+use crate::{
+    now_ms, Backend, ExpireCondition, FieldExpiry, KeyType, RespArray, RespFrame, RespNull,
+};
+
+use super::{
+    command_name, extract_args, extract_i64, extract_string, validate_command, CmpType,
+    CommandError, CommandExecutor, RESP_WRONGTYPE,
+};
+
+// HEXPIRE, HPEXPIRE, HEXPIREAT and HPEXPIREAT, with the expiry resolved to unix milliseconds
+#[derive(Debug)]
+pub struct HExpire {
+    key: String,
+    at: i64,
+    condition: Option<ExpireCondition>,
+    fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TtlReply {
+    Ttl,
+    PTtl,
+    ExpireTime,
+    PExpireTime,
+}
+
+// HTTL, HPTTL, HEXPIRETIME and HPEXPIRETIME
+#[derive(Debug)]
+pub struct HTtl {
+    key: String,
+    reply: TtlReply,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HPersist {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HGetEx {
+    key: String,
+    expiry: FieldExpiry,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HSetEx {
+    key: String,
+    only_if: Option<bool>,
+    expiry: FieldExpiry,
+    fields: Vec<(String, RespFrame)>,
+}
+
+impl CommandExecutor for HExpire {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend.hexpire(&self.key, &self.fields, self.at, self.condition);
+        int_array(ret)
+    }
+}
+
+impl CommandExecutor for HTtl {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let now = now_ms() as i64;
+        let ret = backend
+            .hexpiretime(&self.key, &self.fields)
+            .into_iter()
+            .map(|at| match self.reply {
+                _ if at < 0 => at,
+                TtlReply::Ttl => (at - now + 500) / 1000,
+                TtlReply::PTtl => at - now,
+                TtlReply::ExpireTime => at / 1000,
+                TtlReply::PExpireTime => at,
+            })
+            .collect();
+        int_array(ret)
+    }
+}
+
+impl CommandExecutor for HPersist {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        int_array(backend.hpersist(&self.key, &self.fields))
+    }
+}
+
+impl CommandExecutor for HGetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .hgetex(&self.key, &self.fields, self.expiry)
+            .into_iter()
+            .map(|v| v.unwrap_or(RespFrame::Null(RespNull)))
+            .collect::<Vec<_>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for HSetEx {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Hash) {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.hsetex(self.key, self.fields, self.only_if, self.expiry) as i64).into()
+    }
+}
+
+fn int_array(v: Vec<i64>) -> RespFrame {
+    RespArray::new(v.into_iter().map(RespFrame::from).collect::<Vec<_>>()).into()
+}
+
+// turns a relative or absolute time in seconds or milliseconds into unix milliseconds
+fn resolve_expiry(
+    value: i64,
+    unit_ms: i64,
+    absolute: bool,
+    name: &str,
+) -> Result<i64, CommandError> {
+    let err =
+        || CommandError::InvalidArgument(format!("invalid expire time in '{}' command", name));
+    if value < 0 {
+        return Err(err());
+    }
+    let ms = value.checked_mul(unit_ms).ok_or_else(err)?;
+    if absolute {
+        Ok(ms)
+    } else {
+        ms.checked_add(now_ms() as i64).ok_or_else(err)
+    }
+}
+
+// parses `FIELDS numfields field [field ...]`
+fn parse_fields(args: &mut impl Iterator<Item = RespFrame>) -> Result<Vec<String>, CommandError> {
+    let n = parse_numfields(args)?;
+    let fields = args
+        .map(|f| extract_string(Some(f)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if fields.len() != n {
+        return Err(CommandError::InvalidArgument(
+            "The `numfields` parameter must match the number of arguments".to_string(),
+        ));
+    }
+    Ok(fields)
+}
+
+fn parse_numfields(args: &mut impl Iterator<Item = RespFrame>) -> Result<usize, CommandError> {
+    if !extract_string(args.next())?.eq_ignore_ascii_case("fields") {
+        return Err(CommandError::InvalidArgument(
+            "Mandatory argument FIELDS is missing or not at the right position".to_string(),
+        ));
+    }
+    match extract_i64(args.next())? {
+        n if n > 0 => Ok(n as usize),
+        _ => Err(CommandError::InvalidArgument(
+            "Parameter `numFields` should be greater than 0".to_string(),
+        )),
+    }
+}
+
+impl TryFrom<RespArray> for HExpire {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, unit_ms, absolute) = match command_name(&value).as_str() {
+            "hexpire" => ("hexpire", 1000, false),
+            "hpexpire" => ("hpexpire", 1, false),
+            "hexpireat" => ("hexpireat", 1000, true),
+            "hpexpireat" => ("hpexpireat", 1, true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let at = resolve_expiry(extract_i64(args.next())?, unit_ms, absolute, name)?;
+        let condition = match args.peek() {
+            Some(RespFrame::BulkString(s)) => match s.to_ascii_lowercase().as_slice() {
+                b"nx" => Some(ExpireCondition::Nx),
+                b"xx" => Some(ExpireCondition::Xx),
+                b"gt" => Some(ExpireCondition::Gt),
+                b"lt" => Some(ExpireCondition::Lt),
+                _ => None,
+            },
+            _ => None,
+        };
+        if condition.is_some() {
+            args.next();
+        }
+        let fields = parse_fields(&mut args)?;
+        Ok(HExpire {
+            key,
+            at,
+            condition,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HTtl {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, reply) = match command_name(&value).as_str() {
+            "httl" => ("httl", TtlReply::Ttl),
+            "hpttl" => ("hpttl", TtlReply::PTtl),
+            "hexpiretime" => ("hexpiretime", TtlReply::ExpireTime),
+            "hpexpiretime" => ("hpexpiretime", TtlReply::PExpireTime),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let fields = parse_fields(&mut args)?;
+        Ok(HTtl { key, reply, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HPersist {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hpersist"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let fields = parse_fields(&mut args)?;
+        Ok(HPersist { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HGetEx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hgetex"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let mut expiry = FieldExpiry::Keep;
+        while let Some(RespFrame::BulkString(s)) = args.peek() {
+            let opt = s.to_ascii_lowercase();
+            if opt == b"fields" {
+                break;
+            }
+            args.next();
+            expiry = match opt.as_slice() {
+                b"persist" => FieldExpiry::Persist,
+                _ => parse_expiry_option(&opt, &mut args, "hgetex")?,
+            };
+        }
+        let fields = parse_fields(&mut args)?;
+        Ok(HGetEx {
+            key,
+            expiry,
+            fields,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HSetEx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetex"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        // without KEEPTTL, HSETEX behaves like HSET and clears existing TTLs
+        let mut expiry = FieldExpiry::Persist;
+        let mut only_if = None;
+        while let Some(RespFrame::BulkString(s)) = args.peek() {
+            let opt = s.to_ascii_lowercase();
+            if opt == b"fields" {
+                break;
+            }
+            args.next();
+            match opt.as_slice() {
+                b"fnx" => only_if = Some(false),
+                b"fxx" => only_if = Some(true),
+                b"keepttl" => expiry = FieldExpiry::Keep,
+                _ => expiry = parse_expiry_option(&opt, &mut args, "hsetex")?,
+            }
+        }
+        let n = parse_numfields(&mut args)?;
+        let mut fields = Vec::new();
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(Some(field))?, value));
+        }
+        if fields.len() != n || args.next().is_some() {
+            return Err(CommandError::InvalidArgument(
+                "The `numfields` parameter must match the number of arguments".to_string(),
+            ));
+        }
+        Ok(HSetEx {
+            key,
+            only_if,
+            expiry,
+            fields,
+        })
+    }
+}
+
+// parses the value following EX, PX, EXAT or PXAT
+fn parse_expiry_option(
+    opt: &[u8],
+    args: &mut impl Iterator<Item = RespFrame>,
+    name: &str,
+) -> Result<FieldExpiry, CommandError> {
+    let (unit_ms, absolute) = match opt {
+        b"ex" => (1000, false),
+        b"px" => (1, false),
+        b"exat" => (1000, true),
+        b"pxat" => (1, true),
+        _ => {
+            return Err(CommandError::InvalidArgument(format!(
+                "unknown argument '{}'",
+                String::from_utf8_lossy(opt)
+            )))
+        }
+    };
+    let value = extract_i64(args.next())?;
+    Ok(FieldExpiry::At(resolve_expiry(
+        value, unit_ms, absolute, name,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::{BulkString, RespDecode};
+
+    use super::*;
+
+    #[test]
+    fn test_hexpire_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*7\r\n$10\r\nhpexpireat\r\n$3\r\nmap\r\n$4\r\n1000\r\n$2\r\nGT\r\n$6\r\nFIELDS\r\n$1\r\n1\r\n$1\r\na\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: HExpire = frame.try_into()?;
+        assert_eq!(result.key, "map");
+        assert_eq!(result.at, 1000);
+        assert_eq!(result.condition, Some(ExpireCondition::Gt));
+        assert_eq!(result.fields, vec!["a".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hexpire_numfields_mismatch_should_fail() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*6\r\n$4\r\nhttl\r\n$3\r\nmap\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<HTtl, _> = frame.try_into();
+        assert!(result.is_ok());
+
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nhttl\r\n$3\r\nmap\r\n$6\r\nFIELDS\r\n$1\r\n2\r\n$1\r\na\r\n",
+        );
+        let frame = RespArray::decode(&mut buf)?;
+        let result: Result<HTtl, _> = frame.try_into();
+        assert!(result.is_err());
+
+        // a count far past the arguments given is a mismatch, not an allocation
+        let huge = ["hsetex", "map", "FIELDS", "9223372036854775807", "f", "v"];
+        assert!(HSetEx::try_from(args(&huge)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hsetex_hgetex_httl_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd = HSetEx {
+            key: "map".to_string(),
+            only_if: Some(false),
+            expiry: FieldExpiry::At(now_ms() as i64 + 10_000),
+            fields: vec![
+                ("a".to_string(), BulkString::from("1").into()),
+                ("b".to_string(), BulkString::from("2").into()),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        let cmd = HTtl {
+            key: "map".to_string(),
+            reply: TtlReply::Ttl,
+            fields: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([10.into(), (-2).into()]).into()
+        );
+
+        let cmd = HGetEx {
+            key: "map".to_string(),
+            expiry: FieldExpiry::Persist,
+            fields: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("1").into(), RespFrame::Null(RespNull)]).into()
+        );
+
+        let cmd = HPersist {
+            key: "map".to_string(),
+            fields: vec!["a".to_string(), "b".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([(-1).into(), 1.into()]).into()
+        );
+
+        let cmd = HGetEx {
+            key: "map".to_string(),
+            expiry: FieldExpiry::At(0),
+            fields: vec!["a".to_string(), "b".to_string()],
+        };
+        cmd.execute(&backend);
+        assert!(!backend.hmap.contains_key("map"));
+
+        Ok(())
+    }
+}
@@ -1,10 +1,80 @@
-use crate::{BulkString, RespArray, RespFrame, SimpleString};
+use crate::{BulkString, KeyType, RespArray, RespFrame, RespNull, SimpleString};
 
 use super::{
-    extract_args, validate_command, CmpType, CommandError, CommandExecutor, HGet, HGetAll, HMget,
-    HSet, RESP_OK,
+    extract_args, extract_f64, extract_i64, extract_rand_count, extract_string, validate_command,
+    CmpType, CommandError, CommandExecutor, HGet, HGetAll, HMget, HSet, RESP_WRONGTYPE,
 };
 
+#[derive(Debug)]
+pub struct HDel {
+    key: String,
+    fields: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct HExists {
+    key: String,
+    field: String,
+}
+
+#[derive(Debug)]
+pub struct HLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HKeys {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HVals {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct HSetNx {
+    key: String,
+    field: String,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct HIncrBy {
+    key: String,
+    field: String,
+    increment: i64,
+}
+
+#[derive(Debug)]
+pub struct HIncrByFloat {
+    key: String,
+    field: String,
+    increment: f64,
+}
+
+#[derive(Debug)]
+pub struct HScan {
+    key: String,
+    cursor: u64,
+    pattern: Option<String>,
+    count: usize,
+    no_values: bool,
+}
+
+#[derive(Debug)]
+pub struct HRandField {
+    key: String,
+    count: Option<i64>,
+    with_values: bool,
+}
+
+#[derive(Debug)]
+pub struct HStrLen {
+    key: String,
+    field: String,
+}
+
 impl CommandExecutor for HGetAll {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
         let hmap = backend.hmap.get(&self.key);
@@ -12,9 +82,8 @@ impl CommandExecutor for HGetAll {
         match hmap {
             Some(hmap) => {
                 let mut data = Vec::with_capacity(hmap.len());
-                for v in hmap.iter() {
-                    let key = v.key().to_owned();
-                    data.push((key, v.value().clone()));
+                for (k, v) in hmap.iter() {
+                    data.push((k.to_owned(), v));
                 }
                 if self.sort {
                     data.sort_by(|a, b| a.0.cmp(&b.0));
@@ -33,8 +102,133 @@ impl CommandExecutor for HGetAll {
 
 impl CommandExecutor for HSet {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.hset(self.key, self.field, self.value);
-        RESP_OK.clone()
+        if backend.holds_other_type(&self.key, KeyType::Hash) {
+            return RESP_WRONGTYPE.clone();
+        }
+        backend.hset_multi(self.key, self.fields).into()
+    }
+}
+
+impl CommandExecutor for HDel {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        backend.hdel(&self.key, &self.fields).into()
+    }
+}
+
+impl CommandExecutor for HExists {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        (backend.hexists(&self.key, &self.field) as i64).into()
+    }
+}
+
+impl CommandExecutor for HLen {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        (backend.hlen(&self.key) as i64).into()
+    }
+}
+
+impl CommandExecutor for HKeys {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let keys = backend
+            .hkeys(&self.key)
+            .into_iter()
+            .map(|k| BulkString::from(k).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(keys).into()
+    }
+}
+
+impl CommandExecutor for HVals {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespArray::new(backend.hvals(&self.key)).into()
+    }
+}
+
+impl CommandExecutor for HSetNx {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Hash) {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.hsetnx(self.key, self.field, self.value) as i64).into()
+    }
+}
+
+impl CommandExecutor for HStrLen {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let len = match backend.hget(&self.key, &self.field) {
+            Some(RespFrame::BulkString(s)) => s.len(),
+            Some(RespFrame::SimpleString(s)) => s.len(),
+            Some(RespFrame::Integer(i)) => i.to_string().len(),
+            Some(RespFrame::Double(d)) => d.to_string().len(),
+            _ => 0,
+        };
+        (len as i64).into()
+    }
+}
+
+impl CommandExecutor for HIncrBy {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Hash) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.hincrby(self.key, self.field, self.increment) {
+            Ok(value) => value.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HIncrByFloat {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Hash) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.hincrbyfloat(self.key, self.field, self.increment) {
+            Ok(value) => BulkString::from(value).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for HScan {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let (cursor, items) = backend.hscan(&self.key, self.cursor, self.count);
+        let mut ret = Vec::with_capacity(items.len() * 2);
+        for (k, v) in items {
+            if let Some(ref pattern) = self.pattern {
+                if !glob_match(pattern.as_bytes(), k.as_bytes()) {
+                    continue;
+                }
+            }
+            ret.push(BulkString::from(k).into());
+            if !self.no_values {
+                ret.push(v);
+            }
+        }
+        RespArray::new([
+            BulkString::from(cursor.to_string()).into(),
+            RespArray::new(ret).into(),
+        ])
+        .into()
+    }
+}
+
+impl CommandExecutor for HRandField {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let Some(count) = self.count else {
+            return match backend.hrandfield(&self.key, 1).pop() {
+                Some((k, _)) => BulkString::from(k).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        };
+        let mut ret = Vec::new();
+        for (k, v) in backend.hrandfield(&self.key, count) {
+            ret.push(BulkString::from(k).into());
+            if self.with_values {
+                ret.push(v);
+            }
+        }
+        RespArray::new(ret).into()
     }
 }
 
@@ -124,29 +318,259 @@ impl TryFrom<RespArray> for HSet {
     type Error = CommandError;
 
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["hset"], 3, CmpType::EQ)?;
+        validate_command(&value, &["hset"], 3, CmpType::LEAST)?;
+        if !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "hset command must have field value pairs".to_string(),
+            ));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut fields = Vec::new();
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(Some(field))?, value));
+        }
+        Ok(HSet { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hdel"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let fields = args
+            .map(|f| extract_string(Some(f)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(HDel { key, fields })
+    }
+}
+
+impl TryFrom<RespArray> for HExists {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hexists"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HExists {
+            key: extract_string(args.next())?,
+            field: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hlen"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HLen {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HKeys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hkeys"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HKeys {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HVals {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hvals"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HVals {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HSetNx {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hsetnx"], 3, CmpType::EQ)?;
 
         let mut args = extract_args(value, 1)?.into_iter();
         match (args.next(), args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(field)), Some(value)) => {
-                Ok(HSet {
-                    key: String::from_utf8(key.0)?,
-                    field: String::from_utf8(field.0)?,
-                    value,
-                })
-            }
+            (Some(key), Some(field), Some(value)) => Ok(HSetNx {
+                key: extract_string(Some(key))?,
+                field: extract_string(Some(field))?,
+                value,
+            }),
             _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
         }
     }
 }
 
+impl TryFrom<RespArray> for HIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hincrby"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HIncrBy {
+            key: extract_string(args.next())?,
+            field: extract_string(args.next())?,
+            increment: extract_i64(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HIncrByFloat {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hincrbyfloat"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HIncrByFloat {
+            key: extract_string(args.next())?,
+            field: extract_string(args.next())?,
+            increment: extract_f64(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HScan {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hscan"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let cursor = extract_string(args.next())?
+            .parse::<u64>()
+            .map_err(|_| CommandError::InvalidArgument("invalid cursor".to_string()))?;
+        let mut cmd = HScan {
+            key,
+            cursor,
+            pattern: None,
+            count: 10,
+            no_values: false,
+        };
+        while let Some(arg) = args.next() {
+            match extract_string(Some(arg))?.to_ascii_lowercase().as_str() {
+                "match" => cmd.pattern = Some(extract_string(args.next())?),
+                "count" => match extract_i64(args.next())? {
+                    n if n >= 1 => cmd.count = n as usize,
+                    _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+                },
+                "novalues" => cmd.no_values = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(cmd)
+    }
+}
+
+impl TryFrom<RespArray> for HRandField {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hrandfield"], 1, CmpType::LEAST)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = args
+            .next()
+            .map(|c| extract_rand_count(Some(c)))
+            .transpose()?;
+        let with_values = match args.next() {
+            Some(arg) => match extract_string(Some(arg))?.eq_ignore_ascii_case("withvalues") {
+                true => true,
+                false => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => false,
+        };
+        Ok(HRandField {
+            key,
+            count,
+            with_values,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for HStrLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["hstrlen"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(HStrLen {
+            key: extract_string(args.next())?,
+            field: extract_string(args.next())?,
+        })
+    }
+}
+
+// glob-style matching as used by the MATCH option: `*`, `?`, `[...]` and `\\` escapes
+pub(super) fn glob_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'*', rest)) => {
+            // consecutive stars behave like a single one
+            let rest = &rest[rest.iter().take_while(|&&c| c == b'*').count()..];
+            (0..=s.len()).any(|i| glob_match(rest, &s[i..]))
+        }
+        Some((b'?', rest)) => !s.is_empty() && glob_match(rest, &s[1..]),
+        Some((b'[', rest)) => {
+            let Some((&ch, s_rest)) = s.split_first() else {
+                return false;
+            };
+            let (negate, mut p) = match rest.first() {
+                Some(b'^') => (true, &rest[1..]),
+                _ => (false, rest),
+            };
+            let mut matched = false;
+            loop {
+                match p {
+                    [] => break,
+                    [b']', tail @ ..] => {
+                        p = tail;
+                        break;
+                    }
+                    [b'\\', c, tail @ ..] => {
+                        matched |= *c == ch;
+                        p = tail;
+                    }
+                    [a, b'-', b, tail @ ..] if *b != b']' => {
+                        matched |= (*a.min(b)..=*a.max(b)).contains(&ch);
+                        p = tail;
+                    }
+                    [c, tail @ ..] => {
+                        matched |= *c == ch;
+                        p = tail;
+                    }
+                }
+            }
+            matched != negate && glob_match(p, s_rest)
+        }
+        Some((b'\\', [c, rest @ ..])) => s.first() == Some(c) && glob_match(rest, &s[1..]),
+        Some((c, rest)) => s.first() == Some(c) && glob_match(rest, &s[1..]),
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use anyhow::Result;
     use bytes::BytesMut;
 
-    use crate::RespDecode;
+    use crate::{RespDecode, SimpleError};
 
     use super::*;
     #[test]
@@ -185,8 +609,10 @@ mod tests {
 
         let result: HSet = frame.try_into()?;
         assert_eq!(result.key, "map");
-        assert_eq!(result.field, "hello");
-        assert_eq!(result.value, RespFrame::BulkString(b"world".into()));
+        assert_eq!(
+            result.fields,
+            vec![("hello".to_string(), RespFrame::BulkString(b"world".into()))]
+        );
 
         Ok(())
     }
@@ -196,18 +622,23 @@ mod tests {
         let backend = crate::Backend::new();
         let cmd = HSet {
             key: "map".to_string(),
-            field: "hello".to_string(),
-            value: RespFrame::BulkString(b"world".into()),
+            fields: vec![("hello".to_string(), RespFrame::BulkString(b"world".into()))],
         };
         let result = cmd.execute(&backend);
-        assert_eq!(result, RESP_OK.clone());
+        assert_eq!(result, 1.into());
 
         let cmd = HSet {
             key: "map".to_string(),
-            field: "hello1".to_string(),
-            value: RespFrame::BulkString(b"world1".into()),
+            fields: vec![
+                ("hello".to_string(), RespFrame::BulkString(b"world".into())),
+                (
+                    "hello1".to_string(),
+                    RespFrame::BulkString(b"world1".into()),
+                ),
+            ],
         };
-        cmd.execute(&backend);
+        let result = cmd.execute(&backend);
+        assert_eq!(result, 1.into());
 
         let cmd = HGet {
             key: "map".to_string(),
@@ -231,4 +662,273 @@ mod tests {
         assert_eq!(result, expected.into());
         Ok(())
     }
+
+    #[test]
+    fn test_hdel_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nhdel\r\n$3\r\nmap\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: HDel = frame.try_into()?;
+        assert_eq!(result.key, "map");
+        assert_eq!(result.fields, vec!["a".to_string(), "b".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hset_odd_arguments_should_fail() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*5\r\n$4\r\nhset\r\n$3\r\nmap\r\n$1\r\na\r\n$1\r\n1\r\n$1\r\nb\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: Result<HSet, _> = frame.try_into();
+        assert!(result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_family_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        let cmd = HSet {
+            key: "map".to_string(),
+            fields: vec![
+                ("a".to_string(), RespFrame::BulkString(b"hello".into())),
+                ("b".to_string(), RespFrame::BulkString(b"world!".into())),
+            ],
+        };
+        assert_eq!(cmd.execute(&backend), 2.into());
+
+        let cmd = HSetNx {
+            key: "map".to_string(),
+            field: "a".to_string(),
+            value: RespFrame::BulkString(b"other".into()),
+        };
+        assert_eq!(cmd.execute(&backend), 0.into());
+
+        let cmd = HStrLen {
+            key: "map".to_string(),
+            field: "b".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), 6.into());
+
+        let cmd = HExists {
+            key: "map".to_string(),
+            field: "a".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        let cmd = HLen {
+            key: "map".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), 2.into());
+
+        let cmd = HDel {
+            key: "map".to_string(),
+            fields: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        let cmd = HKeys {
+            key: "map".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("b").into()]).into()
+        );
+
+        let cmd = HVals {
+            key: "map".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("world!").into()]).into()
+        );
+
+        let cmd = HDel {
+            key: "map".to_string(),
+            fields: vec!["b".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+        assert!(!backend.hmap.contains_key("map"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincrbyfloat_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(
+            b"*4\r\n$12\r\nhincrbyfloat\r\n$3\r\nmap\r\n$1\r\na\r\n$3\r\n0.1\r\n",
+        );
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: HIncrByFloat = frame.try_into()?;
+        assert_eq!(result.key, "map");
+        assert_eq!(result.field, "a");
+        assert_eq!(result.increment, 0.1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hincrby_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        let cmd = HIncrBy {
+            key: "map".to_string(),
+            field: "a".to_string(),
+            increment: 5,
+        };
+        assert_eq!(cmd.execute(&backend), 5.into());
+
+        let cmd = HIncrBy {
+            key: "map".to_string(),
+            field: "a".to_string(),
+            increment: i64::MAX,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR increment or decrement would overflow").into()
+        );
+
+        let cmd = HIncrByFloat {
+            key: "map".to_string(),
+            field: "a".to_string(),
+            increment: 0.5,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("5.5").into());
+
+        let cmd = HIncrBy {
+            key: "map".to_string(),
+            field: "a".to_string(),
+            increment: 1,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            SimpleError::new("ERR hash value is not an integer").into()
+        );
+
+        let cmd = HIncrByFloat {
+            key: "map".to_string(),
+            field: "a".to_string(),
+            increment: 4.5,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("10").into());
+
+        for increment in [0.1, 0.2] {
+            let cmd = HIncrByFloat {
+                key: "map".to_string(),
+                field: "b".to_string(),
+                increment,
+            };
+            cmd.execute(&backend);
+        }
+        assert_eq!(
+            backend.hget("map", "b"),
+            Some(BulkString::from("0.3").into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hscan_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*8\r\n$5\r\nhscan\r\n$3\r\nmap\r\n$1\r\n0\r\n$5\r\nMATCH\r\n$2\r\nf*\r\n$5\r\nCOUNT\r\n$3\r\n100\r\n$8\r\nNOVALUES\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: HScan = frame.try_into()?;
+        assert_eq!(result.key, "map");
+        assert_eq!(result.cursor, 0);
+        assert_eq!(result.pattern, Some("f*".to_string()));
+        assert_eq!(result.count, 100);
+        assert!(result.no_values);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hscan_hrandfield_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        let cmd = HSet {
+            key: "map".to_string(),
+            fields: vec![
+                ("foo".to_string(), RespFrame::BulkString(b"1".into())),
+                ("bar".to_string(), RespFrame::BulkString(b"2".into())),
+            ],
+        };
+        cmd.execute(&backend);
+
+        let cmd = HScan {
+            key: "map".to_string(),
+            cursor: 0,
+            pattern: Some("f?o".to_string()),
+            count: 10,
+            no_values: false,
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("0").into(),
+                RespArray::new([BulkString::from("foo").into(), BulkString::from("1").into()])
+                    .into(),
+            ])
+            .into()
+        );
+
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(-3),
+            with_values: true,
+        };
+        let RespFrame::Array(ret) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret.len(), 6);
+
+        let cmd = HRandField {
+            key: "missing".to_string(),
+            count: None,
+            with_values: false,
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        // a count past the hash's size returns each field once, without allocating for it
+        let cmd = HRandField {
+            key: "map".to_string(),
+            count: Some(i64::MAX),
+            with_values: false,
+        };
+        let RespFrame::Array(ret) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret.len(), 2);
+        let value = RespArray::new([
+            BulkString::from("hrandfield").into(),
+            BulkString::from("map").into(),
+            BulkString::from("-9223372036854775808").into(),
+        ]);
+        assert!(HRandField::try_from(value).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"*", b"anything"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-c]llo", b"hbllo"));
+        assert!(glob_match(b"user:**:name", b"user:42:name"));
+        assert!(glob_match(b"a\\*", b"a*"));
+        assert!(!glob_match(b"a\\*", b"ab"));
+    }
 }
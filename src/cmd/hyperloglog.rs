@@ -0,0 +1,146 @@
+use crate::{Backend, KeyType, RespArray, RespFrame};
+
+use super::{
+    extract_args, extract_bytes, extract_string, validate_command, CmpType, CommandError,
+    CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct PfAdd {
+    key: String,
+    elements: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct PfCount {
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PfMerge {
+    dest: String,
+    sources: Vec<String>,
+}
+
+impl CommandExecutor for PfAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::String) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.pfadd(self.key, &self.elements) {
+            Ok(changed) => RespFrame::Integer(changed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.pfcount(&self.keys) {
+            Ok(count) => RespFrame::Integer(count as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for PfMerge {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.dest, KeyType::String) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.pfmerge(self.dest, &self.sources) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for PfAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfadd"], 1, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let elements = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PfAdd { key, elements })
+    }
+}
+
+impl TryFrom<RespArray> for PfCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfcount"], 1, CmpType::LEAST)?;
+
+        let keys = extract_args(value, 1)?
+            .into_iter()
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PfCount { keys })
+    }
+}
+
+impl TryFrom<RespArray> for PfMerge {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["pfmerge"], 1, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dest = extract_string(args.next())?;
+        let sources = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(PfMerge { dest, sources })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+    use crate::BulkString;
+
+    use super::*;
+
+    #[test]
+    fn test_hyperloglog_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: PfAdd = args(&["pfadd", "a", "x", "y", "z"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: PfAdd = args(&["pfadd", "a", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd: PfAdd = args(&["pfadd", "b", "z", "w"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd: PfCount = args(&["pfcount", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        let cmd: PfCount = args(&["pfcount", "a", "b", "missing"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+
+        let cmd: PfMerge = args(&["pfmerge", "c", "a", "b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: PfCount = args(&["pfcount", "c"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+
+        // HyperLogLogs are plain strings: they can be copied around with GET and SET
+        let Some(RespFrame::BulkString(blob)) = backend.get("c") else {
+            panic!("expected a string");
+        };
+        assert!(blob.starts_with(b"HYLL"));
+        backend.set("copy".to_string(), BulkString::new(blob.0).into());
+        let cmd: PfCount = args(&["pfcount", "copy"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+
+        backend.set("str".to_string(), BulkString::from("hello").into());
+        let cmd: PfCount = args(&["pfcount", "str"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        let cmd: PfCount = args(&["pfcount", "missing"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        assert_eq!(backend.get("missing"), None);
+
+        Ok(())
+    }
+}
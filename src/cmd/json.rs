@@ -0,0 +1,562 @@
+use serde_json::Value;
+
+use crate::{
+    json_to_string, Backend, BulkString, JsonMatches, JsonPath, KeyType, RespArray, RespFrame,
+    RespNull, SimpleString,
+};
+
+use super::{
+    extract_args, extract_i64, extract_string, validate_command, CmpType, CommandError,
+    CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct JsonSet {
+    key: String,
+    path: JsonPath,
+    value: Value,
+    // Some(false) for NX, Some(true) for XX
+    only_if: Option<bool>,
+}
+
+#[derive(Debug)]
+pub struct JsonGet {
+    key: String,
+    // INDENT, NEWLINE and SPACE
+    format: (String, String, String),
+    paths: Vec<JsonPath>,
+}
+
+#[derive(Debug)]
+pub struct JsonMGet {
+    keys: Vec<String>,
+    path: JsonPath,
+}
+
+#[derive(Debug)]
+pub struct JsonDel {
+    key: String,
+    path: JsonPath,
+}
+
+#[derive(Debug)]
+pub struct JsonType {
+    key: String,
+    path: JsonPath,
+}
+
+#[derive(Debug)]
+pub struct JsonNumIncrBy {
+    key: String,
+    path: JsonPath,
+    by: serde_json::Number,
+}
+
+#[derive(Debug)]
+pub struct JsonStrAppend {
+    key: String,
+    path: JsonPath,
+    suffix: String,
+}
+
+#[derive(Debug)]
+pub struct JsonArrAppend {
+    key: String,
+    path: JsonPath,
+    values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct JsonArrInsert {
+    key: String,
+    path: JsonPath,
+    index: i64,
+    values: Vec<Value>,
+}
+
+#[derive(Debug)]
+pub struct JsonArrPop {
+    key: String,
+    path: JsonPath,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct JsonObjKeys {
+    key: String,
+    path: JsonPath,
+}
+
+impl CommandExecutor for JsonSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Json) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.json_set(self.key, &self.path, self.value, self.only_if) {
+            Ok(true) => RESP_OK.clone(),
+            Ok(false) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (indent, newline, space) = &self.format;
+        match backend.json_get(&self.key, &self.paths) {
+            Ok(Some(value)) => {
+                BulkString::from(json_to_string(&value, indent, newline, space)).into()
+            }
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonMGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .json_mget(&self.keys, &self.path)
+            .into_iter()
+            .map(|value| match value {
+                Some(value) => BulkString::from(value.to_string()).into(),
+                None => RespFrame::Null(RespNull),
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for JsonDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespFrame::Integer(backend.json_del(&self.key, &self.path) as i64)
+    }
+}
+
+impl CommandExecutor for JsonType {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.json_type(&self.key, &self.path) {
+            Ok(Some(JsonMatches::Legacy(name))) => SimpleString::new(name).into(),
+            Ok(Some(matches)) => matches_reply(matches, |name| BulkString::from(name).into()),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonNumIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        // the new values are replied as JSON: a number, or an array with null for non-numbers
+        let value = match backend.json_numincrby(&self.key, &self.path, &self.by) {
+            Ok(JsonMatches::Legacy(value)) => value,
+            Ok(JsonMatches::Path(values)) => {
+                Value::Array(values.into_iter().map(Option::unwrap_or_default).collect())
+            }
+            Err(e) => return e.into(),
+        };
+        BulkString::from(value.to_string()).into()
+    }
+}
+
+impl CommandExecutor for JsonStrAppend {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.json_strappend(&self.key, &self.path, &self.suffix) {
+            Ok(matches) => matches_reply(matches, |len| RespFrame::Integer(len as i64)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonArrAppend {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.json_arrappend(&self.key, &self.path, &self.values) {
+            Ok(matches) => matches_reply(matches, |len| RespFrame::Integer(len as i64)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonArrInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.json_arrinsert(&self.key, &self.path, self.index, &self.values) {
+            Ok(matches) => matches_reply(matches, |len| RespFrame::Integer(len as i64)),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonArrPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.json_arrpop(&self.key, &self.path, self.index) {
+            Ok(matches) => matches_reply(matches, |value| match value {
+                Some(value) => BulkString::from(value.to_string()).into(),
+                None => RespFrame::Null(RespNull),
+            }),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for JsonObjKeys {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let keys_reply = |keys: Vec<String>| {
+            let keys = keys.into_iter().map(|k| BulkString::from(k).into());
+            RespArray::new(keys.collect::<Vec<RespFrame>>()).into()
+        };
+        match backend.json_objkeys(&self.key, &self.path) {
+            Ok(Some(matches)) => matches_reply(matches, keys_reply),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+// a legacy path replies with its single result, a JSONPath with an array holding null for
+// the matches of the wrong type
+fn matches_reply<T>(matches: JsonMatches<T>, f: impl Fn(T) -> RespFrame) -> RespFrame {
+    match matches {
+        JsonMatches::Legacy(value) => f(value),
+        JsonMatches::Path(values) => {
+            let ret = values.into_iter().map(|value| match value {
+                Some(value) => f(value),
+                None => RespFrame::Null(RespNull),
+            });
+            RespArray::new(ret.collect::<Vec<RespFrame>>()).into()
+        }
+    }
+}
+
+fn extract_path(arg: Option<RespFrame>) -> Result<JsonPath, CommandError> {
+    let raw = extract_string(arg)?;
+    JsonPath::parse(&raw)
+        .ok_or_else(|| CommandError::InvalidArgument(format!("invalid JSON path '{}'", raw)))
+}
+
+fn extract_json(arg: Option<RespFrame>) -> Result<Value, CommandError> {
+    serde_json::from_str(&extract_string(arg)?)
+        .map_err(|e| CommandError::InvalidArgument(format!("invalid JSON value: {}", e)))
+}
+
+// the path argument, or the root when it is left out
+fn extract_optional_path(arg: Option<RespFrame>) -> Result<JsonPath, CommandError> {
+    match arg {
+        Some(arg) => extract_path(Some(arg)),
+        None => Ok(JsonPath::root()),
+    }
+}
+
+// `key [path]`, for commands that take nothing else
+fn parse_key_path(
+    value: RespArray,
+    name: &'static str,
+) -> Result<(String, JsonPath), CommandError> {
+    validate_command(&value, &[name], 1, CmpType::LEAST)?;
+    if value.len() > 3 {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let path = extract_optional_path(args.next())?;
+    Ok((key, path))
+}
+
+// the JSON values ARRAPPEND and ARRINSERT add
+fn parse_values(args: impl Iterator<Item = RespFrame>) -> Result<Vec<Value>, CommandError> {
+    args.map(|arg| extract_json(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()
+}
+
+impl TryFrom<RespArray> for JsonSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.set"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = extract_path(args.next())?;
+        let value = extract_json(args.next())?;
+        let only_if = match args.next() {
+            None => None,
+            Some(opt) => match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "nx" => Some(false),
+                "xx" => Some(true),
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(JsonSet {
+            key,
+            path,
+            value,
+            only_if,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for JsonGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.get"], 1, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let mut format = (String::new(), String::new(), String::new());
+        while let Some(RespFrame::BulkString(opt)) = args.peek() {
+            let target = match opt.to_ascii_lowercase().as_slice() {
+                b"indent" => &mut format.0,
+                b"newline" => &mut format.1,
+                b"space" => &mut format.2,
+                _ => break,
+            };
+            args.next();
+            *target = extract_string(args.next())?;
+        }
+        let paths = args
+            .map(|arg| extract_path(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(JsonGet { key, format, paths })
+    }
+}
+
+impl TryFrom<RespArray> for JsonMGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.mget"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?;
+        let path = extract_path(args.pop())?;
+        let keys = args
+            .into_iter()
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(JsonMGet { keys, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.del")?;
+        Ok(JsonDel { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonType {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.type")?;
+        Ok(JsonType { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonObjKeys {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (key, path) = parse_key_path(value, "json.objkeys")?;
+        Ok(JsonObjKeys { key, path })
+    }
+}
+
+impl TryFrom<RespArray> for JsonNumIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.numincrby"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = extract_path(args.next())?;
+        let by = match extract_json(args.next())? {
+            Value::Number(by) => by,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "increment is not a number".to_string(),
+                ))
+            }
+        };
+        Ok(JsonNumIncrBy { key, path, by })
+    }
+}
+
+impl TryFrom<RespArray> for JsonStrAppend {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.strappend"], 2, CmpType::LEAST)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?;
+        let suffix = match extract_json(args.pop())? {
+            Value::String(suffix) => suffix,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "the appended value must be a JSON string".to_string(),
+                ))
+            }
+        };
+        let mut args = args.into_iter();
+        let key = extract_string(args.next())?;
+        let path = extract_optional_path(args.next())?;
+        Ok(JsonStrAppend { key, path, suffix })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrAppend {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.arrappend"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = extract_path(args.next())?;
+        let values = parse_values(args)?;
+        Ok(JsonArrAppend { key, path, values })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.arrinsert"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = extract_path(args.next())?;
+        let index = extract_i64(args.next())?;
+        let values = parse_values(args)?;
+        Ok(JsonArrInsert {
+            key,
+            path,
+            index,
+            values,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for JsonArrPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["json.arrpop"], 1, CmpType::LEAST)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let path = extract_optional_path(args.next())?;
+        let index = match args.next() {
+            Some(index) => extract_i64(Some(index))?,
+            None => -1,
+        };
+        Ok(JsonArrPop { key, path, index })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    fn bulk(s: &str) -> RespFrame {
+        BulkString::from(s).into()
+    }
+
+    #[test]
+    fn test_json_from_resp_array() -> Result<()> {
+        let result: JsonSet = args(&["json.set", "k", "$.a", r#"{"b":[1,2]}"#, "NX"]).try_into()?;
+        assert_eq!(result.path, JsonPath::parse("$.a").unwrap());
+        assert_eq!(result.value, serde_json::json!({"b": [1, 2]}));
+        assert_eq!(result.only_if, Some(false));
+
+        let result: JsonGet =
+            args(&["json.get", "k", "INDENT", "\t", "space", " ", "$..a", ".b"]).try_into()?;
+        assert_eq!(
+            result.format,
+            ("\t".to_string(), String::new(), " ".to_string())
+        );
+        assert_eq!(result.paths.len(), 2);
+
+        let result: JsonStrAppend = args(&["json.strappend", "k", r#""x""#]).try_into()?;
+        assert_eq!(
+            (result.path, result.suffix.as_str()),
+            (JsonPath::root(), "x")
+        );
+
+        for bad in [
+            &["json.set", "k", "$", "{bad"][..],
+            &["json.set", "k", "$[", "1"],
+            &["json.set", "k", "$", "1", "nx", "xx"],
+        ] {
+            assert!(JsonSet::try_from(args(bad)).is_err(), "{:?}", bad);
+        }
+        assert!(JsonStrAppend::try_from(args(&["json.strappend", "k", "x"])).is_err());
+        assert!(JsonNumIncrBy::try_from(args(&["json.numincrby", "k", "$", "\"1\""])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_commands() -> Result<()> {
+        let backend = Backend::new();
+        let doc = r#"{"a":{"a":2,"b":"x"},"arr":[1,2],"n":1}"#;
+        let cmd: JsonSet = args(&["json.set", "doc", "$", doc]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: JsonSet = args(&["json.set", "doc", "$.n", "5", "nx"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: JsonGet = args(&["json.get", "doc", "$..a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), bulk(r#"[{"a":2,"b":"x"},2]"#));
+        let cmd: JsonGet = args(&["json.get", "doc", ".a.b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), bulk(r#""x""#));
+        let cmd: JsonGet = args(&["json.get", "doc", ".missing"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        let cmd: JsonNumIncrBy = args(&["json.numincrby", "doc", "$..a", "1.5"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), bulk("[null,3.5]"));
+        let cmd: JsonStrAppend = args(&["json.strappend", "doc", ".a.b", r#""yz""#]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        let cmd: JsonArrAppend =
+            args(&["json.arrappend", "doc", "$.arr", "3", "[4]"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(4)]).into()
+        );
+        let cmd: JsonArrInsert = args(&["json.arrinsert", "doc", ".arr", "0", "0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        let cmd: JsonArrPop = args(&["json.arrpop", "doc", ".arr"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), bulk("[4]"));
+        let cmd: JsonType = args(&["json.type", "doc", ".arr"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), SimpleString::new("array").into());
+        let cmd: JsonObjKeys = args(&["json.objkeys", "doc", "$..a"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespArray::new([bulk("a"), bulk("b")]).into(),
+                RespFrame::Null(RespNull)
+            ])
+            .into()
+        );
+
+        let cmd: JsonMGet = args(&["json.mget", "doc", "missing", "$.n"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([bulk("[1]"), RespFrame::Null(RespNull)]).into()
+        );
+        let cmd: JsonDel = args(&["json.del", "doc", "$.a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: JsonGet = args(&["json.get", "doc"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), bulk(r#"{"arr":[0,1,2,3],"n":1}"#));
+        let cmd: JsonDel = args(&["json.del", "doc"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: JsonGet = args(&["json.get", "doc"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,884 @@
+use std::time::Duration;
+
+use crate::{Backend, BulkString, KeyType, ListEnd, RespArray, RespFrame, RespNull};
+
+use super::{
+    command_name, extract_args, extract_i64, extract_numkeys, extract_string, extract_timeout,
+    validate_command, BlockingCommand, CmpType, CommandError, CommandExecutor, RESP_OK,
+    RESP_WRONGTYPE,
+};
+
+// LPUSH, RPUSH, LPUSHX and RPUSHX
+#[derive(Debug)]
+pub struct ListPush {
+    key: String,
+    end: ListEnd,
+    only_existing: bool,
+    values: Vec<RespFrame>,
+}
+
+// LPOP and RPOP
+#[derive(Debug)]
+pub struct ListPop {
+    key: String,
+    end: ListEnd,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct LRange {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct LIndex {
+    key: String,
+    index: i64,
+}
+
+#[derive(Debug)]
+pub struct LSet {
+    key: String,
+    index: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LInsert {
+    key: String,
+    before: bool,
+    pivot: RespFrame,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LRem {
+    key: String,
+    count: i64,
+    value: RespFrame,
+}
+
+#[derive(Debug)]
+pub struct LTrim {
+    key: String,
+    start: i64,
+    stop: i64,
+}
+
+#[derive(Debug)]
+pub struct LPos {
+    key: String,
+    value: RespFrame,
+    rank: i64,
+    count: Option<usize>,
+    maxlen: usize,
+}
+
+#[derive(Debug)]
+pub struct LMove {
+    source: String,
+    destination: String,
+    from: ListEnd,
+    to: ListEnd,
+}
+
+#[derive(Debug)]
+pub struct RPopLPush {
+    source: String,
+    destination: String,
+}
+
+#[derive(Debug)]
+pub struct LMPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+}
+
+// BLPOP and BRPOP
+#[derive(Debug)]
+pub struct BlockingPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMove {
+    // the source is the only key, kept as a slice for `BlockingCommand::keys`
+    keys: [String; 1],
+    destination: String,
+    from: ListEnd,
+    to: ListEnd,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BLMPop {
+    keys: Vec<String>,
+    end: ListEnd,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+impl CommandExecutor for ListPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::List) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let len = backend.list_push(self.key, self.end, self.values, self.only_existing);
+        (len as i64).into()
+    }
+}
+
+impl CommandExecutor for ListPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let popped = backend.list_pop(&self.key, self.end, self.count.unwrap_or(1));
+        match (popped, self.count) {
+            (Some(values), Some(_)) => RespArray::new(values).into(),
+            (Some(mut values), None) => values.pop().unwrap_or(RespFrame::Null(RespNull)),
+            (None, _) => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for LRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        RespArray::new(backend.lrange(&self.key, self.start, self.stop)).into()
+    }
+}
+
+impl CommandExecutor for LLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.llen(&self.key) as i64).into()
+    }
+}
+
+impl CommandExecutor for LIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .lindex(&self.key, self.index)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for LSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.lset(&self.key, self.index, self.value) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for LInsert {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend
+            .linsert(&self.key, self.before, &self.pivot, self.value)
+            .into()
+    }
+}
+
+impl CommandExecutor for LRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.lrem(&self.key, self.count, &self.value) as i64).into()
+    }
+}
+
+impl CommandExecutor for LTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        backend.ltrim(&self.key, self.start, self.stop);
+        RESP_OK.clone()
+    }
+}
+
+impl CommandExecutor for LPos {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let positions = backend.lpos(
+            &self.key,
+            &self.value,
+            self.rank,
+            self.count.unwrap_or(1),
+            self.maxlen,
+        );
+        match self.count {
+            Some(_) => RespArray::new(
+                positions
+                    .into_iter()
+                    .map(|p| RespFrame::Integer(p as i64))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            None => positions
+                .first()
+                .map_or(RespFrame::Null(RespNull), |&p| RespFrame::Integer(p as i64)),
+        }
+    }
+}
+
+impl CommandExecutor for LMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::List) {
+            return RESP_WRONGTYPE.clone();
+        }
+        backend
+            .lmove(&self.source, &self.destination, self.from, self.to)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for RPopLPush {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::List) {
+            return RESP_WRONGTYPE.clone();
+        }
+        backend
+            .lmove(
+                &self.source,
+                &self.destination,
+                ListEnd::Right,
+                ListEnd::Left,
+            )
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for LMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        lmpop(backend, &self.keys, self.end, self.count).unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for BlockingPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for BLMove {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for BLMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl BlockingCommand for BlockingPop {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        let (key, mut values) = backend.lmpop(&self.keys, self.end, 1)?;
+        let value = values.pop()?;
+        Some(RespArray::new([BulkString::from(key).into(), value]).into())
+    }
+}
+
+impl BlockingCommand for BLMove {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        if backend.holds_other_type(&self.destination, KeyType::List) {
+            return Some(RESP_WRONGTYPE.clone());
+        }
+        backend.lmove(&self.keys[0], &self.destination, self.from, self.to)
+    }
+}
+
+impl BlockingCommand for BLMPop {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        lmpop(backend, &self.keys, self.end, self.count)
+    }
+}
+
+// the `[key, [element ...]]` reply of LMPOP and BLMPOP
+fn lmpop(backend: &Backend, keys: &[String], end: ListEnd, count: usize) -> Option<RespFrame> {
+    let (key, values) = backend.lmpop(keys, end, count)?;
+    Some(RespArray::new([BulkString::from(key).into(), RespArray::new(values).into()]).into())
+}
+
+fn parse_end(arg: Option<RespFrame>) -> Result<ListEnd, CommandError> {
+    match extract_string(arg)?.to_ascii_lowercase().as_str() {
+        "left" => Ok(ListEnd::Left),
+        "right" => Ok(ListEnd::Right),
+        _ => Err(CommandError::InvalidArgument("syntax error".to_string())),
+    }
+}
+
+fn extract_non_negative(arg: Option<RespFrame>, name: &str) -> Result<usize, CommandError> {
+    match extract_i64(arg)? {
+        n if n >= 0 => Ok(n as usize),
+        _ => Err(CommandError::InvalidArgument(format!(
+            "{} can't be negative",
+            name
+        ))),
+    }
+}
+
+impl TryFrom<RespArray> for ListPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, end, only_existing) = match command_name(&value).as_str() {
+            "lpush" => ("lpush", ListEnd::Left, false),
+            "rpush" => ("rpush", ListEnd::Right, false),
+            "lpushx" => ("lpushx", ListEnd::Left, true),
+            "rpushx" => ("rpushx", ListEnd::Right, true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(ListPush {
+            key,
+            end,
+            only_existing,
+            values: args.collect(),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ListPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, end) = match command_name(&value).as_str() {
+            "lpop" => ("lpop", ListEnd::Left),
+            "rpop" => ("rpop", ListEnd::Right),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = match args.next() {
+            Some(c) => match extract_i64(Some(c))? {
+                n if n >= 0 => Some(n as usize),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "value is out of range, must be positive".to_string(),
+                    ))
+                }
+            },
+            None => None,
+        };
+        Ok(ListPop { key, end, count })
+    }
+}
+
+impl TryFrom<RespArray> for LRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrange"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LRange {
+            key: extract_string(args.next())?,
+            start: extract_i64(args.next())?,
+            stop: extract_i64(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["llen"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LLen {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LIndex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lindex"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LIndex {
+            key: extract_string(args.next())?,
+            index: extract_i64(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lset"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let index = extract_i64(args.next())?;
+        let value = args
+            .next()
+            .ok_or_else(|| CommandError::InvalidArgument("Invalid value".to_string()))?;
+        Ok(LSet { key, index, value })
+    }
+}
+
+impl TryFrom<RespArray> for LInsert {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["linsert"], 4, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let before = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+            "before" => true,
+            "after" => false,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        match (args.next(), args.next()) {
+            (Some(pivot), Some(value)) => Ok(LInsert {
+                key,
+                before,
+                pivot,
+                value,
+            }),
+            _ => Err(CommandError::InvalidArgument("Invalid value".to_string())),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for LRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lrem"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = extract_i64(args.next())?;
+        let value = args
+            .next()
+            .ok_or_else(|| CommandError::InvalidArgument("Invalid value".to_string()))?;
+        Ok(LRem { key, count, value })
+    }
+}
+
+impl TryFrom<RespArray> for LTrim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ltrim"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LTrim {
+            key: extract_string(args.next())?,
+            start: extract_i64(args.next())?,
+            stop: extract_i64(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LPos {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lpos"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let value = args
+            .next()
+            .ok_or_else(|| CommandError::InvalidArgument("Invalid value".to_string()))?;
+        let (mut rank, mut count, mut maxlen) = (1, None, 0);
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "rank" => {
+                    rank = match extract_i64(args.next())? {
+                        0 | i64::MIN => {
+                            return Err(CommandError::InvalidArgument(
+                                "RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list".to_string(),
+                            ))
+                        }
+                        n => n,
+                    }
+                }
+                "count" => count = Some(extract_non_negative(args.next(), "COUNT")?),
+                "maxlen" => maxlen = extract_non_negative(args.next(), "MAXLEN")?,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(LPos {
+            key,
+            value,
+            rank,
+            count,
+            maxlen,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmove"], 4, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(LMove {
+            source: extract_string(args.next())?,
+            destination: extract_string(args.next())?,
+            from: parse_end(args.next())?,
+            to: parse_end(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for RPopLPush {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["rpoplpush"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(RPopLPush {
+            source: extract_string(args.next())?,
+            destination: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for LMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["lmpop"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (keys, end, count) = parse_mpop(&mut args)?;
+        Ok(LMPop { keys, end, count })
+    }
+}
+
+impl TryFrom<RespArray> for BlockingPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, end) = match command_name(&value).as_str() {
+            "blpop" => ("blpop", ListEnd::Left),
+            "brpop" => ("brpop", ListEnd::Right),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?;
+        let timeout = extract_timeout(args.pop())?;
+        let keys = args
+            .into_iter()
+            .map(|k| extract_string(Some(k)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BlockingPop { keys, end, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BLMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blmove"], 5, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(BLMove {
+            keys: [extract_string(args.next())?],
+            destination: extract_string(args.next())?,
+            from: parse_end(args.next())?,
+            to: parse_end(args.next())?,
+            timeout: extract_timeout(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for BLMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["blmpop"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = extract_timeout(args.next())?;
+        let (keys, end, count) = parse_mpop(&mut args)?;
+        Ok(BLMPop {
+            keys,
+            end,
+            count,
+            timeout,
+        })
+    }
+}
+
+// parses `numkeys key [key ...] LEFT|RIGHT [COUNT count]`
+fn parse_mpop(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, ListEnd, usize), CommandError> {
+    let keys = extract_numkeys(args)?;
+    let end = parse_end(args.next())?;
+    let count = match args.next().map(|o| extract_string(Some(o))).transpose()? {
+        Some(opt) if opt.eq_ignore_ascii_case("count") => match extract_i64(args.next())? {
+            n if n > 0 => n as usize,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "count should be greater than 0".to_string(),
+                ))
+            }
+        },
+        Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        None => 1,
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok((keys, end, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::RespDecode;
+
+    use super::*;
+
+    fn values(v: &[&str]) -> Vec<RespFrame> {
+        v.iter().map(|s| BulkString::from(*s).into()).collect()
+    }
+
+    #[test]
+    fn test_list_push_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$6\r\nRPUSHX\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: ListPush = frame.try_into()?;
+        assert_eq!(result.key, "list");
+        assert_eq!(result.end, ListEnd::Right);
+        assert!(result.only_existing);
+        assert_eq!(result.values, values(&["a", "b"]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_pop_from_resp_array() -> Result<()> {
+        let result: ListPop = args(&["lpop", "list", "2"]).try_into()?;
+        assert_eq!(result.end, ListEnd::Left);
+        assert_eq!(result.count, Some(2));
+        assert!(ListPop::try_from(args(&["rpop", "list", "-1"])).is_err());
+        assert!(ListPop::try_from(args(&["rpop", "list", "1", "2"])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ListPush = args(&["lpushx", "list", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd: ListPush = args(&["rpush", "list", "a", "b", "c", "d"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+        let cmd: LLen = args(&["llen", "list"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(4));
+
+        let cmd: LRange = args(&["lrange", "list", "1", "-2"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(values(&["b", "c"])).into()
+        );
+        let cmd: LIndex = args(&["lindex", "list", "-1"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("d").into());
+        let cmd: LIndex = args(&["lindex", "list", "10"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: LSet = args(&["lset", "list", "0", "z"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: LSet = args(&["lset", "list", "9", "z"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        let cmd: ListPop = args(&["lpop", "list"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("z").into());
+        let cmd: ListPop = args(&["rpop", "list", "2"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new(values(&["d", "c"])).into()
+        );
+        let cmd: ListPop = args(&["rpop", "list", "0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespArray::new([]).into());
+        let cmd: ListPop = args(&["rpop", "list", "5"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespArray::new(values(&["b"])).into());
+        let cmd: ListPop = args(&["rpop", "list", "5"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lpos_from_resp_array() -> Result<()> {
+        let result: LPos = args(&[
+            "lpos", "list", "a", "RANK", "-2", "COUNT", "0", "MAXLEN", "10",
+        ])
+        .try_into()?;
+        assert_eq!(result.rank, -2);
+        assert_eq!(result.count, Some(0));
+        assert_eq!(result.maxlen, 10);
+        assert!(LPos::try_from(args(&["lpos", "list", "a", "rank", "0"])).is_err());
+        assert!(LPos::try_from(args(&["lpos", "list", "a", "count", "-1"])).is_err());
+
+        let result: LMPop = args(&["lmpop", "2", "a", "b", "RIGHT", "COUNT", "3"]).try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(result.end, ListEnd::Right);
+        assert_eq!(result.count, 3);
+        assert!(LMPop::try_from(args(&["lmpop", "3", "a", "b", "left"])).is_err());
+        assert!(LMPop::try_from(args(&["lmpop", "1", "a", "up"])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_editing_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ListPush = args(&["rpush", "list", "a", "b", "a", "c"]).try_into()?;
+        cmd.execute(&backend);
+
+        let cmd: LPos = args(&["lpos", "list", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(0));
+        let cmd: LPos = args(&["lpos", "list", "a", "count", "0"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(0), RespFrame::Integer(2)]).into()
+        );
+        let cmd: LPos = args(&["lpos", "list", "z"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: LInsert = args(&["linsert", "list", "BEFORE", "c", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        let cmd: LRem = args(&["lrem", "list", "0", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd: LTrim = args(&["ltrim", "list", "0", "1"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.lrange("list", 0, -1), values(&["b", "x"]));
+
+        let cmd: RPopLPush = args(&["rpoplpush", "list", "other"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("x").into());
+        let cmd: LMove = args(&["lmove", "list", "other", "left", "right"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), BulkString::from("b").into());
+        let cmd: LMove = args(&["lmove", "list", "other", "left", "right"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: LMPop = args(&["lmpop", "2", "list", "other", "left", "count", "5"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("other").into(),
+                RespArray::new(values(&["x", "b"])).into(),
+            ])
+            .into()
+        );
+        let cmd: LMPop = args(&["lmpop", "1", "other", "left"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_writes_to_other_types() -> Result<()> {
+        let backend = Backend::new();
+        backend.set("str".to_string(), BulkString::from("v").into());
+        backend.sadd("set".to_string(), vec!["m".to_string()]);
+        let cmd: ListPush = args(&["rpush", "list", "a"]).try_into()?;
+        cmd.execute(&backend);
+
+        let cmd: ListPush = args(&["lpush", "str", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        let cmd: LMove = args(&["lmove", "list", "set", "left", "right"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_WRONGTYPE.clone());
+        assert!(!backend.list.contains_key("str"));
+        assert_eq!(backend.key_type("set"), Some(KeyType::Set));
+        assert_eq!(backend.llen("list"), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocking_from_resp_array() -> Result<()> {
+        let result: BlockingPop = args(&["brpop", "a", "b", "0.25"]).try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(result.end, ListEnd::Right);
+        assert_eq!(result.timeout, Some(Duration::from_millis(250)));
+        let result: BlockingPop = args(&["blpop", "a", "0"]).try_into()?;
+        assert_eq!(result.timeout, None);
+        assert!(BlockingPop::try_from(args(&["blpop", "a", "-1"])).is_err());
+        assert!(BlockingPop::try_from(args(&["blpop", "a", "soon"])).is_err());
+
+        let result: BLMPop = args(&["blmpop", "1.5", "1", "a", "left", "count", "2"]).try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string()]);
+        assert_eq!(result.count, 2);
+        assert_eq!(result.timeout, Some(Duration::from_millis(1500)));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_blocking_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: crate::cmd::Command = args(&["blpop", "q", "0.01"]).try_into()?;
+        assert_eq!(cmd.execute_async(&backend).await, RespFrame::Null(RespNull));
+
+        let b = backend.clone();
+        let handle = tokio::spawn(async move {
+            let cmd: crate::cmd::Command = args(&["blmove", "q", "done", "left", "right", "0"])
+                .try_into()
+                .unwrap();
+            cmd.execute_async(&b).await
+        });
+        while backend.blocked_clients("q") == 0 {
+            tokio::task::yield_now().await;
+        }
+        let cmd: ListPush = args(&["rpush", "q", "job"]).try_into()?;
+        cmd.execute(&backend);
+        assert_eq!(handle.await?, BulkString::from("job").into());
+        assert_eq!(backend.lrange("done", 0, -1), values(&["job"]));
+
+        // with data already there BRPOP replies right away
+        let cmd: crate::cmd::Command = args(&["brpop", "missing", "done", "0"]).try_into()?;
+        assert_eq!(
+            cmd.execute_async(&backend).await,
+            RespArray::new(values(&["done", "job"])).into()
+        );
+
+        Ok(())
+    }
+}
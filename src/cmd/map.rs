@@ -53,7 +53,7 @@ impl TryFrom<RespArray> for Set {
 
 #[cfg(test)]
 mod tests {
-    use crate::RespDecode;
+    use crate::{KeyType, ListEnd, RespDecode};
     use anyhow::Result;
     use bytes::BytesMut;
 
@@ -101,4 +101,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_replaces_other_types() {
+        let backend = Backend::new();
+        let value = vec![RespFrame::BulkString(b"x".into())];
+        backend.list_push("key".to_string(), ListEnd::Right, value, false);
+        backend.hset(
+            "key".to_string(),
+            "f".to_string(),
+            RespFrame::BulkString(b"v".into()),
+        );
+
+        let cmd = Set {
+            key: "key".to_string(),
+            value: RespFrame::BulkString(b"world".into()),
+        };
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        assert!(backend.lrange("key", 0, -1).is_empty());
+        assert!(backend.hgetall("key").is_none());
+        assert_eq!(backend.key_type("key"), Some(KeyType::String));
+        assert_eq!(
+            backend.get("key"),
+            Some(RespFrame::BulkString(b"world".into()))
+        );
+    }
 }
@@ -1,16 +1,85 @@
+mod bitfield;
+mod bloom;
+mod cms;
+mod cuckoo;
 mod echo;
+mod geo;
+mod hexpire;
 mod hmap;
+mod hyperloglog;
+mod json;
+mod list;
 mod map;
+mod object;
+mod search;
+mod server;
 mod set;
+mod stream;
+mod tdigest;
+mod timeseries;
+mod topk;
+mod zset;
 
-use crate::{Backend, RespArray, RespError, RespFrame, SimpleString};
+use std::time::Duration;
+
+use crate::{Backend, RespArray, RespError, RespFrame, RespNull, SimpleError, SimpleString};
+use bitfield::{BitField, BitFieldRo};
+use bloom::{BfAdd, BfCard, BfExists, BfInfo, BfInsert, BfLoadChunk, BfReserve, BfScanDump};
+use cms::{CmsIncrBy, CmsInfo, CmsInit, CmsMerge, CmsQuery};
+use cuckoo::{
+    CfAdd, CfCount, CfDel, CfExists, CfInfo, CfInsert, CfLoadChunk, CfReserve, CfScanDump,
+};
 use echo::Echo;
 use enum_dispatch::enum_dispatch;
+use geo::{GeoAdd, GeoDist, GeoHash, GeoPos, GeoSearch, GeoSearchStore};
+use hexpire::{HExpire, HGetEx, HPersist, HSetEx, HTtl};
+use hmap::{
+    HDel, HExists, HIncrBy, HIncrByFloat, HKeys, HLen, HRandField, HScan, HSetNx, HStrLen, HVals,
+};
+use hyperloglog::{PfAdd, PfCount, PfMerge};
+use json::{
+    JsonArrAppend, JsonArrInsert, JsonArrPop, JsonDel, JsonGet, JsonMGet, JsonNumIncrBy,
+    JsonObjKeys, JsonSet, JsonStrAppend, JsonType,
+};
 use lazy_static::lazy_static;
+use list::{
+    BLMPop, BLMove, BlockingPop, LIndex, LInsert, LLen, LMPop, LMove, LPos, LRange, LRem, LSet,
+    LTrim, ListPop, ListPush, RPopLPush,
+};
+use object::ObjectEncoding;
+use search::{FtAggregate, FtCreate, FtDropIndex, FtInfo, FtSearch};
+use server::{ConfigGet, ConfigSet, MemoryUsage};
+use set::{
+    SCard, SInterCard, SMIsMember, SMembers, SMove, SPop, SRandMember, SRem, SetAlgebra,
+    SetAlgebraStore,
+};
+use stream::{
+    XAck, XAdd, XAutoClaim, XClaim, XDel, XGroupConsumer, XGroupCreate, XGroupDestroy, XGroupSetId,
+    XInfoConsumers, XInfoGroups, XInfoStream, XLen, XPending, XRange, XRead, XReadGroup, XTrim,
+};
+use tdigest::{
+    TDigestAdd, TDigestCdf, TDigestCreate, TDigestMerge, TDigestMinMax, TDigestQuantile,
+    TDigestRank, TDigestReset, TDigestTrimmedMean,
+};
 use thiserror::Error;
+use timeseries::{
+    TsAdd, TsCreate, TsCreateRule, TsDeleteRule, TsGet, TsIncrBy, TsInfo, TsMAdd, TsMRange, TsRange,
+};
+use topk::{TopKIncrBy, TopKInfo, TopKList, TopKQuery, TopKReserve};
+use zset::{
+    BZMPop, BZPop, ZAdd, ZCard, ZCount, ZIncrBy, ZInterCard, ZLexCount, ZMPop, ZMScore, ZPop,
+    ZRandMember, ZRange, ZRangeStore, ZRank, ZRem, ZRemRange, ZScore, ZSetAlgebra,
+    ZSetAlgebraStore,
+};
+
+// the most members a negative HRANDFIELD/SRANDMEMBER/ZRANDMEMBER count may return
+const RAND_MAX_REPEATS: i64 = 1 << 20;
 
 lazy_static! {
     static ref RESP_OK: RespFrame = SimpleString::new("OK").into();
+    static ref RESP_WRONGTYPE: RespFrame =
+        SimpleError::new("WRONGTYPE Operation against a key holding the wrong kind of value")
+            .into();
 }
 
 #[derive(Error, Debug)]
@@ -32,6 +101,17 @@ pub trait CommandExecutor {
     fn execute(self, backend: &Backend) -> RespFrame;
 }
 
+/// Commands that wait for data to arrive when there is none yet, such as BLPOP. Their
+/// `CommandExecutor::execute` never blocks; `Command::execute_async` waits for them.
+pub trait BlockingCommand {
+    // the keys whose writes may let the command proceed
+    fn keys(&self) -> &[String];
+    // None waits forever
+    fn timeout(&self) -> Option<Duration>;
+    // runs the command if it can be served right now
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame>;
+}
+
 #[enum_dispatch(CommandExecutor)]
 #[derive(Debug)]
 pub enum Command {
@@ -40,10 +120,167 @@ pub enum Command {
     HGet(HGet),
     HSet(HSet),
     HGetAll(HGetAll),
+    HDel(HDel),
+    HExists(HExists),
+    HLen(HLen),
+    HKeys(HKeys),
+    HVals(HVals),
+    HSetNx(HSetNx),
+    HStrLen(HStrLen),
+    HIncrBy(HIncrBy),
+    HIncrByFloat(HIncrByFloat),
+    HExpire(HExpire),
+    HTtl(HTtl),
+    HPersist(HPersist),
+    HGetEx(HGetEx),
+    HSetEx(HSetEx),
+    HScan(HScan),
+    HRandField(HRandField),
     Echo(Echo),
     HMget(HMget),
     SAdd(SAdd),
     Sismember(Sismember),
+    SRem(SRem),
+    SMembers(SMembers),
+    SCard(SCard),
+    SMIsMember(SMIsMember),
+    SPop(SPop),
+    SRandMember(SRandMember),
+    SMove(SMove),
+    SetAlgebra(SetAlgebra),
+    SetAlgebraStore(SetAlgebraStore),
+    SInterCard(SInterCard),
+    BitField(BitField),
+    BitFieldRo(BitFieldRo),
+    ObjectEncoding(ObjectEncoding),
+    ConfigGet(ConfigGet),
+    ConfigSet(ConfigSet),
+    MemoryUsage(MemoryUsage),
+    ListPush(ListPush),
+    ListPop(ListPop),
+    LRange(LRange),
+    LLen(LLen),
+    LIndex(LIndex),
+    LSet(LSet),
+    LInsert(LInsert),
+    LRem(LRem),
+    LTrim(LTrim),
+    LPos(LPos),
+    LMove(LMove),
+    RPopLPush(RPopLPush),
+    LMPop(LMPop),
+    BlockingPop(BlockingPop),
+    BLMove(BLMove),
+    BLMPop(BLMPop),
+    ZAdd(ZAdd),
+    ZRem(ZRem),
+    ZScore(ZScore),
+    ZMScore(ZMScore),
+    ZIncrBy(ZIncrBy),
+    ZCard(ZCard),
+    ZCount(ZCount),
+    ZRank(ZRank),
+    ZRange(ZRange),
+    ZRangeStore(ZRangeStore),
+    ZRemRange(ZRemRange),
+    ZLexCount(ZLexCount),
+    ZSetAlgebra(ZSetAlgebra),
+    ZSetAlgebraStore(ZSetAlgebraStore),
+    ZInterCard(ZInterCard),
+    ZPop(ZPop),
+    ZMPop(ZMPop),
+    BZPop(BZPop),
+    BZMPop(BZMPop),
+    ZRandMember(ZRandMember),
+    XAdd(XAdd),
+    XRange(XRange),
+    XLen(XLen),
+    XDel(XDel),
+    XTrim(XTrim),
+    XRead(XRead),
+    XGroupCreate(XGroupCreate),
+    XGroupSetId(XGroupSetId),
+    XGroupDestroy(XGroupDestroy),
+    XGroupConsumer(XGroupConsumer),
+    XReadGroup(XReadGroup),
+    XAck(XAck),
+    XPending(XPending),
+    XClaim(XClaim),
+    XAutoClaim(XAutoClaim),
+    XInfoStream(XInfoStream),
+    XInfoGroups(XInfoGroups),
+    XInfoConsumers(XInfoConsumers),
+    PfAdd(PfAdd),
+    PfCount(PfCount),
+    PfMerge(PfMerge),
+    GeoAdd(GeoAdd),
+    GeoPos(GeoPos),
+    GeoDist(GeoDist),
+    GeoHash(GeoHash),
+    GeoSearch(GeoSearch),
+    GeoSearchStore(GeoSearchStore),
+    JsonSet(JsonSet),
+    JsonGet(JsonGet),
+    JsonMGet(JsonMGet),
+    JsonDel(JsonDel),
+    JsonType(JsonType),
+    JsonNumIncrBy(JsonNumIncrBy),
+    JsonStrAppend(JsonStrAppend),
+    JsonArrAppend(JsonArrAppend),
+    JsonArrInsert(JsonArrInsert),
+    JsonArrPop(JsonArrPop),
+    JsonObjKeys(JsonObjKeys),
+    TsCreate(TsCreate),
+    TsAdd(TsAdd),
+    TsMAdd(TsMAdd),
+    TsIncrBy(TsIncrBy),
+    TsGet(TsGet),
+    TsInfo(TsInfo),
+    TsRange(TsRange),
+    TsMRange(TsMRange),
+    TsCreateRule(TsCreateRule),
+    TsDeleteRule(TsDeleteRule),
+    BfReserve(BfReserve),
+    BfAdd(BfAdd),
+    BfInsert(BfInsert),
+    BfExists(BfExists),
+    BfCard(BfCard),
+    BfInfo(BfInfo),
+    BfScanDump(BfScanDump),
+    BfLoadChunk(BfLoadChunk),
+    CfReserve(CfReserve),
+    CfAdd(CfAdd),
+    CfInsert(CfInsert),
+    CfExists(CfExists),
+    CfDel(CfDel),
+    CfCount(CfCount),
+    CfInfo(CfInfo),
+    CfScanDump(CfScanDump),
+    CfLoadChunk(CfLoadChunk),
+    CmsInit(CmsInit),
+    CmsIncrBy(CmsIncrBy),
+    CmsQuery(CmsQuery),
+    CmsMerge(CmsMerge),
+    CmsInfo(CmsInfo),
+    TopKReserve(TopKReserve),
+    TopKIncrBy(TopKIncrBy),
+    TopKQuery(TopKQuery),
+    TopKList(TopKList),
+    TopKInfo(TopKInfo),
+    TDigestCreate(TDigestCreate),
+    TDigestAdd(TDigestAdd),
+    TDigestReset(TDigestReset),
+    TDigestQuantile(TDigestQuantile),
+    TDigestCdf(TDigestCdf),
+    TDigestRank(TDigestRank),
+    TDigestTrimmedMean(TDigestTrimmedMean),
+    TDigestMinMax(TDigestMinMax),
+    TDigestMerge(TDigestMerge),
+    FtCreate(FtCreate),
+    FtSearch(FtSearch),
+    FtAggregate(FtAggregate),
+    FtDropIndex(FtDropIndex),
+    FtInfo(FtInfo),
     //unrecognized command
     Unrecognized(Unrecognized),
 }
@@ -68,8 +305,7 @@ pub struct HGet {
 #[derive(Debug)]
 pub struct HSet {
     key: String,
-    field: String,
-    value: RespFrame,
+    fields: Vec<(String, RespFrame)>,
 }
 
 #[derive(Debug)]
@@ -87,7 +323,7 @@ pub struct HMget {
 #[derive(Debug)]
 pub struct SAdd {
     key: String,
-    value: String,
+    members: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -99,6 +335,34 @@ pub struct Sismember {
 #[derive(Debug)]
 pub struct Unrecognized;
 
+impl Command {
+    /// Like `execute`, except that blocking commands wait for their keys to be ready instead of
+    /// replying right away.
+    pub async fn execute_async(self, backend: &Backend) -> RespFrame {
+        match self {
+            Command::BlockingPop(cmd) => execute_blocking(cmd, backend).await,
+            Command::BLMove(cmd) => execute_blocking(cmd, backend).await,
+            Command::BLMPop(cmd) => execute_blocking(cmd, backend).await,
+            Command::BZPop(cmd) => execute_blocking(cmd, backend).await,
+            Command::BZMPop(cmd) => execute_blocking(cmd, backend).await,
+            Command::XRead(mut cmd) if cmd.blocking => {
+                cmd.resolve_last_ids(backend);
+                execute_blocking(cmd, backend).await
+            }
+            Command::XReadGroup(cmd) if cmd.blocking => execute_blocking(cmd, backend).await,
+            cmd => cmd.execute(backend),
+        }
+    }
+}
+
+// replies with null once the timeout elapses
+async fn execute_blocking(cmd: impl BlockingCommand, backend: &Backend) -> RespFrame {
+    backend
+        .block_on(cmd.keys(), cmd.timeout(), || cmd.try_execute(backend))
+        .await
+        .unwrap_or(RespFrame::Null(RespNull))
+}
+
 impl TryFrom<RespFrame> for Command {
     type Error = CommandError;
     fn try_from(value: RespFrame) -> Result<Self, Self::Error> {
@@ -122,9 +386,185 @@ impl TryFrom<RespArray> for Command {
                 b"hset" => Ok(HSet::try_from(v)?.into()),
                 b"hgetall" => Ok(HGetAll::try_from(v)?.into()),
                 b"hmget" => Ok(HMget::try_from(v)?.into()),
+                b"hdel" => Ok(HDel::try_from(v)?.into()),
+                b"hexists" => Ok(HExists::try_from(v)?.into()),
+                b"hlen" => Ok(HLen::try_from(v)?.into()),
+                b"hkeys" => Ok(HKeys::try_from(v)?.into()),
+                b"hvals" => Ok(HVals::try_from(v)?.into()),
+                b"hsetnx" => Ok(HSetNx::try_from(v)?.into()),
+                b"hstrlen" => Ok(HStrLen::try_from(v)?.into()),
+                b"hincrby" => Ok(HIncrBy::try_from(v)?.into()),
+                b"hincrbyfloat" => Ok(HIncrByFloat::try_from(v)?.into()),
+                b"hexpire" | b"hpexpire" | b"hexpireat" | b"hpexpireat" => {
+                    Ok(HExpire::try_from(v)?.into())
+                }
+                b"httl" | b"hpttl" | b"hexpiretime" | b"hpexpiretime" => {
+                    Ok(HTtl::try_from(v)?.into())
+                }
+                b"hpersist" => Ok(HPersist::try_from(v)?.into()),
+                b"hgetex" => Ok(HGetEx::try_from(v)?.into()),
+                b"hsetex" => Ok(HSetEx::try_from(v)?.into()),
+                b"hscan" => Ok(HScan::try_from(v)?.into()),
+                b"hrandfield" => Ok(HRandField::try_from(v)?.into()),
                 b"echo" => Ok(Echo::try_from(v)?.into()),
                 b"sadd" => Ok(SAdd::try_from(v)?.into()),
                 b"sismember" => Ok(Sismember::try_from(v)?.into()),
+                b"srem" => Ok(SRem::try_from(v)?.into()),
+                b"smembers" => Ok(SMembers::try_from(v)?.into()),
+                b"scard" => Ok(SCard::try_from(v)?.into()),
+                b"smismember" => Ok(SMIsMember::try_from(v)?.into()),
+                b"spop" => Ok(SPop::try_from(v)?.into()),
+                b"srandmember" => Ok(SRandMember::try_from(v)?.into()),
+                b"smove" => Ok(SMove::try_from(v)?.into()),
+                b"sinter" | b"sunion" | b"sdiff" => Ok(SetAlgebra::try_from(v)?.into()),
+                b"sinterstore" | b"sunionstore" | b"sdiffstore" => {
+                    Ok(SetAlgebraStore::try_from(v)?.into())
+                }
+                b"sintercard" => Ok(SInterCard::try_from(v)?.into()),
+                b"lpush" | b"rpush" | b"lpushx" | b"rpushx" => Ok(ListPush::try_from(v)?.into()),
+                b"lpop" | b"rpop" => Ok(ListPop::try_from(v)?.into()),
+                b"lrange" => Ok(LRange::try_from(v)?.into()),
+                b"llen" => Ok(LLen::try_from(v)?.into()),
+                b"lindex" => Ok(LIndex::try_from(v)?.into()),
+                b"lset" => Ok(LSet::try_from(v)?.into()),
+                b"linsert" => Ok(LInsert::try_from(v)?.into()),
+                b"lrem" => Ok(LRem::try_from(v)?.into()),
+                b"ltrim" => Ok(LTrim::try_from(v)?.into()),
+                b"lpos" => Ok(LPos::try_from(v)?.into()),
+                b"lmove" => Ok(LMove::try_from(v)?.into()),
+                b"rpoplpush" => Ok(RPopLPush::try_from(v)?.into()),
+                b"lmpop" => Ok(LMPop::try_from(v)?.into()),
+                b"blpop" | b"brpop" => Ok(BlockingPop::try_from(v)?.into()),
+                b"blmove" => Ok(BLMove::try_from(v)?.into()),
+                b"blmpop" => Ok(BLMPop::try_from(v)?.into()),
+                b"zadd" => Ok(ZAdd::try_from(v)?.into()),
+                b"zrem" => Ok(ZRem::try_from(v)?.into()),
+                b"zscore" => Ok(ZScore::try_from(v)?.into()),
+                b"zmscore" => Ok(ZMScore::try_from(v)?.into()),
+                b"zincrby" => Ok(ZIncrBy::try_from(v)?.into()),
+                b"zcard" => Ok(ZCard::try_from(v)?.into()),
+                b"zcount" => Ok(ZCount::try_from(v)?.into()),
+                b"zrank" | b"zrevrank" => Ok(ZRank::try_from(v)?.into()),
+                b"zrange" | b"zrevrange" | b"zrangebyscore" | b"zrevrangebyscore"
+                | b"zrangebylex" | b"zrevrangebylex" => Ok(ZRange::try_from(v)?.into()),
+                b"zrangestore" => Ok(ZRangeStore::try_from(v)?.into()),
+                b"zremrangebyrank" | b"zremrangebyscore" | b"zremrangebylex" => {
+                    Ok(ZRemRange::try_from(v)?.into())
+                }
+                b"zlexcount" => Ok(ZLexCount::try_from(v)?.into()),
+                b"zunion" | b"zinter" | b"zdiff" => Ok(ZSetAlgebra::try_from(v)?.into()),
+                b"zunionstore" | b"zinterstore" | b"zdiffstore" => {
+                    Ok(ZSetAlgebraStore::try_from(v)?.into())
+                }
+                b"zintercard" => Ok(ZInterCard::try_from(v)?.into()),
+                b"zpopmin" | b"zpopmax" => Ok(ZPop::try_from(v)?.into()),
+                b"zmpop" => Ok(ZMPop::try_from(v)?.into()),
+                b"bzpopmin" | b"bzpopmax" => Ok(BZPop::try_from(v)?.into()),
+                b"bzmpop" => Ok(BZMPop::try_from(v)?.into()),
+                b"zrandmember" => Ok(ZRandMember::try_from(v)?.into()),
+                b"xadd" => Ok(XAdd::try_from(v)?.into()),
+                b"xrange" | b"xrevrange" => Ok(XRange::try_from(v)?.into()),
+                b"xlen" => Ok(XLen::try_from(v)?.into()),
+                b"xdel" => Ok(XDel::try_from(v)?.into()),
+                b"xtrim" => Ok(XTrim::try_from(v)?.into()),
+                b"xread" => Ok(XRead::try_from(v)?.into()),
+                b"xgroup" => match subcommand(&v).as_str() {
+                    "create" => Ok(XGroupCreate::try_from(v)?.into()),
+                    "setid" => Ok(XGroupSetId::try_from(v)?.into()),
+                    "destroy" => Ok(XGroupDestroy::try_from(v)?.into()),
+                    _ => Ok(XGroupConsumer::try_from(v)?.into()),
+                },
+                b"xreadgroup" => Ok(XReadGroup::try_from(v)?.into()),
+                b"xack" => Ok(XAck::try_from(v)?.into()),
+                b"xpending" => Ok(XPending::try_from(v)?.into()),
+                b"xclaim" => Ok(XClaim::try_from(v)?.into()),
+                b"xautoclaim" => Ok(XAutoClaim::try_from(v)?.into()),
+                b"xinfo" => match subcommand(&v).as_str() {
+                    "groups" => Ok(XInfoGroups::try_from(v)?.into()),
+                    "consumers" => Ok(XInfoConsumers::try_from(v)?.into()),
+                    _ => Ok(XInfoStream::try_from(v)?.into()),
+                },
+                b"pfadd" => Ok(PfAdd::try_from(v)?.into()),
+                b"pfcount" => Ok(PfCount::try_from(v)?.into()),
+                b"pfmerge" => Ok(PfMerge::try_from(v)?.into()),
+                b"geoadd" => Ok(GeoAdd::try_from(v)?.into()),
+                b"geopos" => Ok(GeoPos::try_from(v)?.into()),
+                b"geodist" => Ok(GeoDist::try_from(v)?.into()),
+                b"geohash" => Ok(GeoHash::try_from(v)?.into()),
+                b"geosearch" => Ok(GeoSearch::try_from(v)?.into()),
+                b"geosearchstore" => Ok(GeoSearchStore::try_from(v)?.into()),
+                b"json.set" => Ok(JsonSet::try_from(v)?.into()),
+                b"json.get" => Ok(JsonGet::try_from(v)?.into()),
+                b"json.mget" => Ok(JsonMGet::try_from(v)?.into()),
+                b"json.del" => Ok(JsonDel::try_from(v)?.into()),
+                b"json.type" => Ok(JsonType::try_from(v)?.into()),
+                b"json.numincrby" => Ok(JsonNumIncrBy::try_from(v)?.into()),
+                b"json.strappend" => Ok(JsonStrAppend::try_from(v)?.into()),
+                b"json.arrappend" => Ok(JsonArrAppend::try_from(v)?.into()),
+                b"json.arrinsert" => Ok(JsonArrInsert::try_from(v)?.into()),
+                b"json.arrpop" => Ok(JsonArrPop::try_from(v)?.into()),
+                b"json.objkeys" => Ok(JsonObjKeys::try_from(v)?.into()),
+                b"ts.create" => Ok(TsCreate::try_from(v)?.into()),
+                b"ts.add" => Ok(TsAdd::try_from(v)?.into()),
+                b"ts.madd" => Ok(TsMAdd::try_from(v)?.into()),
+                b"ts.incrby" | b"ts.decrby" => Ok(TsIncrBy::try_from(v)?.into()),
+                b"ts.get" => Ok(TsGet::try_from(v)?.into()),
+                b"ts.info" => Ok(TsInfo::try_from(v)?.into()),
+                b"ts.range" | b"ts.revrange" => Ok(TsRange::try_from(v)?.into()),
+                b"ts.mrange" | b"ts.mrevrange" => Ok(TsMRange::try_from(v)?.into()),
+                b"ts.createrule" => Ok(TsCreateRule::try_from(v)?.into()),
+                b"ts.deleterule" => Ok(TsDeleteRule::try_from(v)?.into()),
+                b"bf.reserve" => Ok(BfReserve::try_from(v)?.into()),
+                b"bf.add" | b"bf.madd" => Ok(BfAdd::try_from(v)?.into()),
+                b"bf.insert" => Ok(BfInsert::try_from(v)?.into()),
+                b"bf.exists" | b"bf.mexists" => Ok(BfExists::try_from(v)?.into()),
+                b"bf.card" => Ok(BfCard::try_from(v)?.into()),
+                b"bf.info" => Ok(BfInfo::try_from(v)?.into()),
+                b"bf.scandump" => Ok(BfScanDump::try_from(v)?.into()),
+                b"bf.loadchunk" => Ok(BfLoadChunk::try_from(v)?.into()),
+                b"cf.reserve" => Ok(CfReserve::try_from(v)?.into()),
+                b"cf.add" | b"cf.addnx" => Ok(CfAdd::try_from(v)?.into()),
+                b"cf.insert" | b"cf.insertnx" => Ok(CfInsert::try_from(v)?.into()),
+                b"cf.exists" | b"cf.mexists" => Ok(CfExists::try_from(v)?.into()),
+                b"cf.del" => Ok(CfDel::try_from(v)?.into()),
+                b"cf.count" => Ok(CfCount::try_from(v)?.into()),
+                b"cf.info" => Ok(CfInfo::try_from(v)?.into()),
+                b"cf.scandump" => Ok(CfScanDump::try_from(v)?.into()),
+                b"cf.loadchunk" => Ok(CfLoadChunk::try_from(v)?.into()),
+                b"cms.initbydim" | b"cms.initbyprob" => Ok(CmsInit::try_from(v)?.into()),
+                b"cms.incrby" => Ok(CmsIncrBy::try_from(v)?.into()),
+                b"cms.query" => Ok(CmsQuery::try_from(v)?.into()),
+                b"cms.merge" => Ok(CmsMerge::try_from(v)?.into()),
+                b"cms.info" => Ok(CmsInfo::try_from(v)?.into()),
+                b"topk.reserve" => Ok(TopKReserve::try_from(v)?.into()),
+                b"topk.add" | b"topk.incrby" => Ok(TopKIncrBy::try_from(v)?.into()),
+                b"topk.query" => Ok(TopKQuery::try_from(v)?.into()),
+                b"topk.list" => Ok(TopKList::try_from(v)?.into()),
+                b"topk.info" => Ok(TopKInfo::try_from(v)?.into()),
+                b"tdigest.create" => Ok(TDigestCreate::try_from(v)?.into()),
+                b"tdigest.add" => Ok(TDigestAdd::try_from(v)?.into()),
+                b"tdigest.reset" => Ok(TDigestReset::try_from(v)?.into()),
+                b"tdigest.quantile" => Ok(TDigestQuantile::try_from(v)?.into()),
+                b"tdigest.cdf" => Ok(TDigestCdf::try_from(v)?.into()),
+                b"tdigest.rank" | b"tdigest.revrank" => Ok(TDigestRank::try_from(v)?.into()),
+                b"tdigest.trimmed_mean" => Ok(TDigestTrimmedMean::try_from(v)?.into()),
+                b"tdigest.min" | b"tdigest.max" => Ok(TDigestMinMax::try_from(v)?.into()),
+                b"tdigest.merge" => Ok(TDigestMerge::try_from(v)?.into()),
+                b"ft.create" => Ok(FtCreate::try_from(v)?.into()),
+                b"ft.search" => Ok(FtSearch::try_from(v)?.into()),
+                b"ft.aggregate" => Ok(FtAggregate::try_from(v)?.into()),
+                b"ft.dropindex" => Ok(FtDropIndex::try_from(v)?.into()),
+                b"ft.info" => Ok(FtInfo::try_from(v)?.into()),
+                b"bitfield" => Ok(BitField::try_from(v)?.into()),
+                b"bitfield_ro" => Ok(BitFieldRo::try_from(v)?.into()),
+                b"object" => Ok(ObjectEncoding::try_from(v)?.into()),
+                b"config" => match v.get(1) {
+                    Some(RespFrame::BulkString(sub)) if sub.eq_ignore_ascii_case(b"set") => {
+                        Ok(ConfigSet::try_from(v)?.into())
+                    }
+                    _ => Ok(ConfigGet::try_from(v)?.into()),
+                },
+                b"memory" => Ok(MemoryUsage::try_from(v)?.into()),
                 _ => Ok(Unrecognized.into()),
             },
             _ => Err(CommandError::InvalidCommand(
@@ -140,10 +580,125 @@ impl CommandExecutor for Unrecognized {
     }
 }
 
+// the lowercased command name, for structs that serve several commands
+fn command_name(value: &RespArray) -> String {
+    match value.first() {
+        Some(RespFrame::BulkString(cmd)) => String::from_utf8_lossy(cmd).to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+// the lowercased second argument, for commands with subcommands
+fn subcommand(value: &RespArray) -> String {
+    match value.get(1) {
+        Some(RespFrame::BulkString(sub)) => String::from_utf8_lossy(sub).to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
 fn extract_args(value: RespArray, start: usize) -> Result<Vec<RespFrame>, CommandError> {
     Ok(value.0.into_iter().skip(start).collect::<Vec<RespFrame>>())
 }
 
+fn extract_string(arg: Option<RespFrame>) -> Result<String, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(s)) => Ok(String::from_utf8(s.0)?),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid argument".to_string(),
+        )),
+    }
+}
+
+// a bulk string argument as raw bytes, for values that need not be UTF-8
+fn extract_bytes(arg: Option<RespFrame>) -> Result<Vec<u8>, CommandError> {
+    match arg {
+        Some(RespFrame::BulkString(s)) => Ok(s.0),
+        _ => Err(CommandError::InvalidArgument(
+            "Invalid argument".to_string(),
+        )),
+    }
+}
+
+fn extract_i64(arg: Option<RespFrame>) -> Result<i64, CommandError> {
+    extract_string(arg)?.parse().map_err(|_| {
+        CommandError::InvalidArgument("value is not an integer or out of range".to_string())
+    })
+}
+
+fn extract_f64(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    extract_string(arg)?
+        .parse::<f64>()
+        .ok()
+        .filter(|v| !v.is_nan())
+        .ok_or_else(|| CommandError::InvalidArgument("value is not a valid float".to_string()))
+}
+
+// the count of HRANDFIELD, SRANDMEMBER and ZRANDMEMBER; a negative count repeats members, so
+// it is bounded by the size of the reply that would have to be built
+fn extract_rand_count(arg: Option<RespFrame>) -> Result<i64, CommandError> {
+    match extract_i64(arg)? {
+        count if count < -RAND_MAX_REPEATS => Err(CommandError::InvalidArgument(
+            "value is out of range".to_string(),
+        )),
+        count => Ok(count),
+    }
+}
+
+// a blocking timeout in (possibly fractional) seconds, where 0 means forever
+fn extract_timeout(arg: Option<RespFrame>) -> Result<Option<Duration>, CommandError> {
+    let timeout = extract_string(arg)?
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| {
+            CommandError::InvalidArgument("timeout is not a float or out of range".to_string())
+        })?;
+    match timeout {
+        t if t < 0.0 => Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        )),
+        0.0 => Ok(None),
+        t => Duration::try_from_secs_f64(t)
+            .map(Some)
+            .map_err(|_| CommandError::InvalidArgument("timeout is out of range".to_string())),
+    }
+}
+
+// a command as a client would send it, for tests
+#[cfg(test)]
+fn args(parts: &[&str]) -> RespArray {
+    RespArray::new(
+        parts
+            .iter()
+            .map(|p| crate::BulkString::from(*p).into())
+            .collect::<Vec<RespFrame>>(),
+    )
+}
+
+// parses `numkeys key [key ...]` as used by SINTERCARD, LMPOP and friends
+fn extract_numkeys(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<Vec<String>, CommandError> {
+    let numkeys = match extract_i64(args.next())? {
+        n if n > 0 => n as usize,
+        _ => {
+            return Err(CommandError::InvalidArgument(
+                "numkeys should be greater than 0".to_string(),
+            ))
+        }
+    };
+    let keys = args
+        .take(numkeys)
+        .map(|k| extract_string(Some(k)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.len() != numkeys {
+        return Err(CommandError::InvalidArgument(
+            "Number of keys can't be greater than number of args".to_string(),
+        ));
+    }
+    Ok(keys)
+}
+
 pub enum CmpType {
     EQ,
     LEAST,
@@ -157,23 +712,19 @@ fn validate_command(
     args_cmp_type: CmpType,
 ) -> Result<(), CommandError> {
     match args_cmp_type {
-        CmpType::EQ => {
-            if value.len() != n_args + names.len() {
-                return Err(CommandError::InvalidArgument(format!(
-                    "{} command must have exactly {} arguments",
-                    names.join(" "),
-                    n_args
-                )));
-            }
+        CmpType::EQ if value.len() != n_args + names.len() => {
+            return Err(CommandError::InvalidArgument(format!(
+                "{} command must have exactly {} arguments",
+                names.join(" "),
+                n_args
+            )));
         }
-        CmpType::LEAST => {
-            if value.len() < n_args + names.len() {
-                return Err(CommandError::InvalidArgument(format!(
-                    "{} command must have at least {} arguments",
-                    names.join(" "),
-                    n_args
-                )));
-            }
+        CmpType::LEAST if value.len() < n_args + names.len() => {
+            return Err(CommandError::InvalidArgument(format!(
+                "{} command must have at least {} arguments",
+                names.join(" "),
+                n_args
+            )));
         }
         _ => {}
     }
@@ -0,0 +1,84 @@
+use crate::{Backend, BulkString, RespArray, RespFrame, RespNull};
+
+use super::{
+    extract_args, extract_string, validate_command, CmpType, CommandError, CommandExecutor,
+};
+
+#[derive(Debug)]
+pub struct ObjectEncoding {
+    key: String,
+}
+
+impl CommandExecutor for ObjectEncoding {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.object_encoding(&self.key) {
+            Some(encoding) => BulkString::from(encoding).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ObjectEncoding {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["object", "encoding"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(ObjectEncoding {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_object_encoding_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nobject\r\n$8\r\nENCODING\r\n$3\r\nset\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: ObjectEncoding = frame.try_into()?;
+        assert_eq!(result.key, "set");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_object_encoding_command() -> Result<()> {
+        let backend = Backend::new();
+        backend.sadd("set".to_string(), vec!["1".to_string(), "2".to_string()]);
+        backend.set("str".to_string(), BulkString::from("12345").into());
+
+        let cmd = ObjectEncoding {
+            key: "set".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("intset").into());
+
+        backend.sadd("set".to_string(), vec!["a".to_string()]);
+        let cmd = ObjectEncoding {
+            key: "set".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("hashtable").into());
+
+        let cmd = ObjectEncoding {
+            key: "str".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("int").into());
+
+        let cmd = ObjectEncoding {
+            key: "missing".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        Ok(())
+    }
+}
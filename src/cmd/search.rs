@@ -0,0 +1,655 @@
+use std::{iter::Peekable, vec::IntoIter};
+
+use crate::{
+    AggregateQuery, Backend, BulkString, IndexDefinition, Reducer, RespArray, RespFrame, RespMap,
+    RespNull, SearchField, SearchFieldType, SearchOptions, SearchQuery,
+};
+
+use super::{
+    extract_args, extract_i64, extract_string, validate_command, CmpType, CommandError,
+    CommandExecutor, RESP_OK,
+};
+
+type Args = Peekable<IntoIter<RespFrame>>;
+
+#[derive(Debug)]
+pub struct FtCreate {
+    name: String,
+    definition: IndexDefinition,
+}
+
+#[derive(Debug)]
+pub struct FtSearch {
+    name: String,
+    query: SearchQuery,
+    no_content: bool,
+    options: SearchOptions,
+}
+
+#[derive(Debug)]
+pub struct FtAggregate {
+    name: String,
+    query: SearchQuery,
+    aggregate: AggregateQuery,
+}
+
+#[derive(Debug)]
+pub struct FtDropIndex {
+    name: String,
+    delete_docs: bool,
+}
+
+#[derive(Debug)]
+pub struct FtInfo {
+    name: String,
+}
+
+impl CommandExecutor for FtCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ft_create(self.name, self.definition) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for FtSearch {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (total, docs) = match backend.ft_search(&self.name, &self.query, &self.options) {
+            Ok(found) => found,
+            Err(e) => return e.into(),
+        };
+        let mut ret = vec![RespFrame::Integer(total as i64)];
+        for (key, fields) in docs {
+            ret.push(BulkString::from(key).into());
+            if self.no_content {
+                continue;
+            }
+            let mut values = Vec::with_capacity(fields.len() * 2);
+            for (field, value) in fields {
+                values.push(BulkString::from(field).into());
+                values.push(value);
+            }
+            ret.push(RespArray::new(values).into());
+        }
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for FtAggregate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let (total, rows) = match backend.ft_aggregate(&self.name, &self.query, &self.aggregate) {
+            Ok(found) => found,
+            Err(e) => return e.into(),
+        };
+        let mut ret = vec![RespFrame::Integer(total as i64)];
+        for row in rows {
+            let mut values = Vec::with_capacity(row.len() * 2);
+            for (property, value) in row {
+                values.push(BulkString::from(property).into());
+                values.push(match value {
+                    Some(value) => BulkString::from(value).into(),
+                    None => RespFrame::Null(RespNull),
+                });
+            }
+            ret.push(RespArray::new(values).into());
+        }
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for FtDropIndex {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ft_dropindex(&self.name, self.delete_docs) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for FtInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.ft_info(&self.name) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let prefixes = info
+            .definition
+            .prefixes
+            .into_iter()
+            .map(|p| BulkString::from(p).into())
+            .collect::<Vec<RespFrame>>();
+        let definition: Vec<RespFrame> = vec![
+            BulkString::from("key_type").into(),
+            BulkString::from("HASH").into(),
+            BulkString::from("prefixes").into(),
+            RespArray::new(prefixes).into(),
+        ];
+        let attributes = info
+            .definition
+            .fields
+            .into_iter()
+            .map(|field| {
+                let mut attribute: Vec<RespFrame> = vec![
+                    BulkString::from("identifier").into(),
+                    BulkString::from(field.name).into(),
+                    BulkString::from("attribute").into(),
+                    BulkString::from(field.alias).into(),
+                    BulkString::from("type").into(),
+                ];
+                match field.kind {
+                    SearchFieldType::Tag {
+                        separator,
+                        case_sensitive,
+                    } => {
+                        attribute.push(BulkString::from("TAG").into());
+                        attribute.push(BulkString::from("SEPARATOR").into());
+                        attribute.push(BulkString::from(separator.to_string()).into());
+                        if case_sensitive {
+                            attribute.push(BulkString::from("CASESENSITIVE").into());
+                        }
+                    }
+                    SearchFieldType::Numeric => {
+                        attribute.push(BulkString::from("NUMERIC").into());
+                    }
+                }
+                RespArray::new(attribute).into()
+            })
+            .collect::<Vec<RespFrame>>();
+
+        let mut map = RespMap::new();
+        map.insert("index_name".to_string(), BulkString::from(info.name).into());
+        map.insert(
+            "index_definition".to_string(),
+            RespArray::new(definition).into(),
+        );
+        map.insert("attributes".to_string(), RespArray::new(attributes).into());
+        map.insert(
+            "num_docs".to_string(),
+            RespFrame::Integer(info.num_docs as i64),
+        );
+        map.into()
+    }
+}
+
+impl TryFrom<RespArray> for FtCreate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.create"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let name = extract_string(args.next())?;
+        let mut prefixes = Vec::new();
+        loop {
+            match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                "on" => {
+                    if !extract_string(args.next())?.eq_ignore_ascii_case("hash") {
+                        return Err(CommandError::InvalidArgument(
+                            "only HASH indexes are supported".to_string(),
+                        ));
+                    }
+                }
+                "prefix" => {
+                    let count = extract_count(&mut args)?;
+                    for _ in 0..count {
+                        prefixes.push(extract_string(args.next())?);
+                    }
+                }
+                "schema" => break,
+                _ => return Err(syntax_error()),
+            }
+        }
+
+        let mut fields: Vec<SearchField> = Vec::new();
+        while let Some(name) = args.next() {
+            let name = extract_string(Some(name))?;
+            let alias = match next_if_keyword(&mut args, b"as") {
+                true => extract_string(args.next())?,
+                false => name.clone(),
+            };
+            if fields.iter().any(|f| f.alias == alias) {
+                return Err(CommandError::InvalidArgument(format!(
+                    "Duplicate field in schema - {}",
+                    alias
+                )));
+            }
+            let mut kind = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                "tag" => SearchFieldType::Tag {
+                    separator: ',',
+                    case_sensitive: false,
+                },
+                "numeric" => SearchFieldType::Numeric,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "Invalid field type for field `{}`",
+                        name
+                    )))
+                }
+            };
+            loop {
+                if next_if_keyword(&mut args, b"sortable") {
+                    // every numeric field sorts, and tags sort by their raw value
+                    continue;
+                }
+                let SearchFieldType::Tag {
+                    separator,
+                    case_sensitive,
+                } = &mut kind
+                else {
+                    break;
+                };
+                if next_if_keyword(&mut args, b"separator") {
+                    let sep = extract_string(args.next())?;
+                    let mut chars = sep.chars();
+                    *separator = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "Tag separator must be a single character".to_string(),
+                            ))
+                        }
+                    };
+                } else if next_if_keyword(&mut args, b"casesensitive") {
+                    *case_sensitive = true;
+                } else {
+                    break;
+                }
+            }
+            fields.push(SearchField { name, alias, kind });
+        }
+        if fields.is_empty() {
+            return Err(CommandError::InvalidArgument(
+                "Fields arguments are missing".to_string(),
+            ));
+        }
+        Ok(FtCreate {
+            name,
+            definition: IndexDefinition { prefixes, fields },
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtSearch {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.search"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let name = extract_string(args.next())?;
+        let query = extract_query(args.next())?;
+        let (mut no_content, mut options) = (false, SearchOptions::default());
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "nocontent" => no_content = true,
+                "return" => {
+                    let count = extract_count(&mut args)?;
+                    let fields = (0..count)
+                        .map(|_| extract_string(args.next()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    // returning no fields leaves just the keys
+                    no_content |= fields.is_empty();
+                    options.return_fields = Some(fields);
+                }
+                "sortby" => {
+                    let field = extract_string(args.next())?;
+                    let descending = extract_order(&mut args);
+                    options.sort_by = Some((field, descending));
+                }
+                "limit" => (options.offset, options.num) = extract_limit(&mut args)?,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(FtSearch {
+            name,
+            query,
+            no_content,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtAggregate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.aggregate"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let name = extract_string(args.next())?;
+        let query = extract_query(args.next())?;
+        let mut aggregate = AggregateQuery::default();
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "load" => {
+                    let count = extract_count(&mut args)?;
+                    for _ in 0..count {
+                        let property = extract_string(args.next())?;
+                        let property = property.strip_prefix('@').unwrap_or(&property);
+                        aggregate.load.push(property.to_string());
+                    }
+                }
+                "groupby" => {
+                    let count = extract_count(&mut args)?;
+                    let properties = (0..count)
+                        .map(|_| extract_property(args.next()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    aggregate.group_by = Some(properties);
+                    while next_if_keyword(&mut args, b"reduce") {
+                        aggregate.reducers.push(extract_reducer(&mut args)?);
+                    }
+                }
+                "sortby" => {
+                    let count = extract_count(&mut args)?;
+                    let mut sort_args = (0..count)
+                        .map(|_| args.next().ok_or_else(syntax_error))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .into_iter()
+                        .peekable();
+                    while let Some(property) = sort_args.next() {
+                        let property = extract_property(Some(property))?;
+                        let descending = extract_order(&mut sort_args);
+                        aggregate.sort_by.push((property, descending));
+                    }
+                }
+                "limit" => aggregate.limit = Some(extract_limit(&mut args)?),
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(FtAggregate {
+            name,
+            query,
+            aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for FtDropIndex {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.dropindex"], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(syntax_error());
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let name = extract_string(args.next())?;
+        let delete_docs = match args.next() {
+            Some(opt) => match extract_string(Some(opt))?.eq_ignore_ascii_case("dd") {
+                true => true,
+                false => return Err(syntax_error()),
+            },
+            None => false,
+        };
+        Ok(FtDropIndex { name, delete_docs })
+    }
+}
+
+impl TryFrom<RespArray> for FtInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ft.info"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let name = extract_string(args.next())?;
+        Ok(FtInfo { name })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+// consumes the next argument if it is the (lowercase) keyword, in any case
+fn next_if_keyword(args: &mut Args, keyword: &[u8]) -> bool {
+    args.next_if(|arg| matches!(arg, RespFrame::BulkString(s) if s.to_ascii_lowercase() == keyword))
+        .is_some()
+}
+
+// an optional ASC or DESC, returning true for descending
+fn extract_order(args: &mut Args) -> bool {
+    match next_if_keyword(args, b"desc") {
+        true => true,
+        false => {
+            next_if_keyword(args, b"asc");
+            false
+        }
+    }
+}
+
+fn extract_query(arg: Option<RespFrame>) -> Result<SearchQuery, CommandError> {
+    let query = extract_string(arg)?;
+    SearchQuery::parse(&query)
+        .ok_or_else(|| CommandError::InvalidArgument(format!("Syntax error in query `{}`", query)))
+}
+
+// the argument count in front of RETURN, LOAD, GROUPBY and the like
+fn extract_count(args: &mut Args) -> Result<usize, CommandError> {
+    match extract_i64(args.next()) {
+        Ok(count) if count >= 0 && count as usize <= args.len() => Ok(count as usize),
+        _ => Err(CommandError::InvalidArgument(
+            "Bad arguments: invalid argument count".to_string(),
+        )),
+    }
+}
+
+fn extract_limit(args: &mut Args) -> Result<(usize, usize), CommandError> {
+    match (extract_i64(args.next())?, extract_i64(args.next())?) {
+        (offset, num) if offset >= 0 && num >= 0 => Ok((offset as usize, num as usize)),
+        _ => Err(CommandError::InvalidArgument(
+            "LIMIT offset and num must be non-negative".to_string(),
+        )),
+    }
+}
+
+// an aggregation property, which is written `@name`
+fn extract_property(arg: Option<RespFrame>) -> Result<String, CommandError> {
+    let property = extract_string(arg)?;
+    match property.strip_prefix('@') {
+        Some(name) if !name.is_empty() => Ok(name.to_string()),
+        _ => Err(CommandError::InvalidArgument(format!(
+            "Bad arguments for property `{}`: must start with @",
+            property
+        ))),
+    }
+}
+
+// `COUNT 0` or `SUM 1 @property`, either optionally followed by `AS name`
+fn extract_reducer(args: &mut Args) -> Result<(Reducer, String), CommandError> {
+    let function = extract_string(args.next())?.to_ascii_lowercase();
+    let count = extract_count(args)?;
+    let reducer = match (function.as_str(), count) {
+        ("count", 0) => Reducer::Count,
+        ("sum", 1) => Reducer::Sum(extract_property(args.next())?),
+        _ => {
+            return Err(CommandError::InvalidArgument(format!(
+                "Bad arguments for reducer `{}`",
+                function
+            )))
+        }
+    };
+    let name = match next_if_keyword(args, b"as") {
+        true => extract_string(args.next())?,
+        false => match &reducer {
+            Reducer::Count => "__generated_aliascount".to_string(),
+            Reducer::Sum(property) => format!("__generated_aliassum{}", property),
+        },
+    };
+    Ok((reducer, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    fn bulk(s: &str) -> RespFrame {
+        BulkString::from(s).into()
+    }
+
+    fn setup() -> Result<Backend> {
+        let backend = Backend::new();
+        for (key, status, age) in [
+            ("user:1", "active", "30"),
+            ("user:2", "inactive", "41"),
+            ("user:3", "active", "17"),
+            ("user:4", "active", "52"),
+        ] {
+            backend.hset(key.to_string(), "status".to_string(), bulk(status));
+            backend.hset(key.to_string(), "age".to_string(), bulk(age));
+        }
+        let cmd: FtCreate = args(&[
+            "ft.create",
+            "idx",
+            "on",
+            "hash",
+            "prefix",
+            "1",
+            "user:",
+            "schema",
+            "status",
+            "tag",
+            "age",
+            "as",
+            "years",
+            "numeric",
+            "sortable",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        Ok(backend)
+    }
+
+    #[test]
+    fn test_ft_search_command() -> Result<()> {
+        let backend = setup()?;
+        let cmd: FtSearch = args(&[
+            "ft.search",
+            "idx",
+            "@status:{active} @years:[18 65]",
+            "sortby",
+            "years",
+            "desc",
+            "limit",
+            "0",
+            "1",
+            "return",
+            "1",
+            "years",
+        ])
+        .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(2),
+                bulk("user:4"),
+                RespArray::new([bulk("years"), bulk("52")]).into(),
+            ])
+            .into()
+        );
+
+        // the new hash is indexed as it is written
+        backend.hset("user:5".to_string(), "status".to_string(), bulk("ACTIVE"));
+        let cmd: FtSearch =
+            args(&["ft.search", "idx", "-@years:[-inf 100]", "nocontent"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(1), bulk("user:5")]).into()
+        );
+
+        assert!(FtSearch::try_from(args(&["ft.search", "idx", "@status:active"])).is_err());
+        let cmd: FtSearch = args(&["ft.search", "missing", "*"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ft_aggregate_command() -> Result<()> {
+        let backend = setup()?;
+        let cmd: FtAggregate = args(&[
+            "ft.aggregate",
+            "idx",
+            "*",
+            "groupby",
+            "1",
+            "@status",
+            "reduce",
+            "count",
+            "0",
+            "as",
+            "users",
+            "reduce",
+            "sum",
+            "1",
+            "@years",
+            "sortby",
+            "2",
+            "@users",
+            "desc",
+        ])
+        .try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(2),
+                RespArray::new([
+                    bulk("status"),
+                    bulk("active"),
+                    bulk("users"),
+                    bulk("3"),
+                    bulk("__generated_aliassumyears"),
+                    bulk("99"),
+                ])
+                .into(),
+                RespArray::new([
+                    bulk("status"),
+                    bulk("inactive"),
+                    bulk("users"),
+                    bulk("1"),
+                    bulk("__generated_aliassumyears"),
+                    bulk("41"),
+                ])
+                .into(),
+            ])
+            .into()
+        );
+        assert!(FtAggregate::try_from(args(&[
+            "ft.aggregate",
+            "idx",
+            "*",
+            "groupby",
+            "1",
+            "status"
+        ]))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ft_info_and_dropindex_commands() -> Result<()> {
+        let backend = setup()?;
+        let cmd: FtCreate = args(&["ft.create", "idx", "schema", "status", "tag"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::from(crate::SimpleError::new("ERR Index already exists"))
+        );
+        let cmd: FtInfo = args(&["ft.info", "idx"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend) else {
+            panic!("FT.INFO should reply with a map");
+        };
+        assert_eq!(info.get("index_name"), Some(&bulk("idx")));
+        assert_eq!(info.get("num_docs"), Some(&RespFrame::Integer(4)));
+
+        let cmd: FtDropIndex = args(&["ft.dropindex", "idx", "dd"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        assert_eq!(backend.hlen("user:1"), 0);
+        let cmd: FtInfo = args(&["ft.info", "idx"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::from(crate::SimpleError::new("ERR idx: no such index"))
+        );
+        assert!(FtCreate::try_from(args(&["ft.create", "idx", "schema"])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{backend::Config, Backend, BulkString, RespArray, RespFrame, RespNull};
+
+use super::{
+    extract_args, extract_string, hmap::glob_match, validate_command, CmpType, CommandError,
+    CommandExecutor, RESP_OK,
+};
+
+#[derive(Debug)]
+pub struct ConfigGet {
+    patterns: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ConfigSet {
+    params: Vec<(String, String)>,
+}
+
+#[derive(Debug)]
+pub struct MemoryUsage {
+    key: String,
+}
+
+impl CommandExecutor for ConfigGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = Config::NAMES
+            .iter()
+            .filter(|name| {
+                self.patterns
+                    .iter()
+                    .any(|p| glob_match(p.to_ascii_lowercase().as_bytes(), name.as_bytes()))
+            })
+            .flat_map(|name| {
+                let value = backend.config.get(name).unwrap_or_default();
+                [
+                    BulkString::from(*name).into(),
+                    BulkString::from(value).into(),
+                ]
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for ConfigSet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.config.set_all(&self.params) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for MemoryUsage {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.memory_usage(&self.key) {
+            Some(bytes) => RespFrame::Integer(bytes as i64),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl TryFrom<RespArray> for ConfigGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["config", "get"], 1, CmpType::LEAST)?;
+
+        let patterns = extract_args(value, 2)?
+            .into_iter()
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ConfigGet { patterns })
+    }
+}
+
+impl TryFrom<RespArray> for ConfigSet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["config", "set"], 2, CmpType::LEAST)?;
+        if !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "config set command must have parameter value pairs".to_string(),
+            ));
+        }
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        let mut params = Vec::new();
+        while let Some(name) = args.next() {
+            params.push((extract_string(Some(name))?, extract_string(args.next())?));
+        }
+        Ok(ConfigSet { params })
+    }
+}
+
+impl TryFrom<RespArray> for MemoryUsage {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["memory", "usage"], 1, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        let key = extract_string(args.next())?;
+        // SAMPLES only tunes sampling of large values, which the estimate does not do
+        let rest = args
+            .map(|arg| extract_string(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        match rest.as_slice() {
+            [] => {}
+            [opt, n] if opt.eq_ignore_ascii_case("samples") && n.parse::<u64>().is_ok() => {}
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "memory usage command only accepts a SAMPLES option".to_string(),
+                ))
+            }
+        }
+        Ok(MemoryUsage { key })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::cmd::args;
+    use crate::{cmd::HSet, RespDecode};
+
+    use super::*;
+
+    #[test]
+    fn test_config_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*3\r\n$6\r\nCONFIG\r\n$3\r\nGET\r\n$6\r\nhash-*\r\n");
+        let result: ConfigGet = RespArray::decode(&mut buf)?.try_into()?;
+        assert_eq!(result.patterns, vec!["hash-*".to_string()]);
+
+        let result: ConfigSet = args(&["config", "set", "a", "1", "b", "2"]).try_into()?;
+        assert_eq!(result.params.len(), 2);
+        assert!(ConfigSet::try_from(args(&["config", "set", "a", "1", "b"])).is_err());
+
+        assert!(MemoryUsage::try_from(args(&["memory", "usage", "k", "samples", "5"])).is_ok());
+        assert!(MemoryUsage::try_from(args(&["memory", "usage", "k", "samples"])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_set_get_command() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ConfigSet =
+            args(&["config", "set", "hash-max-listpack-entries", "4"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd: ConfigGet = args(&["config", "get", "*entries"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("hash-max-listpack-entries").into(),
+                BulkString::from("4").into(),
+            ])
+            .into()
+        );
+
+        // nothing is applied if any of the parameters is invalid
+        let cmd: ConfigSet = args(&[
+            "config",
+            "set",
+            "hash-max-listpack-value",
+            "8",
+            "hash-max-listpack-entries",
+            "x",
+        ])
+        .try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        assert_eq!(backend.config.hash_max_listpack_value(), 64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_small_hash_uses_less_memory() -> Result<()> {
+        let backend = Backend::new();
+        for key in ["small", "big"] {
+            let cmd: HSet = args(&["hset", key, "name", "alice", "age", "30"]).try_into()?;
+            cmd.execute(&backend);
+        }
+        // a long value pushes "big" to the hashtable encoding
+        backend.hset(
+            "big".to_string(),
+            "bio".to_string(),
+            BulkString::from("x".repeat(65)).into(),
+        );
+        backend.hdel("big", &["bio".to_string()]);
+        assert_eq!(backend.object_encoding("small"), Some("listpack"));
+        assert_eq!(backend.object_encoding("big"), Some("hashtable"));
+
+        let usage = |key: &str| {
+            let cmd = MemoryUsage {
+                key: key.to_string(),
+            };
+            match cmd.execute(&backend) {
+                RespFrame::Integer(n) => n,
+                frame => panic!("unexpected reply {:?}", frame),
+            }
+        };
+        assert!(usage("small") < usage("big"));
+        assert_eq!(
+            MemoryUsage {
+                key: "missing".to_string()
+            }
+            .execute(&backend),
+            RespFrame::Null(RespNull)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_module_types_memory_usage() -> Result<()> {
+        let backend = Backend::new();
+        backend.cms_init("sketch".to_string(), 1000, 5)?;
+        backend.tdigest_create("digest".to_string(), 100.0)?;
+        for key in ["sketch", "digest"] {
+            assert_eq!(backend.object_encoding(key), Some("raw"));
+            let cmd = MemoryUsage {
+                key: key.to_string(),
+            };
+            assert!(matches!(cmd.execute(&backend), RespFrame::Integer(n) if n > 0));
+        }
+        // the counters dominate a sketch's footprint
+        assert!(backend.memory_usage("sketch") > Some(1000 * 5 * 8));
+
+        Ok(())
+    }
+}
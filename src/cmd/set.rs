@@ -1,13 +1,78 @@
-use crate::{RespArray, RespFrame};
+use crate::{BulkString, KeyType, RespArray, RespFrame, RespNull, SetOp};
 
 use super::{
-    extract_args, validate_command, CmpType, CommandError, CommandExecutor, SAdd, Sismember,
+    extract_args, extract_i64, extract_numkeys, extract_rand_count, extract_string,
+    validate_command, CmpType, CommandError, CommandExecutor, SAdd, Sismember, RESP_WRONGTYPE,
 };
 
+#[derive(Debug)]
+pub struct SRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SMembers {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct SMIsMember {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SPop {
+    key: String,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct SRandMember {
+    key: String,
+    count: Option<i64>,
+}
+
+#[derive(Debug)]
+pub struct SMove {
+    source: String,
+    destination: String,
+    member: String,
+}
+
+// SINTER, SUNION and SDIFF
+#[derive(Debug)]
+pub struct SetAlgebra {
+    op: SetOp,
+    keys: Vec<String>,
+}
+
+// SINTERSTORE, SUNIONSTORE and SDIFFSTORE
+#[derive(Debug)]
+pub struct SetAlgebraStore {
+    op: SetOp,
+    destination: String,
+    keys: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct SInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
 impl CommandExecutor for SAdd {
     fn execute(self, backend: &crate::Backend) -> RespFrame {
-        backend.sadd(self.key, self.value);
-        1.into()
+        if backend.holds_other_type(&self.key, KeyType::Set) {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.sadd(self.key, self.members) as i64).into()
     }
 }
 
@@ -21,19 +86,117 @@ impl CommandExecutor for Sismember {
     }
 }
 
+impl CommandExecutor for SRem {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        (backend.srem(&self.key, &self.members) as i64).into()
+    }
+}
+
+impl CommandExecutor for SMembers {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        // no client negotiates RESP3, so members go out as a plain array
+        RespArray::new(bulk_strings(backend.smembers(&self.key))).into()
+    }
+}
+
+impl CommandExecutor for SCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        (backend.scard(&self.key) as i64).into()
+    }
+}
+
+impl CommandExecutor for SMIsMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        let ret = backend
+            .smismember(&self.key, &self.members)
+            .into_iter()
+            .map(|b| (b as i64).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for SPop {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.count {
+            Some(count) => RespArray::new(bulk_strings(backend.spop(&self.key, count))).into(),
+            None => single_or_null(backend.spop(&self.key, 1)),
+        }
+    }
+}
+
+impl CommandExecutor for SRandMember {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        match self.count {
+            Some(count) => {
+                RespArray::new(bulk_strings(backend.srandmember(&self.key, count))).into()
+            }
+            None => single_or_null(backend.srandmember(&self.key, 1)),
+        }
+    }
+}
+
+impl CommandExecutor for SMove {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::Set) {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.smove(&self.source, &self.destination, &self.member) as i64).into()
+    }
+}
+
+impl CommandExecutor for SetAlgebra {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        RespArray::new(bulk_strings(backend.sop(self.op, &self.keys, 0))).into()
+    }
+}
+
+impl CommandExecutor for SetAlgebraStore {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::Set) {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.sopstore(self.op, &self.destination, &self.keys) as i64).into()
+    }
+}
+
+impl CommandExecutor for SInterCard {
+    fn execute(self, backend: &crate::Backend) -> RespFrame {
+        (backend.sop(SetOp::Inter, &self.keys, self.limit).len() as i64).into()
+    }
+}
+
+fn bulk_strings(members: Vec<String>) -> Vec<RespFrame> {
+    members
+        .into_iter()
+        .map(|m| BulkString::from(m).into())
+        .collect()
+}
+
+fn single_or_null(mut members: Vec<String>) -> RespFrame {
+    match members.pop() {
+        Some(m) => BulkString::from(m).into(),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+// parses `key member [member ...]`
+fn parse_key_members(value: RespArray) -> Result<(String, Vec<String>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let members = args
+        .map(|m| extract_string(Some(m)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((key, members))
+}
+
 impl TryFrom<RespArray> for SAdd {
     type Error = CommandError;
     fn try_from(value: RespArray) -> Result<Self, Self::Error> {
-        validate_command(&value, &["sadd"], 2, CmpType::EQ)?;
+        validate_command(&value, &["sadd"], 2, CmpType::LEAST)?;
 
-        let mut args = extract_args(value, 1)?.into_iter();
-        match (args.next(), args.next()) {
-            (Some(RespFrame::BulkString(key)), Some(RespFrame::BulkString(value))) => Ok(SAdd {
-                key: String::from_utf8(key.0)?,
-                value: String::from_utf8(value.0)?,
-            }),
-            _ => Err(CommandError::InvalidArgument("Invalid key".to_string())),
-        }
+        let (key, members) = parse_key_members(value)?;
+        Ok(SAdd { key, members })
     }
 }
 
@@ -54,3 +217,368 @@ impl TryFrom<RespArray> for Sismember {
         }
     }
 }
+
+impl TryFrom<RespArray> for SRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["srem"], 2, CmpType::LEAST)?;
+
+        let (key, members) = parse_key_members(value)?;
+        Ok(SRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SMembers {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smembers"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SMembers {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["scard"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SCard {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SMIsMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smismember"], 2, CmpType::LEAST)?;
+
+        let (key, members) = parse_key_members(value)?;
+        Ok(SMIsMember { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for SPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["spop"], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = match args.next() {
+            Some(c) => match extract_i64(Some(c))? {
+                n if n >= 0 => Some(n as usize),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "value is out of range, must be positive".to_string(),
+                    ))
+                }
+            },
+            None => None,
+        };
+        Ok(SPop { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SRandMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["srandmember"], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = args
+            .next()
+            .map(|c| extract_rand_count(Some(c)))
+            .transpose()?;
+        Ok(SRandMember { key, count })
+    }
+}
+
+impl TryFrom<RespArray> for SMove {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["smove"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(SMove {
+            source: extract_string(args.next())?,
+            destination: extract_string(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SetAlgebra {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => match cmd.to_ascii_lowercase().as_slice() {
+                b"sinter" => ("sinter", SetOp::Inter),
+                b"sunion" => ("sunion", SetOp::Union),
+                b"sdiff" => ("sdiff", SetOp::Diff),
+                _ => return Err(CommandError::CommandNotFound),
+            },
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 1, CmpType::LEAST)?;
+
+        let keys = extract_args(value, 1)?
+            .into_iter()
+            .map(|k| extract_string(Some(k)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(SetAlgebra { op, keys })
+    }
+}
+
+impl TryFrom<RespArray> for SetAlgebraStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = match value.first() {
+            Some(RespFrame::BulkString(cmd)) => match cmd.to_ascii_lowercase().as_slice() {
+                b"sinterstore" => ("sinterstore", SetOp::Inter),
+                b"sunionstore" => ("sunionstore", SetOp::Union),
+                b"sdiffstore" => ("sdiffstore", SetOp::Diff),
+                _ => return Err(CommandError::CommandNotFound),
+            },
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let (destination, keys) = parse_key_members(value)?;
+        Ok(SetAlgebraStore {
+            op,
+            destination,
+            keys,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for SInterCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["sintercard"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let keys = extract_numkeys(&mut args)?;
+        let limit = match args.next().map(|o| extract_string(Some(o))).transpose()? {
+            Some(opt) if opt.eq_ignore_ascii_case("limit") => match extract_i64(args.next())? {
+                n if n >= 0 => n as usize,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "LIMIT can't be negative".to_string(),
+                    ))
+                }
+            },
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => 0,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(SInterCard { keys, limit })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use bytes::BytesMut;
+
+    use crate::RespDecode;
+
+    use super::*;
+
+    #[test]
+    fn test_sadd_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*4\r\n$4\r\nsadd\r\n$3\r\nset\r\n$1\r\na\r\n$1\r\nb\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: SAdd = frame.try_into()?;
+        assert_eq!(result.key, "set");
+        assert_eq!(result.members, vec!["a".to_string(), "b".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_family_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        let cmd = SAdd {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), 2.into());
+
+        let cmd = SAdd {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "c".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        let cmd = SCard {
+            key: "set".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), 3.into());
+
+        let cmd = SMIsMember {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "z".to_string()],
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([1.into(), 0.into()]).into()
+        );
+
+        let cmd = SRem {
+            key: "set".to_string(),
+            members: vec!["a".to_string(), "z".to_string()],
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        let cmd = SMove {
+            source: "set".to_string(),
+            destination: "other".to_string(),
+            member: "b".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        let cmd = SMembers {
+            key: "set".to_string(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("c").into()]).into()
+        );
+
+        let cmd = SPop {
+            key: "set".to_string(),
+            count: None,
+        };
+        assert_eq!(cmd.execute(&backend), BulkString::from("c").into());
+        assert!(!backend.set.contains_key("set"));
+
+        let cmd = Sismember {
+            key: "set".to_string(),
+            value: "c".to_string(),
+        };
+        assert_eq!(cmd.execute(&backend), 0.into());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_srandmember_counts() -> Result<()> {
+        let backend = crate::Backend::new();
+        backend.sadd("set".to_string(), vec!["a".to_string(), "b".to_string()]);
+
+        let cmd = SRandMember {
+            key: "set".to_string(),
+            count: Some(5),
+        };
+        let RespFrame::Array(ret) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret.len(), 2);
+
+        let cmd = SRandMember {
+            key: "set".to_string(),
+            count: Some(-5),
+        };
+        let RespFrame::Array(ret) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret.len(), 5);
+
+        let cmd = SRandMember {
+            key: "set".to_string(),
+            count: Some(i64::MAX),
+        };
+        let RespFrame::Array(ret) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret.len(), 2);
+        let value = RespArray::new([
+            BulkString::from("srandmember").into(),
+            BulkString::from("set").into(),
+            BulkString::from("-9223372036854775808").into(),
+        ]);
+        assert!(SRandMember::try_from(value).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_spop_huge_count() -> Result<()> {
+        let backend = crate::Backend::new();
+        backend.sadd("set".to_string(), vec!["a".to_string(), "b".to_string()]);
+
+        let cmd = SPop {
+            key: "set".to_string(),
+            count: Some(i64::MAX as usize),
+        };
+        let RespFrame::Array(ret) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(ret.len(), 2);
+        assert!(!backend.set.contains_key("set"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sintercard_from_resp_array() -> Result<()> {
+        let mut buf = BytesMut::new();
+        buf.extend_from_slice(b"*6\r\n$10\r\nsintercard\r\n$1\r\n2\r\n$1\r\na\r\n$1\r\nb\r\n$5\r\nLIMIT\r\n$1\r\n1\r\n");
+
+        let frame = RespArray::decode(&mut buf)?;
+
+        let result: SInterCard = frame.try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(result.limit, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_algebra_commands() -> Result<()> {
+        let backend = crate::Backend::new();
+        backend.sadd("a".to_string(), vec!["1".to_string(), "2".to_string()]);
+        backend.sadd("b".to_string(), vec!["2".to_string(), "3".to_string()]);
+        let keys = vec!["a".to_string(), "b".to_string()];
+
+        let cmd = SetAlgebra {
+            op: SetOp::Inter,
+            keys: keys.clone(),
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("2").into()]).into()
+        );
+
+        let cmd = SetAlgebraStore {
+            op: SetOp::Union,
+            destination: "dst".to_string(),
+            keys: keys.clone(),
+        };
+        assert_eq!(cmd.execute(&backend), 3.into());
+        assert_eq!(backend.scard("dst"), 3);
+
+        let cmd = SInterCard { keys, limit: 0 };
+        assert_eq!(cmd.execute(&backend), 1.into());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1545 @@
+use std::{
+    ops::Bound::{self, Excluded, Included, Unbounded},
+    time::Duration,
+};
+
+use crate::{
+    Backend, BulkString, ClaimOptions, KeyType, PendingSummary, RespArray, RespFrame, RespMap,
+    RespNull, StreamFields, StreamId, StreamTrim, TrimStrategy, XAddId,
+};
+
+use super::{
+    command_name, extract_args, extract_i64, extract_string, subcommand, validate_command,
+    BlockingCommand, CmpType, CommandError, CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct XAdd {
+    key: String,
+    id: XAddId,
+    fields: StreamFields,
+    no_mkstream: bool,
+    trim: Option<StreamTrim>,
+}
+
+// XRANGE and XREVRANGE
+#[derive(Debug)]
+pub struct XRange {
+    key: String,
+    start: Bound<StreamId>,
+    end: Bound<StreamId>,
+    count: Option<usize>,
+    rev: bool,
+}
+
+#[derive(Debug)]
+pub struct XLen {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XDel {
+    key: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XTrim {
+    key: String,
+    trim: StreamTrim,
+}
+
+#[derive(Debug)]
+pub struct XRead {
+    keys: Vec<String>,
+    // None for `$`, the last id of the stream when the command runs
+    ids: Vec<Option<StreamId>>,
+    count: Option<usize>,
+    // whether BLOCK was given; `timeout` is only used then
+    pub(super) blocking: bool,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct XGroupCreate {
+    key: String,
+    group: String,
+    // None for `$`
+    id: Option<StreamId>,
+    mkstream: bool,
+    entries_read: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct XGroupSetId {
+    key: String,
+    group: String,
+    id: Option<StreamId>,
+    entries_read: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct XGroupDestroy {
+    key: String,
+    group: String,
+}
+
+// XGROUP CREATECONSUMER and XGROUP DELCONSUMER
+#[derive(Debug)]
+pub struct XGroupConsumer {
+    key: String,
+    group: String,
+    consumer: String,
+    delete: bool,
+}
+
+#[derive(Debug)]
+pub struct XReadGroup {
+    group: String,
+    consumer: String,
+    keys: Vec<String>,
+    // None for `>`, entries never delivered to the group
+    ids: Vec<Option<StreamId>>,
+    count: Option<usize>,
+    noack: bool,
+    pub(super) blocking: bool,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct XAck {
+    key: String,
+    group: String,
+    ids: Vec<StreamId>,
+}
+
+#[derive(Debug)]
+pub struct XPending {
+    key: String,
+    group: String,
+    // None for the summary form
+    range: Option<PendingRange>,
+}
+
+#[derive(Debug, PartialEq)]
+struct PendingRange {
+    min_idle: u64,
+    start: Bound<StreamId>,
+    end: Bound<StreamId>,
+    count: usize,
+    consumer: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct XClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    ids: Vec<StreamId>,
+    opts: ClaimOptions,
+}
+
+#[derive(Debug)]
+pub struct XAutoClaim {
+    key: String,
+    group: String,
+    consumer: String,
+    min_idle: u64,
+    start: StreamId,
+    count: usize,
+    just_id: bool,
+}
+
+#[derive(Debug)]
+pub struct XInfoStream {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XInfoGroups {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct XInfoConsumers {
+    key: String,
+    group: String,
+}
+
+impl CommandExecutor for XAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Stream) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.xadd(self.key, self.id, self.fields, self.no_mkstream, self.trim) {
+            Ok(Some(id)) => BulkString::from(id.to_string()).into(),
+            Ok(None) => RespFrame::Null(RespNull),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let entries = backend.xrange(&self.key, self.start, self.end, self.count, self.rev);
+        entries_reply(entries)
+    }
+}
+
+impl CommandExecutor for XLen {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.xlen(&self.key) as i64).into()
+    }
+}
+
+impl CommandExecutor for XDel {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.xdel(&self.key, &self.ids) as i64).into()
+    }
+}
+
+impl CommandExecutor for XTrim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.xtrim(&self.key, &self.trim) as i64).into()
+    }
+}
+
+impl CommandExecutor for XRead {
+    fn execute(mut self, backend: &Backend) -> RespFrame {
+        self.resolve_last_ids(backend);
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl XRead {
+    // pins `$` to the streams' current last ids, so a blocked read only sees newer entries
+    pub(super) fn resolve_last_ids(&mut self, backend: &Backend) {
+        for (key, id) in self.keys.iter().zip(self.ids.iter_mut()) {
+            id.get_or_insert_with(|| backend.stream_last_id(key));
+        }
+    }
+}
+
+impl BlockingCommand for XRead {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        let streams = self
+            .keys
+            .iter()
+            .zip(&self.ids)
+            .map(|(key, id)| (key.clone(), id.unwrap_or(StreamId::MAX)))
+            .collect::<Vec<_>>();
+        let ret = backend
+            .xread(&streams, self.count)
+            .into_iter()
+            .map(|(key, entries)| {
+                RespArray::new([BulkString::from(key).into(), entries_reply(entries)]).into()
+            })
+            .collect::<Vec<RespFrame>>();
+        (!ret.is_empty()).then(|| RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for XGroupCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::Stream) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let created = backend.xgroup_create(
+            &self.key,
+            &self.group,
+            self.id,
+            self.mkstream,
+            self.entries_read,
+        );
+        match created {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupSetId {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_setid(&self.key, &self.group, self.id, self.entries_read) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupDestroy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.xgroup_destroy(&self.key, &self.group) {
+            Ok(destroyed) => RespFrame::Integer(destroyed as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XGroupConsumer {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.delete {
+            true => backend.xgroup_delconsumer(&self.key, &self.group, &self.consumer),
+            false => backend
+                .xgroup_createconsumer(&self.key, &self.group, &self.consumer)
+                .map(usize::from),
+        };
+        match ret {
+            Ok(n) => RespFrame::Integer(n as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XReadGroup {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl BlockingCommand for XReadGroup {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    // history reads always reply, even with no entries, so only `>` reads ever wait
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        let mut ret = Vec::new();
+        for (key, id) in self.keys.iter().zip(&self.ids) {
+            let entries = backend.xreadgroup(
+                key,
+                &self.group,
+                &self.consumer,
+                *id,
+                self.count,
+                self.noack,
+            );
+            let entries = match entries {
+                Ok(entries) => entries,
+                Err(e) => return Some(e.into()),
+            };
+            if id.is_none() && entries.is_empty() {
+                continue;
+            }
+            let entries = entries
+                .into_iter()
+                .map(|(id, fields)| match fields {
+                    Some(fields) => entry_reply(id, fields),
+                    None => RespArray::new([id_reply(id), RespFrame::Null(RespNull)]).into(),
+                })
+                .collect::<Vec<RespFrame>>();
+            ret.push(
+                RespArray::new([
+                    BulkString::from(key.as_str()).into(),
+                    RespArray::new(entries).into(),
+                ])
+                .into(),
+            );
+        }
+        (!ret.is_empty()).then(|| RespArray::new(ret).into())
+    }
+}
+
+impl CommandExecutor for XAck {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.xack(&self.key, &self.group, &self.ids) as i64).into()
+    }
+}
+
+impl CommandExecutor for XPending {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(range) = self.range else {
+            return match backend.xpending_summary(&self.key, &self.group) {
+                Ok(summary) => pending_summary_reply(summary),
+                Err(e) => e.into(),
+            };
+        };
+        let pending = backend.xpending(
+            &self.key,
+            &self.group,
+            (range.start, range.end),
+            range.count,
+            range.consumer.as_deref(),
+            range.min_idle,
+        );
+        match pending {
+            Ok(pending) => {
+                let ret = pending
+                    .into_iter()
+                    .map(|p| {
+                        RespArray::new([
+                            id_reply(p.id),
+                            BulkString::from(p.consumer).into(),
+                            RespFrame::Integer(p.idle as i64),
+                            RespFrame::Integer(p.delivery_count as i64),
+                        ])
+                        .into()
+                    })
+                    .collect::<Vec<RespFrame>>();
+                RespArray::new(ret).into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let claimed = backend.xclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            &self.ids,
+            self.opts,
+        );
+        match claimed {
+            Ok(claimed) => claimed_reply(claimed, self.opts.just_id),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XAutoClaim {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let claimed = backend.xautoclaim(
+            &self.key,
+            &self.group,
+            &self.consumer,
+            self.min_idle,
+            self.start,
+            self.count,
+            self.just_id,
+        );
+        match claimed {
+            Ok((next, claimed, deleted)) => {
+                let deleted = deleted.into_iter().map(id_reply).collect::<Vec<_>>();
+                RespArray::new([
+                    id_reply(next),
+                    claimed_reply(claimed, self.just_id),
+                    RespArray::new(deleted).into(),
+                ])
+                .into()
+            }
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for XInfoStream {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.xinfo_stream(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let entry_or_null = |entry: Option<(StreamId, StreamFields)>| match entry {
+            Some((id, fields)) => entry_reply(id, fields),
+            None => RespFrame::Null(RespNull),
+        };
+        let mut map = RespMap::new();
+        map.insert("length".to_string(), (info.length as i64).into());
+        map.insert(
+            "last-generated-id".to_string(),
+            id_reply(info.last_generated_id),
+        );
+        map.insert(
+            "max-deleted-entry-id".to_string(),
+            id_reply(info.max_deleted_entry_id),
+        );
+        map.insert(
+            "entries-added".to_string(),
+            (info.entries_added as i64).into(),
+        );
+        map.insert(
+            "recorded-first-entry-id".to_string(),
+            id_reply(info.recorded_first_entry_id),
+        );
+        map.insert("groups".to_string(), (info.groups as i64).into());
+        map.insert("first-entry".to_string(), entry_or_null(info.first_entry));
+        map.insert("last-entry".to_string(), entry_or_null(info.last_entry));
+        map.into()
+    }
+}
+
+impl CommandExecutor for XInfoGroups {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let groups = match backend.xinfo_groups(&self.key) {
+            Ok(groups) => groups,
+            Err(e) => return e.into(),
+        };
+        let ret = groups
+            .into_iter()
+            .map(|g| {
+                let mut map = RespMap::new();
+                map.insert("name".to_string(), BulkString::from(g.name).into());
+                map.insert("consumers".to_string(), (g.consumers as i64).into());
+                map.insert("pending".to_string(), (g.pending as i64).into());
+                map.insert(
+                    "last-delivered-id".to_string(),
+                    id_reply(g.last_delivered_id),
+                );
+                map.insert("entries-read".to_string(), integer_or_null(g.entries_read));
+                map.insert("lag".to_string(), integer_or_null(g.lag));
+                map.into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for XInfoConsumers {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let consumers = match backend.xinfo_consumers(&self.key, &self.group) {
+            Ok(consumers) => consumers,
+            Err(e) => return e.into(),
+        };
+        let ret = consumers
+            .into_iter()
+            .map(|c| {
+                let mut map = RespMap::new();
+                map.insert("name".to_string(), BulkString::from(c.name).into());
+                map.insert("pending".to_string(), (c.pending as i64).into());
+                map.insert("idle".to_string(), (c.idle as i64).into());
+                // -1 for consumers that never got an entry
+                let inactive = c.inactive.map_or(-1, |ms| ms as i64);
+                map.insert("inactive".to_string(), inactive.into());
+                map.into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+fn id_reply(id: StreamId) -> RespFrame {
+    BulkString::from(id.to_string()).into()
+}
+
+fn integer_or_null(n: Option<u64>) -> RespFrame {
+    match n {
+        Some(n) => RespFrame::Integer(n as i64),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+// claimed entries, or only their ids with JUSTID
+fn claimed_reply(claimed: Vec<(StreamId, StreamFields)>, just_id: bool) -> RespFrame {
+    match just_id {
+        true => {
+            let ids = claimed
+                .into_iter()
+                .map(|(id, _)| id_reply(id))
+                .collect::<Vec<_>>();
+            RespArray::new(ids).into()
+        }
+        false => entries_reply(claimed),
+    }
+}
+
+// [count, smallest id, greatest id, [[consumer, count], ...]], with nulls when nothing is pending
+fn pending_summary_reply(summary: PendingSummary) -> RespFrame {
+    let Some((min, max)) = summary.range else {
+        return RespArray::new([
+            RespFrame::Integer(0),
+            RespFrame::Null(RespNull),
+            RespFrame::Null(RespNull),
+            RespFrame::Null(RespNull),
+        ])
+        .into();
+    };
+    let consumers = summary
+        .consumers
+        .into_iter()
+        .map(|(name, count)| {
+            RespArray::new([
+                BulkString::from(name).into(),
+                BulkString::from(count.to_string()).into(),
+            ])
+            .into()
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new([
+        RespFrame::Integer(summary.count as i64),
+        id_reply(min),
+        id_reply(max),
+        RespArray::new(consumers).into(),
+    ])
+    .into()
+}
+
+// an entry as [id, [field, value, ...]]
+fn entry_reply(id: StreamId, fields: StreamFields) -> RespFrame {
+    let fields = fields
+        .into_iter()
+        .flat_map(|(field, value)| [BulkString::from(field).into(), value])
+        .collect::<Vec<RespFrame>>();
+    RespArray::new([
+        BulkString::from(id.to_string()).into(),
+        RespArray::new(fields).into(),
+    ])
+    .into()
+}
+
+fn entries_reply(entries: Vec<(StreamId, StreamFields)>) -> RespFrame {
+    let entries = entries
+        .into_iter()
+        .map(|(id, fields)| entry_reply(id, fields))
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(entries).into()
+}
+
+fn invalid_id() -> CommandError {
+    CommandError::InvalidArgument(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+// parses `<ms>-<seq>`, or `<ms>` with the sequence number `default_seq`
+fn extract_stream_id(arg: Option<RespFrame>, default_seq: u64) -> Result<StreamId, CommandError> {
+    StreamId::parse(&extract_string(arg)?, default_seq).ok_or_else(invalid_id)
+}
+
+// parses an XRANGE bound: `-`, `+`, an id, or an id prefixed with `(` to exclude it. A bare
+// `<ms>` starts at <ms>-0, or ends at the last possible sequence number if `end`.
+fn parse_range_bound(arg: Option<RespFrame>, end: bool) -> Result<Bound<StreamId>, CommandError> {
+    let s = extract_string(arg)?;
+    let default_seq = if end { u64::MAX } else { 0 };
+    match s.as_str() {
+        "-" if !end => Ok(Unbounded),
+        "+" if end => Ok(Unbounded),
+        "-" => Ok(Included(StreamId::MIN)),
+        "+" => Ok(Included(StreamId::MAX)),
+        _ => match s.strip_prefix('(') {
+            Some(id) => StreamId::parse(id, default_seq)
+                .map(Excluded)
+                .ok_or_else(invalid_id),
+            None => StreamId::parse(&s, default_seq)
+                .map(Included)
+                .ok_or_else(invalid_id),
+        },
+    }
+}
+
+// a BLOCK timeout in milliseconds, where 0 means forever
+fn extract_block_timeout(arg: Option<RespFrame>) -> Result<Option<Duration>, CommandError> {
+    match extract_i64(arg)? {
+        ms if ms < 0 => Err(CommandError::InvalidArgument(
+            "timeout is negative".to_string(),
+        )),
+        0 => Ok(None),
+        ms => Ok(Some(Duration::from_millis(ms as u64))),
+    }
+}
+
+fn extract_count(arg: Option<RespFrame>) -> Result<usize, CommandError> {
+    Ok(extract_i64(arg)?.max(0) as usize)
+}
+
+// parses `[=|~] threshold [LIMIT count]` following MAXLEN or MINID
+fn parse_trim(
+    strategy: &str,
+    args: &mut std::iter::Peekable<impl Iterator<Item = RespFrame>>,
+) -> Result<StreamTrim, CommandError> {
+    let mut threshold = extract_string(args.next())?;
+    let approximate = match threshold.as_str() {
+        "~" | "=" => {
+            let approximate = threshold == "~";
+            threshold = extract_string(args.next())?;
+            approximate
+        }
+        _ => false,
+    };
+    let strategy = match strategy {
+        "maxlen" => match threshold.parse::<i64>() {
+            Ok(n) if n >= 0 => TrimStrategy::MaxLen(n as usize),
+            Ok(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "The MAXLEN argument must be >= 0.".to_string(),
+                ))
+            }
+            Err(_) => {
+                return Err(CommandError::InvalidArgument(
+                    "value is not an integer or out of range".to_string(),
+                ))
+            }
+        },
+        _ => TrimStrategy::MinId(StreamId::parse(&threshold, 0).ok_or_else(invalid_id)?),
+    };
+
+    let is_limit =
+        |f: &RespFrame| matches!(f, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"limit"));
+    // an approximate trim evicts at most 100 nodes by default, LIMIT 0 lifts that
+    let mut limit = approximate.then_some(100 * 100);
+    if args.peek().is_some_and(is_limit) {
+        args.next();
+        if !approximate {
+            return Err(CommandError::InvalidArgument(
+                "syntax error, LIMIT cannot be used without the special ~ option".to_string(),
+            ));
+        }
+        limit = match extract_i64(args.next())? {
+            0 => None,
+            n if n > 0 => Some(n as usize),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "The LIMIT argument must be >= 0.".to_string(),
+                ))
+            }
+        };
+    }
+    Ok(StreamTrim {
+        strategy,
+        approximate,
+        limit,
+    })
+}
+
+impl TryFrom<RespArray> for XAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xadd"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let (mut no_mkstream, mut trim) = (false, None);
+        let id = loop {
+            let arg = extract_string(args.next())?;
+            match arg.to_ascii_lowercase().as_str() {
+                "nomkstream" => no_mkstream = true,
+                opt @ ("maxlen" | "minid") => trim = Some(parse_trim(opt, &mut args)?),
+                "*" => break XAddId::Auto,
+                _ => match arg.strip_suffix("-*") {
+                    Some(ms) => break XAddId::Partial(ms.parse().map_err(|_| invalid_id())?),
+                    None => {
+                        break XAddId::Explicit(StreamId::parse(&arg, 0).ok_or_else(invalid_id)?)
+                    }
+                },
+            }
+        };
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'xadd' command".to_string(),
+            ));
+        }
+        let mut fields = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let (Some(field), Some(value)) = (args.next(), args.next()) {
+            fields.push((extract_string(Some(field))?, value));
+        }
+        Ok(XAdd {
+            key,
+            id,
+            fields,
+            no_mkstream,
+            trim,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, rev) = match command_name(&value).as_str() {
+            "xrange" => ("xrange", false),
+            "xrevrange" => ("xrevrange", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        // XREVRANGE takes the end first
+        let (start, end) = match rev {
+            true => {
+                let end = parse_range_bound(args.next(), true)?;
+                (parse_range_bound(args.next(), false)?, end)
+            }
+            false => {
+                let start = parse_range_bound(args.next(), false)?;
+                (start, parse_range_bound(args.next(), true)?)
+            }
+        };
+        let count = match args.next().map(|o| extract_string(Some(o))).transpose()? {
+            Some(opt) if opt.eq_ignore_ascii_case("count") => Some(extract_count(args.next())?),
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => None,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XRange {
+            key,
+            start,
+            end,
+            count,
+            rev,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XLen {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xlen"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(XLen {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XDel {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xdel"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let ids = args
+            .map(|id| extract_stream_id(Some(id), 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XDel { key, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XTrim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xtrim"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let trim = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+            strategy @ ("maxlen" | "minid") => parse_trim(strategy, &mut args)?,
+            _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(XTrim { key, trim })
+    }
+}
+
+// parses `key [key ...] id [id ...]` following STREAMS, where `$` stands for None
+fn parse_streams(
+    name: &str,
+    args: impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, Vec<String>), CommandError> {
+    let mut args = args
+        .map(|a| extract_string(Some(a)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if args.is_empty() || !args.len().is_multiple_of(2) {
+        return Err(CommandError::InvalidArgument(format!(
+            "Unbalanced '{}' list of streams: for each stream key an ID or '$' must be specified.",
+            name
+        )));
+    }
+    let ids = args.split_off(args.len() / 2);
+    Ok((args, ids))
+}
+
+impl TryFrom<RespArray> for XRead {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xread"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (mut count, mut blocking, mut timeout) = (None, false, None);
+        loop {
+            match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                "count" => count = Some(extract_count(args.next())?).filter(|&c| c > 0),
+                "block" => {
+                    blocking = true;
+                    timeout = extract_block_timeout(args.next())?;
+                }
+                "streams" => break,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        let (keys, ids) = parse_streams("xread", args)?;
+        let ids = ids
+            .into_iter()
+            .map(|id| match id.as_str() {
+                "$" => Ok(None),
+                _ => StreamId::parse(&id, 0).map(Some).ok_or_else(invalid_id),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XRead {
+            keys,
+            ids,
+            count,
+            blocking,
+            timeout,
+        })
+    }
+}
+
+// a consumer group's starting id, where `$` stands for None
+fn extract_group_id(arg: Option<RespFrame>) -> Result<Option<StreamId>, CommandError> {
+    match extract_string(arg)?.as_str() {
+        "$" => Ok(None),
+        id => StreamId::parse(id, 0).map(Some).ok_or_else(invalid_id),
+    }
+}
+
+// parses `[ENTRIESREAD entries-read]`, where -1 means unknown
+fn parse_entries_read(args: impl Iterator<Item = RespFrame>) -> Result<Option<u64>, CommandError> {
+    let mut args = args.peekable();
+    let mut entries_read = None;
+    while let Some(opt) = args.next() {
+        if !extract_string(Some(opt))?.eq_ignore_ascii_case("entriesread") {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        entries_read = match extract_i64(args.next())? {
+            -1 => None,
+            n if n >= 0 => Some(n as u64),
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "value for ENTRIESREAD must be positive or -1".to_string(),
+                ))
+            }
+        };
+    }
+    Ok(entries_read)
+}
+
+fn extract_ms(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    Ok(extract_i64(arg)?.max(0) as u64)
+}
+
+impl TryFrom<RespArray> for XGroupCreate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup", "create"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let id = extract_group_id(args.next())?;
+        let mut args = args.peekable();
+        let is_mkstream = |f: &RespFrame| matches!(f, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"mkstream"));
+        let mkstream = args.next_if(is_mkstream).is_some();
+        let entries_read = parse_entries_read(args)?;
+        Ok(XGroupCreate {
+            key,
+            group,
+            id,
+            mkstream,
+            entries_read,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroupSetId {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup", "setid"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(XGroupSetId {
+            key: extract_string(args.next())?,
+            group: extract_string(args.next())?,
+            id: extract_group_id(args.next())?,
+            entries_read: parse_entries_read(args)?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroupDestroy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xgroup", "destroy"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(XGroupDestroy {
+            key: extract_string(args.next())?,
+            group: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XGroupConsumer {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, delete) = match subcommand(&value).as_str() {
+            "createconsumer" => ("createconsumer", false),
+            "delconsumer" => ("delconsumer", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &["xgroup", name], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(XGroupConsumer {
+            key: extract_string(args.next())?,
+            group: extract_string(args.next())?,
+            consumer: extract_string(args.next())?,
+            delete,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XReadGroup {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xreadgroup"], 6, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        if !extract_string(args.next())?.eq_ignore_ascii_case("group") {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let (mut count, mut noack, mut blocking, mut timeout) = (None, false, false, None);
+        loop {
+            match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                "count" => count = Some(extract_count(args.next())?).filter(|&c| c > 0),
+                "block" => {
+                    blocking = true;
+                    timeout = extract_block_timeout(args.next())?;
+                }
+                "noack" => noack = true,
+                "streams" => break,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        let (keys, ids) = parse_streams("xreadgroup", args)?;
+        let ids = ids
+            .into_iter()
+            .map(|id| match id.as_str() {
+                ">" => Ok(None),
+                "$" => Err(CommandError::InvalidArgument(
+                    "The $ ID is meaningless in the context of XREADGROUP: you want to read the history of this consumer by specifying a proper ID, or use the > ID to get new messages. The $ ID would just return an empty result set.".to_string(),
+                )),
+                _ => StreamId::parse(&id, 0).map(Some).ok_or_else(invalid_id),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XReadGroup {
+            group,
+            consumer,
+            keys,
+            ids,
+            count,
+            noack,
+            blocking,
+            timeout,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAck {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xack"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let ids = args
+            .map(|id| extract_stream_id(Some(id), 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(XAck { key, group, ids })
+    }
+}
+
+impl TryFrom<RespArray> for XPending {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xpending"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        if args.peek().is_none() {
+            return Ok(XPending {
+                key,
+                group,
+                range: None,
+            });
+        }
+        let is_idle = |f: &RespFrame| matches!(f, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"idle"));
+        let min_idle = match args.next_if(is_idle) {
+            Some(_) => extract_ms(args.next())?,
+            None => 0,
+        };
+        let start = parse_range_bound(args.next(), false)?;
+        let end = parse_range_bound(args.next(), true)?;
+        let count = extract_count(args.next())?;
+        let consumer = args.next().map(|c| extract_string(Some(c))).transpose()?;
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        let range = PendingRange {
+            min_idle,
+            start,
+            end,
+            count,
+            consumer,
+        };
+        Ok(XPending {
+            key,
+            group,
+            range: Some(range),
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XClaim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xclaim"], 5, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let min_idle = extract_ms(args.next())?;
+        let (mut ids, mut opts) = (Vec::new(), ClaimOptions::default());
+        // ids come first, the options follow the first argument that isn't one
+        let mut args = args.peekable();
+        while let Some(id) = args.peek() {
+            let id = extract_string(Some(id.clone()))?;
+            match StreamId::parse(&id, 0) {
+                Some(id) => ids.push(id),
+                None if ids.is_empty() => return Err(invalid_id()),
+                None => break,
+            }
+            args.next();
+        }
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "idle" => opts.idle = Some(extract_ms(args.next())?),
+                "time" => opts.time = Some(extract_ms(args.next())?),
+                "retrycount" => opts.retry_count = Some(extract_ms(args.next())?),
+                "force" => opts.force = true,
+                "justid" => opts.just_id = true,
+                "lastid" => opts.last_id = Some(extract_stream_id(args.next(), 0)?),
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            opts,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XAutoClaim {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xautoclaim"], 5, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let group = extract_string(args.next())?;
+        let consumer = extract_string(args.next())?;
+        let min_idle = extract_ms(args.next())?;
+        let start = match parse_range_bound(args.next(), false)? {
+            Included(id) => id,
+            Excluded(id) => id.next().ok_or_else(invalid_id)?,
+            Unbounded => StreamId::MIN,
+        };
+        let (mut count, mut just_id) = (100, false);
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "count" => {
+                    count = match extract_i64(args.next())? {
+                        n if n > 0 && n <= i64::MAX / 10 => n as usize,
+                        _ => {
+                            return Err(CommandError::InvalidArgument(
+                                "COUNT must be > 0".to_string(),
+                            ))
+                        }
+                    }
+                }
+                "justid" => just_id = true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            }
+        }
+        Ok(XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            just_id,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoStream {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xinfo", "stream"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(XInfoStream {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoGroups {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xinfo", "groups"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(XInfoGroups {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for XInfoConsumers {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["xinfo", "consumers"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 2)?.into_iter();
+        Ok(XInfoConsumers {
+            key: extract_string(args.next())?,
+            group: extract_string(args.next())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    fn entry(id: &str, fields: &[&str]) -> RespFrame {
+        let fields = fields
+            .iter()
+            .map(|f| BulkString::from(*f).into())
+            .collect::<Vec<RespFrame>>();
+        RespArray::new([BulkString::from(id).into(), RespArray::new(fields).into()]).into()
+    }
+
+    #[test]
+    fn test_xadd_from_resp_array() -> Result<()> {
+        let result: XAdd = args(&[
+            "xadd",
+            "s",
+            "NOMKSTREAM",
+            "MAXLEN",
+            "~",
+            "1000",
+            "LIMIT",
+            "10",
+            "5-*",
+            "f",
+            "v",
+        ])
+        .try_into()?;
+        assert!(result.no_mkstream);
+        assert_eq!(result.id, XAddId::Partial(5));
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MaxLen(1000),
+            approximate: true,
+            limit: Some(10),
+        };
+        assert_eq!(result.trim, Some(trim));
+        assert_eq!(result.fields.len(), 1);
+
+        let result: XAdd = args(&["xadd", "s", "minid", "3", "7", "f", "v"]).try_into()?;
+        assert_eq!(result.id, XAddId::Explicit(StreamId::new(7, 0)));
+        let trim = StreamTrim {
+            strategy: TrimStrategy::MinId(StreamId::new(3, 0)),
+            approximate: false,
+            limit: None,
+        };
+        assert_eq!(result.trim, Some(trim));
+
+        assert!(XAdd::try_from(args(&["xadd", "s", "*", "f"])).is_err());
+        assert!(XAdd::try_from(args(&["xadd", "s", "1-x", "f", "v"])).is_err());
+        assert!(XAdd::try_from(args(&["xadd", "s", "maxlen", "-1", "*", "f", "v"])).is_err());
+        assert!(XAdd::try_from(args(&[
+            "xadd", "s", "maxlen", "1", "limit", "5", "*", "f", "v"
+        ]))
+        .is_err());
+
+        let result: XRange = args(&["xrevrange", "s", "+", "(5", "COUNT", "2"]).try_into()?;
+        assert_eq!(result.start, Excluded(StreamId::new(5, 0)));
+        assert_eq!(result.end, Unbounded);
+        assert_eq!(result.count, Some(2));
+
+        let result: XRead =
+            args(&["xread", "block", "0", "streams", "a", "b", "$", "1-1"]).try_into()?;
+        assert!(result.blocking);
+        assert_eq!(result.timeout, None);
+        assert_eq!(result.ids, vec![None, Some(StreamId::new(1, 1))]);
+        assert!(XRead::try_from(args(&["xread", "streams", "a", "b", "$"])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_commands() -> Result<()> {
+        let backend = Backend::new();
+        for id in ["1-1", "1-2", "2-0"] {
+            let cmd: XAdd = args(&["xadd", "s", id, "f", id]).try_into()?;
+            assert_eq!(cmd.execute(&backend), BulkString::from(id).into());
+        }
+        let cmd: XAdd = args(&["xadd", "s", "1-5", "f", "v"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        let cmd: XAdd = args(&["xadd", "new", "nomkstream", "*", "f", "v"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: XRange = args(&["xrange", "s", "1", "1"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([entry("1-1", &["f", "1-1"]), entry("1-2", &["f", "1-2"])]).into()
+        );
+        let cmd: XRange = args(&["xrevrange", "s", "+", "-", "count", "1"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([entry("2-0", &["f", "2-0"])]).into()
+        );
+
+        let cmd: XRead = args(&["xread", "streams", "s", "new", "1-1", "0"]).try_into()?;
+        let reply = RespArray::new([
+            BulkString::from("s").into(),
+            RespArray::new([entry("1-2", &["f", "1-2"]), entry("2-0", &["f", "2-0"])]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), RespArray::new([reply.into()]).into());
+        let cmd: XRead = args(&["xread", "streams", "s", "$"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: XDel = args(&["xdel", "s", "1-1", "9"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: XTrim = args(&["xtrim", "s", "maxlen", "=", "1"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: XLen = args(&["xlen", "s"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_consumer_group_commands_from_resp_array() -> Result<()> {
+        let result: XGroupCreate = args(&[
+            "xgroup",
+            "CREATE",
+            "s",
+            "g",
+            "$",
+            "mkstream",
+            "entriesread",
+            "3",
+        ])
+        .try_into()?;
+        assert_eq!((result.id, result.mkstream), (None, true));
+        assert_eq!(result.entries_read, Some(3));
+
+        let result: XReadGroup = args(&[
+            "xreadgroup",
+            "group",
+            "g",
+            "c",
+            "noack",
+            "count",
+            "2",
+            "streams",
+            "a",
+            "b",
+            ">",
+            "0",
+        ])
+        .try_into()?;
+        assert!(result.noack && !result.blocking);
+        assert_eq!(result.ids, vec![None, Some(StreamId::MIN)]);
+        assert!(XReadGroup::try_from(args(&[
+            "xreadgroup",
+            "group",
+            "g",
+            "c",
+            "streams",
+            "a",
+            "$"
+        ]))
+        .is_err());
+
+        let result: XPending =
+            args(&["xpending", "s", "g", "idle", "10", "-", "(5", "3", "c"]).try_into()?;
+        let range = PendingRange {
+            min_idle: 10,
+            start: Unbounded,
+            end: Excluded(StreamId::new(5, u64::MAX)),
+            count: 3,
+            consumer: Some("c".to_string()),
+        };
+        assert_eq!(result.range, Some(range));
+
+        let result: XClaim = args(&[
+            "xclaim",
+            "s",
+            "g",
+            "c",
+            "0",
+            "1-1",
+            "2",
+            "justid",
+            "retrycount",
+            "4",
+        ])
+        .try_into()?;
+        assert_eq!(result.ids, vec![StreamId::new(1, 1), StreamId::new(2, 0)]);
+        assert!(result.opts.just_id);
+        assert_eq!(result.opts.retry_count, Some(4));
+
+        let result: XAutoClaim = args(&["xautoclaim", "s", "g", "c", "0", "(1-1"]).try_into()?;
+        assert_eq!((result.start, result.count), (StreamId::new(1, 2), 100));
+        assert!(
+            XAutoClaim::try_from(args(&["xautoclaim", "s", "g", "c", "0", "-", "count", "0"]))
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_consumer_group_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: crate::cmd::Command = args(&["xgroup", "create", "s", "g", "0"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+        let cmd: crate::cmd::Command =
+            args(&["xgroup", "create", "s", "g", "0", "mkstream"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        for id in ["1-0", "2-0"] {
+            let cmd: XAdd = args(&["xadd", "s", id, "f", id]).try_into()?;
+            cmd.execute(&backend);
+        }
+
+        let cmd: XReadGroup =
+            args(&["xreadgroup", "group", "g", "c", "streams", "s", ">"]).try_into()?;
+        let reply = RespArray::new([
+            BulkString::from("s").into(),
+            RespArray::new([entry("1-0", &["f", "1-0"]), entry("2-0", &["f", "2-0"])]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), RespArray::new([reply.into()]).into());
+        let cmd: XReadGroup =
+            args(&["xreadgroup", "group", "g", "c", "streams", "s", ">"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: XAck = args(&["xack", "s", "g", "1-0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: XPending = args(&["xpending", "s", "g"]).try_into()?;
+        let consumer = RespArray::new([BulkString::from("c").into(), BulkString::from("1").into()]);
+        let summary = RespArray::new([
+            RespFrame::Integer(1),
+            BulkString::from("2-0").into(),
+            BulkString::from("2-0").into(),
+            RespArray::new([consumer.into()]).into(),
+        ]);
+        assert_eq!(cmd.execute(&backend), summary.into());
+
+        let cmd: XClaim = args(&["xclaim", "s", "g", "d", "0", "2-0", "justid"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("2-0").into()]).into()
+        );
+        let cmd: XGroupConsumer = args(&["xgroup", "delconsumer", "s", "g", "d"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd: XInfoGroups = args(&["xinfo", "groups", "s"]).try_into()?;
+        let RespFrame::Array(groups) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        let RespFrame::Map(group) = &groups[0] else {
+            panic!("expected a map");
+        };
+        assert_eq!(group["last-delivered-id"], BulkString::from("2-0").into());
+        assert_eq!(group["lag"], RespFrame::Integer(0));
+        assert_eq!(group["pending"], RespFrame::Integer(0));
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_xread_block() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: crate::cmd::Command =
+            args(&["xread", "block", "10", "streams", "s", "$"]).try_into()?;
+        assert_eq!(cmd.execute_async(&backend).await, RespFrame::Null(RespNull));
+
+        let cmd: XAdd = args(&["xadd", "s", "1-1", "f", "old"]).try_into()?;
+        cmd.execute(&backend);
+        let cmd: crate::cmd::Command =
+            args(&["xread", "block", "0", "streams", "s", "$"]).try_into()?;
+        let b = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_async(&b).await });
+        while backend.blocked_clients("s") == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let cmd: XAdd = args(&["xadd", "s", "2-1", "f", "new"]).try_into()?;
+        cmd.execute(&backend);
+        let reply = RespArray::new([
+            BulkString::from("s").into(),
+            RespArray::new([entry("2-1", &["f", "new"])]).into(),
+        ]);
+        assert_eq!(handle.await?, RespArray::new([reply.into()]).into());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_xreadgroup_block() -> Result<()> {
+        let backend = Backend::new();
+        backend.xgroup_create("s", "g", None, true, None)?;
+        let cmd: crate::cmd::Command = args(&[
+            "xreadgroup",
+            "group",
+            "g",
+            "c",
+            "block",
+            "0",
+            "streams",
+            "s",
+            ">",
+        ])
+        .try_into()?;
+        let b = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_async(&b).await });
+        while backend.blocked_clients("s") == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let cmd: XAdd = args(&["xadd", "s", "1-1", "f", "new"]).try_into()?;
+        cmd.execute(&backend);
+        let reply = RespArray::new([
+            BulkString::from("s").into(),
+            RespArray::new([entry("1-1", &["f", "new"])]).into(),
+        ]);
+        assert_eq!(handle.await?, RespArray::new([reply.into()]).into());
+        assert_eq!(backend.xpending_summary("s", "g")?.count, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,455 @@
+use crate::{Backend, KeyType, RespArray, RespFrame};
+
+use super::{
+    command_name, extract_args, extract_f64, extract_numkeys, extract_string, validate_command,
+    CmpType, CommandError, CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+const TDIGEST_DEFAULT_COMPRESSION: f64 = 100.0;
+
+#[derive(Debug)]
+pub struct TDigestCreate {
+    key: String,
+    compression: f64,
+}
+
+#[derive(Debug)]
+pub struct TDigestAdd {
+    key: String,
+    values: Vec<f64>,
+}
+
+#[derive(Debug)]
+pub struct TDigestReset {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct TDigestQuantile {
+    key: String,
+    quantiles: Vec<f64>,
+}
+
+#[derive(Debug)]
+pub struct TDigestCdf {
+    key: String,
+    values: Vec<f64>,
+}
+
+// TDIGEST.RANK and TDIGEST.REVRANK
+#[derive(Debug)]
+pub struct TDigestRank {
+    key: String,
+    values: Vec<f64>,
+    reverse: bool,
+}
+
+#[derive(Debug)]
+pub struct TDigestTrimmedMean {
+    key: String,
+    low: f64,
+    high: f64,
+}
+
+// TDIGEST.MIN and TDIGEST.MAX
+#[derive(Debug)]
+pub struct TDigestMinMax {
+    key: String,
+    max: bool,
+}
+
+#[derive(Debug)]
+pub struct TDigestMerge {
+    dest: String,
+    sources: Vec<String>,
+    compression: Option<f64>,
+    override_dest: bool,
+}
+
+impl CommandExecutor for TDigestCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::TDigest) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.tdigest_create(self.key, self.compression) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.tdigest_add(&self.key, &self.values) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestReset {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.tdigest_reset(&self.key) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestQuantile {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.tdigest_quantile(&self.key, &self.quantiles) {
+            Ok(values) => doubles_reply(values),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestCdf {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.tdigest_cdf(&self.key, &self.values) {
+            Ok(fractions) => doubles_reply(fractions),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.tdigest_rank(&self.key, &self.values, self.reverse) {
+            Ok(ranks) => RespArray::new(
+                ranks
+                    .into_iter()
+                    .map(RespFrame::Integer)
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestTrimmedMean {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.tdigest_trimmed_mean(&self.key, self.low, self.high) {
+            Ok(mean) => mean.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestMinMax {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = match self.max {
+            true => backend.tdigest_max(&self.key),
+            false => backend.tdigest_min(&self.key),
+        };
+        match ret {
+            Ok(value) => value.into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TDigestMerge {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.dest, KeyType::TDigest) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.tdigest_merge(
+            self.dest,
+            &self.sources,
+            self.compression,
+            self.override_dest,
+        ) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn doubles_reply(values: Vec<f64>) -> RespFrame {
+    RespArray::new(
+        values
+            .into_iter()
+            .map(RespFrame::Double)
+            .collect::<Vec<_>>(),
+    )
+    .into()
+}
+
+impl TryFrom<RespArray> for TDigestCreate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.create"], 1, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let compression = match args.next() {
+            None => TDIGEST_DEFAULT_COMPRESSION,
+            Some(opt) => {
+                if !extract_string(Some(opt))?.eq_ignore_ascii_case("compression") {
+                    return Err(syntax_error());
+                }
+                extract_compression(args.next())?
+            }
+        };
+        if args.next().is_some() {
+            return Err(syntax_error());
+        }
+        Ok(TDigestCreate { key, compression })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.add"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let values = args
+            .map(|arg| match extract_f64(Some(arg)) {
+                Ok(v) if v.is_finite() => Ok(v),
+                _ => Err(CommandError::InvalidArgument(
+                    "T-Digest: error parsing val parameter".to_string(),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TDigestAdd { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestReset {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.reset"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(TDigestReset { key })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestQuantile {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.quantile"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let quantiles = args
+            .map(|arg| extract_fraction(Some(arg), "quantile should be in [0,1]"))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TDigestQuantile { key, quantiles })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestCdf {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.cdf"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let values = args
+            .map(|arg| extract_f64(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TDigestCdf { key, values })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, reverse) = match command_name(&value).as_str() {
+            "tdigest.rank" => ("tdigest.rank", false),
+            "tdigest.revrank" => ("tdigest.revrank", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let values = args
+            .map(|arg| extract_f64(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TDigestRank {
+            key,
+            values,
+            reverse,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestTrimmedMean {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.trimmed_mean"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let msg = "low_cut_percentile and high_cut_percentile should be in [0,1]";
+        let low = extract_fraction(args.next(), msg)?;
+        let high = extract_fraction(args.next(), msg)?;
+        if low >= high {
+            return Err(CommandError::InvalidArgument(
+                "T-Digest: low_cut_percentile should be lower than high_cut_percentile".to_string(),
+            ));
+        }
+        Ok(TDigestTrimmedMean { key, low, high })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestMinMax {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, max) = match command_name(&value).as_str() {
+            "tdigest.min" => ("tdigest.min", false),
+            "tdigest.max" => ("tdigest.max", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(TDigestMinMax { key, max })
+    }
+}
+
+impl TryFrom<RespArray> for TDigestMerge {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["tdigest.merge"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let dest = extract_string(args.next())?;
+        let sources = extract_numkeys(&mut args)?;
+        let (mut compression, mut override_dest) = (None, false);
+        while let Some(opt) = args.next() {
+            match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "compression" => compression = Some(extract_compression(args.next())?),
+                "override" => override_dest = true,
+                _ => return Err(syntax_error()),
+            }
+        }
+        Ok(TDigestMerge {
+            dest,
+            sources,
+            compression,
+            override_dest,
+        })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+fn extract_compression(arg: Option<RespFrame>) -> Result<f64, CommandError> {
+    match extract_f64(arg) {
+        Ok(c) if c > 0.0 && c.is_finite() => Ok(c),
+        _ => Err(CommandError::InvalidArgument(
+            "T-Digest: compression parameter needs to be a positive number".to_string(),
+        )),
+    }
+}
+
+fn extract_fraction(arg: Option<RespFrame>, msg: &str) -> Result<f64, CommandError> {
+    match extract_f64(arg) {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(CommandError::InvalidArgument(format!("T-Digest: {}", msg))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    fn doubles(values: &[f64]) -> RespFrame {
+        doubles_reply(values.to_vec())
+    }
+
+    #[test]
+    fn test_tdigest_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: TDigestCreate = args(&["tdigest.create", "t", "compression", "50"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TDigestMinMax = args(&["tdigest.min", "t"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Double(v) if v.is_nan()));
+
+        let cmd: TDigestAdd =
+            args(&["tdigest.add", "t", "10", "20", "30", "40", "50", "60"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TDigestQuantile = args(&["tdigest.quantile", "t", "0", "0.5", "1"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), doubles(&[10.0, 40.0, 60.0]));
+        let cmd: TDigestCdf = args(&["tdigest.cdf", "t", "0", "35", "100"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), doubles(&[0.0, 0.5, 1.0]));
+        let cmd: TDigestRank = args(&["tdigest.revrank", "t", "10", "70"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(5), RespFrame::Integer(-1)]).into()
+        );
+        let cmd: TDigestTrimmedMean =
+            args(&["tdigest.trimmed_mean", "t", "0.5", "1"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(50.0));
+        let cmd: TDigestMinMax = args(&["tdigest.max", "t"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(60.0));
+
+        let cmd: TDigestReset = args(&["tdigest.reset", "t"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TDigestRank = args(&["tdigest.rank", "t", "10"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(-2)]).into()
+        );
+
+        assert!(TDigestQuantile::try_from(args(&["tdigest.quantile", "t", "1.5"])).is_err());
+        assert!(
+            TDigestTrimmedMean::try_from(args(&["tdigest.trimmed_mean", "t", "0.5", "0.1"]))
+                .is_err()
+        );
+        assert!(TDigestAdd::try_from(args(&["tdigest.add", "t", "inf"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_tdigest_merge_command() -> Result<()> {
+        let backend = Backend::new();
+        for (key, values) in [("a", ["1", "2"]), ("b", ["3", "4"])] {
+            let cmd: TDigestCreate = args(&["tdigest.create", key]).try_into()?;
+            cmd.execute(&backend);
+            let cmd: TDigestAdd = args(&["tdigest.add", key, values[0], values[1]]).try_into()?;
+            cmd.execute(&backend);
+        }
+
+        let cmd: TDigestMerge = args(&["tdigest.merge", "dest", "2", "a", "b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TDigestMerge = args(&["tdigest.merge", "dest", "1", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TDigestRank = args(&["tdigest.rank", "dest", "5"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(6)]).into()
+        );
+
+        let cmd: TDigestMerge = args(&[
+            "tdigest.merge",
+            "dest",
+            "1",
+            "b",
+            "compression",
+            "20",
+            "override",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TDigestMinMax = args(&["tdigest.min", "dest"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(3.0));
+        Ok(())
+    }
+}
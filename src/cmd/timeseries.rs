@@ -0,0 +1,846 @@
+use std::iter::Peekable;
+
+use crate::{
+    now_ms, Backend, BulkString, DuplicatePolicy, KeyType, LabelMatcher, Labels, RespArray,
+    RespFrame, RespMap, RespNull, Sample, TsAggregation, TsAggregator, TsOptions, TsRangeQuery,
+};
+
+use super::{
+    command_name, extract_args, extract_f64, extract_i64, extract_string, validate_command,
+    CmpType, CommandError, CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct TsCreate {
+    key: String,
+    options: TsOptions,
+}
+
+#[derive(Debug)]
+pub struct TsAdd {
+    key: String,
+    ts: u64,
+    value: f64,
+    on_duplicate: Option<DuplicatePolicy>,
+    options: TsOptions,
+}
+
+#[derive(Debug)]
+pub struct TsMAdd {
+    samples: Vec<(String, u64, f64)>,
+}
+
+/// TS.INCRBY and TS.DECRBY.
+#[derive(Debug)]
+pub struct TsIncrBy {
+    key: String,
+    by: f64,
+    // None for the current time
+    ts: Option<u64>,
+    options: TsOptions,
+}
+
+#[derive(Debug)]
+pub struct TsGet {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct TsInfo {
+    key: String,
+}
+
+/// TS.RANGE and TS.REVRANGE.
+#[derive(Debug)]
+pub struct TsRange {
+    key: String,
+    query: TsRangeQuery,
+}
+
+/// TS.MRANGE and TS.MREVRANGE.
+#[derive(Debug)]
+pub struct TsMRange {
+    query: TsRangeQuery,
+    labels: LabelsReply,
+    matchers: Vec<LabelMatcher>,
+}
+
+#[derive(Debug)]
+pub struct TsCreateRule {
+    source: String,
+    dest: String,
+    aggregation: TsAggregation,
+}
+
+#[derive(Debug)]
+pub struct TsDeleteRule {
+    source: String,
+    dest: String,
+}
+
+// which labels TS.MRANGE replies with for each series
+#[derive(Debug, PartialEq)]
+enum LabelsReply {
+    None,
+    All,
+    Selected(Vec<String>),
+}
+
+impl CommandExecutor for TsCreate {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::TimeSeries) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.ts_create(self.key, self.options) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::TimeSeries) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.ts_add(
+            self.key,
+            self.ts,
+            self.value,
+            self.on_duplicate,
+            self.options,
+        ) {
+            Ok(ts) => RespFrame::Integer(ts as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsMAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .ts_madd(self.samples)
+            .into_iter()
+            .map(|added| match added {
+                Ok(ts) => RespFrame::Integer(ts as i64),
+                Err(e) => e.into(),
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for TsIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::TimeSeries) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let ts = self.ts.unwrap_or_else(now_ms);
+        match backend.ts_incrby(self.key, self.by, ts, self.options) {
+            Ok(ts) => RespFrame::Integer(ts as i64),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsGet {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_get(&self.key) {
+            Ok(Some(sample)) => sample_reply(sample),
+            Ok(None) => RespArray::new(Vec::new()).into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.ts_info(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let rules = info
+            .rules
+            .into_iter()
+            .map(|rule| {
+                let aggregation = rule.aggregation;
+                RespArray::new([
+                    BulkString::from(rule.dest).into(),
+                    RespFrame::Integer(aggregation.bucket as i64),
+                    BulkString::from(aggregation.aggregator.as_str()).into(),
+                    RespFrame::Integer(aggregation.align as i64),
+                ])
+                .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        let mut map = RespMap::new();
+        map.insert(
+            "totalSamples".to_string(),
+            RespFrame::Integer(info.total_samples as i64),
+        );
+        map.insert(
+            "firstTimestamp".to_string(),
+            RespFrame::Integer(info.first_timestamp as i64),
+        );
+        map.insert(
+            "lastTimestamp".to_string(),
+            RespFrame::Integer(info.last_timestamp as i64),
+        );
+        map.insert(
+            "retentionTime".to_string(),
+            RespFrame::Integer(info.retention as i64),
+        );
+        map.insert(
+            "duplicatePolicy".to_string(),
+            BulkString::from(info.duplicate_policy.as_str()).into(),
+        );
+        map.insert("labels".to_string(), labels_reply(info.labels));
+        map.insert(
+            "sourceKey".to_string(),
+            match info.source_key {
+                Some(key) => BulkString::from(key).into(),
+                None => RespFrame::Null(RespNull),
+            },
+        );
+        map.insert("rules".to_string(), RespArray::new(rules).into());
+        map.into()
+    }
+}
+
+impl CommandExecutor for TsRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_range(&self.key, &self.query) {
+            Ok(samples) => samples_reply(samples),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsMRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let ret = backend
+            .ts_mrange(&self.matchers, &self.query)
+            .into_iter()
+            .map(|(key, labels, samples)| {
+                let labels = match &self.labels {
+                    LabelsReply::None => RespArray::new(Vec::new()).into(),
+                    LabelsReply::All => labels_reply(labels),
+                    LabelsReply::Selected(selected) => {
+                        let ret = selected.iter().map(|name| {
+                            let value = labels.iter().find(|(l, _)| l == name);
+                            let value = match value {
+                                Some((_, v)) => BulkString::from(v.as_str()).into(),
+                                None => RespFrame::Null(RespNull),
+                            };
+                            RespArray::new([BulkString::from(name.as_str()).into(), value]).into()
+                        });
+                        RespArray::new(ret.collect::<Vec<RespFrame>>()).into()
+                    }
+                };
+                RespArray::new([BulkString::from(key).into(), labels, samples_reply(samples)])
+                    .into()
+            })
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for TsCreateRule {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_createrule(&self.source, &self.dest, self.aggregation) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TsDeleteRule {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.ts_deleterule(&self.source, &self.dest) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+fn sample_reply((ts, value): Sample) -> RespFrame {
+    RespArray::new([RespFrame::Integer(ts as i64), value.into()]).into()
+}
+
+fn samples_reply(samples: Vec<Sample>) -> RespFrame {
+    let ret = samples.into_iter().map(sample_reply);
+    RespArray::new(ret.collect::<Vec<RespFrame>>()).into()
+}
+
+fn labels_reply(labels: Labels) -> RespFrame {
+    let ret = labels.into_iter().map(|(label, value)| {
+        RespArray::new([
+            BulkString::from(label).into(),
+            BulkString::from(value).into(),
+        ])
+        .into()
+    });
+    RespArray::new(ret.collect::<Vec<RespFrame>>()).into()
+}
+
+fn extract_timestamp(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    extract_string(arg)?
+        .parse()
+        .map_err(|_| CommandError::InvalidArgument("TSDB: invalid timestamp".to_string()))
+}
+
+// a range bound, where `-` and `+` are the smallest and largest timestamps
+fn extract_bound(arg: Option<RespFrame>) -> Result<u64, CommandError> {
+    match extract_string(arg)?.as_str() {
+        "-" => Ok(0),
+        "+" => Ok(u64::MAX),
+        ts => ts
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument("TSDB: invalid timestamp".to_string())),
+    }
+}
+
+fn extract_u64(arg: Option<RespFrame>, what: &str) -> Result<u64, CommandError> {
+    u64::try_from(extract_i64(arg)?)
+        .map_err(|_| CommandError::InvalidArgument(format!("TSDB: invalid {}", what)))
+}
+
+fn extract_policy(arg: Option<RespFrame>) -> Result<DuplicatePolicy, CommandError> {
+    DuplicatePolicy::parse(&extract_string(arg)?)
+        .ok_or_else(|| CommandError::InvalidArgument("TSDB: Unknown DUPLICATE_POLICY".to_string()))
+}
+
+// `aggregator bucketDuration`
+fn extract_aggregation(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<TsAggregation, CommandError> {
+    let aggregator = TsAggregator::parse(&extract_string(args.next())?).ok_or_else(|| {
+        CommandError::InvalidArgument("TSDB: Unknown aggregation type".to_string())
+    })?;
+    let bucket = match extract_u64(args.next(), "bucketDuration")? {
+        0 => {
+            return Err(CommandError::InvalidArgument(
+                "TSDB: bucketDuration must be greater than zero".to_string(),
+            ))
+        }
+        bucket => bucket,
+    };
+    Ok(TsAggregation {
+        aggregator,
+        bucket,
+        align: 0,
+    })
+}
+
+// parses an option shared by TS.CREATE, TS.ADD and TS.INCRBY; false if `opt` is not one.
+// LABELS takes the remaining arguments
+fn parse_series_option(
+    opt: &str,
+    args: &mut impl Iterator<Item = RespFrame>,
+    options: &mut TsOptions,
+) -> Result<bool, CommandError> {
+    match opt {
+        "retention" => options.retention = Some(extract_u64(args.next(), "RETENTION")?),
+        "duplicate_policy" => options.duplicate_policy = Some(extract_policy(args.next())?),
+        "labels" => {
+            let mut labels = Vec::new();
+            while let Some(label) = args.next() {
+                labels.push((extract_string(Some(label))?, extract_string(args.next())?));
+            }
+            options.labels = Some(labels);
+        }
+        // samples are not stored in chunks; accepted for compatibility
+        "encoding" | "chunk_size" => {
+            extract_string(args.next())?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("TSDB: wrong parameters".to_string())
+}
+
+// a FILTER expression: `l=v`, `l!=v`, `l=`, `l!=`, `l=(v1,v2)` or `l!=(v1,v2)`
+fn parse_matcher(expr: &str) -> Result<LabelMatcher, CommandError> {
+    let (label, values) = expr.split_once('=').ok_or_else(syntax_error)?;
+    let values = match values.strip_prefix('(').and_then(|v| v.strip_suffix(')')) {
+        Some(list) => list.split(',').map(|v| v.trim().to_string()).collect(),
+        None if values.is_empty() => Vec::new(),
+        None => vec![values.to_string()],
+    };
+    match label.strip_suffix('!') {
+        Some(label) => Ok(LabelMatcher::Ne(label.to_string(), values)),
+        None => Ok(LabelMatcher::Eq(label.to_string(), values)),
+    }
+}
+
+// `fromTimestamp toTimestamp` and the options of TS.RANGE; with `mrange`, also the labels
+// and filters of TS.MRANGE
+fn parse_range(
+    mut args: Peekable<impl Iterator<Item = RespFrame>>,
+    rev: bool,
+    mrange: bool,
+) -> Result<(TsRangeQuery, LabelsReply, Vec<LabelMatcher>), CommandError> {
+    let from = extract_bound(args.next())?;
+    let to = extract_bound(args.next())?;
+    let mut query = TsRangeQuery {
+        from,
+        to,
+        rev,
+        filter_ts: None,
+        filter_value: None,
+        count: None,
+        aggregation: None,
+    };
+    let (mut align, mut labels, mut matchers) = (None, LabelsReply::None, Vec::new());
+    while let Some(opt) = args.next() {
+        match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+            "filter_by_ts" => {
+                let mut timestamps = Vec::new();
+                while let Some(ts) =
+                    args.next_if(|arg| extract_timestamp(Some(arg.clone())).is_ok())
+                {
+                    timestamps.push(extract_timestamp(Some(ts))?);
+                }
+                query.filter_ts = Some(timestamps);
+            }
+            "filter_by_value" => {
+                let min = extract_f64(args.next())?;
+                query.filter_value = Some((min, extract_f64(args.next())?));
+            }
+            "count" => query.count = Some(extract_u64(args.next(), "COUNT")? as usize),
+            "align" => {
+                align = Some(
+                    match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                        "start" | "-" => from,
+                        "end" | "+" => to,
+                        ts => ts.parse().map_err(|_| {
+                            CommandError::InvalidArgument(
+                                "TSDB: unknown ALIGN parameter".to_string(),
+                            )
+                        })?,
+                    },
+                )
+            }
+            "aggregation" => query.aggregation = Some(extract_aggregation(&mut args)?),
+            "withlabels" if mrange && labels == LabelsReply::None => labels = LabelsReply::All,
+            "selected_labels" if mrange && labels == LabelsReply::None => {
+                let mut selected = Vec::new();
+                while let Some(label) = args.next_if(|arg| {
+                    !matches!(arg, RespFrame::BulkString(s) if s.eq_ignore_ascii_case(b"filter"))
+                }) {
+                    selected.push(extract_string(Some(label))?);
+                }
+                labels = LabelsReply::Selected(selected);
+            }
+            "filter" if mrange => {
+                for expr in args.by_ref() {
+                    matchers.push(parse_matcher(&extract_string(Some(expr))?)?);
+                }
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+    match (&mut query.aggregation, align) {
+        (Some(aggregation), Some(align)) => aggregation.align = align,
+        (None, Some(_)) => {
+            return Err(CommandError::InvalidArgument(
+                "TSDB: ALIGN parameter can only be used with AGGREGATION".to_string(),
+            ))
+        }
+        _ => {}
+    }
+    // a series must be selected by a label it has, not only by the ones it lacks
+    let selects = |m: &LabelMatcher| matches!(m, LabelMatcher::Eq(_, values) if !values.is_empty());
+    if mrange && !matchers.iter().any(selects) {
+        return Err(CommandError::InvalidArgument(
+            "TSDB: please provide at least one matcher".to_string(),
+        ));
+    }
+    Ok((query, labels, matchers))
+}
+
+impl TryFrom<RespArray> for TsCreate {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.create"], 1, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut options = TsOptions::default();
+        while let Some(opt) = args.next() {
+            let opt = extract_string(Some(opt))?.to_ascii_lowercase();
+            if !parse_series_option(&opt, &mut args, &mut options)? {
+                return Err(syntax_error());
+            }
+        }
+        Ok(TsCreate { key, options })
+    }
+}
+
+impl TryFrom<RespArray> for TsAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.add"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let ts = match args.next() {
+            Some(RespFrame::BulkString(ts)) if ts.as_slice() == b"*" => now_ms(),
+            ts => extract_timestamp(ts)?,
+        };
+        let value = extract_f64(args.next())?;
+        let (mut on_duplicate, mut options) = (None, TsOptions::default());
+        while let Some(opt) = args.next() {
+            let opt = extract_string(Some(opt))?.to_ascii_lowercase();
+            if opt == "on_duplicate" {
+                on_duplicate = Some(extract_policy(args.next())?);
+            } else if !parse_series_option(&opt, &mut args, &mut options)? {
+                return Err(syntax_error());
+            }
+        }
+        Ok(TsAdd {
+            key,
+            ts,
+            value,
+            on_duplicate,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsMAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.madd"], 3, CmpType::LEAST)?;
+        if !(value.len() - 1).is_multiple_of(3) {
+            return Err(syntax_error());
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let mut samples = Vec::new();
+        while let Some(key) = args.next() {
+            let key = extract_string(Some(key))?;
+            let ts = match args.next() {
+                Some(RespFrame::BulkString(ts)) if ts.as_slice() == b"*" => now_ms(),
+                ts => extract_timestamp(ts)?,
+            };
+            samples.push((key, ts, extract_f64(args.next())?));
+        }
+        Ok(TsMAdd { samples })
+    }
+}
+
+impl TryFrom<RespArray> for TsIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, sign) = match command_name(&value).as_str() {
+            "ts.incrby" => ("ts.incrby", 1.0),
+            "ts.decrby" => ("ts.decrby", -1.0),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let by = sign * extract_f64(args.next())?;
+        let (mut ts, mut options) = (None, TsOptions::default());
+        while let Some(opt) = args.next() {
+            let opt = extract_string(Some(opt))?.to_ascii_lowercase();
+            if opt == "timestamp" {
+                ts = match args.next() {
+                    Some(RespFrame::BulkString(ts)) if ts.as_slice() == b"*" => None,
+                    arg => Some(extract_timestamp(arg)?),
+                };
+            } else if !parse_series_option(&opt, &mut args, &mut options)? {
+                return Err(syntax_error());
+            }
+        }
+        Ok(TsIncrBy {
+            key,
+            by,
+            ts,
+            options,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsGet {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.get"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(TsGet {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.info"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(TsInfo {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, rev) = match command_name(&value).as_str() {
+            "ts.range" => ("ts.range", false),
+            "ts.revrange" => ("ts.revrange", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (query, _, _) = parse_range(args.peekable(), rev, false)?;
+        Ok(TsRange { key, query })
+    }
+}
+
+impl TryFrom<RespArray> for TsMRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, rev) = match command_name(&value).as_str() {
+            "ts.mrange" => ("ts.mrange", false),
+            "ts.mrevrange" => ("ts.mrevrange", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 4, CmpType::LEAST)?;
+
+        let args = extract_args(value, 1)?.into_iter();
+        let (query, labels, matchers) = parse_range(args.peekable(), rev, true)?;
+        Ok(TsMRange {
+            query,
+            labels,
+            matchers,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsCreateRule {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.createrule"], 5, CmpType::LEAST)?;
+        if value.len() > 7 {
+            return Err(syntax_error());
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let source = extract_string(args.next())?;
+        let dest = extract_string(args.next())?;
+        if !extract_string(args.next())?.eq_ignore_ascii_case("aggregation") {
+            return Err(syntax_error());
+        }
+        let mut aggregation = extract_aggregation(&mut args)?;
+        if let Some(align) = args.next() {
+            aggregation.align = extract_timestamp(Some(align))?;
+        }
+        Ok(TsCreateRule {
+            source,
+            dest,
+            aggregation,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for TsDeleteRule {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["ts.deleterule"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let source = extract_string(args.next())?;
+        let dest = extract_string(args.next())?;
+        Ok(TsDeleteRule { source, dest })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    fn samples(samples: &[(i64, f64)]) -> RespFrame {
+        let ret = samples
+            .iter()
+            .map(|(ts, v)| RespArray::new([RespFrame::Integer(*ts), (*v).into()]).into());
+        RespArray::new(ret.collect::<Vec<RespFrame>>()).into()
+    }
+
+    #[test]
+    fn test_ts_from_resp_array() -> Result<()> {
+        let result: TsAdd = args(&[
+            "ts.add",
+            "k",
+            "10",
+            "1.5",
+            "RETENTION",
+            "100",
+            "ON_DUPLICATE",
+            "sum",
+            "LABELS",
+            "a",
+            "1",
+        ])
+        .try_into()?;
+        assert_eq!((result.ts, result.value), (10, 1.5));
+        assert_eq!(result.on_duplicate, Some(DuplicatePolicy::Sum));
+        assert_eq!(result.options.retention, Some(100));
+        assert_eq!(
+            result.options.labels,
+            Some(vec![("a".to_string(), "1".to_string())])
+        );
+
+        let result: TsRange = args(&[
+            "ts.revrange",
+            "k",
+            "-",
+            "+",
+            "FILTER_BY_TS",
+            "1",
+            "2",
+            "COUNT",
+            "3",
+            "ALIGN",
+            "end",
+            "AGGREGATION",
+            "avg",
+            "10",
+        ])
+        .try_into()?;
+        assert!(result.query.rev);
+        assert_eq!(result.query.filter_ts, Some(vec![1, 2]));
+        assert_eq!(result.query.count, Some(3));
+        assert_eq!(
+            result.query.aggregation,
+            Some(TsAggregation {
+                aggregator: TsAggregator::Avg,
+                bucket: 10,
+                align: u64::MAX
+            })
+        );
+
+        let result: TsMRange = args(&[
+            "ts.mrange",
+            "-",
+            "+",
+            "SELECTED_LABELS",
+            "a",
+            "b",
+            "FILTER",
+            "a=(1,2)",
+            "b!=",
+        ])
+        .try_into()?;
+        assert_eq!(
+            result.labels,
+            LabelsReply::Selected(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            result.matchers,
+            vec![
+                LabelMatcher::Eq("a".to_string(), vec!["1".to_string(), "2".to_string()]),
+                LabelMatcher::Ne("b".to_string(), Vec::new()),
+            ]
+        );
+
+        for bad in [
+            &["ts.mrange", "-", "+", "FILTER", "a="][..],
+            &["ts.mrange", "-", "+", "WITHLABELS"],
+            &["ts.range", "k", "-", "+", "ALIGN", "start"],
+            &["ts.range", "k", "-", "+", "AGGREGATION", "median", "10"],
+            &["ts.range", "k", "-", "+", "AGGREGATION", "avg", "0"],
+        ] {
+            let value = args(bad);
+            let result = match bad[0] {
+                "ts.mrange" => TsMRange::try_from(value).map(|_| ()),
+                _ => TsRange::try_from(value).map(|_| ()),
+            };
+            assert!(result.is_err(), "{:?}", bad);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ts_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: TsCreate = args(&["ts.create", "cpu", "LABELS", "host", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TsCreate = args(&["ts.create", "cpu:max", "LABELS", "host", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TsCreateRule = args(&[
+            "ts.createrule",
+            "cpu",
+            "cpu:max",
+            "AGGREGATION",
+            "max",
+            "10",
+        ])
+        .try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd: TsMAdd =
+            args(&["ts.madd", "cpu", "1", "5", "cpu", "2", "7", "mem", "1", "1"]).try_into()?;
+        let RespFrame::Array(added) = cmd.execute(&backend) else {
+            panic!("expected an array");
+        };
+        assert_eq!(added[..2], [RespFrame::Integer(1), RespFrame::Integer(2)]);
+        assert!(matches!(added[2], RespFrame::Error(_)));
+        let cmd: TsIncrBy = args(&["ts.incrby", "cpu", "3", "TIMESTAMP", "11"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(11));
+        let cmd: TsGet = args(&["ts.get", "cpu"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(11), 10.0.into()]).into()
+        );
+
+        let cmd: TsRange =
+            args(&["ts.range", "cpu", "0", "20", "AGGREGATION", "sum", "10"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), samples(&[(0, 12.0), (10, 10.0)]));
+        let cmd: TsRange = args(&["ts.range", "cpu:max", "-", "+"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), samples(&[(0, 7.0)]));
+
+        let cmd: TsMRange =
+            args(&["ts.mrevrange", "-", "+", "COUNT", "1", "FILTER", "host=a"]).try_into()?;
+        let series = |key: &str, samples| {
+            RespArray::new([
+                BulkString::from(key).into(),
+                RespArray::new(Vec::new()).into(),
+                samples,
+            ])
+            .into()
+        };
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                series("cpu", self::samples(&[(11, 10.0)])),
+                series("cpu:max", self::samples(&[(0, 7.0)])),
+            ])
+            .into()
+        );
+
+        let cmd: TsDeleteRule = args(&["ts.deleterule", "cpu", "cpu:max"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let cmd: TsInfo = args(&["ts.info", "cpu:max"]).try_into()?;
+        let RespFrame::Map(info) = cmd.execute(&backend) else {
+            panic!("expected a map");
+        };
+        assert_eq!(info.get("sourceKey"), Some(&RespFrame::Null(RespNull)));
+        assert_eq!(info.get("totalSamples"), Some(&RespFrame::Integer(1)));
+        Ok(())
+    }
+}
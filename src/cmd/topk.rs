@@ -0,0 +1,331 @@
+use crate::{Backend, BulkString, KeyType, RespArray, RespFrame, RespMap, RespNull, TopKOptions};
+
+use super::{
+    command_name, extract_args, extract_bytes, extract_f64, extract_i64, extract_string,
+    validate_command, CmpType, CommandError, CommandExecutor, RESP_OK, RESP_WRONGTYPE,
+};
+
+// the largest TOPK.INCRBY increment, as each unit may decay a counter
+const TOPK_MAX_INCREMENT: i64 = 100_000;
+
+#[derive(Debug)]
+pub struct TopKReserve {
+    key: String,
+    options: TopKOptions,
+}
+
+// TOPK.ADD and TOPK.INCRBY, where TOPK.ADD counts each item once
+#[derive(Debug)]
+pub struct TopKIncrBy {
+    key: String,
+    items: Vec<(Vec<u8>, u64)>,
+}
+
+#[derive(Debug)]
+pub struct TopKQuery {
+    key: String,
+    items: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct TopKList {
+    key: String,
+    with_count: bool,
+}
+
+#[derive(Debug)]
+pub struct TopKInfo {
+    key: String,
+}
+
+impl CommandExecutor for TopKReserve {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::TopK) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.topk_reserve(self.key, self.options) {
+            Ok(()) => RESP_OK.clone(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TopKIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.topk_incrby(&self.key, &self.items) {
+            Ok(expelled) => RespArray::new(
+                expelled
+                    .into_iter()
+                    .map(|item| match item {
+                        Some(item) => BulkString::new(item).into(),
+                        None => RespFrame::Null(RespNull),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TopKQuery {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.topk_query(&self.key, &self.items) {
+            Ok(found) => RespArray::new(
+                found
+                    .into_iter()
+                    .map(|found| RespFrame::Integer(found as i64))
+                    .collect::<Vec<_>>(),
+            )
+            .into(),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for TopKList {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let list = match backend.topk_list(&self.key) {
+            Ok(list) => list,
+            Err(e) => return e.into(),
+        };
+        let mut ret = Vec::new();
+        for (item, count) in list {
+            ret.push(BulkString::new(item).into());
+            if self.with_count {
+                ret.push(RespFrame::Integer(count as i64));
+            }
+        }
+        RespArray::new(ret).into()
+    }
+}
+
+impl CommandExecutor for TopKInfo {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let info = match backend.topk_info(&self.key) {
+            Ok(info) => info,
+            Err(e) => return e.into(),
+        };
+        let mut map = RespMap::new();
+        map.insert("k".to_string(), RespFrame::Integer(info.k as i64));
+        map.insert("width".to_string(), RespFrame::Integer(info.width as i64));
+        map.insert("depth".to_string(), RespFrame::Integer(info.depth as i64));
+        map.insert("decay".to_string(), info.decay.into());
+        map.into()
+    }
+}
+
+impl TryFrom<RespArray> for TopKReserve {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["topk.reserve"], 2, CmpType::LEAST)?;
+        if value.len() != 3 && value.len() != 6 {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'topk.reserve' command".to_string(),
+            ));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut options = TopKOptions {
+            k: extract_positive(args.next(), "invalid k")?,
+            ..Default::default()
+        };
+        if args.len() > 0 {
+            options.width = extract_positive(args.next(), "invalid width")?;
+            options.depth = extract_positive(args.next(), "invalid depth")?;
+            options.decay = match extract_f64(args.next()) {
+                Ok(decay) if decay > 0.0 && decay <= 1.0 => decay,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "TopK: invalid decay value. must be '<= 1' & '> 0'".to_string(),
+                    ))
+                }
+            };
+        }
+        Ok(TopKReserve { key, options })
+    }
+}
+
+impl TryFrom<RespArray> for TopKIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, incrby) = match command_name(&value).as_str() {
+            "topk.add" => ("topk.add", false),
+            "topk.incrby" => ("topk.incrby", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        match incrby {
+            true => validate_command(&value, &[name], 3, CmpType::LEAST)?,
+            false => validate_command(&value, &[name], 2, CmpType::LEAST)?,
+        }
+        if incrby && !value.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument(
+                "wrong number of arguments for 'topk.incrby' command".to_string(),
+            ));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let mut items = Vec::new();
+        while let Some(item) = args.next() {
+            let item = extract_bytes(Some(item))?;
+            if !incrby {
+                items.push((item, 1));
+                continue;
+            }
+            let incr = match extract_i64(args.next()) {
+                Ok(incr) if (1..=TOPK_MAX_INCREMENT).contains(&incr) => incr as u64,
+                _ => {
+                    return Err(CommandError::InvalidArgument(format!(
+                        "TopK: increment must be an integer between 1 and {}",
+                        TOPK_MAX_INCREMENT
+                    )))
+                }
+            };
+            items.push((item, incr));
+        }
+        Ok(TopKIncrBy { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for TopKQuery {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["topk.query"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let items = args
+            .map(|arg| extract_bytes(Some(arg)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(TopKQuery { key, items })
+    }
+}
+
+impl TryFrom<RespArray> for TopKList {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["topk.list"], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(syntax_error());
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let with_count = match args.next() {
+            Some(opt) => match extract_string(Some(opt))?.eq_ignore_ascii_case("withcount") {
+                true => true,
+                false => return Err(syntax_error()),
+            },
+            None => false,
+        };
+        Ok(TopKList { key, with_count })
+    }
+}
+
+impl TryFrom<RespArray> for TopKInfo {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["topk.info"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        Ok(TopKInfo { key })
+    }
+}
+
+fn syntax_error() -> CommandError {
+    CommandError::InvalidArgument("syntax error".to_string())
+}
+
+fn extract_positive(arg: Option<RespFrame>, msg: &str) -> Result<u64, CommandError> {
+    match extract_i64(arg) {
+        Ok(v) if v > 0 => Ok(v as u64),
+        _ => Err(CommandError::InvalidArgument(format!("TopK: {}", msg))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+    use crate::SimpleError;
+
+    use super::*;
+
+    #[test]
+    fn test_topk_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: TopKReserve = args(&["topk.reserve", "top", "2", "50", "5", "0.9"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+
+        let cmd: TopKIncrBy = args(&["topk.add", "top", "a", "b"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Null(RespNull), RespFrame::Null(RespNull)]).into()
+        );
+        let cmd: TopKIncrBy = args(&["topk.incrby", "top", "c", "10", "a", "3"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            // a tie for the smallest count pushes out the earliest item
+            RespArray::new([BulkString::from("a").into(), BulkString::from("b").into()]).into()
+        );
+        let cmd: TopKQuery = args(&["topk.query", "top", "a", "b", "c"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                RespFrame::Integer(1),
+                RespFrame::Integer(0),
+                RespFrame::Integer(1)
+            ])
+            .into()
+        );
+        let cmd: TopKList = args(&["topk.list", "top", "withcount"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("c").into(),
+                RespFrame::Integer(10),
+                BulkString::from("a").into(),
+                RespFrame::Integer(4),
+            ])
+            .into()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_topk_parse_errors() -> Result<()> {
+        let backend = Backend::new();
+        assert!(TopKReserve::try_from(args(&["topk.reserve", "top", "0"])).is_err());
+        assert!(TopKReserve::try_from(args(&["topk.reserve", "top", "1", "8", "7"])).is_err());
+        assert!(
+            TopKReserve::try_from(args(&["topk.reserve", "top", "1", "8", "7", "1.5"])).is_err()
+        );
+        assert!(TopKIncrBy::try_from(args(&["topk.incrby", "top", "a", "0"])).is_err());
+        assert!(TopKList::try_from(args(&["topk.list", "top", "counts"])).is_err());
+
+        let cmd: TopKInfo = args(&["topk.info", "missing"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        // a huge k costs nothing until items are counted, a huge sketch is refused
+        let cmd: TopKReserve = args(&["topk.reserve", "top", "4611686018427387904"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RESP_OK.clone());
+        let huge = [
+            "topk.reserve",
+            "big",
+            "10",
+            "4294967296",
+            "4294967296",
+            "0.9",
+        ];
+        let cmd: TopKReserve = args(&huge).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespFrame::from(SimpleError::new("ERR sketch would be too large"))
+        );
+        Ok(())
+    }
+}
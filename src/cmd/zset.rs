@@ -0,0 +1,1297 @@
+use std::time::Duration;
+
+use crate::{
+    Aggregate, Backend, BulkString, KeyType, LexBound, RangeBy, RespArray, RespFrame, RespNull,
+    ScoreBound, SetOp, ZAddFlags, ZRangeSpec,
+};
+
+use super::{
+    command_name, extract_args, extract_f64, extract_i64, extract_numkeys, extract_rand_count,
+    extract_string, extract_timeout, validate_command, BlockingCommand, CmpType, CommandError,
+    CommandExecutor, RESP_WRONGTYPE,
+};
+
+#[derive(Debug)]
+pub struct ZAdd {
+    key: String,
+    flags: ZAddFlags,
+    // count changed members rather than only added ones
+    ch: bool,
+    incr: bool,
+    members: Vec<(f64, String)>,
+}
+
+#[derive(Debug)]
+pub struct ZRem {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZScore {
+    key: String,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZMScore {
+    key: String,
+    members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ZIncrBy {
+    key: String,
+    increment: f64,
+    member: String,
+}
+
+#[derive(Debug)]
+pub struct ZCard {
+    key: String,
+}
+
+#[derive(Debug)]
+pub struct ZCount {
+    key: String,
+    min: ScoreBound,
+    max: ScoreBound,
+}
+
+// ZRANK and ZREVRANK
+#[derive(Debug)]
+pub struct ZRank {
+    key: String,
+    member: String,
+    rev: bool,
+    with_score: bool,
+}
+
+// ZRANGE and the legacy ZREVRANGE, ZRANGEBYSCORE, ZREVRANGEBYSCORE, ZRANGEBYLEX and
+// ZREVRANGEBYLEX
+#[derive(Debug)]
+pub struct ZRange {
+    key: String,
+    spec: ZRangeSpec,
+    with_scores: bool,
+}
+
+#[derive(Debug)]
+pub struct ZRangeStore {
+    destination: String,
+    source: String,
+    spec: ZRangeSpec,
+}
+
+// ZREMRANGEBYRANK, ZREMRANGEBYSCORE and ZREMRANGEBYLEX
+#[derive(Debug)]
+pub struct ZRemRange {
+    key: String,
+    by: RangeBy,
+}
+
+#[derive(Debug)]
+pub struct ZLexCount {
+    key: String,
+    min: LexBound,
+    max: LexBound,
+}
+
+// ZUNION, ZINTER and ZDIFF
+#[derive(Debug)]
+pub struct ZSetAlgebra {
+    op: SetOp,
+    keys: Vec<String>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+    with_scores: bool,
+}
+
+// ZUNIONSTORE, ZINTERSTORE and ZDIFFSTORE
+#[derive(Debug)]
+pub struct ZSetAlgebraStore {
+    op: SetOp,
+    destination: String,
+    keys: Vec<String>,
+    weights: Vec<f64>,
+    aggregate: Aggregate,
+}
+
+#[derive(Debug)]
+pub struct ZInterCard {
+    keys: Vec<String>,
+    limit: usize,
+}
+
+// ZPOPMIN and ZPOPMAX
+#[derive(Debug)]
+pub struct ZPop {
+    key: String,
+    max: bool,
+    count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub struct ZMPop {
+    keys: Vec<String>,
+    max: bool,
+    count: usize,
+}
+
+// BZPOPMIN and BZPOPMAX
+#[derive(Debug)]
+pub struct BZPop {
+    keys: Vec<String>,
+    max: bool,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BZMPop {
+    keys: Vec<String>,
+    max: bool,
+    count: usize,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct ZRandMember {
+    key: String,
+    count: Option<i64>,
+    with_scores: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RangeKind {
+    Rank,
+    Score,
+    Lex,
+}
+
+impl CommandExecutor for ZAdd {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::ZSet) {
+            return RESP_WRONGTYPE.clone();
+        }
+        if self.incr {
+            // parsing makes sure INCR comes with exactly one pair
+            let Some((increment, member)) = self.members.into_iter().next() else {
+                return RespFrame::Null(RespNull);
+            };
+            return match backend.zincrby(self.key, member, increment, self.flags) {
+                Ok(Some(score)) => score.into(),
+                Ok(None) => RespFrame::Null(RespNull),
+                Err(e) => e.into(),
+            };
+        }
+        let (added, changed) = backend.zadd(self.key, self.members, self.flags);
+        (if self.ch { changed } else { added } as i64).into()
+    }
+}
+
+impl CommandExecutor for ZRem {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.zrem(&self.key, &self.members) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        score_or_null(backend.zscore(&self.key, &self.member))
+    }
+}
+
+impl CommandExecutor for ZMScore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let scores = backend
+            .zmscore(&self.key, &self.members)
+            .into_iter()
+            .map(score_or_null)
+            .collect::<Vec<RespFrame>>();
+        RespArray::new(scores).into()
+    }
+}
+
+impl CommandExecutor for ZIncrBy {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.key, KeyType::ZSet) {
+            return RESP_WRONGTYPE.clone();
+        }
+        match backend.zincrby(self.key, self.member, self.increment, ZAddFlags::default()) {
+            Ok(score) => score_or_null(score),
+            Err(e) => e.into(),
+        }
+    }
+}
+
+impl CommandExecutor for ZCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.zcard(&self.key) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.zcount(&self.key, self.min, self.max) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZRank {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        match backend.zrank(&self.key, &self.member, self.rev) {
+            Some((rank, score)) if self.with_score => {
+                RespArray::new([(rank as i64).into(), score.into()]).into()
+            }
+            Some((rank, _)) => (rank as i64).into(),
+            None => RespFrame::Null(RespNull),
+        }
+    }
+}
+
+impl CommandExecutor for ZRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        scores_reply(backend.zrange(&self.key, &self.spec), self.with_scores)
+    }
+}
+
+impl CommandExecutor for ZRangeStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::ZSet) {
+            return RESP_WRONGTYPE.clone();
+        }
+        (backend.zrangestore(&self.destination, &self.source, &self.spec) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZRemRange {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.zremrange(&self.key, &self.by) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZLexCount {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.zlexcount(&self.key, &self.min, &self.max) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZSetAlgebra {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let members = backend.zop(self.op, &self.keys, &self.weights, self.aggregate);
+        scores_reply(members, self.with_scores)
+    }
+}
+
+impl CommandExecutor for ZSetAlgebraStore {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        if backend.holds_other_type(&self.destination, KeyType::ZSet) {
+            return RESP_WRONGTYPE.clone();
+        }
+        let len = backend.zopstore(
+            self.op,
+            &self.destination,
+            &self.keys,
+            &self.weights,
+            self.aggregate,
+        );
+        (len as i64).into()
+    }
+}
+
+impl CommandExecutor for ZInterCard {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        (backend.zintercard(&self.keys, self.limit) as i64).into()
+    }
+}
+
+impl CommandExecutor for ZPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let popped = backend.zpop(&self.key, self.max, self.count.unwrap_or(1));
+        match self.count {
+            Some(_) => scores_reply(popped, true),
+            // a single member is replied as a bare pair
+            None => match popped.into_iter().next() {
+                Some(pair) => score_pair(pair),
+                None => RespArray::new([]).into(),
+            },
+        }
+    }
+}
+
+impl CommandExecutor for ZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        zmpop(backend, &self.keys, self.max, self.count).unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for BZPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for BZMPop {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        self.try_execute(backend)
+            .unwrap_or(RespFrame::Null(RespNull))
+    }
+}
+
+impl CommandExecutor for ZRandMember {
+    fn execute(self, backend: &Backend) -> RespFrame {
+        let Some(count) = self.count else {
+            return match backend.zrandmember(&self.key, 1).pop() {
+                Some((member, _)) => BulkString::from(member).into(),
+                None => RespFrame::Null(RespNull),
+            };
+        };
+        scores_reply(backend.zrandmember(&self.key, count), self.with_scores)
+    }
+}
+
+impl BlockingCommand for BZPop {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        let (key, popped) = backend.zmpop(&self.keys, self.max, 1)?;
+        let (member, score) = popped.into_iter().next()?;
+        let reply = [
+            BulkString::from(key).into(),
+            BulkString::from(member).into(),
+            score.into(),
+        ];
+        Some(RespArray::new(reply).into())
+    }
+}
+
+impl BlockingCommand for BZMPop {
+    fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn try_execute(&self, backend: &Backend) -> Option<RespFrame> {
+        zmpop(backend, &self.keys, self.max, self.count)
+    }
+}
+
+fn zmpop(backend: &Backend, keys: &[String], max: bool, count: usize) -> Option<RespFrame> {
+    let (key, popped) = backend.zmpop(keys, max, count)?;
+    Some(RespArray::new([BulkString::from(key).into(), scores_reply(popped, true)]).into())
+}
+
+fn score_pair((member, score): (String, f64)) -> RespFrame {
+    RespArray::new([BulkString::from(member).into(), score.into()]).into()
+}
+
+// members alone, or [member, score] pairs as RESP3 replies them
+fn scores_reply(members: Vec<(String, f64)>, with_scores: bool) -> RespFrame {
+    let ret = members
+        .into_iter()
+        .map(|(member, score)| match with_scores {
+            true => score_pair((member, score)),
+            false => BulkString::from(member).into(),
+        })
+        .collect::<Vec<RespFrame>>();
+    RespArray::new(ret).into()
+}
+
+fn score_or_null(score: Option<f64>) -> RespFrame {
+    match score {
+        Some(score) => score.into(),
+        None => RespFrame::Null(RespNull),
+    }
+}
+
+// parses a ZCOUNT style bound: a score, optionally prefixed with '(' to exclude it
+pub(super) fn parse_score_bound(arg: Option<RespFrame>) -> Result<ScoreBound, CommandError> {
+    let s = extract_string(arg)?;
+    let (value, exclusive) = match s.strip_prefix('(') {
+        Some(rest) => (rest, true),
+        None => (s.as_str(), false),
+    };
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| !v.is_nan())
+        .map(|value| ScoreBound { value, exclusive })
+        .ok_or_else(|| CommandError::InvalidArgument("min or max is not a float".to_string()))
+}
+
+// parses a lexicographic bound: `-`, `+`, or a member prefixed with `[` or `(`
+fn parse_lex_bound(arg: Option<RespFrame>) -> Result<LexBound, CommandError> {
+    let s = extract_string(arg)?;
+    match s.as_bytes().first() {
+        Some(b'-') if s.len() == 1 => Ok(LexBound::Min),
+        Some(b'+') if s.len() == 1 => Ok(LexBound::Max),
+        Some(b'[') => Ok(LexBound::Inclusive(s[1..].to_string())),
+        Some(b'(') => Ok(LexBound::Exclusive(s[1..].to_string())),
+        _ => Err(CommandError::InvalidArgument(
+            "min or max not valid string range item".to_string(),
+        )),
+    }
+}
+
+// parses `start stop [options]` of the ZRANGE family, accepting only `options`. The legacy
+// commands fix `kind` and `rev`; ZRANGE picks them with BYSCORE, BYLEX and REV. Returns the
+// query and whether WITHSCORES was given.
+fn parse_range(
+    args: &mut impl Iterator<Item = RespFrame>,
+    mut kind: RangeKind,
+    mut rev: bool,
+    options: &[&str],
+) -> Result<(ZRangeSpec, bool), CommandError> {
+    let (start, stop) = (args.next(), args.next());
+    let (mut limit, mut with_scores) = (None, false);
+    while let Some(opt) = args.next() {
+        let opt = extract_string(Some(opt))?.to_ascii_lowercase();
+        if !options.contains(&opt.as_str()) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        match opt.as_str() {
+            "byscore" => kind = RangeKind::Score,
+            "bylex" => kind = RangeKind::Lex,
+            "rev" => rev = true,
+            "limit" => limit = Some((extract_i64(args.next())?, extract_i64(args.next())?)),
+            _ => with_scores = true,
+        }
+    }
+    if limit.is_some() && kind == RangeKind::Rank {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_string(),
+        ));
+    }
+    if with_scores && kind == RangeKind::Lex {
+        return Err(CommandError::InvalidArgument(
+            "syntax error, WITHSCORES not supported in combination with BYLEX".to_string(),
+        ));
+    }
+
+    // reversed score and lex ranges are given from the high end
+    let (min, max) = match rev && kind != RangeKind::Rank {
+        true => (stop, start),
+        false => (start, stop),
+    };
+    let by = match kind {
+        RangeKind::Rank => RangeBy::Rank(extract_i64(min)?, extract_i64(max)?),
+        RangeKind::Score => RangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?),
+        RangeKind::Lex => RangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?),
+    };
+    let (offset, count) = limit.unwrap_or((0, -1));
+    let spec = ZRangeSpec {
+        by,
+        rev,
+        offset,
+        count,
+    };
+    Ok((spec, with_scores))
+}
+
+// parses `numkeys key [key ...]` and the WEIGHTS, AGGREGATE and (if `with_scores` is allowed)
+// WITHSCORES options of ZUNION and friends; ZDIFF takes neither WEIGHTS nor AGGREGATE
+fn parse_zop(
+    args: &mut impl Iterator<Item = RespFrame>,
+    op: SetOp,
+    allow_with_scores: bool,
+) -> Result<(Vec<String>, Vec<f64>, Aggregate, bool), CommandError> {
+    let keys = extract_numkeys(args)?;
+    let (mut weights, mut aggregate, mut with_scores) = (vec![], Aggregate::Sum, false);
+    let syntax_error = || CommandError::InvalidArgument("syntax error".to_string());
+    while let Some(opt) = args.next() {
+        match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+            "weights" if op != SetOp::Diff => {
+                weights = (0..keys.len())
+                    .map(|_| {
+                        extract_f64(args.next()).map_err(|_| {
+                            CommandError::InvalidArgument("weight value is not a float".to_string())
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+            "aggregate" if op != SetOp::Diff => {
+                aggregate = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+                    "sum" => Aggregate::Sum,
+                    "min" => Aggregate::Min,
+                    "max" => Aggregate::Max,
+                    _ => return Err(syntax_error()),
+                }
+            }
+            "withscores" if allow_with_scores => with_scores = true,
+            _ => return Err(syntax_error()),
+        }
+    }
+    Ok((keys, weights, aggregate, with_scores))
+}
+
+// parses `numkeys key [key ...] MIN|MAX [COUNT count]` as used by ZMPOP and BZMPOP
+fn parse_zmpop(
+    args: &mut impl Iterator<Item = RespFrame>,
+) -> Result<(Vec<String>, bool, usize), CommandError> {
+    let keys = extract_numkeys(args)?;
+    let max = match extract_string(args.next())?.to_ascii_lowercase().as_str() {
+        "min" => false,
+        "max" => true,
+        _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+    };
+    let count = match args.next().map(|o| extract_string(Some(o))).transpose()? {
+        Some(opt) if opt.eq_ignore_ascii_case("count") => match extract_i64(args.next())? {
+            n if n > 0 => n as usize,
+            _ => {
+                return Err(CommandError::InvalidArgument(
+                    "count should be greater than 0".to_string(),
+                ))
+            }
+        },
+        Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+        None => 1,
+    };
+    if args.next().is_some() {
+        return Err(CommandError::InvalidArgument("syntax error".to_string()));
+    }
+    Ok((keys, max, count))
+}
+
+// parses `key member [member ...]`
+pub(super) fn parse_key_members(value: RespArray) -> Result<(String, Vec<String>), CommandError> {
+    let mut args = extract_args(value, 1)?.into_iter();
+    let key = extract_string(args.next())?;
+    let members = args
+        .map(|m| extract_string(Some(m)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((key, members))
+}
+
+impl TryFrom<RespArray> for ZAdd {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zadd"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter().peekable();
+        let key = extract_string(args.next())?;
+        let (mut flags, mut ch, mut incr) = (ZAddFlags::default(), false, false);
+        let (mut nx, mut xx) = (false, false);
+        while let Some(RespFrame::BulkString(opt)) = args.peek() {
+            match opt.to_ascii_lowercase().as_slice() {
+                b"nx" => nx = true,
+                b"xx" => xx = true,
+                b"gt" => flags.gt = true,
+                b"lt" => flags.lt = true,
+                b"ch" => ch = true,
+                b"incr" => incr = true,
+                _ => break,
+            }
+            args.next();
+        }
+        if nx && xx {
+            return Err(CommandError::InvalidArgument(
+                "XX and NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        if (flags.gt && flags.lt) || (nx && (flags.gt || flags.lt)) {
+            return Err(CommandError::InvalidArgument(
+                "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+            ));
+        }
+        flags.only_if = match (nx, xx) {
+            (true, _) => Some(false),
+            (_, true) => Some(true),
+            _ => None,
+        };
+
+        let args = args.collect::<Vec<_>>();
+        if args.is_empty() || !args.len().is_multiple_of(2) {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        if incr && args.len() != 2 {
+            return Err(CommandError::InvalidArgument(
+                "INCR option supports a single increment-element pair".to_string(),
+            ));
+        }
+        let mut members = Vec::with_capacity(args.len() / 2);
+        let mut args = args.into_iter();
+        while let Some(score) = args.next() {
+            members.push((extract_f64(Some(score))?, extract_string(args.next())?));
+        }
+        Ok(ZAdd {
+            key,
+            flags,
+            ch,
+            incr,
+            members,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRem {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrem"], 2, CmpType::LEAST)?;
+
+        let (key, members) = parse_key_members(value)?;
+        Ok(ZRem { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zscore"], 2, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZScore {
+            key: extract_string(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZMScore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zmscore"], 2, CmpType::LEAST)?;
+
+        let (key, members) = parse_key_members(value)?;
+        Ok(ZMScore { key, members })
+    }
+}
+
+impl TryFrom<RespArray> for ZIncrBy {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zincrby"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZIncrBy {
+            key: extract_string(args.next())?,
+            increment: extract_f64(args.next())?,
+            member: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcard"], 1, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCard {
+            key: extract_string(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zcount"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZCount {
+            key: extract_string(args.next())?,
+            min: parse_score_bound(args.next())?,
+            max: parse_score_bound(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRank {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, rev) = match command_name(&value).as_str() {
+            "zrank" => ("zrank", false),
+            "zrevrank" => ("zrevrank", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let member = extract_string(args.next())?;
+        let with_score = match args.next() {
+            Some(opt) => match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "withscore" => true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => false,
+        };
+        Ok(ZRank {
+            key,
+            member,
+            rev,
+            with_score,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind, rev, options): (_, _, _, &[&str]) = match command_name(&value).as_str() {
+            "zrange" => (
+                "zrange",
+                RangeKind::Rank,
+                false,
+                &["byscore", "bylex", "rev", "limit", "withscores"],
+            ),
+            "zrevrange" => ("zrevrange", RangeKind::Rank, true, &["withscores"]),
+            "zrangebyscore" => (
+                "zrangebyscore",
+                RangeKind::Score,
+                false,
+                &["limit", "withscores"],
+            ),
+            "zrevrangebyscore" => (
+                "zrevrangebyscore",
+                RangeKind::Score,
+                true,
+                &["limit", "withscores"],
+            ),
+            "zrangebylex" => ("zrangebylex", RangeKind::Lex, false, &["limit"]),
+            "zrevrangebylex" => ("zrevrangebylex", RangeKind::Lex, true, &["limit"]),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (spec, with_scores) = parse_range(&mut args, kind, rev, options)?;
+        Ok(ZRange {
+            key,
+            spec,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRangeStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrangestore"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = extract_string(args.next())?;
+        let source = extract_string(args.next())?;
+        let options = ["byscore", "bylex", "rev", "limit"];
+        let (spec, _) = parse_range(&mut args, RangeKind::Rank, false, &options)?;
+        Ok(ZRangeStore {
+            destination,
+            source,
+            spec,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRemRange {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, kind) = match command_name(&value).as_str() {
+            "zremrangebyrank" => ("zremrangebyrank", RangeKind::Rank),
+            "zremrangebyscore" => ("zremrangebyscore", RangeKind::Score),
+            "zremrangebylex" => ("zremrangebylex", RangeKind::Lex),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let (spec, _) = parse_range(&mut args, kind, false, &[])?;
+        Ok(ZRemRange { key, by: spec.by })
+    }
+}
+
+impl TryFrom<RespArray> for ZLexCount {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zlexcount"], 3, CmpType::EQ)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        Ok(ZLexCount {
+            key: extract_string(args.next())?,
+            min: parse_lex_bound(args.next())?,
+            max: parse_lex_bound(args.next())?,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZSetAlgebra {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = match command_name(&value).as_str() {
+            "zunion" => ("zunion", SetOp::Union),
+            "zinter" => ("zinter", SetOp::Inter),
+            "zdiff" => ("zdiff", SetOp::Diff),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (keys, weights, aggregate, with_scores) = parse_zop(&mut args, op, true)?;
+        Ok(ZSetAlgebra {
+            op,
+            keys,
+            weights,
+            aggregate,
+            with_scores,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZSetAlgebraStore {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, op) = match command_name(&value).as_str() {
+            "zunionstore" => ("zunionstore", SetOp::Union),
+            "zinterstore" => ("zinterstore", SetOp::Inter),
+            "zdiffstore" => ("zdiffstore", SetOp::Diff),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let destination = extract_string(args.next())?;
+        let (keys, weights, aggregate, _) = parse_zop(&mut args, op, false)?;
+        Ok(ZSetAlgebraStore {
+            op,
+            destination,
+            keys,
+            weights,
+            aggregate,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZInterCard {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zintercard"], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let keys = extract_numkeys(&mut args)?;
+        let limit = match args.next().map(|o| extract_string(Some(o))).transpose()? {
+            Some(opt) if opt.eq_ignore_ascii_case("limit") => match extract_i64(args.next())? {
+                n if n >= 0 => n as usize,
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "LIMIT can't be negative".to_string(),
+                    ))
+                }
+            },
+            Some(_) => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            None => 0,
+        };
+        if args.next().is_some() {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+        Ok(ZInterCard { keys, limit })
+    }
+}
+
+impl TryFrom<RespArray> for ZPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, max) = match command_name(&value).as_str() {
+            "zpopmin" => ("zpopmin", false),
+            "zpopmax" => ("zpopmax", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 1, CmpType::LEAST)?;
+        if value.len() > 3 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = match args.next() {
+            Some(c) => match extract_i64(Some(c))? {
+                n if n >= 0 => Some(n as usize),
+                _ => {
+                    return Err(CommandError::InvalidArgument(
+                        "value is out of range, must be positive".to_string(),
+                    ))
+                }
+            },
+            None => None,
+        };
+        Ok(ZPop { key, max, count })
+    }
+}
+
+impl TryFrom<RespArray> for ZMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zmpop"], 3, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let (keys, max, count) = parse_zmpop(&mut args)?;
+        Ok(ZMPop { keys, max, count })
+    }
+}
+
+impl TryFrom<RespArray> for BZPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        let (name, max) = match command_name(&value).as_str() {
+            "bzpopmin" => ("bzpopmin", false),
+            "bzpopmax" => ("bzpopmax", true),
+            _ => return Err(CommandError::CommandNotFound),
+        };
+        validate_command(&value, &[name], 2, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?;
+        let timeout = extract_timeout(args.pop())?;
+        let keys = args
+            .into_iter()
+            .map(|k| extract_string(Some(k)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BZPop { keys, max, timeout })
+    }
+}
+
+impl TryFrom<RespArray> for BZMPop {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["bzmpop"], 4, CmpType::LEAST)?;
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let timeout = extract_timeout(args.next())?;
+        let (keys, max, count) = parse_zmpop(&mut args)?;
+        Ok(BZMPop {
+            keys,
+            max,
+            count,
+            timeout,
+        })
+    }
+}
+
+impl TryFrom<RespArray> for ZRandMember {
+    type Error = CommandError;
+    fn try_from(value: RespArray) -> Result<Self, Self::Error> {
+        validate_command(&value, &["zrandmember"], 1, CmpType::LEAST)?;
+        if value.len() > 4 {
+            return Err(CommandError::InvalidArgument("syntax error".to_string()));
+        }
+
+        let mut args = extract_args(value, 1)?.into_iter();
+        let key = extract_string(args.next())?;
+        let count = args
+            .next()
+            .map(|c| extract_rand_count(Some(c)))
+            .transpose()?;
+        let with_scores = match args.next() {
+            Some(opt) => match extract_string(Some(opt))?.to_ascii_lowercase().as_str() {
+                "withscores" => true,
+                _ => return Err(CommandError::InvalidArgument("syntax error".to_string())),
+            },
+            None => false,
+        };
+        Ok(ZRandMember {
+            key,
+            count,
+            with_scores,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use crate::cmd::args;
+
+    use super::*;
+
+    #[test]
+    fn test_zadd_from_resp_array() -> Result<()> {
+        let result: ZAdd =
+            args(&["zadd", "z", "XX", "gt", "CH", "1", "a", "-inf", "b"]).try_into()?;
+        assert_eq!(result.flags.only_if, Some(true));
+        assert!(result.flags.gt && result.ch && !result.incr);
+        assert_eq!(
+            result.members,
+            vec![(1.0, "a".to_string()), (f64::NEG_INFINITY, "b".to_string())]
+        );
+
+        assert!(ZAdd::try_from(args(&["zadd", "z", "nx", "xx", "1", "a"])).is_err());
+        assert!(ZAdd::try_from(args(&["zadd", "z", "nx", "gt", "1", "a"])).is_err());
+        assert!(ZAdd::try_from(args(&["zadd", "z", "1", "a", "2"])).is_err());
+        assert!(ZAdd::try_from(args(&["zadd", "z", "nan", "a"])).is_err());
+        assert!(ZAdd::try_from(args(&["zadd", "z", "incr", "1", "a", "2", "b"])).is_err());
+
+        let bound = parse_score_bound(Some(BulkString::from("(1.5").into()))?;
+        assert_eq!(
+            bound,
+            ScoreBound {
+                value: 1.5,
+                exclusive: true
+            }
+        );
+        assert!(parse_score_bound(Some(BulkString::from("(").into())).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zset_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ZAdd = args(&["zadd", "z", "1", "a", "2", "b", "3", "c"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        let cmd: ZAdd = args(&["zadd", "z", "ch", "5", "a", "2", "b", "4", "d"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd: ZAdd = args(&["zadd", "z", "xx", "incr", "1", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        let cmd: ZAdd = args(&["zadd", "z", "incr", "0.5", "b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(2.5));
+
+        let cmd: ZScore = args(&["zscore", "z", "a"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(5.0));
+        let cmd: ZMScore = args(&["zmscore", "z", "c", "x"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Double(3.0), RespFrame::Null(RespNull)]).into()
+        );
+        let cmd: ZIncrBy = args(&["zincrby", "z", "-10", "c"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Double(-7.0));
+        let cmd: ZAdd = args(&["zadd", "z", "inf", "inf"]).try_into()?;
+        cmd.execute(&backend);
+        let cmd: ZIncrBy = args(&["zincrby", "z", "-inf", "inf"]).try_into()?;
+        assert!(matches!(cmd.execute(&backend), RespFrame::Error(_)));
+
+        // c=-7, b=2.5, d=4, a=5, inf=inf
+        let cmd: ZCard = args(&["zcard", "z"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(5));
+        let cmd: ZCount = args(&["zcount", "z", "(2.5", "+inf"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        let cmd: ZRank = args(&["zrank", "z", "d"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd: ZRank = args(&["zrevrank", "z", "c", "WITHSCORE"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([RespFrame::Integer(4), RespFrame::Double(-7.0)]).into()
+        );
+        let cmd: ZRank = args(&["zrank", "z", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: ZRem = args(&["zrem", "z", "a", "b", "x"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_from_resp_array() -> Result<()> {
+        let result: ZRange = args(&[
+            "zrange",
+            "z",
+            "(5",
+            "-inf",
+            "BYSCORE",
+            "REV",
+            "LIMIT",
+            "1",
+            "2",
+            "WITHSCORES",
+        ])
+        .try_into()?;
+        let min = ScoreBound {
+            value: f64::NEG_INFINITY,
+            exclusive: false,
+        };
+        let max = ScoreBound {
+            value: 5.0,
+            exclusive: true,
+        };
+        assert_eq!(result.spec.by, RangeBy::Score(min, max));
+        assert!(result.spec.rev && result.with_scores);
+        assert_eq!((result.spec.offset, result.spec.count), (1, 2));
+
+        let result: ZRange = args(&["zrevrangebylex", "z", "+", "[b"]).try_into()?;
+        let by = RangeBy::Lex(LexBound::Inclusive("b".to_string()), LexBound::Max);
+        assert_eq!(result.spec.by, by);
+
+        assert!(ZRange::try_from(args(&["zrange", "z", "0", "1", "limit", "0", "1"])).is_err());
+        assert!(ZRange::try_from(args(&["zrangebylex", "z", "-", "+", "withscores"])).is_err());
+        assert!(ZRange::try_from(args(&["zrangebylex", "z", "a", "+"])).is_err());
+        assert!(ZRange::try_from(args(&["zrevrange", "z", "0", "1", "rev"])).is_err());
+        assert!(
+            ZRangeStore::try_from(args(&["zrangestore", "d", "z", "0", "1", "withscores"]))
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zrange_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ZAdd = args(&["zadd", "z", "0", "a", "0", "b", "0", "c", "0", "d"]).try_into()?;
+        cmd.execute(&backend);
+        let reply = |members: &[&str]| {
+            RespArray::new(
+                members
+                    .iter()
+                    .map(|m| BulkString::from(*m).into())
+                    .collect::<Vec<RespFrame>>(),
+            )
+            .into()
+        };
+
+        let cmd: ZRange = args(&["zrange", "z", "0", "1", "rev", "withscores"]).try_into()?;
+        let pair = |m: &str| RespArray::new([BulkString::from(m).into(), 0.0.into()]).into();
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([pair("d"), pair("c")]).into()
+        );
+        let cmd: ZRange = args(&["zrange", "z", "[b", "(d", "bylex"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), reply(&["b", "c"]));
+        let cmd: ZRange = args(&["zrevrangebylex", "z", "+", "-", "limit", "1", "2"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), reply(&["c", "b"]));
+        let cmd: ZRange = args(&["zrangebyscore", "z", "(0", "+inf"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), reply(&[]));
+        let cmd: ZLexCount = args(&["zlexcount", "z", "(a", "+"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+
+        let cmd: ZRangeStore = args(&["zrangestore", "dst", "z", "0", "2", "rev"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(3));
+        let cmd: ZRemRange = args(&["zremrangebylex", "dst", "-", "[c"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd: ZRemRange = args(&["zremrangebyrank", "z", "1", "-2"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        let cmd: ZRemRange = args(&["zremrangebyscore", "z", "-inf", "0"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(2));
+        assert_eq!(backend.zcard("z"), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zop_from_resp_array() -> Result<()> {
+        let result: ZSetAlgebra = args(&[
+            "zunion",
+            "2",
+            "a",
+            "b",
+            "WEIGHTS",
+            "2",
+            "0.5",
+            "AGGREGATE",
+            "max",
+            "withscores",
+        ])
+        .try_into()?;
+        assert_eq!(result.op, SetOp::Union);
+        assert_eq!(result.weights, vec![2.0, 0.5]);
+        assert_eq!(result.aggregate, Aggregate::Max);
+        assert!(result.with_scores);
+
+        assert!(ZSetAlgebra::try_from(args(&["zinter", "2", "a", "b", "weights", "1"])).is_err());
+        assert!(ZSetAlgebra::try_from(args(&["zdiff", "1", "a", "weights", "1"])).is_err());
+        assert!(
+            ZSetAlgebraStore::try_from(args(&["zunionstore", "d", "1", "a", "withscores"]))
+                .is_err()
+        );
+
+        let result: BZMPop =
+            args(&["bzmpop", "0", "2", "a", "b", "MAX", "count", "3"]).try_into()?;
+        assert_eq!(result.keys, vec!["a".to_string(), "b".to_string()]);
+        assert!(result.max);
+        assert_eq!((result.count, result.timeout), (3, None));
+        assert!(ZMPop::try_from(args(&["zmpop", "1", "a", "left"])).is_err());
+        assert!(ZPop::try_from(args(&["zpopmin", "a", "-1"])).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_zop_and_pop_commands() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: ZAdd = args(&["zadd", "a", "1", "x", "2", "y"]).try_into()?;
+        cmd.execute(&backend);
+        let cmd: ZAdd = args(&["zadd", "b", "3", "y", "4", "z"]).try_into()?;
+        cmd.execute(&backend);
+        let pair = |m: &str, s: f64| score_pair((m.to_string(), s));
+
+        let cmd: ZSetAlgebra = args(&["zinter", "2", "a", "b", "withscores"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([pair("y", 5.0)]).into()
+        );
+        let cmd: ZSetAlgebraStore = args(&["zdiffstore", "d", "2", "a", "b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+        let cmd: ZInterCard = args(&["zintercard", "2", "a", "d"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Integer(1));
+
+        let cmd: ZPop = args(&["zpopmax", "b"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), pair("z", 4.0));
+        let cmd: ZPop = args(&["zpopmin", "a", "5"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([pair("x", 1.0), pair("y", 2.0)]).into()
+        );
+        let cmd: ZMPop = args(&["zmpop", "2", "a", "b", "min"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([
+                BulkString::from("b").into(),
+                RespArray::new([pair("y", 3.0)]).into()
+            ])
+            .into()
+        );
+        let cmd: BZPop = args(&["bzpopmin", "a", "b", "1"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+
+        let cmd: ZRandMember = args(&["zrandmember", "d", "-2", "withscores"]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([pair("x", 1.0), pair("x", 1.0)]).into()
+        );
+        let cmd: ZRandMember = args(&["zrandmember", "missing"]).try_into()?;
+        assert_eq!(cmd.execute(&backend), RespFrame::Null(RespNull));
+        let cmd: ZRandMember = args(&["zrandmember", "d", &i64::MAX.to_string()]).try_into()?;
+        assert_eq!(
+            cmd.execute(&backend),
+            RespArray::new([BulkString::from("x").into()]).into()
+        );
+        assert!(ZRandMember::try_from(args(&["zrandmember", "d", &i64::MIN.to_string()])).is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bzpopmax_waits_for_zadd() -> Result<()> {
+        let backend = Backend::new();
+        let cmd: crate::cmd::Command = args(&["bzpopmax", "z", "0"]).try_into()?;
+        let b = backend.clone();
+        let handle = tokio::spawn(async move { cmd.execute_async(&b).await });
+        while backend.blocked_clients("z") == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let cmd: ZAdd = args(&["zadd", "z", "1", "a", "2", "b"]).try_into()?;
+        cmd.execute(&backend);
+        let reply = [
+            BulkString::from("z").into(),
+            BulkString::from("b").into(),
+            RespFrame::Double(2.0),
+        ];
+        assert_eq!(handle.await?, RespArray::new(reply).into());
+        assert_eq!(backend.zcard("z"), 1);
+
+        Ok(())
+    }
+}
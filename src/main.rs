@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use simpleredis::{network, Backend};
-use tokio::net::TcpListener;
+use tokio::{net::TcpListener, time};
 use tracing::{info, warn};
 
 #[tokio::main]
@@ -11,6 +13,15 @@ async fn main() -> Result<()> {
 
     let backend = Backend::new();
 
+    let sweeper = backend.clone();
+    tokio::spawn(async move {
+        let mut interval = time::interval(Duration::from_millis(100));
+        loop {
+            interval.tick().await;
+            sweeper.sweep_expired_fields();
+        }
+    });
+
     let listener = TcpListener::bind(addr).await?;
     loop {
         let cloned_backend = backend.clone();
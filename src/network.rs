@@ -5,10 +5,7 @@ use tokio_stream::StreamExt;
 use tokio_util::codec::{Decoder, Encoder, Framed};
 use tracing::info;
 
-use crate::{
-    cmd::{Command, CommandExecutor},
-    Backend, RespDecodeV2, RespEncode, RespError, RespFrame,
-};
+use crate::{cmd::Command, Backend, RespDecodeV2, RespEncode, RespError, RespFrame};
 
 #[derive(Debug)]
 struct RespFrameCodec;
@@ -48,7 +45,7 @@ async fn request_handler(request: RedisRequest) -> Result<RedisResponse> {
     let (frame, backend) = (request.frame, request.backend);
     let cmd = Command::try_from(frame)?;
     info!("Executing command: {:?}", cmd);
-    let frame = cmd.execute(&backend);
+    let frame = cmd.execute_async(&backend).await;
     Ok(RedisResponse { frame })
 }
 
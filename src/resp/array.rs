@@ -14,10 +14,7 @@ pub struct RespArray(pub(crate) Vec<RespFrame>);
 impl RespEncode for RespArray {
     fn encode(self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(BUF_CAP);
-        //nullarray
-        if self.0.len() <= 1 {
-            return b"*-1\r\n".to_vec();
-        }
+        // an array is never null here, missing replies are sent as RespNull instead
         buf.extend_from_slice(&format!("*{}\r\n", self.0.len()).into_bytes());
         for frame in self.0 {
             buf.extend_from_slice(&frame.encode());
@@ -97,12 +94,12 @@ mod tests {
     }
 
     #[test]
-    fn test_null_array_encode() {
+    fn test_short_array_encode() {
         let frame: RespFrame = RespArray(vec![BulkString::new("set".to_string()).into()]).into();
-        assert_eq!(frame.encode(), b"*-1\r\n");
+        assert_eq!(frame.encode(), b"*1\r\n$3\r\nset\r\n");
 
         let frame: RespFrame = RespArray(vec![]).into();
-        assert_eq!(frame.encode(), b"*-1\r\n");
+        assert_eq!(frame.encode(), b"*0\r\n");
     }
 
     #[test]
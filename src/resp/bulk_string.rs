@@ -12,7 +12,7 @@ pub struct BulkString(pub(crate) Vec<u8>);
 //bulk string: "$<length>\r\n<data>\r\n"
 impl RespEncode for BulkString {
     fn encode(self) -> Vec<u8> {
-        if self.len() == 0 {
+        if self.is_empty() {
             return b"$-1\r\n".to_vec();
         }
         let mut buf = Vec::with_capacity(self.len() + 16);
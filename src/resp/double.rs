@@ -5,7 +5,12 @@ use super::{extract_simple_frame_data, RespDecode, RespEncode, RespError, CRLF_L
 //double: ",[<+|->]<integral>[.<fractional>][<E|e>[sign]<exponent>]\r\n"
 impl RespEncode for f64 {
     fn encode(self) -> Vec<u8> {
-        let ret = if self.abs() > 1e+8 || self.abs() < 1e-8 {
+        let ret = if self.is_nan() {
+            ",nan\r\n".to_string()
+        } else if self.is_infinite() {
+            let sign = if self < 0.0 { "-" } else { "" };
+            format!(",{}inf\r\n", sign)
+        } else if self.abs() > 1e+8 || self.abs() < 1e-8 {
             format!(",{:+e}\r\n", self)
         } else {
             let sign = if self < 0.0 { "" } else { "+" };
@@ -48,6 +53,10 @@ mod tests {
         assert_eq!(frame.encode(), b",+1.23456e8\r\n");
         let frame: RespFrame = (-1.23456e-9).into();
         assert_eq!(String::from_utf8_lossy(&frame.encode()), ",-1.23456e-9\r\n");
+        let frame: RespFrame = f64::NAN.into();
+        assert_eq!(frame.encode(), b",nan\r\n");
+        let frame: RespFrame = f64::NEG_INFINITY.into();
+        assert_eq!(frame.encode(), b",-inf\r\n");
     }
 
     #[test]